@@ -0,0 +1,82 @@
+// How long one tick of the classic rules takes, with snakes from 10 to 10,000 long on boards
+// up to 200 across. The snake is laid out back and forth along the rows from the bottom, so
+// it fills the board without touching itself, and each tick it moves on into the next free
+// cell. Sizes where the snake wouldn't fit on the board are skipped.
+//
+// "move" is a plain step, and "eat" puts the food right in front of the head, so the new
+// food has to be found somewhere the snake isn't.
+use std::hint::black_box;
+
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main};
+use rusty_snake::snake_core::{Direction, GridPosition, Sim};
+
+
+const LENGTHS : [usize; 3] = [10, 1_000, 10_000];
+const BOARDS : [i32; 3] = [20, 50, 200];
+
+
+
+// The nth cell along the rows, going right on the even ones and left on the odd ones.
+fn path_cell(n : usize, size : i32) -> GridPosition {
+	let y = n as i32 / size;
+	let along = n as i32 % size;
+	let x = if y % 2 == 0 { along } else { size - 1 - along };
+	GridPosition { x, y }
+}
+
+// Which way to go from one cell to the one next to it.
+fn towards(from : GridPosition, to : GridPosition) -> Direction {
+	match (to.x - from.x, to.y - from.y) {
+		(1, 0) => Direction::Right,
+		(-1, 0) => Direction::Left,
+		(0, 1) => Direction::Up,
+		_ => Direction::Down,
+	}
+}
+
+// A run with a snake this long laid along the path, and the way it goes next. The food
+// is either the next cell or the last one on the board.
+fn laid_out(length : usize, size : i32, food_ahead : bool) -> (Sim, Direction) {
+	let mut sim = Sim::sized(0, 1, 0, size, size);
+	sim.head = path_cell(length - 1, size);
+	sim.body = (0..length - 1).rev().map(|n| path_cell(n, size)).collect();
+	let next = path_cell(length, size);
+	let cells = (size * size) as usize;
+	sim.food = if food_ahead { next } else { path_cell(cells - 1, size) };
+	let dir = towards(sim.head, next);
+	(sim, dir)
+}
+
+fn bench_tick(c : &mut Criterion, name : &str, food_ahead : bool) {
+	let mut group = c.benchmark_group(name);
+	for size in BOARDS {
+		for length in LENGTHS {
+			// It needs the next cell to go into, and one more for the food.
+			if length + 2 > (size * size) as usize {
+				continue;
+			}
+			let (sim, dir) = laid_out(length, size, food_ahead);
+			let id = BenchmarkId::new(format!("{size}x{size}"), length);
+			group.bench_with_input(id, &sim, |b, sim| {
+				b.iter_batched(|| sim.clone(), |mut sim| {
+					sim.step(black_box(dir));
+					sim
+				}, BatchSize::LargeInput);
+			});
+		}
+	}
+	group.finish();
+}
+
+fn move_tick(c : &mut Criterion) {
+	bench_tick(c, "move", false);
+}
+
+fn eat_tick(c : &mut Criterion) {
+	bench_tick(c, "eat", true);
+}
+
+
+
+criterion_group!(benches, move_tick, eat_tick);
+criterion_main!(benches);
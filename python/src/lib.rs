@@ -0,0 +1,99 @@
+// Python bindings for the classic rules, so bots can be trained against exactly the rules
+// the game plays by. Build it with maturin (`maturin develop` in this folder), and then:
+//
+//     import snake_core
+//     env = snake_core.SnakeEnv()
+//     board = env.reset(7)
+//     board, reward, done = env.step(snake_core.RIGHT)
+//
+// The board is a row at a time from the bottom, one byte per cell holding EMPTY, BODY,
+// HEAD or FOOD, so `numpy.frombuffer(board, numpy.uint8).reshape(env.height, env.width)`
+// turns it into a grid. The reward is the points the step scored, combo and all.
+//
+// The comments with three slashes turn into the Python docstrings.
+
+/// The rules of Rusty Snake, for training bots against.
+#[pyo3::pymodule]
+mod snake_core {
+	use pyo3::exceptions::PyValueError;
+	use pyo3::prelude::*;
+	use rusty_snake::snake_core::{self as core, Direction, Env};
+
+	// The actions, in the order the directions are usually listed.
+	#[pymodule_export]
+	const UP : u8 = 0;
+	#[pymodule_export]
+	const DOWN : u8 = 1;
+	#[pymodule_export]
+	const LEFT : u8 = 2;
+	#[pymodule_export]
+	const RIGHT : u8 = 3;
+
+	#[pymodule_export]
+	const EMPTY : u8 = core::EMPTY;
+	#[pymodule_export]
+	const BODY : u8 = core::BODY;
+	#[pymodule_export]
+	const HEAD : u8 = core::HEAD;
+	#[pymodule_export]
+	const FOOD : u8 = core::FOOD;
+
+	// Smaller than this and there's no room to turn around in.
+	const MIN_CELLS : i32 = 3;
+
+	/// A classic run. reset(seed) starts one and returns the board, and step(action)
+	/// returns (board, reward, done). Turning straight back is ignored, like in the game.
+	#[pyclass]
+	struct SnakeEnv {
+		env : Env,
+	}
+
+	#[pymethods]
+	impl SnakeEnv {
+		#[new]
+		#[pyo3(signature = (width = 20, height = 20, lives = 1))]
+		fn new(width : i32, height : i32, lives : u32) -> PyResult<Self> {
+			if width < MIN_CELLS || height < MIN_CELLS {
+				return Err(PyValueError::new_err(format!("the board has to be at least {MIN_CELLS} cells each way")));
+			}
+			if lives == 0 {
+				return Err(PyValueError::new_err("the snake needs at least one life"));
+			}
+			Ok(SnakeEnv { env : Env::new(width, height, lives) })
+		}
+
+		/// Starts a new run on this seed and returns the board.
+		#[pyo3(signature = (seed = 0))]
+		fn reset(&mut self, seed : u64) -> Vec<u8> {
+			self.env.reset(seed)
+		}
+
+		/// Presses UP, DOWN, LEFT or RIGHT for one tick, and returns (board, reward, done).
+		fn step(&mut self, action : u8) -> PyResult<(Vec<u8>, u32, bool)> {
+			let pressed = match action {
+				UP => Direction::Up,
+				DOWN => Direction::Down,
+				LEFT => Direction::Left,
+				RIGHT => Direction::Right,
+				_ => return Err(PyValueError::new_err(format!("{action} isn't an action, use UP, DOWN, LEFT or RIGHT"))),
+			};
+			Ok(self.env.step(pressed))
+		}
+
+		#[getter]
+		fn width(&self) -> i32 {
+			self.env.sim().width()
+		}
+
+		#[getter]
+		fn height(&self) -> i32 {
+			self.env.sim().height()
+		}
+
+		/// The score so far this run.
+		#[getter]
+		fn score(&self) -> u32 {
+			self.env.sim().score
+		}
+	}
+}
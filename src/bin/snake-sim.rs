@@ -0,0 +1,5 @@
+// Plays lots of classic runs without a window and prints how they went, for balancing.
+// See the sim module for the flags it takes.
+fn main() {
+	rusty_snake::sim::run();
+}
@@ -0,0 +1,168 @@
+// Boosting and braking. Holding shift makes the snake go twice as fast, for as long as its
+// stamina lasts. Stamina comes back on its own while shift isn't held, and the meter for it
+// sits at the top of the screen. Holding ctrl does the opposite, and slows the snake to
+// half speed for as long as you like, for a bit of time to think in a tight spot. That
+// costs a point every couple of seconds though.
+//
+// Both only change how long a tick takes, not what happens in one, so runs with boosting in
+// them still play back the same as replays. Points lost to braking aren't in the replay,
+// so a run that loses any doesn't get saved as one.
+use bevy::prelude::*;
+
+use crate::replay::ReplayRecorder;
+use crate::settings::MoveSpeed;
+use crate::{Direction, GameState, Score, SnakeState, DARK_GREY, GREEN, MARGIN};
+
+
+// How long a full meter lasts, and how long an empty one takes to fill back up, in seconds.
+const BOOST_SECONDS : f32 = 2.0;
+const RECHARGE_SECONDS : f32 = 6.0;
+// How much faster the snake goes while boosting, and how much slower while braking.
+const BOOST_FACTOR : f32 = 2.0;
+const BRAKE_FACTOR : f32 = 2.0;
+// How long the brake can be held for each point it costs.
+const BRAKE_SECONDS_PER_POINT : f32 = 2.0;
+const METER_WIDTH : f32 = 160.0;
+const METER_HEIGHT : f32 = 12.0;
+const BOOSTING_COLOR : Color = Color::srgb(1.0, 0.75, 0.2);
+const BRAKING_COLOR : Color = Color::srgb(0.3, 0.55, 1.0);
+
+
+
+// Boosting, braking, and the stamina meter.
+pub struct BoostPlugin;
+impl Plugin for BoostPlugin {
+	fn build(&self, app : &mut App) {
+		app.init_resource::<Stamina>()
+			.add_systems(OnEnter(GameState::Playing), start_boost_sys)
+			.add_systems(Update, (boost_sys, (brake_cost_sys, update_meter_sys)).chain().run_if(in_state(GameState::Playing)))
+			.add_systems(PostUpdate, apply_boost_speed_sys
+				.before(crate::settings::apply_move_speed_sys)
+				.run_if(in_state(GameState::Playing)))
+			.add_systems(OnExit(GameState::Playing), cleanup_meter_sys);
+	}
+}
+
+
+
+// How much boost is left, from 0 to 1, and whether the snake's boosting or braking.
+#[derive(Resource, Default)]
+struct Stamina {
+	left : f32,
+	boosting : bool,
+	braking : bool,
+	// How long the brake's been held since it last cost a point.
+	braked_for : f32,
+}
+
+// The stamina meter, and the bar inside it that shows how full it is.
+#[derive(Component)]
+struct StaminaMeterUI;
+#[derive(Component)]
+struct StaminaFill;
+
+
+
+fn start_boost_sys(mut commands : Commands, mut stamina : ResMut<Stamina>) {
+	*stamina = Stamina { left : 1.0, ..default() };
+	commands.spawn((
+		StaminaMeterUI,
+		Node {
+			position_type : PositionType::Absolute,
+			top : Val::Px(MARGIN as f32),
+			left : Val::Percent(50.0),
+			margin : UiRect::left(Val::Px(-METER_WIDTH / 2.0)),
+			width : Val::Px(METER_WIDTH),
+			height : Val::Px(METER_HEIGHT),
+			..default()
+		},
+		BackgroundColor(DARK_GREY),
+	))
+	.with_child((
+		StaminaFill,
+		Node {
+			width : Val::Percent(100.0),
+			height : Val::Percent(100.0),
+			..default()
+		},
+		BackgroundColor(GREEN),
+	));
+}
+
+
+
+// Boosts while shift is held and there's stamina left, and brakes while ctrl is, which wins
+// if they both are. A snake that's sitting still waiting to set off can't do either, and
+// neither can a paused one, since its clock isn't moving.
+fn boost_sys(
+	time : Res<Time>,
+	keys : Res<ButtonInput<KeyCode>>,
+	snake : Res<SnakeState>,
+	mut stamina : ResMut<Stamina>,
+) {
+	let held = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+	let moving = snake.dir != Direction::None;
+	stamina.braking = moving && keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+	stamina.boosting = held && moving && !stamina.braking && stamina.left > 0.0;
+	let change = if stamina.boosting { -1.0 / BOOST_SECONDS } else if held { 0.0 } else { 1.0 / RECHARGE_SECONDS };
+	stamina.left = (stamina.left + change * time.delta_secs()).clamp(0.0, 1.0);
+}
+
+
+
+// Takes a point off for every couple of seconds of braking, as long as there are any to take.
+fn brake_cost_sys(
+	time : Res<Time>,
+	mut stamina : ResMut<Stamina>,
+	mut score : ResMut<Score>,
+	mut recorder : ResMut<ReplayRecorder>,
+) {
+	if !stamina.braking {
+		return;
+	}
+	stamina.braked_for += time.delta_secs();
+	if stamina.braked_for < BRAKE_SECONDS_PER_POINT {
+		return;
+	}
+	stamina.braked_for -= BRAKE_SECONDS_PER_POINT;
+	if score.0 > 0 {
+		score.0 -= 1;
+		recorder.discard();
+	}
+}
+
+
+
+fn update_meter_sys(stamina : Res<Stamina>, mut fill : Query<(&mut Node, &mut BackgroundColor), With<StaminaFill>>) {
+	if !stamina.is_changed() {
+		return;
+	}
+	for (mut node, mut color) in fill.iter_mut() {
+		node.width = Val::Percent(stamina.left * 100.0);
+		color.0 = if stamina.boosting {
+			BOOSTING_COLOR
+		} else if stamina.braking {
+			BRAKING_COLOR
+		} else {
+			GREEN
+		};
+	}
+}
+
+
+
+fn apply_boost_speed_sys(stamina : Res<Stamina>, mut speed : ResMut<MoveSpeed>) {
+	if stamina.boosting {
+		speed.boost = 1.0 / BOOST_FACTOR;
+	} else if stamina.braking {
+		speed.boost = BRAKE_FACTOR;
+	}
+}
+
+
+
+fn cleanup_meter_sys(mut commands : Commands, meter : Query<Entity, With<StaminaMeterUI>>) {
+	for e in &meter {
+		commands.entity(e).despawn();
+	}
+}
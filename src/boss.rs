@@ -0,0 +1,231 @@
+// Boss mode. The arena has a giant boss fruit in it, two cells by two, that wanders around
+// the board, and running the head into any part of it counts as a hit. Every hit makes it
+// smaller and quicker, and it jumps off somewhere else, and the last one finishes it and
+// clears the stage. The normal food is still there for growing and scoring in between.
+//
+// The snake still has its lives, and losing them all before the boss is beaten is game
+// over like anywhere else.
+use bevy::prelude::*;
+use rand::seq::SliceRandom;
+
+use crate::settings::Settings;
+use crate::{
+	get_random_pos, ArenaMap, CellMeshes, DangerZone, Direction, GameMode, GameRng, GameState, GridPosition, OccupancyGrid, Score,
+	SnakeHead, TickSet, CELL_SIZE,
+};
+
+
+// How big the boss is, how big it's drawn, in cells, and how many ticks it waits between
+// moves, for each number of hits it has left, starting from the last one.
+const BOSS_STAGES : [(i32, f32, u32); 3] = [(1, 0.9, 1), (2, 1.5, 2), (2, 1.9, 3)];
+// What each hit is worth, and what finishing it off is worth on top.
+const HIT_POINTS : u32 = 5;
+const BEATEN_POINTS : u32 = 20;
+// The boss jumps at least this far from the head after a hit, across plus up.
+const JUMP_DISTANCE : i32 = 6;
+// Above the snake, so it can be seen when the snake's wrapped around it.
+const BOSS_Z : f32 = 4.0;
+
+
+
+// Spawns the boss for a boss run, moves it, and keeps track of the hits.
+pub struct BossPlugin;
+impl Plugin for BossPlugin {
+	fn build(&self, app : &mut App) {
+		app.init_resource::<BossFight>()
+			.add_systems(OnEnter(GameState::Playing), spawn_boss_sys
+				.after(crate::build_arena_sys)
+				.before(crate::respawn_food_sys)
+				.run_if(resource_equals(GameMode::Boss)))
+			.add_systems(FixedUpdate, hit_boss_sys
+				.in_set(TickSet::Collide)
+				.after(crate::food_collision_sys)
+				.run_if(in_state(GameState::Playing).and(crate::countdown_done)))
+			.add_systems(FixedUpdate, move_boss_sys
+				.in_set(TickSet::Spawn)
+				.run_if(in_state(GameState::Playing).and(crate::countdown_done)))
+			.add_systems(Update, draw_boss_sys)
+			.add_systems(OnExit(GameState::Playing), despawn_boss_sys);
+	}
+}
+
+
+
+// How the fight with the boss is going.
+#[derive(Resource, Default)]
+pub struct BossFight {
+	pub hits_left : u32,
+	pub beaten : bool,
+}
+
+// The boss fruit. Its grid position is its bottom left cell.
+#[derive(Component)]
+struct BossFruit {
+	// How many ticks until its next move.
+	wait : u32,
+	// Set when it's been hit, so it jumps away on the next move.
+	jump : bool,
+}
+
+// The part of the boss that gets drawn, which is a child so it can be moved into the middle
+// of all the boss's cells.
+#[derive(Component)]
+struct BossFill;
+
+// How big the boss is, how big it's drawn, and how long it waits between moves, with this
+// many hits left.
+fn stage(hits_left : u32) -> (i32, f32, u32) {
+	BOSS_STAGES[(hits_left.max(1) as usize - 1).min(BOSS_STAGES.len() - 1)]
+}
+
+// Every cell a boss of this size covers, with its bottom left cell here.
+fn footprint(pos : GridPosition, size : i32) -> impl Iterator<Item = GridPosition> {
+	(0..size).flat_map(move |dy| (0..size).map(move |dx| GridPosition { x : pos.x + dx, y : pos.y + dy }))
+}
+
+// Whether the boss would fit here, on the arena and clear of the snake and the obstacles.
+fn fits(pos : GridPosition, size : i32, occupancy : &OccupancyGrid, head : GridPosition) -> bool {
+	footprint(pos, size).all(|cell| occupancy.contains(cell) && !occupancy.is_blocked(cell) && cell != head)
+}
+
+
+
+// Puts the boss somewhere away from where the snake starts.
+fn spawn_boss_sys(
+	mut commands : Commands,
+	mut rng : ResMut<GameRng>,
+	mut fight : ResMut<BossFight>,
+	map : Res<ArenaMap>,
+	occupancy : Res<OccupancyGrid>,
+	cells : Res<CellMeshes>,
+) {
+	*fight = BossFight { hits_left : BOSS_STAGES.len() as u32, beaten : false };
+	let (size, _, wait) = stage(fight.hits_left);
+	let start = map.start();
+	let mut pos = start;
+	for _ in 0..100 {
+		let candidate = get_random_pos(&mut rng.rng, &DangerZone::default(), map.width(), map.height());
+		let far = (candidate.x - start.x).abs() + (candidate.y - start.y).abs() >= JUMP_DISTANCE;
+		if far && fits(candidate, size, &occupancy, start) {
+			pos = candidate;
+			break;
+		}
+	}
+	commands.spawn((
+		BossFruit { wait, jump : false },
+		pos,
+		Transform::default(),
+		Visibility::default(),
+	))
+	.with_child((
+		BossFill,
+		Mesh2d(cells.food_fill.clone()),
+		MeshMaterial2d(cells.food.clone()),
+		Transform::from_xyz(0.0, 0.0, BOSS_Z),
+	));
+}
+
+
+
+// Counts a hit when the head runs into any part of the boss, and ends the stage when that
+// was the last one it could take.
+fn hit_boss_sys(
+	mut commands : Commands,
+	mut fight : ResMut<BossFight>,
+	mut score : ResMut<Score>,
+	mut next_state : ResMut<NextState<GameState>>,
+	settings : Res<Settings>,
+	head : Query<&GridPosition, With<SnakeHead>>,
+	mut boss : Query<(Entity, &GridPosition, &mut BossFruit)>,
+) {
+	let Ok(&head) = head.single() else {
+		return;
+	};
+	for (e, &pos, mut fruit) in boss.iter_mut() {
+		let (size, _, _) = stage(fight.hits_left);
+		if !footprint(pos, size).any(|cell| cell == head) {
+			continue;
+		}
+		fight.hits_left -= 1;
+		score.0 += HIT_POINTS;
+		if fight.hits_left > 0 {
+			crate::spawn_score_popup(&mut commands, &settings, head, format!("+{HIT_POINTS}"));
+			fruit.jump = true;
+			fruit.wait = 0;
+			continue;
+		}
+		score.0 += BEATEN_POINTS;
+		fight.beaten = true;
+		commands.entity(e).despawn();
+		crate::spawn_toast(&mut commands, "Boss beaten!");
+		next_state.set(GameState::GameOver);
+	}
+}
+
+
+
+// Moves the boss a cell in a random direction when it's waited long enough, or jumps it
+// away from the head after a hit. If there's nowhere it fits, it stays where it is.
+fn move_boss_sys(
+	mut rng : ResMut<GameRng>,
+	fight : Res<BossFight>,
+	occupancy : Res<OccupancyGrid>,
+	head : Query<&GridPosition, With<SnakeHead>>,
+	mut boss : Query<(&mut GridPosition, &mut BossFruit), Without<SnakeHead>>,
+) {
+	let Ok(&head) = head.single() else {
+		return;
+	};
+	let (size, _, wait) = stage(fight.hits_left);
+	for (mut pos, mut fruit) in boss.iter_mut() {
+		if fruit.jump {
+			fruit.jump = false;
+			for _ in 0..100 {
+				let candidate = get_random_pos(&mut rng.rng, &DangerZone::default(), occupancy.width, occupancy.height);
+				let far = (candidate.x - head.x).abs() + (candidate.y - head.y).abs() >= JUMP_DISTANCE;
+				if far && fits(candidate, size, &occupancy, head) {
+					*pos = candidate;
+					break;
+				}
+			}
+			fruit.wait = wait;
+			continue;
+		}
+		if fruit.wait > 0 {
+			fruit.wait -= 1;
+			continue;
+		}
+		fruit.wait = wait;
+		let mut dirs = [Direction::Up, Direction::Down, Direction::Left, Direction::Right];
+		dirs.shuffle(&mut rng.rng);
+		let next = dirs.into_iter()
+			.map(|dir| {
+				let (dx, dy) = dir.delta();
+				GridPosition { x : pos.x + dx, y : pos.y + dy }
+			})
+			.find(|&next| fits(next, size, &occupancy, head));
+		if let Some(next) = next {
+			*pos = next;
+		}
+	}
+}
+
+
+
+// Sizes the boss for how many hits it has left, and centres it over all its cells.
+fn draw_boss_sys(fight : Res<BossFight>, mut fills : Query<&mut Transform, With<BossFill>>) {
+	let (size, drawn, _) = stage(fight.hits_left);
+	let offset = (size - 1) as f32 * CELL_SIZE / 2.0;
+	for mut transform in fills.iter_mut() {
+		transform.translation = Vec3::new(offset, offset, BOSS_Z);
+		transform.scale = Vec3::new(drawn, drawn, 1.0);
+	}
+}
+
+
+
+fn despawn_boss_sys(mut commands : Commands, boss : Query<Entity, With<BossFruit>>) {
+	for e in &boss {
+		commands.entity(e).despawn();
+	}
+}
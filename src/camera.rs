@@ -0,0 +1,212 @@
+// Where the camera looks, and how close. On the normal arena it just sits over the middle
+// of the board. Whenever the arena doesn't fit on the screen, like on the big arena, the
+// wide one in a window that isn't wide enough, or when zoomed in, the camera follows the snake's head around instead, stopping at the
+// edges so it never shows more than a margin of empty space.
+//
+// On the rooms layout the camera zooms in on one room at a time, and pans over to the next
+// one as the head goes through a doorway.
+//
+// Plus and minus or the mouse wheel zoom in and out, and 0 zooms out to fit the whole
+// arena on the screen.
+//
+// The window can be any size. The board keeps its own size in the world, and the camera
+// scales it so the normal arena fits in the shorter side of the window, centred.
+//
+// A run with a mirrored screen gets the camera flipped left to right, for as long as the
+// run lasts.
+use bevy::prelude::*;
+use bevy::input::mouse::{AccumulatedMouseScroll, MouseScrollUnit};
+use bevy::window::PrimaryWindow;
+
+use crate::effects::CameraShake;
+use crate::settings::Settings;
+use crate::{
+	grid_to_world, ArenaMap, ArenaShape, GameState, GridPosition, Mirror, OccupancyGrid, SnakeHead, CELL_SIZE, MARGIN, NUM_CELLS, WORLD_SIZE,
+};
+
+
+// How quickly the camera catches up with the head. Higher is snappier.
+const FOLLOW_RATE : f32 = 6.0;
+// How much one key press or one notch of the wheel zooms by, and the closest it can get.
+// It can zoom out until the whole arena fits.
+const ZOOM_STEP : f32 = 1.25;
+const MIN_ZOOM : f32 = 0.5;
+// Roughly how many pixels of smooth scrolling make up one notch of the wheel.
+const PIXELS_PER_NOTCH : f32 = 100.0;
+
+
+
+// Moves the camera along, and puts it in place after anything else has had its say.
+pub struct FollowCameraPlugin;
+impl Plugin for FollowCameraPlugin {
+	fn build(&self, app : &mut App) {
+		app.init_resource::<CameraFocus>()
+			.init_resource::<CameraZoom>()
+			.add_systems(Update, (zoom_sys, follow_head_sys, place_camera_sys).chain())
+			.add_systems(OnEnter(GameState::Playing), zoom_to_room_sys)
+			.add_systems(OnExit(GameState::Playing), zoom_out_of_room_sys);
+	}
+}
+
+
+
+// Where the camera is looking, before any shaking.
+#[derive(Resource, Default)]
+pub struct CameraFocus(Vec2);
+
+// How zoomed out the camera is. 1 is the normal arena filling the screen, and bigger
+// shows more.
+#[derive(Resource, PartialEq)]
+pub struct CameraZoom(f32);
+impl Default for CameraZoom {
+	fn default() -> Self {
+		CameraZoom(1.0)
+	}
+}
+
+
+
+// How far out the camera has to zoom to fit an arena this many cells across and up in the
+// window. The normal arena always fits at 1, since it's square and fills the shorter side.
+fn fit_zoom(window : Vec2, width : i32, height : i32) -> f32 {
+	let window = window.max(Vec2::ONE);
+	let shorter = window.min_element();
+	let across = width as f32 / NUM_CELLS as f32 * shorter / window.x;
+	let up = height as f32 / NUM_CELLS as f32 * shorter / window.y;
+	across.max(up)
+}
+
+// The window's size, measured along the board's sides, which are the other way round when
+// the board's turned on the screen.
+fn board_view(window : &Window, settings : &Settings) -> Vec2 {
+	if settings.rotated { window.size().yx() } else { window.size() }
+}
+
+// How many world units each pixel of the window covers at this zoom. At 1 the world
+// fits exactly in the shorter side of the window.
+fn view_scale(window : &Window, zoom : f32) -> f32 {
+	zoom * WORLD_SIZE as f32 / window.width().min(window.height()).max(1.0)
+}
+
+
+
+// Zooms in and out with the keys and the mouse wheel, and keeps the zoom in bounds when
+// the arena changes size.
+fn zoom_sys(
+	keys : Res<ButtonInput<KeyCode>>,
+	scroll : Res<AccumulatedMouseScroll>,
+	occupancy : Res<OccupancyGrid>,
+	settings : Res<Settings>,
+	window : Query<&Window, With<PrimaryWindow>>,
+	mut zoom : ResMut<CameraZoom>,
+) {
+	let Ok(window) = window.single() else {
+		return;
+	};
+	let fit = fit_zoom(board_view(window, &settings), occupancy.width, occupancy.height);
+	let mut notches = match scroll.unit {
+		MouseScrollUnit::Line => scroll.delta.y,
+		MouseScrollUnit::Pixel => scroll.delta.y / PIXELS_PER_NOTCH,
+	};
+	if keys.any_just_pressed([KeyCode::Equal, KeyCode::NumpadAdd]) {
+		notches += 1.0;
+	}
+	if keys.any_just_pressed([KeyCode::Minus, KeyCode::NumpadSubtract]) {
+		notches -= 1.0;
+	}
+	let mut wanted = zoom.0 * ZOOM_STEP.powf(-notches);
+	if keys.any_just_pressed([KeyCode::Digit0, KeyCode::Numpad0]) {
+		wanted = fit;
+	}
+	zoom.set_if_neq(CameraZoom(wanted.clamp(MIN_ZOOM, fit.max(MIN_ZOOM))));
+}
+
+
+
+// Zooms in to fit the first room at the start of a run on the rooms layout.
+fn zoom_to_room_sys(
+	occupancy : Res<OccupancyGrid>,
+	settings : Res<Settings>,
+	window : Query<&Window, With<PrimaryWindow>>,
+	mut zoom : ResMut<CameraZoom>,
+) {
+	let Ok(window) = window.single() else {
+		return;
+	};
+	let Some((first, last)) = occupancy.shape.room(GridPosition { x : 0, y : 0 }, occupancy.width, occupancy.height) else {
+		return;
+	};
+	zoom.0 = fit_zoom(board_view(window, &settings), last.x - first.x + 1, last.y - first.y + 1);
+}
+
+// And back out again afterwards, so the rest of the game isn't left zoomed in.
+fn zoom_out_of_room_sys(occupancy : Res<OccupancyGrid>, mut zoom : ResMut<CameraZoom>) {
+	if occupancy.shape == ArenaShape::Rooms {
+		*zoom = CameraZoom::default();
+	}
+}
+
+
+
+// Eases the focus towards the head, or the middle of the room it's in on the rooms
+// layout, then keeps it inside the arena. When the whole arena fits on the screen that
+// always lands it right in the middle.
+fn follow_head_sys(
+	time : Res<Time>,
+	occupancy : Res<OccupancyGrid>,
+	zoom : Res<CameraZoom>,
+	settings : Res<Settings>,
+	window : Query<&Window, With<PrimaryWindow>>,
+	head : Query<(&Transform, &GridPosition), With<SnakeHead>>,
+	mut focus : ResMut<CameraFocus>,
+) {
+	let Ok(window) = window.single() else {
+		return;
+	};
+	if let Ok((head, &pos)) = head.single() {
+		let target = match occupancy.shape.room(pos, occupancy.width, occupancy.height) {
+			Some((first, last)) => (grid_to_world(first) + grid_to_world(last)) / 2.0,
+			None => head.translation.truncate(),
+		};
+		let catch_up = 1.0 - (-FOLLOW_RATE * time.delta_secs()).exp();
+		focus.0 = focus.0.lerp(target, catch_up);
+	}
+
+	// The furthest the middle of the screen can go from each edge of the arena, while still
+	// only showing a margin past it. The margin gets zoomed along with everything else. A
+	// side that fits in the window stays centred.
+	let first = grid_to_world(GridPosition { x : 0, y : 0 }) - Vec2::splat(CELL_SIZE / 2.0);
+	let last = grid_to_world(GridPosition { x : occupancy.width - 1, y : occupancy.height - 1 }) + Vec2::splat(CELL_SIZE / 2.0);
+	let reach = board_view(window, &settings) / 2.0 * view_scale(window, zoom.0) - Vec2::splat(MARGIN as f32 * zoom.0);
+	let low = first + reach;
+	let high = last - reach;
+	let middle = (first + last) / 2.0;
+	focus.0 = Vec2::new(
+		if low.x > high.x { middle.x } else { focus.0.x.clamp(low.x, high.x) },
+		if low.y > high.y { middle.y } else { focus.0.y.clamp(low.y, high.y) },
+	);
+}
+
+
+
+pub fn place_camera_sys(
+	focus : Res<CameraFocus>,
+	zoom : Res<CameraZoom>,
+	shake : Res<CameraShake>,
+	map : Res<ArenaMap>,
+	state : Res<State<GameState>>,
+	window : Query<&Window, With<PrimaryWindow>>,
+	mut camera : Query<(&mut Transform, &mut Projection), With<Camera2d>>,
+) {
+	let Ok(window) = window.single() else {
+		return;
+	};
+	let mirrored = map.mirror == Mirror::Screen && matches!(state.get(), GameState::Playing | GameState::Dying);
+	for (mut transform, mut projection) in camera.iter_mut() {
+		transform.translation = (focus.0 + shake.offset).extend(transform.translation.z);
+		transform.scale.x = if mirrored { -1.0 } else { 1.0 };
+		if let Projection::Orthographic(orthographic) = &mut *projection {
+			orthographic.scale = view_scale(window, zoom.0);
+		}
+	}
+}
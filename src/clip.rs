@@ -0,0 +1,208 @@
+// Saving a clip of the end of a run as an animated GIF.
+//
+// Nothing gets recorded off the screen. The run's replay is played back instead, and the
+// last ten seconds of it are drawn out cell by cell in the palette's colours. That means
+// clips are only there for runs that have a replay, which are the classic runs on the
+// open map, and they're only offered when the run set a new high score. The game over
+// screen gets a button for it, and G does the same.
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io;
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use web_time::{SystemTime, UNIX_EPOCH}; // The standard library's clock doesn't work in the browser.
+
+use crate::leaderboard::{self, Leaderboard};
+use crate::mutators::Mutators;
+use crate::replay::ReplayRecorder;
+use crate::theme::Theme;
+use crate::{GameMode, GameOverUI, GameState, GridPosition, Score, DARK_GREY, NUM_CELLS, WHITE};
+
+
+// Where clips get saved.
+const CLIP_DIR : &str = "clips";
+// How much of the end of the run goes in the clip.
+const CLIP_SECONDS : f32 = 10.0;
+// How big each cell is in the clip, in pixels, including the line along its bottom and left.
+const CLIP_CELL_PIXELS : usize = 8;
+
+
+
+// Offers a clip on the game over screen, and saves it.
+pub struct ClipPlugin;
+impl Plugin for ClipPlugin {
+	fn build(&self, app : &mut App) {
+		app.init_resource::<ClipOffer>()
+			.add_systems(OnEnter(GameState::GameOver), offer_clip_sys
+				.after(crate::spawn_game_over_ui)
+				.run_if(resource_equals(GameMode::Classic).and(crate::map_is_open)))
+			.add_systems(OnExit(GameState::GameOver), withdraw_clip_sys)
+			// G types into the leaderboard name instead while one's being typed in.
+			.add_systems(Update, save_clip_on_input
+				.run_if(in_state(GameState::GameOver).and(not(leaderboard::typing_name))));
+	}
+}
+
+
+
+// Whether the run that just ended can be saved as a clip.
+#[derive(Resource, Default)]
+struct ClipOffer(bool);
+
+// The "Save Clip" button on the game over screen.
+#[derive(Component)]
+struct ClipButton;
+
+// Where everything was on one tick of the clip.
+struct ClipFrame {
+	head : GridPosition,
+	body : Vec<GridPosition>,
+	food : GridPosition,
+}
+
+// The places in the clip's palette.
+const CELL : u8 = 0;
+const LINE : u8 = 1;
+const SNAKE : u8 = 2;
+const FOOD : u8 = 3;
+
+
+
+fn offer_clip_sys(
+	mut commands : Commands,
+	score : Res<Score>,
+	board : Res<Leaderboard>,
+	recorder : Res<ReplayRecorder>,
+	mut offer : ResMut<ClipOffer>,
+	game_over : Query<Entity, With<GameOverUI>>,
+) {
+	offer.0 = board.is_high_score(GameMode::Classic, Mutators::default(), score.0) && recorder.playback().is_some();
+	if !offer.0 {
+		return;
+	}
+	for root in &game_over {
+		commands.entity(root).with_children(|parent| {
+			parent.spawn((
+				Button,
+				ClipButton,
+				Node {
+					padding : UiRect::all(Val::Px(6.0)),
+					justify_content : JustifyContent::Center,
+					..default()
+				},
+				BackgroundColor(DARK_GREY),
+			))
+			.with_child((
+				Text::new("Save Clip (G)"),
+				TextFont {
+					font_size : 28.0,
+					..default()
+				},
+				TextColor(WHITE),
+			));
+		});
+	}
+}
+
+
+
+fn withdraw_clip_sys(mut offer : ResMut<ClipOffer>) {
+	offer.0 = false;
+}
+
+
+
+// Saves the clip when the button's clicked or G is pressed. It can only be saved once.
+fn save_clip_on_input(
+	mut commands : Commands,
+	keys : Res<ButtonInput<KeyCode>>,
+	mut offer : ResMut<ClipOffer>,
+	recorder : Res<ReplayRecorder>,
+	theme : Res<Theme>,
+	fixed : Res<Time<Fixed>>,
+	button : Query<(Entity, Ref<Interaction>), With<ClipButton>>,
+) {
+	let clicked = button.iter().any(|(_, interaction)| interaction.is_changed() && *interaction == Interaction::Pressed);
+	if !offer.0 || !(clicked || keys.just_pressed(KeyCode::KeyG)) {
+		return;
+	}
+	offer.0 = false;
+	for (e, _) in &button {
+		commands.entity(e).despawn();
+	}
+	let Some(frames) = last_frames(&recorder, fixed.timestep().as_secs_f32()) else {
+		return;
+	};
+	match write_clip(&frames, &theme, fixed.timestep().as_secs_f32()) {
+		Ok(path) => crate::spawn_toast(&mut commands, format!("Saved a clip to {}", path.display())),
+		Err(err) => {
+			warn!("Couldn't save the clip: {err}");
+			crate::spawn_toast(&mut commands, "Couldn't save the clip");
+		}
+	}
+}
+
+
+
+// Plays the run back, keeping hold of the last few seconds of it.
+fn last_frames(recorder : &ReplayRecorder, tick_seconds : f32) -> Option<VecDeque<ClipFrame>> {
+	let (mut sim, ticks) = recorder.playback()?;
+	let keep = (CLIP_SECONDS / tick_seconds).ceil() as usize;
+	let mut frames = VecDeque::with_capacity(keep + 1);
+	for &dir in ticks {
+		sim.step(dir);
+		frames.push_back(ClipFrame { head : sim.head, body : sim.body.clone(), food : sim.food });
+		if frames.len() > keep {
+			frames.pop_front();
+		}
+	}
+	Some(frames)
+}
+
+
+
+fn write_clip(frames : &VecDeque<ClipFrame>, theme : &Theme, tick_seconds : f32) -> io::Result<PathBuf> {
+	let size = NUM_CELLS as usize * CLIP_CELL_PIXELS;
+	let palette : Vec<u8> = [theme.cell, theme.line, theme.snake, theme.food]
+		.iter()
+		.flat_map(|color| {
+			let [r, g, b, _] = color.to_srgba().to_u8_array();
+			[r, g, b]
+		})
+		.collect();
+
+	fs::create_dir_all(CLIP_DIR)?;
+	let stamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+	let path = PathBuf::from(CLIP_DIR).join(format!("clip-{stamp}.gif"));
+	let mut encoder = gif::Encoder::new(File::create(&path)?, size as u16, size as u16, &palette).map_err(io::Error::other)?;
+	encoder.set_repeat(gif::Repeat::Infinite).map_err(io::Error::other)?;
+	// GIFs count time in hundredths of a second, and most viewers don't go any faster than two.
+	let delay = ((tick_seconds * 100.0).round() as u16).max(2);
+	for frame in frames {
+		let mut gif_frame = gif::Frame::from_indexed_pixels(size as u16, size as u16, draw_frame(frame, size), None);
+		gif_frame.delay = delay;
+		encoder.write_frame(&gif_frame).map_err(io::Error::other)?;
+	}
+	Ok(path)
+}
+
+// One frame of the clip, as places in the palette. The grid's y goes up, and the picture's
+// goes down.
+fn draw_frame(frame : &ClipFrame, size : usize) -> Vec<u8> {
+	let mut pixels = vec![CELL; size * size];
+	for (i, pixel) in pixels.iter_mut().enumerate() {
+		let (px, py) = (i % size, size - 1 - i / size);
+		if px % CLIP_CELL_PIXELS == 0 || py % CLIP_CELL_PIXELS == 0 {
+			*pixel = LINE;
+			continue;
+		}
+		let pos = GridPosition { x : (px / CLIP_CELL_PIXELS) as i32, y : (py / CLIP_CELL_PIXELS) as i32 };
+		if pos == frame.head || frame.body.contains(&pos) {
+			*pixel = SNAKE;
+		} else if pos == frame.food {
+			*pixel = FOOD;
+		}
+	}
+	pixels
+}
@@ -0,0 +1,78 @@
+// The perfect player. It follows a route that goes through every cell on the board once
+// and comes back to where it started, so it can never trap itself, and it can keep going
+// until the snake fills the whole board. Just following the route takes forever, so while
+// the snake is short it cuts across it towards the food, as long as the cut doesn't
+// skip past its own tail.
+//
+// The route snakes back and forth along every row, missing out the first column, and comes
+// back down the first column. That only joins up on a rectangular board with an even
+// number of rows, and it has to be clear of obstacles, so anywhere else the greedy AI
+// plays instead.
+use crate::{choose_ai_direction, ArenaShape, Direction, GridPosition, OccupancyGrid};
+
+
+// How much room to leave between the head and the tail when cutting across. It covers
+// the snake growing while it's on its way.
+const SHORTCUT_MARGIN : usize = 4;
+
+
+
+// Picks the next direction for the perfect player. `tail` is the last segment, if the
+// snake has grown any.
+pub fn choose_cycle_direction(
+	dir : Direction,
+	head : GridPosition,
+	tail : Option<GridPosition>,
+	food : GridPosition,
+	occupancy : &OccupancyGrid,
+) -> Direction {
+	let (width, height) = (occupancy.width, occupancy.height);
+	let rectangle = occupancy.shape == ArenaShape::Rectangle;
+	if !rectangle || height % 2 != 0 || width < 2 || occupancy.obstacles.iter().any(|&obstacle| obstacle) {
+		return choose_ai_direction(dir, head, food, occupancy);
+	}
+	let cells = (width * height) as usize;
+	let here = cycle_index(head, width, height);
+	let ahead = |pos : GridPosition| (cycle_index(pos, width, height) + cells - here) % cells;
+	// How far round the route the tail is. Anything closer than that is free.
+	let to_tail = tail.map_or(cells, ahead);
+	let to_food = ahead(food);
+	let length = cells - to_tail + 1;
+
+	let mut best : Option<(usize, Direction)> = None;
+	for candidate in [Direction::Up, Direction::Down, Direction::Left, Direction::Right] {
+		if dir.is_opposite(candidate) {
+			continue;
+		}
+		let (dx, dy) = candidate.delta();
+		let next = GridPosition { x : head.x + dx, y : head.y + dy };
+		if !occupancy.contains(next) || occupancy.is_blocked(next) {
+			continue;
+		}
+		let skip = ahead(next);
+		let following = skip == 1;
+		// Once the snake takes up half the board there's no room for cutting across, so it
+		// sticks to the route from then on.
+		let safe_cut = length * 2 < cells && skip <= to_food && skip + SHORTCUT_MARGIN < to_tail;
+		if (following || safe_cut) && best.is_none_or(|(best_skip, _)| skip > best_skip) {
+			best = Some((skip, candidate));
+		}
+	}
+	match best {
+		Some((_, candidate)) => candidate,
+		None => choose_ai_direction(dir, head, food, occupancy),
+	}
+}
+
+// Where a cell comes on the route, counting from the bottom of the second column.
+fn cycle_index(pos : GridPosition, width : i32, height : i32) -> usize {
+	let (x, y, width, height) = (pos.x as usize, pos.y as usize, width as usize, height as usize);
+	if x == 0 {
+		// The way back, from the top of the first column down to the bottom.
+		height * (width - 1) + (height - 1 - y)
+	} else if y % 2 == 0 {
+		y * (width - 1) + (x - 1)
+	} else {
+		y * (width - 1) + (width - 1 - x)
+	}
+}
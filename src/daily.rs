@@ -0,0 +1,175 @@
+// The daily challenge. Everything about the run, from the seed to the obstacle layout and
+// the speed, comes from the date, so everyone playing on the same day gets the same board.
+// Days go by UTC, so they change over at the same moment for everyone.
+//
+// It's played with the classic rules on the normal arena, and without the prestige perks,
+// so nobody starts out ahead. The best score for the day gets kept apart from the ladder,
+// and starts over when the day does.
+use std::time::Duration;
+
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use web_time::{SystemTime, UNIX_EPOCH}; // The standard library's clock doesn't work in the browser.
+
+use crate::settings::MoveSpeed;
+use crate::storage;
+use crate::{GameState, MapSymmetry, Score};
+
+
+// Where the daily best gets saved.
+pub const DAILY_FILE : &str = "daily.txt";
+// The speeds a day can have, as the time between moves in milliseconds.
+const DAILY_MOVE_PERIODS_MS : [u64; 4] = [110, 130, 150, 170];
+const DAILY_MAPS : [MapSymmetry; 3] = [MapSymmetry::Open, MapSymmetry::Rotational, MapSymmetry::Mirror];
+const SECONDS_PER_DAY : u64 = 24 * 60 * 60;
+
+
+
+// Sets the speed for daily runs, and keeps the best score for the day.
+pub struct DailyPlugin;
+impl Plugin for DailyPlugin {
+	fn build(&self, app : &mut App) {
+		app.insert_resource(Daily::load())
+			// The daily goes at its own speed, whatever the settings and the slow start perk say.
+			.add_systems(Update, daily_speed_sys
+				.after(crate::progress::slow_start_sys)
+				.run_if(in_state(GameState::Playing).and(playing_daily)))
+			.add_systems(OnEnter(GameState::GameOver), record_daily_sys
+				.before(crate::spawn_game_over_ui)
+				.run_if(playing_daily));
+	}
+}
+
+
+
+// Today's challenge, whether it's being played, and the best score on it.
+#[derive(Resource, Default, PartialEq, Debug)]
+pub struct Daily {
+	// Whether the current run is the daily challenge. Like a featured seed, it stays on
+	// through restarts, until something else is started from the menu.
+	pub playing : bool,
+	// The day being played, counted in days since 1970. It's picked when the challenge is
+	// started, so a run going past midnight stays on the same board.
+	day : u64,
+	// The best score, and which day it was on.
+	best_day : u64,
+	best : u32,
+}
+impl Daily {
+	// Reads the daily file, or starts with no best score if there isn't one yet.
+	fn load() -> Self {
+		let mut daily = match storage::read(DAILY_FILE) {
+			Some(text) => Daily::from_text(&text),
+			None => Daily::default(),
+		};
+		daily.day = today();
+		daily
+	}
+
+	// Starts today's challenge.
+	pub fn start(&mut self) {
+		self.day = today();
+		self.playing = true;
+	}
+
+	// The seed for the next run, if it's the daily challenge.
+	pub fn seed_to_play(&self) -> Option<u64> {
+		self.playing.then(|| seed_for(self.day))
+	}
+
+	// The obstacle layout for today.
+	pub fn map(&self) -> MapSymmetry {
+		DAILY_MAPS[modifiers(self.day).0]
+	}
+
+	fn move_period(&self) -> Duration {
+		Duration::from_millis(DAILY_MOVE_PERIODS_MS[modifiers(self.day).1])
+	}
+
+	// The best score on the day being played, or the one before it's started.
+	pub fn best_today(&self) -> u32 {
+		if self.best_day == self.day { self.best } else { 0 }
+	}
+
+	// A line saying what today's board is like, for the menu.
+	pub fn describe(&self) -> String {
+		format!(
+			"Today: {} map, {} ms between moves. Best today: {}",
+			self.map().name(),
+			self.move_period().as_millis(),
+			self.best_today(),
+		)
+	}
+
+	fn to_text(&self) -> String {
+		format!("day {}\nbest {}\n", self.best_day, self.best)
+	}
+
+	fn from_text(text : &str) -> Self {
+		let mut daily = Daily::default();
+		for line in text.lines() {
+			let Some((name, value)) = line.split_once(' ') else {
+				continue;
+			};
+			let value = value.trim();
+			match name {
+				"day" => if let Ok(v) = value.parse() { daily.best_day = v },
+				"best" => if let Ok(v) = value.parse() { daily.best = v },
+				_ => {}
+			}
+		}
+		daily
+	}
+}
+
+
+
+// How many days it's been since 1970, in UTC.
+fn today() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / SECONDS_PER_DAY
+}
+
+// Scrambles the day into a seed, so days next to each other don't get similar runs.
+fn seed_for(day : u64) -> u64 {
+	let mut z = day.wrapping_add(0x9E37_79B9_7F4A_7C15);
+	z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+	z ^ (z >> 31)
+}
+
+// Which map and which speed a day gets, as places in the lists above. They come from their
+// own generator, so picking them doesn't use up any of the run's random numbers.
+fn modifiers(day : u64) -> (usize, usize) {
+	let mut rng = StdRng::seed_from_u64(!seed_for(day));
+	(rng.gen_range(0..DAILY_MAPS.len()), rng.gen_range(0..DAILY_MOVE_PERIODS_MS.len()))
+}
+
+
+
+pub fn playing_daily(daily : Res<Daily>) -> bool {
+	daily.playing
+}
+
+
+
+fn daily_speed_sys(daily : Res<Daily>, mut speed : ResMut<MoveSpeed>) {
+	speed.base = daily.move_period();
+	speed.slow_start = 1.0;
+}
+
+
+
+fn record_daily_sys(score : Res<Score>, mut daily : ResMut<Daily>) {
+	if daily.best_day != daily.day {
+		daily.best_day = daily.day;
+		daily.best = 0;
+	}
+	if score.0 <= daily.best {
+		return;
+	}
+	daily.best = score.0;
+	if let Err(err) = storage::write(DAILY_FILE, &daily.to_text()) {
+		warn!("Couldn't save the daily best: {err}");
+	}
+}
@@ -0,0 +1,187 @@
+// The death animation. A snake that dies leaves its body behind where it crashed, which
+// flashes red and then crumbles away from the tail up to the head. The fresh snake is
+// already waiting at the start while that happens.
+//
+// When the death ends the run, the game sits in the Dying state until the body is all
+// gone, and only then moves on to the game over screen. It waits longer if the player's
+// being offered a rewind.
+//
+// A snake that comes back after losing a life blinks for a couple of seconds once it sets
+// off, and nothing can kill it in that time except the walls and its own body, so whatever
+// was waiting at the start doesn't get to take another life straight away.
+use bevy::prelude::*;
+
+use crate::theme::{ThemeColor, Themed};
+use crate::sprites::HeadSprite;
+use crate::{countdown_done, CellMeshes, GameState, GridPosition, PreviousGridPosition, SnakeHead, SnakeSegment};
+
+
+// How long the body flashes for, and how fast.
+const FLASH_SECONDS : f32 = 0.25;
+const BLINK_SECONDS : f32 = 0.05;
+// How long it takes the body to crumble from the tail to the head, and how long each
+// piece takes to shrink away once it's its turn.
+const CRUMBLE_SECONDS : f32 = 0.5;
+const SHRINK_SECONDS : f32 = 0.1;
+const DEATH_RED : Color = Color::srgb(0.85, 0.1, 0.1);
+// How long a snake that's come back can't be killed for, and how fast it blinks meanwhile.
+const INVINCIBLE_SECONDS : f32 = 2.0;
+const INVINCIBLE_BLINK_SECONDS : f32 = 0.1;
+
+
+
+// Plays the death animation, and holds the game over screen back until it's done.
+pub struct DeathPlugin;
+impl Plugin for DeathPlugin {
+	fn build(&self, app : &mut App) {
+		app.init_resource::<DeathMaterial>()
+			.init_resource::<Invincible>()
+			.add_systems(OnEnter(GameState::Playing), reset_invincible_sys)
+			.add_systems(Update, blink_invincible_sys.run_if(in_state(GameState::Playing).and(countdown_done)))
+			.add_systems(Update, (redden_corpses_sys, crumble_corpses_sys).chain())
+			.add_systems(Update, wait_for_corpses_sys
+				.after(crumble_corpses_sys)
+				.run_if(in_state(GameState::Dying).and(not(crate::snapshot::offering_rewind))));
+	}
+}
+
+
+
+// A piece of a dead snake.
+#[derive(Component)]
+pub struct Corpse {
+	// How long since the snake died.
+	age : f32,
+	// When this piece starts to shrink away.
+	crumble_at : f32,
+}
+
+// How many seconds the snake has left of not being able to die. It only starts running out
+// once the countdown's over.
+#[derive(Resource, Default)]
+pub struct Invincible(f32);
+impl Invincible {
+	pub fn start(&mut self) {
+		self.0 = INVINCIBLE_SECONDS;
+	}
+
+	pub fn active(&self) -> bool {
+		self.0 > 0.0
+	}
+}
+
+// Every piece of the living snake.
+type SnakePiece = Or<(With<SnakeHead>, With<SnakeSegment>)>;
+
+// The parts of a dead body that get turned red. Pieces are meshes, or sprites off the
+// sheet.
+type CorpseParts<'a> = (Entity, Option<&'a mut MeshMaterial2d<ColorMaterial>>, Option<&'a mut Sprite>, Option<&'a Themed>);
+
+// The material the dead body's meshes get swapped to. The outlines are left alone.
+#[derive(Resource)]
+struct DeathMaterial(Handle<ColorMaterial>);
+impl FromWorld for DeathMaterial {
+	fn from_world(world : &mut World) -> Self {
+		DeathMaterial(world.resource_mut::<Assets<ColorMaterial>>().add(DEATH_RED))
+	}
+}
+
+
+
+// Leaves the snake's body behind as a corpse, instead of despawning it. Segments go from
+// the head to the tail, like in SnakeState. Whoever calls this still has to clear out
+// the snake's state and spawn a new one.
+pub fn leave_corpse(commands : &mut Commands, head : Entity, segments : &[Entity]) {
+	let count = segments.len() + 1;
+	for (i, piece) in std::iter::once(head).chain(segments.iter().copied()).enumerate() {
+		// The tail is at the end of the list, and goes first.
+		let from_tail = (count - 1 - i) as f32;
+		// It might have died halfway through a blink.
+		commands.entity(piece)
+			.remove::<(SnakeHead, SnakeSegment, GridPosition, PreviousGridPosition)>()
+			.insert(Visibility::Inherited)
+			.insert(Corpse { age : 0.0, crumble_at : FLASH_SECONDS + CRUMBLE_SECONDS * from_tail / count as f32 });
+	}
+}
+
+
+
+fn reset_invincible_sys(mut invincible : ResMut<Invincible>) {
+	invincible.0 = 0.0;
+}
+
+
+
+// Runs down the time the snake can't die for, blinking it on and off until it's over.
+fn blink_invincible_sys(time : Res<Time>, mut invincible : ResMut<Invincible>, mut pieces : Query<&mut Visibility, SnakePiece>) {
+	if !invincible.active() {
+		return;
+	}
+	invincible.0 = (invincible.0 - time.delta_secs()).max(0.0);
+	let blink = (invincible.0 / INVINCIBLE_BLINK_SECONDS) as u32;
+	let shown = !invincible.active() || blink.is_multiple_of(2);
+	for mut visibility in pieces.iter_mut() {
+		visibility.set_if_neq(if shown { Visibility::Inherited } else { Visibility::Hidden });
+	}
+}
+
+
+
+// Turns a body red as soon as it dies. It stops following the theme, and a dead head
+// stops turning with the new snake.
+fn redden_corpses_sys(
+	mut commands : Commands,
+	death : Res<DeathMaterial>,
+	cells : Res<CellMeshes>,
+	corpses : Query<&Children, Added<Corpse>>,
+	mut pieces : Query<CorpseParts>,
+) {
+	for children in &corpses {
+		for &child in children {
+			let Ok((entity, material, sprite, themed)) = pieces.get_mut(child) else {
+				continue;
+			};
+			if let Some(mut material) = material && material.0 != cells.line {
+				material.0 = death.0.clone();
+			}
+			if let Some(mut sprite) = sprite && !themed.is_some_and(|themed| themed.0 == ThemeColor::Line) {
+				sprite.color = DEATH_RED;
+				commands.entity(entity).remove::<(Themed, HeadSprite)>();
+			}
+		}
+	}
+}
+
+
+
+// Blinks the body, then shrinks each piece away in turn and gets rid of it.
+fn crumble_corpses_sys(
+	mut commands : Commands,
+	time : Res<Time>,
+	mut corpses : Query<(Entity, &mut Corpse, &mut Transform, &mut Visibility)>,
+) {
+	for (entity, mut corpse, mut transform, mut visibility) in corpses.iter_mut() {
+		corpse.age += time.delta_secs();
+		if corpse.age < FLASH_SECONDS {
+			let lit = ((corpse.age / BLINK_SECONDS) as u32).is_multiple_of(2);
+			*visibility = if lit { Visibility::Inherited } else { Visibility::Hidden };
+			continue;
+		}
+		*visibility = Visibility::Inherited;
+		let shrunk = ((corpse.age - corpse.crumble_at) / SHRINK_SECONDS).max(0.0);
+		if shrunk >= 1.0 {
+			commands.entity(entity).despawn();
+			continue;
+		}
+		transform.scale = Vec3::splat(1.0 - shrunk);
+	}
+}
+
+
+
+// Moves on to the game over screen once the body is all gone.
+fn wait_for_corpses_sys(corpses : Query<(), With<Corpse>>, mut next_state : ResMut<NextState<GameState>>) {
+	if corpses.is_empty() {
+		next_state.set(GameState::GameOver);
+	}
+}
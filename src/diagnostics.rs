@@ -0,0 +1,186 @@
+// A small panel, toggled with F3, that shows how much the game is holding on to and what
+// the snake is up to. Handy for spotting anything that keeps growing over a long session,
+// and for working out why a replay went out of step.
+//
+// F4 switches on frame stepping, which stops the clock and only lets the snake move one
+// tick each time F5 is pressed. The snake can still be steered in between, so a crash into
+// the wall and the body at once, or food on the last free cell, can be set up and looked at.
+use bevy::prelude::*;
+use bevy::app::FixedMain;
+use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
+use bevy::ecs::entity::Entities;
+use bevy::ecs::system::SystemParam;
+
+use crate::replay::ReplayRecorder;
+use crate::settings::Settings;
+use crate::theme::{ThemeColor, Themed};
+use crate::{GameRng, GridPosition, SnakeHead, SnakeState, MARGIN};
+
+
+
+// The text of the diagnostics panel.
+#[derive(Component)]
+pub struct DiagnosticsUI;
+
+// Whether the game only moves on when F5 is pressed.
+#[derive(Resource, Default)]
+pub struct FrameStep(pub bool);
+
+// The note that shows while frame stepping.
+#[derive(Component)]
+pub struct FrameStepUI;
+
+// What the snake and the game clock are doing right now.
+#[derive(SystemParam)]
+pub struct GameInternals<'w, 's> {
+	snake : Res<'w, SnakeState>,
+	rng : Res<'w, GameRng>,
+	fixed : Res<'w, Time<Fixed>>,
+	head : Query<'w, 's, &'static GridPosition, With<SnakeHead>>,
+}
+impl GameInternals<'_, '_> {
+	fn describe(&self) -> String {
+		// There's no head on the menu screens, or for the tick after a death.
+		let head = match self.head.single() {
+			Ok(pos) => format!("{}, {}", pos.x, pos.y),
+			Err(_) => "none".to_string(),
+		};
+		format!(
+			"Direction: {:?} (next {:?})\nGrow: {}\nHead: {head}\nTick rate: {:.1} per second\nSeed: {}",
+			self.snake.dir,
+			self.snake.next_dir,
+			self.snake.grow,
+			1.0 / self.fixed.timestep().as_secs_f32(),
+			self.rng.seed,
+		)
+	}
+}
+
+
+
+// Shows or hides the panel when F3 is pressed.
+pub fn toggle_diagnostics_on_input(
+	mut commands : Commands,
+	keys : Res<ButtonInput<KeyCode>>,
+	ui : Query<Entity, With<DiagnosticsUI>>,
+) {
+	if !keys.just_pressed(KeyCode::F3) {
+		return;
+	}
+	if ui.is_empty() {
+		commands.spawn((
+			DiagnosticsUI,
+			Text::new(""),
+			TextFont {
+				font_size : 20.0,
+				..default()
+			},
+			Themed(ThemeColor::Text),
+			Node {
+				position_type: PositionType::Absolute,
+				bottom: Val::Px(MARGIN as f32),
+				left: Val::Px(MARGIN as f32 * 2.0),
+				..default()
+			},
+		));
+	}
+	for e in &ui {
+		commands.entity(e).despawn();
+	}
+}
+
+
+
+// Fills in the panel while it's showing.
+pub fn update_diagnostics_ui(
+	entities : &Entities,
+	recorder : Res<ReplayRecorder>,
+	settings : Res<Settings>,
+	store : Res<DiagnosticsStore>,
+	game : GameInternals,
+	mut ui : Query<&mut Text, With<DiagnosticsUI>>,
+) {
+	let fps = store
+		.get(&FrameTimeDiagnosticsPlugin::FPS)
+		.and_then(|fps| fps.smoothed())
+		.unwrap_or_default();
+	for mut text in ui.iter_mut() {
+		text.0 = format!(
+			"FPS: {fps:.0}\nEntities: {}\nReplay: {} / {} ticks ({:.1} KB)\n{}",
+			entities.len(),
+			recorder.ticks_recorded(),
+			settings.max_replay_ticks,
+			recorder.memory_bytes() as f32 / 1024.0,
+			game.describe(),
+		);
+	}
+}
+
+
+
+pub fn frame_stepping(step : Res<FrameStep>) -> bool {
+	step.0
+}
+
+
+
+// Stops the clock and starts frame stepping when F4 is pressed, or carries on as normal.
+pub fn toggle_frame_step_on_input(
+	mut commands : Commands,
+	keys : Res<ButtonInput<KeyCode>>,
+	mut step : ResMut<FrameStep>,
+	mut time : ResMut<Time<Virtual>>,
+	ui : Query<Entity, With<FrameStepUI>>,
+) {
+	if !keys.just_pressed(KeyCode::F4) {
+		return;
+	}
+	step.0 = !step.0;
+	if !step.0 {
+		time.unpause();
+		for e in &ui {
+			commands.entity(e).despawn();
+		}
+		return;
+	}
+	time.pause();
+	commands.spawn((
+		FrameStepUI,
+		Text::new("FRAME STEP\nF5 for the next tick, F4 to carry on"),
+		TextFont {
+			font_size : 20.0,
+			..default()
+		},
+		Themed(ThemeColor::Text),
+		Node {
+			position_type: PositionType::Absolute,
+			bottom: Val::Px(MARGIN as f32),
+			right: Val::Px(MARGIN as f32 * 2.0),
+			..default()
+		},
+	));
+}
+
+
+
+// Runs exactly one tick when F5 is pressed, the same way the fixed clock would.
+pub fn step_tick_on_input(world : &mut World) {
+	if !world.resource::<ButtonInput<KeyCode>>().just_pressed(KeyCode::F5) {
+		return;
+	}
+	let timestep = world.resource::<Time<Fixed>>().timestep();
+	world.resource_mut::<Time<Fixed>>().advance_by(timestep);
+	*world.resource_mut::<Time>() = world.resource::<Time<Fixed>>().as_generic();
+	world.run_schedule(FixedMain);
+	*world.resource_mut::<Time>() = world.resource::<Time<Virtual>>().as_generic();
+}
+
+
+
+// Frame stepping ends with the run, like pausing does.
+pub fn stop_frame_step_sys(mut commands : Commands, mut step : ResMut<FrameStep>, ui : Query<Entity, With<FrameStepUI>>) {
+	step.0 = false;
+	for e in &ui {
+		commands.entity(e).despawn();
+	}
+}
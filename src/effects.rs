@@ -0,0 +1,185 @@
+// Little bits of flair, like the food gently pulsing, the burst of particles when it gets
+// eaten, and the camera shaking when something big happens.
+//
+// They're only for show, so they can be switched off in the settings: the particles on
+// machines that struggle, and the shaking for anyone it bothers. Nothing here touches the
+// game's own random numbers, so replays come out the same with or without them.
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::settings::Settings;
+use crate::theme::Theme;
+use crate::{grid_to_world, FoodEaten, GameState, SnakeDied, CELL_SIZE};
+
+
+// How many particles fly out of an eaten food, how long they last, and how fast they go
+// in cells per second.
+const BURST_PARTICLES : usize = 12;
+const BURST_SECONDS : f32 = 0.4;
+const MIN_BURST_SPEED : f32 = 2.0;
+const MAX_BURST_SPEED : f32 = 4.0;
+const PARTICLE_SIZE : f32 = 4.0;
+// How much bigger the food gets at the top of its pulse, and how long one pulse takes.
+const PULSE_AMOUNT : f32 = 0.08;
+const PULSE_SECONDS : f32 = 1.2;
+// How far the camera gets thrown about when the snake dies, in pixels, and for how long.
+const DEATH_SHAKE : ShakeCamera = ShakeCamera { strength : 8.0, seconds : 0.3 };
+
+
+
+// Spawns the effects as things happen, and moves them along.
+pub struct EffectsPlugin;
+impl Plugin for EffectsPlugin {
+	fn build(&self, app : &mut App) {
+		app.add_message::<ShakeCamera>()
+			.init_resource::<CameraShake>()
+			.add_systems(Update, (spawn_food_burst_sys, move_particles_sys).chain())
+			.add_systems(Update, pulse_sys)
+			.add_systems(Update, (start_shake_sys, shake_camera_sys).chain().before(crate::camera::place_camera_sys));
+	}
+}
+
+
+
+// Sent to shake the camera. Bigger things should shake it harder, or for longer.
+#[derive(Message, Clone, Copy)]
+pub struct ShakeCamera {
+	// How far the camera can be thrown from where it should be, in pixels. It settles
+	// down as the shake wears off.
+	pub strength : f32,
+	pub seconds : f32,
+}
+
+// The shake that's going on at the moment, if there is one.
+#[derive(Resource, Default)]
+pub struct CameraShake {
+	strength : f32,
+	timer : Timer,
+	// How far the camera is thrown from where it should be this frame.
+	pub offset : Vec2,
+}
+
+// Put on the part of the food that pulses, so it catches the eye.
+#[derive(Component)]
+pub struct Pulse;
+
+// One bit of a burst. It flies off in a straight line and fades out.
+#[derive(Component)]
+struct Particle {
+	velocity : Vec2,
+	timer : Timer,
+}
+
+
+
+// Sends a burst of particles out from every food eaten since last frame, in the food's
+// colour.
+fn spawn_food_burst_sys(
+	mut commands : Commands,
+	settings : Res<Settings>,
+	theme : Res<Theme>,
+	mut eaten : MessageReader<FoodEaten>,
+) {
+	let mut rng = rand::thread_rng();
+	for food in eaten.read() {
+		if !settings.effects {
+			continue;
+		}
+		let center = grid_to_world(food.pos);
+		for _ in 0..BURST_PARTICLES {
+			let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+			let speed = rng.gen_range(MIN_BURST_SPEED..MAX_BURST_SPEED) * CELL_SIZE;
+			commands.spawn((
+				Particle {
+					velocity : Vec2::from_angle(angle) * speed,
+					timer : Timer::from_seconds(BURST_SECONDS, TimerMode::Once),
+				},
+				Sprite {
+					color : theme.food,
+					custom_size : Some(Vec2::splat(PARTICLE_SIZE)),
+					..default()
+				},
+				// Above the snake and the food, under the score popup.
+				Transform::from_translation(center.extend(5.0)),
+			));
+		}
+	}
+}
+
+
+
+// Moves the particles outwards and fades them, then gets rid of them.
+fn move_particles_sys(
+	mut commands : Commands,
+	time : Res<Time>,
+	mut particles : Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+) {
+	for (entity, mut particle, mut transform, mut sprite) in particles.iter_mut() {
+		particle.timer.tick(time.delta());
+		if particle.timer.is_finished() {
+			commands.entity(entity).despawn();
+			continue;
+		}
+		transform.translation += (particle.velocity * time.delta_secs()).extend(0.0);
+		sprite.color.set_alpha(1.0 - particle.timer.fraction());
+	}
+}
+
+
+
+// Grows and shrinks anything that pulses, all in time with each other. With the effects
+// off it all just sits still.
+fn pulse_sys(time : Res<Time>, settings : Res<Settings>, mut pulses : Query<&mut Transform, With<Pulse>>) {
+	let scale = if settings.effects {
+		let wave = (time.elapsed_secs() * std::f32::consts::TAU / PULSE_SECONDS).sin();
+		1.0 + PULSE_AMOUNT * (wave + 1.0) / 2.0
+	} else {
+		1.0
+	};
+	for mut transform in pulses.iter_mut() {
+		transform.scale = Vec3::splat(scale);
+	}
+}
+
+
+
+// Starts the camera shaking when the snake dies, or whenever something else asks for it.
+// A shake that comes along while the camera's already shaking takes over if it's bigger.
+// The demo's deaths don't shake anything.
+fn start_shake_sys(
+	settings : Res<Settings>,
+	state : Res<State<GameState>>,
+	mut shake : ResMut<CameraShake>,
+	mut died : MessageReader<SnakeDied>,
+	mut requests : MessageReader<ShakeCamera>,
+) {
+	let death = (died.read().count() > 0 && *state.get() != GameState::Demo).then_some(DEATH_SHAKE);
+	for request in requests.read().copied().chain(death) {
+		if !settings.shake {
+			continue;
+		}
+		let left = shake.strength * (1.0 - shake.timer.fraction());
+		if shake.timer.is_finished() || request.strength > left {
+			shake.strength = request.strength;
+			shake.timer = Timer::from_seconds(request.seconds, TimerMode::Once);
+		}
+	}
+}
+
+
+
+// Picks a little way to throw the camera in a random direction every frame, less and less
+// as the shake wears off. The camera module puts it on top of wherever the camera is.
+fn shake_camera_sys(time : Res<Time>, mut shake : ResMut<CameraShake>) {
+	if shake.timer.is_finished() {
+		return;
+	}
+	shake.timer.tick(time.delta());
+	shake.offset = if shake.timer.is_finished() {
+		Vec2::ZERO
+	} else {
+		let mut rng = rand::thread_rng();
+		let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+		Vec2::from_angle(angle) * shake.strength * (1.0 - shake.timer.fraction()) * rng.gen_range(0.5..1.0)
+	};
+}
@@ -0,0 +1,378 @@
+// Random events, when they're switched on from the menu. Every half a minute or so one of
+// them comes along for a few seconds, and it's announced at the top of the screen for a
+// moment before it starts, and counted down while it lasts.
+//
+// A food frenzy puts ten extra food out, which go away again when it's over if they
+// haven't been eaten. Eating one of them doesn't move the normal food. A speed storm
+// makes every tick take half as long. A blackout darkens the whole board, except for a
+// little square around the head.
+//
+// Each event hooks into the run with its own systems, which hear about it starting and
+// ending through messages, or check whether it's going on.
+use bevy::prelude::*;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::{
+	countdown_done, get_random_pos, spawn_food_at, ArenaMap, CellMeshes, DangerZone, Food, FoodEaten, GameRng, GameState, GridPosition,
+	OccupancyGrid, SnakeHead, TickSet, CELL_SIZE, MARGIN, WHITE,
+};
+use crate::settings::MoveSpeed;
+
+
+// How long there is between one event ending and the next one starting, in seconds, and
+// how long before it starts it gets announced.
+const EVENT_GAP_SECONDS : std::ops::RangeInclusive<f32> = 25.0..=40.0;
+const WARNING_SECONDS : f32 = 3.0;
+// How much food a frenzy puts out.
+const FRENZY_FOOD : usize = 10;
+// How much faster the ticks come in a speed storm.
+const STORM_FACTOR : f32 = 2.0;
+// How far from the head can still be seen in a blackout, in cells, and how dark the rest
+// of the board goes.
+const BLACKOUT_RADIUS : f32 = 3.0;
+const BLACKOUT_COLOR : Color = Color::srgba(0.0, 0.0, 0.0, 0.9);
+// How far the sides of a dark frame reach, which is far enough to cover any arena wherever
+// the head is on it.
+const FRAME_REACH : f32 = 10000.0;
+// Above everything on the board, and below the score popups.
+const FRAME_Z : f32 = 8.0;
+// Under the stamina meter.
+const BANNER_TOP : f32 = MARGIN as f32 + 24.0;
+
+
+
+// Picks the events, announces them, and runs each one's rules while it's going on.
+pub struct EventsPlugin;
+impl Plugin for EventsPlugin {
+	fn build(&self, app : &mut App) {
+		app.init_resource::<EventClock>()
+			.add_message::<EventStarted>()
+			.add_message::<EventEnded>()
+			.add_systems(OnEnter(GameState::Playing), (reset_events_sys, spawn_banner_sys))
+			.add_systems(Update, (
+					event_clock_sys,
+					update_banner_sys,
+					(start_frenzy_sys, end_frenzy_sys, start_blackout_sys, end_blackout_sys),
+				)
+				.chain()
+				.run_if(in_state(GameState::Playing).and(countdown_done).and(events_on)))
+			// The hole in a dark frame follows the head through a countdown too, after a death.
+			.add_systems(Update, follow_head_sys
+				.after(end_blackout_sys)
+				.run_if(in_state(GameState::Playing)))
+			.add_systems(FixedUpdate, eat_frenzy_food_sys
+				.in_set(TickSet::Spawn)
+				.run_if(in_state(GameState::Playing).and(events_on)))
+			.add_systems(PostUpdate, apply_storm_speed_sys
+				.before(crate::settings::apply_move_speed_sys)
+				.run_if(in_state(GameState::Playing).and(event_on(RunEvent::SpeedStorm))))
+			.add_systems(OnExit(GameState::Playing), cleanup_events_sys);
+	}
+}
+
+
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum RunEvent {
+	FoodFrenzy,
+	SpeedStorm,
+	Blackout,
+}
+impl RunEvent {
+	const ALL : [RunEvent; 3] = [RunEvent::FoodFrenzy, RunEvent::SpeedStorm, RunEvent::Blackout];
+
+	fn name(self) -> &'static str {
+		match self {
+			RunEvent::FoodFrenzy => "Food frenzy",
+			RunEvent::SpeedStorm => "Speed storm",
+			RunEvent::Blackout => "Blackout",
+		}
+	}
+
+	// How long it lasts, in seconds.
+	fn seconds(self) -> f32 {
+		match self {
+			RunEvent::FoodFrenzy => 10.0,
+			RunEvent::SpeedStorm => 8.0,
+			RunEvent::Blackout => 10.0,
+		}
+	}
+}
+
+// What's coming up next, and what's going on now.
+#[derive(Resource)]
+struct EventClock {
+	next : RunEvent,
+	// How long until the next one starts. This doesn't count down while one's going on.
+	until_next : f32,
+	// The event that's going on, and how long it has left.
+	active : Option<(RunEvent, f32)>,
+}
+impl Default for EventClock {
+	fn default() -> Self {
+		EventClock { next : RunEvent::FoodFrenzy, until_next : *EVENT_GAP_SECONDS.end(), active : None }
+	}
+}
+impl EventClock {
+	// Picks what's next and when.
+	fn schedule(&mut self, rng : &mut impl Rng) {
+		self.next = *RunEvent::ALL.choose(rng).unwrap();
+		self.until_next = rng.gen_range(EVENT_GAP_SECONDS);
+	}
+}
+
+// Sent when an event starts, and when it ends.
+#[derive(Message)]
+struct EventStarted(RunEvent);
+#[derive(Message)]
+struct EventEnded(RunEvent);
+
+// Food from a frenzy, which goes away when it's over.
+#[derive(Component)]
+pub struct FrenzyFood;
+
+// The line of text at the top of the screen about the events.
+#[derive(Component)]
+struct EventBanner;
+
+// One of the four sides of a dark frame around the head, which way it is from the head,
+// and how many cells can be seen out past the head inside it.
+#[derive(Component)]
+pub struct DarkFrame {
+	side : Vec2,
+	radius : f32,
+}
+
+// The dark frame from a blackout, rather than any other.
+#[derive(Component, Clone)]
+struct Blackout;
+
+
+
+fn events_on(map : Res<ArenaMap>) -> bool {
+	map.events
+}
+
+// Whether this event is going on right now.
+fn event_on(event : RunEvent) -> impl Fn(Res<EventClock>) -> bool {
+	move |clock : Res<EventClock>| clock.active.is_some_and(|(active, _)| active == event)
+}
+
+
+
+fn reset_events_sys(mut clock : ResMut<EventClock>, mut rng : ResMut<GameRng>, map : Res<ArenaMap>) {
+	*clock = EventClock::default();
+	if map.events {
+		clock.schedule(&mut rng.rng);
+	}
+}
+
+
+
+// Counts down to the next event, starts it, and ends it when its time's up.
+fn event_clock_sys(
+	time : Res<Time>,
+	mut rng : ResMut<GameRng>,
+	mut clock : ResMut<EventClock>,
+	mut started : MessageWriter<EventStarted>,
+	mut ended : MessageWriter<EventEnded>,
+) {
+	if let Some((event, left)) = clock.active {
+		let left = left - time.delta_secs();
+		if left > 0.0 {
+			clock.active = Some((event, left));
+			return;
+		}
+		clock.active = None;
+		clock.schedule(&mut rng.rng);
+		ended.write(EventEnded(event));
+		return;
+	}
+	clock.until_next -= time.delta_secs();
+	if clock.until_next <= 0.0 {
+		let event = clock.next;
+		clock.active = Some((event, event.seconds()));
+		debug!(event = event.name(), "Event started");
+		started.write(EventStarted(event));
+	}
+}
+
+
+
+fn spawn_banner_sys(mut commands : Commands) {
+	commands.spawn((
+		EventBanner,
+		Node {
+			position_type : PositionType::Absolute,
+			top : Val::Px(BANNER_TOP),
+			width : Val::Percent(100.0),
+			justify_content : JustifyContent::Center,
+			..default()
+		},
+	))
+	.with_child((
+		Text::new(""),
+		TextFont {
+			font_size : 32.0,
+			..default()
+		},
+		TextColor(WHITE),
+	));
+}
+
+
+
+// Announces the next event for a moment before it starts, and counts it down while it's on.
+fn update_banner_sys(clock : Res<EventClock>, banners : Query<&Children, With<EventBanner>>, mut texts : Query<&mut Text>) {
+	let banner = match clock.active {
+		Some((event, left)) => format!("{}! {}s", event.name().to_uppercase(), left.ceil()),
+		None if clock.until_next <= WARNING_SECONDS => format!("{} in {}...", clock.next.name(), clock.until_next.ceil()),
+		None => String::new(),
+	};
+	for children in &banners {
+		for &child in children {
+			if let Ok(mut text) = texts.get_mut(child) && text.0 != banner {
+				text.0 = banner.clone();
+			}
+		}
+	}
+}
+
+
+
+// Puts the frenzy's food out on free cells.
+fn start_frenzy_sys(
+	mut commands : Commands,
+	mut started : MessageReader<EventStarted>,
+	mut rng : ResMut<GameRng>,
+	occupancy : Res<OccupancyGrid>,
+	cells : Res<CellMeshes>,
+	food : Query<&GridPosition, With<Food>>,
+) {
+	if !started.read().any(|started| started.0 == RunEvent::FoodFrenzy) {
+		return;
+	}
+	let mut taken : Vec<GridPosition> = food.iter().copied().collect();
+	let mut spawned = 0;
+	for _ in 0..FRENZY_FOOD * 10 {
+		let pos = get_random_pos(&mut rng.rng, &DangerZone::default(), occupancy.width, occupancy.height);
+		if !occupancy.contains(pos) || occupancy.is_blocked(pos) || taken.contains(&pos) {
+			continue;
+		}
+		let e = spawn_food_at(&mut commands, &cells, pos);
+		commands.entity(e).insert(FrenzyFood);
+		taken.push(pos);
+		spawned += 1;
+		if spawned == FRENZY_FOOD {
+			break;
+		}
+	}
+}
+
+
+
+fn end_frenzy_sys(mut commands : Commands, mut ended : MessageReader<EventEnded>, frenzy : Query<Entity, With<FrenzyFood>>) {
+	if !ended.read().any(|ended| ended.0 == RunEvent::FoodFrenzy) {
+		return;
+	}
+	for e in &frenzy {
+		commands.entity(e).despawn();
+	}
+}
+
+
+
+// Takes away the frenzy food that's been eaten. The normal food takes care of itself.
+fn eat_frenzy_food_sys(
+	mut commands : Commands,
+	mut eaten : MessageReader<FoodEaten>,
+	frenzy : Query<(Entity, &GridPosition), With<FrenzyFood>>,
+) {
+	for eaten in eaten.read() {
+		for (e, &pos) in &frenzy {
+			if pos == eaten.pos {
+				commands.entity(e).despawn();
+			}
+		}
+	}
+}
+
+
+
+fn apply_storm_speed_sys(mut speed : ResMut<MoveSpeed>) {
+	speed.storm = 1.0 / STORM_FACTOR;
+}
+
+
+
+// Puts a dark frame around the head, with a square hole in the middle for the head to see
+// out of, this many cells out from it. Each side reaches well off the edges of the arena.
+// The marker goes on every side, so they can all be found again to take it away.
+pub fn spawn_dark_frame(commands : &mut Commands, radius : f32, color : Color, marker : impl Component + Clone) {
+	let hole = (radius * 2.0 + 1.0) * CELL_SIZE;
+	let sides = [
+		(Vec2::NEG_X, Vec2::new(FRAME_REACH, FRAME_REACH * 2.0 + hole)),
+		(Vec2::X, Vec2::new(FRAME_REACH, FRAME_REACH * 2.0 + hole)),
+		(Vec2::NEG_Y, Vec2::new(hole, FRAME_REACH)),
+		(Vec2::Y, Vec2::new(hole, FRAME_REACH)),
+	];
+	for (side, size) in sides {
+		commands.spawn((
+			DarkFrame { side, radius },
+			marker.clone(),
+			Sprite {
+				color,
+				custom_size : Some(size),
+				..default()
+			},
+			Transform::from_xyz(0.0, 0.0, FRAME_Z),
+		));
+	}
+}
+
+
+
+fn start_blackout_sys(mut commands : Commands, mut started : MessageReader<EventStarted>) {
+	if started.read().any(|started| started.0 == RunEvent::Blackout) {
+		spawn_dark_frame(&mut commands, BLACKOUT_RADIUS, BLACKOUT_COLOR, Blackout);
+	}
+}
+
+
+
+fn end_blackout_sys(mut commands : Commands, mut ended : MessageReader<EventEnded>, pieces : Query<Entity, With<Blackout>>) {
+	if !ended.read().any(|ended| ended.0 == RunEvent::Blackout) {
+		return;
+	}
+	for e in &pieces {
+		commands.entity(e).despawn();
+	}
+}
+
+
+
+// Keeps the holes in the dark frames over the head as it moves.
+fn follow_head_sys(head : Query<&Transform, With<SnakeHead>>, mut pieces : Query<(&mut Transform, &DarkFrame), Without<SnakeHead>>) {
+	let Ok(head) = head.single() else {
+		return;
+	};
+	for (mut transform, piece) in pieces.iter_mut() {
+		let offset = (piece.radius + 0.5) * CELL_SIZE + FRAME_REACH / 2.0;
+		let pos = head.translation.truncate() + piece.side * offset;
+		transform.translation = pos.extend(FRAME_Z);
+	}
+}
+
+
+
+fn cleanup_events_sys(
+	mut commands : Commands,
+	banners : Query<Entity, With<EventBanner>>,
+	pieces : Query<Entity, With<Blackout>>,
+	frenzy : Query<Entity, With<FrenzyFood>>,
+) {
+	for e in banners.iter().chain(pieces.iter()).chain(frenzy.iter()) {
+		commands.entity(e).despawn();
+	}
+}
@@ -0,0 +1,84 @@
+// Hand picked seeds with layouts that are worth talking about, so everyone can play the
+// exact same run and compare notes.
+//
+// The list lives in assets/featured_seeds.txt, but it's built into the game rather than
+// loaded, so it's there on every platform and can't go missing. Each seed is a block of
+// "name value" lines like the other files, with a blank line between seeds.
+use bevy::prelude::*;
+
+
+// The bundled list.
+const FEATURED_SEEDS : &str = include_str!("../assets/featured_seeds.txt");
+
+
+
+pub struct FeaturedSeed {
+	pub name : String,
+	pub seed : u64,
+	pub description : String,
+}
+
+// The featured seeds, and which one is picked on the menu.
+#[derive(Resource)]
+pub struct FeaturedSeeds {
+	seeds : Vec<FeaturedSeed>,
+	picked : usize,
+	// Whether the current run is playing the picked seed instead of a random one. It stays
+	// on through restarts, until a normal run is started from the menu.
+	pub playing : bool,
+}
+impl Default for FeaturedSeeds {
+	fn default() -> Self {
+		FeaturedSeeds { seeds : parse_seeds(FEATURED_SEEDS), picked : 0, playing : false }
+	}
+}
+impl FeaturedSeeds {
+	pub fn picked(&self) -> Option<&FeaturedSeed> {
+		self.seeds.get(self.picked)
+	}
+
+	// The seed for the next run, if it's a featured one.
+	pub fn seed_to_play(&self) -> Option<u64> {
+		if self.playing { self.picked().map(|featured| featured.seed) } else { None }
+	}
+
+	// Flips forwards or backwards through the list, going around at the ends.
+	pub fn cycle(&mut self, step : i32) {
+		if self.seeds.is_empty() {
+			return;
+		}
+		let len = self.seeds.len() as i32;
+		self.picked = (self.picked as i32 + step).rem_euclid(len) as usize;
+	}
+}
+
+
+
+// Reads the seeds out of the list. A seed that's missing anything gets left out.
+fn parse_seeds(text : &str) -> Vec<FeaturedSeed> {
+	let mut seeds = Vec::new();
+	// Adding a blank line to the end makes sure the last seed gets finished off.
+	let (mut name, mut seed, mut description) = (None, None, None);
+	for line in text.lines().chain(std::iter::once("")) {
+		let line = line.trim();
+		if line.starts_with('#') {
+			continue;
+		}
+		if line.is_empty() {
+			match (name.take(), seed.take(), description.take()) {
+				(Some(name), Some(seed), Some(description)) => seeds.push(FeaturedSeed { name, seed, description }),
+				(None, None, None) => {}
+				_ => warn!("Skipping a featured seed that's missing its name, seed, or description"),
+			}
+			continue;
+		}
+		let (field, value) = line.split_once(' ').unwrap_or((line, ""));
+		match field {
+			"name" => name = Some(value.to_string()),
+			"seed" => seed = value.parse().ok(),
+			"description" => description = Some(value.to_string()),
+			_ => {}
+		}
+	}
+	seeds
+}
@@ -0,0 +1,169 @@
+// A see-through ghost of the player's best run, racing alongside them.
+//
+// Only runs on a seed that can come up again get a ghost, which means the featured seeds
+// and the daily challenge. When one starts, the best saved replay on the same seed gets
+// played back a tick at a time next to the real snake, using the same rules the replay
+// checker does. The ghost never touches anything, and it goes away once its run is over.
+use bevy::prelude::*;
+
+use crate::replay::ReplayRecorder;
+use crate::snake_core::Sim;
+use crate::theme::Theme;
+use crate::{daily, featured, Direction, GameMode, GameState, GridPosition, CELL_SIZE, MARGIN, NUM_CELLS};
+
+
+// How see-through the ghost is.
+const GHOST_ALPHA : f32 = 0.35;
+
+
+
+// Loads the ghost when a run starts, and moves and draws it as the run goes.
+pub struct GhostPlugin;
+impl Plugin for GhostPlugin {
+	fn build(&self, app : &mut App) {
+		app.init_resource::<Ghost>()
+			// Replays are only kept for classic runs on the open map, so nothing else can
+			// have a ghost.
+			.add_systems(OnEnter(GameState::Playing), load_ghost_sys
+				.after(crate::replay::start_recording_sys)
+				.run_if(resource_equals(GameMode::Classic).and(crate::map_is_open)))
+			.add_systems(OnExit(GameState::Playing), cleanup_ghost_sys)
+			// The ghost moves on the same ticks that get recorded, so it stays in step.
+			.add_systems(FixedUpdate, move_ghost_sys
+				.after(crate::move_snake_sys)
+				.run_if(in_state(GameState::Playing))
+				.run_if(crate::countdown_done))
+			.add_systems(Update, draw_ghost_sys.run_if(in_state(GameState::Playing)));
+	}
+}
+
+
+
+// The run being played back, and how far into it the ghost is.
+#[derive(Resource, Default)]
+struct Ghost {
+	ticks : Vec<Direction>,
+	played : usize,
+	sim : Option<Sim>,
+}
+
+// One cell of the ghost.
+#[derive(Component)]
+struct GhostCell;
+
+// The ghost's score, in the top right corner.
+#[derive(Component)]
+struct GhostUI;
+
+// Everything the ghost puts on the screen.
+type IsGhost = Or<(With<GhostCell>, With<GhostUI>)>;
+
+
+
+fn load_ghost_sys(
+	mut commands : Commands,
+	featured : Res<featured::FeaturedSeeds>,
+	daily : Res<daily::Daily>,
+	recorder : Res<ReplayRecorder>,
+	mut ghost : ResMut<Ghost>,
+) {
+	*ghost = Ghost::default();
+	// A random seed won't have been played before, so there's no point looking.
+	if featured.seed_to_play().is_none() && daily.seed_to_play().is_none() {
+		return;
+	}
+	let Some(best) = recorder.best_saved_run() else {
+		return;
+	};
+	commands.spawn((
+		GhostUI,
+		Text::new(format!("Ghost: {}", best.score)),
+		TextFont {
+			font_size : 32.0,
+			..default()
+		},
+		crate::theme::Themed(crate::theme::ThemeColor::Text),
+		Node {
+			position_type : PositionType::Absolute,
+			top : Val::Px(MARGIN as f32),
+			right : Val::Px(MARGIN as f32 * 2.0),
+			..default()
+		},
+	));
+	ghost.sim = Some(Sim::new(best.seed, best.lives, best.growth));
+	ghost.ticks = best.ticks;
+}
+
+
+
+fn cleanup_ghost_sys(
+	mut commands : Commands,
+	mut ghost : ResMut<Ghost>,
+	cells : Query<Entity, IsGhost>,
+) {
+	*ghost = Ghost::default();
+	for e in &cells {
+		commands.entity(e).despawn();
+	}
+}
+
+
+
+// Plays the next tick of the ghost's run.
+fn move_ghost_sys(mut ghost : ResMut<Ghost>) {
+	if ghost.sim.is_none() {
+		return;
+	}
+	let ghost = &mut *ghost;
+	let (Some(sim), Some(&dir)) = (ghost.sim.as_mut(), ghost.ticks.get(ghost.played)) else {
+		ghost.sim = None;
+		return;
+	};
+	sim.step(dir);
+	ghost.played += 1;
+	if sim.finished {
+		ghost.sim = None;
+	}
+}
+
+
+
+// Puts a see-through cell everywhere the ghost is. The cells are respawned whenever the
+// ghost grows, shrinks, or the colours change, and just moved otherwise.
+fn draw_ghost_sys(
+	mut commands : Commands,
+	ghost : Res<Ghost>,
+	theme : Res<Theme>,
+	mut cells : Query<(Entity, &mut GridPosition), With<GhostCell>>,
+) {
+	if !ghost.is_changed() && !theme.is_changed() {
+		return;
+	}
+	let on_board = |pos : &GridPosition| pos.x >= 0 && pos.x < NUM_CELLS && pos.y >= 0 && pos.y < NUM_CELLS;
+	let positions : Vec<GridPosition> = match &ghost.sim {
+		Some(sim) => std::iter::once(sim.head).chain(sim.body.iter().copied()).filter(on_board).collect(),
+		None => Vec::new(),
+	};
+	if cells.iter().count() == positions.len() && !theme.is_changed() {
+		for ((_, mut cell), pos) in cells.iter_mut().zip(positions) {
+			cell.set_if_neq(pos);
+		}
+		return;
+	}
+	for (e, _) in &cells {
+		commands.entity(e).despawn();
+	}
+	for pos in positions {
+		commands.spawn((
+			GhostCell,
+			pos,
+			Sprite {
+				color : theme.snake.with_alpha(GHOST_ALPHA),
+				custom_size : Some(Vec2::splat(CELL_SIZE)),
+				..default()
+			},
+			// Above the board's fill, and under the real snake.
+			Transform::from_xyz(0.0, 0.0, 1.5),
+		));
+	}
+}
@@ -0,0 +1,589 @@
+// Hazards that turn up on the board partway through a run, when they're picked on the menu.
+//
+// Bombs get dropped every so often, away from the snake's head, with a fuse that counts
+// down a tick at a time. When it runs out the bomb goes off, and the cells around it are
+// deadly for a few ticks while the blast flashes. Unlike a wall, a blast doesn't care
+// which end of the snake is in it, so the body's just as much at risk as the head.
+//
+// The chaser starts in the far corner from the snake and comes after its head, a cell every
+// other tick, by the shortest way around everything in the way. Touching it is the end of
+// the snake. Every so often a power food turns up though, and eating it turns the tables
+// for a while, Pac-Man style. The chaser turns blue and runs, and catching it is worth
+// points and sends it back to its corner. It goes back there whenever the snake dies too.
+//
+// Lasers sweep a whole row or column every so often. The cells light up faintly for two
+// ticks as a warning, and then the laser fires for a tick, and anything of the snake's
+// that's still on the line when it does is done for.
+//
+// Zen has no dying in it, so there's no hazards in it either.
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::pathfinding::find_path;
+use crate::settings::Settings;
+use crate::stats::DeathCause;
+use crate::{
+	arena_cells, death, get_random_pos, spawn_snake, ArenaMap, DangerZone, DeathRules, Direction, Food, GameMode, GameRng, GameState,
+	GridPosition, OccupancyGrid, Score, SnakeDied, SnakeHead, SnakeState, TickSet,
+};
+
+
+// The chance of a bomb being dropped on any tick, out of one.
+const BOMB_CHANCE : f64 = 1.0 / 30.0;
+// How many bombs can be on the board at once.
+const MAX_BOMBS : usize = 2;
+// How many ticks a bomb takes to go off.
+const FUSE_TICKS : u32 = 5;
+// How far a blast reaches from the bomb, so 1 is the 3x3 square around it.
+const BLAST_RADIUS : i32 = 1;
+// How many ticks the blast stays deadly for.
+const BLAST_TICKS : u32 = 3;
+// Bombs don't get dropped any closer to the head than this, across or up.
+const BOMB_CLEARANCE : i32 = 3;
+// How many ticks there are between lasers, give or take a few picked at random.
+const LASER_PERIOD : u32 = 25;
+const LASER_JITTER : u32 = 10;
+// How many ticks of warning there are before a laser fires.
+const LASER_WARNING_TICKS : u32 = 2;
+// How many times a second the blast flashes.
+const FLASH_RATE : f32 = 8.0;
+const BOMB_COLOR : Color = Color::srgb(0.8, 0.15, 0.1);
+const BLAST_COLOR : Color = Color::srgba(1.0, 0.6, 0.1, 0.85);
+const LASER_WARNING_COLOR : Color = Color::srgba(1.0, 0.2, 0.2, 0.3);
+const LASER_COLOR : Color = Color::srgba(1.0, 0.15, 0.15, 0.9);
+// The chance of a power food turning up on any tick, while there isn't one already.
+const POWER_FOOD_CHANCE : f64 = 1.0 / 60.0;
+// How many ticks the chaser stays scared for, which is about five seconds at the normal
+// speed.
+const FRIGHT_TICKS : u32 = 40;
+// What catching the chaser is worth.
+const CHASER_POINTS : u32 = 5;
+const CHASER_COLOR : Color = Color::srgb(0.9, 0.3, 0.9);
+const FRIGHTENED_COLOR : Color = Color::srgb(0.25, 0.35, 1.0);
+const POWER_FOOD_COLOR : Color = Color::srgb(1.0, 0.95, 0.5);
+// Bombs and power food sit with the food, and blasts and the chaser go over the top of the
+// snake so they can be seen.
+const BOMB_Z : f32 = 2.0;
+const BLAST_Z : f32 = 6.0;
+const CHASER_Z : f32 = 5.0;
+
+
+
+// Drops the bombs and sets them off, moves the chaser, and kills the snake if it's caught
+// in a blast or by the chaser.
+pub struct HazardsPlugin;
+impl Plugin for HazardsPlugin {
+	fn build(&self, app : &mut App) {
+		app.init_resource::<Fright>()
+			.init_resource::<NextLaser>()
+			.add_systems(OnEnter(GameState::Playing), (reset_hazards_sys, spawn_chaser_sys.run_if(hazard_on(Hazards::Chaser)))
+				.chain()
+				.after(crate::build_arena_sys))
+			// The chaser moves after the snake, so the two can't pass through each other.
+			.add_systems(FixedUpdate, (tick_bombs_sys, tick_lasers_sys, blast_collision_sys, eat_power_food_sys, chase_sys, chaser_collision_sys)
+				.chain()
+				.in_set(TickSet::Collide)
+				.after(crate::snake_collision_sys)
+				.run_if(in_state(GameState::Playing).and(crate::countdown_done)))
+			.add_systems(FixedUpdate, drop_bomb_sys
+				.in_set(TickSet::Spawn)
+				.run_if(in_state(GameState::Playing).and(crate::countdown_done).and(hazard_on(Hazards::Bombs))))
+			.add_systems(FixedUpdate, drop_power_food_sys
+				.in_set(TickSet::Spawn)
+				.run_if(in_state(GameState::Playing).and(crate::countdown_done).and(hazard_on(Hazards::Chaser))))
+			.add_systems(FixedUpdate, schedule_laser_sys
+				.in_set(TickSet::Spawn)
+				.run_if(in_state(GameState::Playing).and(crate::countdown_done).and(hazard_on(Hazards::Lasers))))
+			.add_systems(Update, flash_blast_sys.run_if(in_state(GameState::Playing)))
+			.add_systems(OnExit(GameState::Playing), clear_hazards_sys);
+	}
+}
+
+
+
+// The hazards that can be picked on the menu.
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub enum Hazards {
+	#[default]
+	Off,
+	Bombs,
+	Chaser,
+	Lasers,
+}
+impl Hazards {
+	// The hazards after these, for flipping through them on the menu.
+	pub fn next(self) -> Self {
+		match self {
+			Hazards::Off => Hazards::Bombs,
+			Hazards::Bombs => Hazards::Chaser,
+			Hazards::Chaser => Hazards::Lasers,
+			Hazards::Lasers => Hazards::Off,
+		}
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Hazards::Off => "Off",
+			Hazards::Bombs => "Bombs",
+			Hazards::Chaser => "Chaser",
+			Hazards::Lasers => "Lasers",
+		}
+	}
+
+	pub fn from_name(name : &str) -> Option<Self> {
+		match name {
+			"off" => Some(Hazards::Off),
+			"bombs" => Some(Hazards::Bombs),
+			"chaser" => Some(Hazards::Chaser),
+			"lasers" => Some(Hazards::Lasers),
+			_ => None,
+		}
+	}
+
+	pub fn to_name(self) -> &'static str {
+		match self {
+			Hazards::Off => "off",
+			Hazards::Bombs => "bombs",
+			Hazards::Chaser => "chaser",
+			Hazards::Lasers => "lasers",
+		}
+	}
+}
+
+// A bomb, and how many more ticks until it goes off.
+#[derive(Component)]
+struct Bomb {
+	fuse : u32,
+}
+
+// The number on a bomb.
+#[derive(Component)]
+struct FuseText;
+
+// One cell of a blast, how many more ticks it's deadly for, and what it counts as when it
+// kills the snake. Lasers are blasts too, once they fire.
+#[derive(Component)]
+struct Blast {
+	ticks_left : u32,
+	cause : DeathCause,
+}
+
+// One cell of a laser that's about to fire, and how many more ticks until it does.
+#[derive(Component)]
+struct LaserWarning {
+	ticks_left : u32,
+}
+
+// How many more ticks until the next laser.
+#[derive(Resource, Default)]
+struct NextLaser(u32);
+
+// The enemy that comes after the snake. It only moves on every other tick, so it can be
+// outrun.
+#[derive(Component)]
+struct Chaser {
+	home : GridPosition,
+	moved_last_tick : bool,
+}
+
+#[derive(Component)]
+struct PowerFood;
+
+// How many more ticks the chaser's scared for.
+#[derive(Resource, Default)]
+struct Fright(u32);
+
+// Everything that's needed to kill the snake off and give the player a new one.
+#[derive(SystemParam)]
+struct SnakeKiller<'w> {
+	snake : ResMut<'w, SnakeState>,
+	occupancy : ResMut<'w, OccupancyGrid>,
+	death : DeathRules<'w>,
+}
+impl SnakeKiller<'_> {
+	// A fresh snake waiting at the start gets to set off first, and one that's come back
+	// after losing a life gets a moment after that, or a hazard sitting on the start could
+	// take one life after another.
+	fn killable(&self) -> bool {
+		self.snake.dir != Direction::None && !self.death.invincible()
+	}
+
+	fn kill(&mut self, commands : &mut Commands, head : Entity, cause : DeathCause) {
+		let length = self.snake.segments.len() + 1;
+		death::leave_corpse(commands, head, &self.snake.segments);
+		self.snake.dir = Direction::None;
+		self.snake.next_dir = Direction::None;
+		self.snake.segments.clear();
+		self.snake.grow = 0;
+		self.occupancy.clear_segments();
+
+		spawn_snake(commands, self.death.map.start());
+		self.death.snake_died(&mut self.snake, length, cause);
+	}
+}
+
+
+
+fn hazard_on(hazard : Hazards) -> impl Fn(Res<ArenaMap>, Res<GameMode>) -> bool {
+	move |map : Res<ArenaMap>, mode : Res<GameMode>| map.hazards == hazard && *mode != GameMode::Zen
+}
+
+
+
+// Every so often drops a bomb on an empty cell that's well away from the head.
+fn drop_bomb_sys(
+	mut commands : Commands,
+	mut rng : ResMut<GameRng>,
+	occupancy : Res<OccupancyGrid>,
+	settings : Res<Settings>,
+	head : Query<&GridPosition, With<SnakeHead>>,
+	food : Query<&GridPosition, With<Food>>,
+	bombs : Query<&GridPosition, With<Bomb>>,
+) {
+	if bombs.iter().count() >= MAX_BOMBS || !rng.rng.gen_bool(BOMB_CHANCE) {
+		return;
+	}
+	let Ok(&head) = head.single() else {
+		return;
+	};
+	// A crowded board might not have anywhere for it, and then it just doesn't get dropped.
+	for _ in 0..20 {
+		let pos = get_random_pos(&mut rng.rng, &DangerZone::default(), occupancy.width, occupancy.height);
+		let near_head = (pos.x - head.x).abs() < BOMB_CLEARANCE && (pos.y - head.y).abs() < BOMB_CLEARANCE;
+		if !occupancy.contains(pos) || occupancy.is_blocked(pos) || near_head || food.iter().chain(bombs.iter()).any(|&other| other == pos) {
+			continue;
+		}
+		commands.spawn((
+			Bomb { fuse : FUSE_TICKS },
+			pos,
+			Sprite {
+				color : BOMB_COLOR,
+				custom_size : Some(Vec2::splat(crate::CELL_SIZE * 0.7)),
+				..default()
+			},
+			Transform::from_xyz(0.0, 0.0, BOMB_Z),
+		))
+		.with_child((
+			FuseText,
+			Text2d::new(FUSE_TICKS.to_string()),
+			TextFont { font_size : 18.0, ..default() },
+			TextColor(Color::WHITE),
+			Transform::from_xyz(0.0, 0.0, 0.1).with_rotation(settings.board_rotation()),
+		));
+		return;
+	}
+}
+
+
+
+// Burns down every fuse by a tick, and sets off the bombs that have run out. The blasts
+// from before burn down too, and go once they're done.
+fn tick_bombs_sys(
+	mut commands : Commands,
+	occupancy : Res<OccupancyGrid>,
+	mut bombs : Query<(Entity, &GridPosition, &mut Bomb, &Children)>,
+	mut fuse_texts : Query<&mut Text2d, With<FuseText>>,
+	mut blasts : Query<(Entity, &mut Blast)>,
+) {
+	for (e, mut blast) in blasts.iter_mut() {
+		blast.ticks_left -= 1;
+		if blast.ticks_left == 0 {
+			commands.entity(e).despawn();
+		}
+	}
+
+	for (e, &pos, mut bomb, children) in bombs.iter_mut() {
+		bomb.fuse -= 1;
+		if bomb.fuse > 0 {
+			for &child in children {
+				if let Ok(mut text) = fuse_texts.get_mut(child) {
+					text.0 = bomb.fuse.to_string();
+				}
+			}
+			continue;
+		}
+		commands.entity(e).despawn();
+		for dx in -BLAST_RADIUS..=BLAST_RADIUS {
+			for dy in -BLAST_RADIUS..=BLAST_RADIUS {
+				let cell = GridPosition { x : pos.x + dx, y : pos.y + dy };
+				if occupancy.contains(cell) {
+					spawn_blast(&mut commands, cell, Blast { ticks_left : BLAST_TICKS, cause : DeathCause::Explosion }, BLAST_COLOR);
+				}
+			}
+		}
+	}
+}
+
+fn spawn_blast(commands : &mut Commands, pos : GridPosition, blast : Blast, color : Color) {
+	commands.spawn((
+		blast,
+		pos,
+		Sprite {
+			color,
+			custom_size : Some(Vec2::splat(crate::CELL_SIZE)),
+			..default()
+		},
+		Transform::from_xyz(0.0, 0.0, BLAST_Z),
+	));
+}
+
+
+
+fn reset_hazards_sys(mut fright : ResMut<Fright>, mut next_laser : ResMut<NextLaser>) {
+	fright.0 = 0;
+	next_laser.0 = LASER_PERIOD;
+}
+
+
+
+// Counts down to the next laser, and when it's time, picks a row or a column for it and
+// lights up the warning along it.
+fn schedule_laser_sys(
+	mut commands : Commands,
+	mut rng : ResMut<GameRng>,
+	mut next_laser : ResMut<NextLaser>,
+	occupancy : Res<OccupancyGrid>,
+) {
+	next_laser.0 = next_laser.0.saturating_sub(1);
+	if next_laser.0 > 0 {
+		return;
+	}
+	next_laser.0 = LASER_PERIOD + rng.rng.gen_range(0..=LASER_JITTER);
+
+	let cells : Vec<GridPosition> = if rng.rng.gen_bool(0.5) {
+		let y = rng.rng.gen_range(0..occupancy.height);
+		(0..occupancy.width).map(|x| GridPosition { x, y }).collect()
+	} else {
+		let x = rng.rng.gen_range(0..occupancy.width);
+		(0..occupancy.height).map(|y| GridPosition { x, y }).collect()
+	};
+	for pos in cells.into_iter().filter(|&pos| occupancy.contains(pos)) {
+		commands.spawn((
+			LaserWarning { ticks_left : LASER_WARNING_TICKS },
+			pos,
+			Sprite {
+				color : LASER_WARNING_COLOR,
+				custom_size : Some(Vec2::splat(crate::CELL_SIZE)),
+				..default()
+			},
+			Transform::from_xyz(0.0, 0.0, BLAST_Z),
+		));
+	}
+}
+
+
+
+// Burns down the warnings, and fires the lasers that have run out of them, for a tick.
+fn tick_lasers_sys(mut commands : Commands, mut warnings : Query<(Entity, &GridPosition, &mut LaserWarning)>) {
+	for (e, &pos, mut warning) in warnings.iter_mut() {
+		warning.ticks_left -= 1;
+		if warning.ticks_left == 0 {
+			commands.entity(e).despawn();
+			spawn_blast(&mut commands, pos, Blast { ticks_left : 1, cause : DeathCause::Laser }, LASER_COLOR);
+		}
+	}
+}
+
+
+
+// Kills the snake if any bit of it is in a blast or a laser. The snake's cells are all in
+// the occupancy grid, so there's no need to go through the segments.
+fn blast_collision_sys(
+	mut commands : Commands,
+	mut killer : SnakeKiller,
+	head : Query<(Entity, &GridPosition), With<SnakeHead>>,
+	blasts : Query<(&GridPosition, &Blast)>,
+) {
+	let Ok((head_entity, &head_pos)) = head.single() else {
+		return;
+	};
+	if !killer.killable() {
+		return;
+	}
+	let hit = blasts.iter().find(|&(&pos, _)| pos == head_pos || killer.occupancy.has_segment(pos));
+	if let Some((_, blast)) = hit {
+		killer.kill(&mut commands, head_entity, blast.cause);
+	}
+}
+
+
+
+// Puts the chaser in the corner of the arena that's furthest from where the snake starts.
+fn spawn_chaser_sys(mut commands : Commands, map : Res<ArenaMap>) {
+	let start = map.start();
+	let distance = |pos : &GridPosition| (pos.x - start.x).abs() + (pos.y - start.y).abs();
+	let home = arena_cells(map.arena, map.shape)
+		.filter(|pos| !map.obstacles.contains(pos))
+		.max_by_key(distance)
+		.unwrap_or(start);
+	commands.spawn((
+		Chaser { home, moved_last_tick : false },
+		home,
+		Sprite {
+			color : CHASER_COLOR,
+			custom_size : Some(Vec2::splat(crate::CELL_SIZE * 0.8)),
+			..default()
+		},
+		Transform::from_xyz(0.0, 0.0, CHASER_Z),
+	));
+}
+
+
+
+// Every so often puts a power food down somewhere empty, as long as the chaser isn't
+// already scared.
+fn drop_power_food_sys(
+	mut commands : Commands,
+	mut rng : ResMut<GameRng>,
+	occupancy : Res<OccupancyGrid>,
+	fright : Res<Fright>,
+	food : Query<&GridPosition, With<Food>>,
+	power : Query<(), With<PowerFood>>,
+) {
+	if fright.0 > 0 || !power.is_empty() || !rng.rng.gen_bool(POWER_FOOD_CHANCE) {
+		return;
+	}
+	for _ in 0..20 {
+		let pos = get_random_pos(&mut rng.rng, &DangerZone::default(), occupancy.width, occupancy.height);
+		if !occupancy.contains(pos) || occupancy.is_blocked(pos) || food.iter().any(|&other| other == pos) {
+			continue;
+		}
+		commands.spawn((
+			PowerFood,
+			pos,
+			Sprite {
+				color : POWER_FOOD_COLOR,
+				custom_size : Some(Vec2::splat(crate::CELL_SIZE * 0.5)),
+				..default()
+			},
+			Transform::from_xyz(0.0, 0.0, BOMB_Z),
+		));
+		return;
+	}
+}
+
+
+
+fn eat_power_food_sys(
+	mut commands : Commands,
+	mut fright : ResMut<Fright>,
+	head : Query<&GridPosition, With<SnakeHead>>,
+	power : Query<(Entity, &GridPosition), With<PowerFood>>,
+) {
+	let Ok(&head) = head.single() else {
+		return;
+	};
+	for (e, &pos) in &power {
+		if pos == head {
+			commands.entity(e).despawn();
+			fright.0 = FRIGHT_TICKS;
+		}
+	}
+}
+
+
+
+// Moves the chaser a cell towards the head, or away from it while it's scared, on every
+// other tick. If the head's already run into it, it stays put so that gets noticed.
+fn chase_sys(
+	occupancy : Res<OccupancyGrid>,
+	mut fright : ResMut<Fright>,
+	mut died : MessageReader<SnakeDied>,
+	head : Query<&GridPosition, With<SnakeHead>>,
+	mut chasers : Query<(&mut GridPosition, &mut Chaser, &mut Sprite), Without<SnakeHead>>,
+) {
+	fright.0 = fright.0.saturating_sub(1);
+	let snake_died = died.read().count() > 0;
+	let Ok(&head) = head.single() else {
+		return;
+	};
+	for (mut pos, mut chaser, mut sprite) in chasers.iter_mut() {
+		sprite.color = if fright.0 > 0 { FRIGHTENED_COLOR } else { CHASER_COLOR };
+		if snake_died {
+			*pos = chaser.home;
+			continue;
+		}
+		chaser.moved_last_tick = !chaser.moved_last_tick;
+		if !chaser.moved_last_tick || *pos == head {
+			continue;
+		}
+		let next = if fright.0 > 0 {
+			flee_step(*pos, head, &occupancy)
+		} else {
+			find_path(*pos, head, &occupancy).and_then(|path| path.get(1).copied())
+		};
+		if let Some(next) = next {
+			*pos = next;
+		}
+	}
+}
+
+// The neighbouring cell that's furthest from the head, if there's one it can get to that's
+// further away than where it is now.
+fn flee_step(from : GridPosition, head : GridPosition, occupancy : &OccupancyGrid) -> Option<GridPosition> {
+	let distance = |pos : GridPosition| (pos.x - head.x).abs() + (pos.y - head.y).abs();
+	[Direction::Up, Direction::Down, Direction::Left, Direction::Right]
+		.into_iter()
+		.map(|dir| {
+			let (dx, dy) = dir.delta();
+			GridPosition { x : from.x + dx, y : from.y + dy }
+		})
+		.filter(|&pos| occupancy.contains(pos) && !occupancy.is_blocked(pos) && distance(pos) > distance(from))
+		.max_by_key(|&pos| distance(pos))
+}
+
+
+
+// Touching the chaser kills the snake, unless it's scared, and then the snake gets it
+// instead.
+fn chaser_collision_sys(
+	mut commands : Commands,
+	mut killer : SnakeKiller,
+	fright : Res<Fright>,
+	mut score : ResMut<Score>,
+	settings : Res<Settings>,
+	head : Query<(Entity, &GridPosition), With<SnakeHead>>,
+	mut chasers : Query<(&mut GridPosition, &Chaser), Without<SnakeHead>>,
+) {
+	let Ok((head_entity, &head_pos)) = head.single() else {
+		return;
+	};
+	for (mut pos, chaser) in chasers.iter_mut() {
+		if !killer.killable() || (*pos != head_pos && !killer.occupancy.has_segment(*pos)) {
+			continue;
+		}
+		if fright.0 > 0 {
+			score.0 += CHASER_POINTS;
+			crate::spawn_score_popup(&mut commands, &settings, *pos, format!("+{CHASER_POINTS}"));
+			*pos = chaser.home;
+		} else {
+			killer.kill(&mut commands, head_entity, DeathCause::Chaser);
+			return;
+		}
+	}
+}
+
+
+
+fn flash_blast_sys(time : Res<Time>, mut blasts : Query<&mut Visibility, With<Blast>>) {
+	let shown = ((time.elapsed_secs() * FLASH_RATE * 2.0) as u32).is_multiple_of(2);
+	for mut visibility in blasts.iter_mut() {
+		visibility.set_if_neq(if shown { Visibility::Inherited } else { Visibility::Hidden });
+	}
+}
+
+
+
+fn clear_hazards_sys(
+	mut commands : Commands,
+	bombs : Query<Entity, With<Bomb>>,
+	blasts : Query<Entity, With<Blast>>,
+	warnings : Query<Entity, With<LaserWarning>>,
+	chasers : Query<Entity, With<Chaser>>,
+	power : Query<Entity, With<PowerFood>>,
+) {
+	for e in bombs.iter().chain(&blasts).chain(&warnings).chain(&chasers).chain(&power) {
+		commands.entity(e).despawn();
+	}
+}
@@ -0,0 +1,168 @@
+// Running the game's rules without a window, one tick at a time, so the tests can play
+// a run and check what happened.
+//
+// This is the same RulesPlugin the game uses, on top of Bevy's minimal plugins. The
+// clock is wound on by hand, one tick's worth each update, so nothing depends on how fast
+// the machine is. Steering goes through the same system as the keyboard, so a reversal
+// gets turned down the same way it would in the game.
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+
+use crate::diagnostics::FrameStep;
+use crate::input::{self, PlayerAction};
+use crate::settings::Settings;
+use crate::{ArenaMap, Countdown, Food, GameMode, GameRng, GameState, Lives, GridPosition, Score, SnakeDied, SnakeHead, SnakeState, RulesPlugin};
+
+pub use crate::Direction;
+
+
+// How many updates a tick is allowed to take before it counts as stuck. The countdown
+// takes a few seconds' worth.
+const MAX_WAIT_UPDATES : u32 = 1000;
+
+
+
+// A run being played without a window.
+pub struct HeadlessGame {
+	app : App,
+}
+impl HeadlessGame {
+	// Starts a classic run on this seed, and waits out the countdown.
+	pub fn new(seed : u64) -> Self {
+		HeadlessGame::start(seed, GameMode::Classic)
+	}
+
+	// Starts a zen run, where the snake goes through the walls and comes out the other side.
+	pub fn wrapping(seed : u64) -> Self {
+		HeadlessGame::start(seed, GameMode::Zen)
+	}
+
+	fn start(seed : u64, mode : GameMode) -> Self {
+		let period = Settings::default().move_period();
+		let mut app = App::new();
+		app.add_plugins((MinimalPlugins, StatesPlugin))
+			.insert_resource(TimeUpdateStrategy::ManualDuration(period))
+			.insert_resource(Time::<Fixed>::from_duration(period))
+			// The snake and the food get meshes, even though nothing draws them.
+			.init_resource::<Assets<Mesh>>()
+			.init_resource::<Assets<ColorMaterial>>()
+			.add_plugins(RulesPlugin)
+			.insert_resource(mode)
+			.init_resource::<FrameStep>()
+			.insert_resource(SeedToPlay(seed))
+			.init_resource::<Deaths>()
+			.add_message::<PlayerAction>()
+			// Steering happens before the tick, so a turn counts on the very next one.
+			.add_systems(PreUpdate, input::steer_snake_sys)
+			.add_systems(OnEnter(GameState::Playing), reseed_sys
+				.after(crate::reseed_run_sys)
+				.before(crate::build_arena_sys))
+			.add_systems(Update, count_deaths_sys);
+		app.update();
+		app.world_mut().resource_mut::<NextState<GameState>>().set(GameState::Playing);
+		app.update();
+		let mut game = HeadlessGame { app };
+		game.wait_for_countdown();
+		game
+	}
+
+	// Points the snake this way, the same as an arrow key would.
+	pub fn steer(&mut self, dir : Direction) {
+		self.app.world_mut().write_message(PlayerAction::SteerBoard(dir));
+	}
+
+	// Plays one tick. After a death, the countdown gets waited out first.
+	pub fn tick(&mut self) {
+		self.wait_for_countdown();
+		self.app.update();
+	}
+
+	// Where the head is.
+	pub fn head(&mut self) -> (i32, i32) {
+		let world = self.app.world_mut();
+		let mut head = world.query_filtered::<&GridPosition, With<SnakeHead>>();
+		head.single(world).map(|pos| (pos.x, pos.y)).expect("there's always a head")
+	}
+
+	// Which way the snake went on the last tick.
+	pub fn facing(&self) -> Direction {
+		self.app.world().resource::<SnakeState>().dir
+	}
+
+	// Every cell the snake covers, head first.
+	pub fn body(&mut self) -> Vec<(i32, i32)> {
+		let mut cells = vec![self.head()];
+		let world = self.app.world_mut();
+		let segments = world.resource::<SnakeState>().segments.clone();
+		for seg in segments {
+			if let Some(pos) = world.get::<GridPosition>(seg) {
+				cells.push((pos.x, pos.y));
+			}
+		}
+		cells
+	}
+
+	// Where the food is.
+	pub fn food(&mut self) -> Vec<(i32, i32)> {
+		let world = self.app.world_mut();
+		let mut food = world.query_filtered::<&GridPosition, With<Food>>();
+		food.iter(world).map(|pos| (pos.x, pos.y)).collect()
+	}
+
+	pub fn score(&self) -> u32 {
+		self.app.world().resource::<Score>().0
+	}
+
+	// What the snake has died of this run, in order, like "wall" or "body".
+	pub fn deaths(&self) -> &[&'static str] {
+		&self.app.world().resource::<Deaths>().0
+	}
+
+	// How many cells wide and high the arena is.
+	pub fn size(&self) -> (i32, i32) {
+		let map = self.app.world().resource::<ArenaMap>();
+		(map.width(), map.height())
+	}
+
+	// Whether the run's over, with no lives left. The game only leaves the Playing state on
+	// the update after, so the lives are what's looked at.
+	pub fn is_over(&self) -> bool {
+		self.app.world().resource::<Lives>().0 == 0
+	}
+
+	// The score the run's replay gets when it's played back.
+	pub fn replayed_score(&self) -> u32 {
+		self.app.world().resource::<crate::replay::ReplayRecorder>().resimulated_score()
+	}
+
+	fn wait_for_countdown(&mut self) {
+		for _ in 0..MAX_WAIT_UPDATES {
+			if self.app.world().resource::<Countdown>().0 <= 0.0 {
+				return;
+			}
+			self.app.update();
+		}
+		panic!("the countdown never finished");
+	}
+}
+
+
+
+// The seed the headless run is played on, instead of a random one.
+#[derive(Resource)]
+struct SeedToPlay(u64);
+
+// What killed the snake each time it died.
+#[derive(Resource, Default)]
+struct Deaths(Vec<&'static str>);
+
+
+
+fn reseed_sys(seed : Res<SeedToPlay>, mut rng : ResMut<GameRng>) {
+	*rng = GameRng::from_seed(seed.0);
+}
+
+fn count_deaths_sys(mut died : MessageReader<SnakeDied>, mut deaths : ResMut<Deaths>) {
+	deaths.0.extend(died.read().map(|died| died.cause.to_name()));
+}
@@ -0,0 +1,169 @@
+// A log of every finished run, for anyone who wants to dig into how they've been playing.
+//
+// Each run gets added onto the end of the log as one line of space separated fields, and
+// nothing already in it ever gets changed. The log can be exported from the stats screen
+// as JSON or CSV, into the exports folder, to be opened in something else.
+use bevy::prelude::*;
+use web_time::{SystemTime, UNIX_EPOCH}; // The standard library's clock doesn't work in the browser.
+
+use crate::stats::{DeathCause, RunStats};
+use crate::storage;
+use crate::{GameMode, GameRng, GameState, Score};
+
+
+// Where the log gets kept.
+pub const HISTORY_FILE : &str = "history.txt";
+// Where the exports go.
+const EXPORT_DIR : &str = "exports";
+
+
+
+// Logs runs as they finish, and exports the log from the stats screen.
+pub struct HistoryPlugin;
+impl Plugin for HistoryPlugin {
+	fn build(&self, app : &mut App) {
+		app.add_systems(OnEnter(GameState::GameOver), log_run_sys)
+			.add_systems(Update, export_history_on_input.run_if(in_state(GameState::Stats)));
+	}
+}
+
+
+
+// The summary of one finished run.
+struct RunRecord {
+	// When it finished, in seconds since 1970.
+	finished_at : u64,
+	mode : GameMode,
+	seed : u64,
+	score : u32,
+	length : u32,
+	seconds : f32,
+	food_eaten : u32,
+	turns : u32,
+	cause : Option<DeathCause>,
+}
+impl RunRecord {
+	const FIELDS : [&'static str; 9] = ["finished_at", "mode", "seed", "score", "length", "seconds", "food_eaten", "turns", "cause"];
+
+	// The fields as text, in the same order as FIELDS. Runs that didn't end in a death,
+	// like time attack, have a cause of "none".
+	fn values(&self) -> [String; 9] {
+		[
+			self.finished_at.to_string(),
+			self.mode.to_name().to_string(),
+			self.seed.to_string(),
+			self.score.to_string(),
+			self.length.to_string(),
+			format!("{:.1}", self.seconds),
+			self.food_eaten.to_string(),
+			self.turns.to_string(),
+			self.cause.map_or("none", DeathCause::to_name).to_string(),
+		]
+	}
+
+	fn to_line(&self) -> String {
+		self.values().join(" ") + "\n"
+	}
+
+	fn from_line(line : &str) -> Option<Self> {
+		let values : Vec<&str> = line.split_whitespace().collect();
+		let [finished_at, mode, seed, score, length, seconds, food_eaten, turns, cause] = values[..] else {
+			return None;
+		};
+		Some(RunRecord {
+			finished_at : finished_at.parse().ok()?,
+			mode : GameMode::from_name(mode)?,
+			seed : seed.parse().ok()?,
+			score : score.parse().ok()?,
+			length : length.parse().ok()?,
+			seconds : seconds.parse().ok()?,
+			food_eaten : food_eaten.parse().ok()?,
+			turns : turns.parse().ok()?,
+			cause : DeathCause::from_name(cause),
+		})
+	}
+
+	// All the fields are numbers or plain words, so nothing needs escaping. The mode and
+	// the cause are the only ones that are strings.
+	fn to_json(&self) -> String {
+		let fields : Vec<String> = Self::FIELDS
+			.iter()
+			.zip(self.values())
+			.map(|(&name, value)| match name {
+				"mode" => format!("\"{name}\": \"{value}\""),
+				"cause" if self.cause.is_none() => format!("\"{name}\": null"),
+				"cause" => format!("\"{name}\": \"{value}\""),
+				_ => format!("\"{name}\": {value}"),
+			})
+			.collect();
+		format!("{{{}}}", fields.join(", "))
+	}
+}
+
+
+
+// Every run in the log. Lines that can't be read, like the end of one that was cut off,
+// get skipped.
+fn load_history() -> Vec<RunRecord> {
+	storage::read(HISTORY_FILE)
+		.unwrap_or_default()
+		.lines()
+		.filter_map(RunRecord::from_line)
+		.collect()
+}
+
+fn to_json(runs : &[RunRecord]) -> String {
+	let runs : Vec<String> = runs.iter().map(|run| format!("  {}", run.to_json())).collect();
+	format!("[\n{}\n]\n", runs.join(",\n"))
+}
+
+fn to_csv(runs : &[RunRecord]) -> String {
+	let mut csv = RunRecord::FIELDS.join(",") + "\n";
+	for run in runs {
+		csv.push_str(&(run.values().join(",") + "\n"));
+	}
+	csv
+}
+
+
+
+// Adds the run that just ended onto the log.
+fn log_run_sys(mode : Res<GameMode>, rng : Res<GameRng>, score : Res<Score>, run : Res<RunStats>) {
+	let record = RunRecord {
+		finished_at : SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+		mode : *mode,
+		seed : rng.seed,
+		score : score.0,
+		length : run.length,
+		seconds : run.seconds,
+		food_eaten : run.food_eaten,
+		turns : run.turns,
+		cause : run.cause,
+	};
+	if let Err(err) = storage::append(HISTORY_FILE, &record.to_line()) {
+		warn!("Couldn't log the run: {err}");
+	}
+}
+
+
+
+// J exports the log as JSON, and C as CSV.
+fn export_history_on_input(mut commands : Commands, keys : Res<ButtonInput<KeyCode>>) {
+	let (extension, format) : (&str, fn(&[RunRecord]) -> String) = if keys.just_pressed(KeyCode::KeyJ) {
+		("json", to_json)
+	} else if keys.just_pressed(KeyCode::KeyC) {
+		("csv", to_csv)
+	} else {
+		return;
+	};
+	let runs = load_history();
+	let stamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+	let path = format!("{EXPORT_DIR}/runs-{stamp}.{extension}");
+	match storage::write(&path, &format(&runs)) {
+		Ok(()) => crate::spawn_toast(&mut commands, format!("Exported {} runs to {path}", runs.len())),
+		Err(err) => {
+			warn!("Couldn't export the run history: {err}");
+			crate::spawn_toast(&mut commands, "Couldn't export the run history");
+		}
+	}
+}
@@ -0,0 +1,323 @@
+// The player's controls. The keyboard, swipes on a touch screen, and the on-screen D-pad
+// all turn into the same few actions, so the rest of the game doesn't care where they
+// came from.
+//
+// A swipe steers as soon as the finger has gone far enough, without waiting for it to
+// lift. A tap that doesn't go anywhere pauses the run, or carries on if it's paused. The
+// D-pad sits in the bottom left corner when it's switched on in the settings, and
+// touching it only ever steers.
+//
+// With the mouse picked in the settings, the snake turns towards the cursor. It never
+// turns straight back on itself, so with the cursor behind it, it turns whichever way
+// the cursor is off to the side.
+//
+// A run with mirrored controls, from the menu or the mutator, swaps left and right on the
+// screen, before the board's turned. The mouse just follows the cursor, so it isn't swapped.
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::diagnostics::{self, FrameStep};
+use crate::mutators::Mutator;
+use crate::settings::{Controls, Settings};
+use crate::{ArenaMap, Direction, GameState, GridPosition, Mirror, SnakeHead, SnakeState, CELL_SIZE, MARGIN, WHITE};
+
+
+// How far a finger has to move before it counts as a swipe, in pixels.
+const SWIPE_PIXELS : f32 = 30.0;
+// How big the D-pad is on the screen, in pixels.
+const DPAD_SIZE : f32 = 180.0;
+const DPAD_COLOR : Color = Color::srgba(1.0, 1.0, 1.0, 0.15);
+
+
+
+// Turns the keyboard and touches into actions, and carries them out.
+pub struct InputPlugin;
+impl Plugin for InputPlugin {
+	fn build(&self, app : &mut App) {
+		app.add_message::<PlayerAction>()
+			.init_resource::<TouchTracker>()
+			.add_systems(Update, (
+					(keyboard_actions_sys, touch_actions_sys, mouse_actions_sys.run_if(mouse_steering.and(in_state(GameState::Playing)))),
+					steer_snake_sys,
+					pause_sys.run_if(in_state(GameState::Playing).and(not(diagnostics::frame_stepping))),
+				).chain())
+			.add_systems(OnEnter(GameState::Playing), spawn_dpad_sys)
+			.add_systems(OnExit(GameState::Playing), (cleanup_dpad_sys, unpause_sys));
+	}
+}
+
+
+
+// Something the player wants the snake or the game to do.
+#[derive(Message, Clone, Copy)]
+pub enum PlayerAction {
+	// Go this way on the screen. It gets turned to match the board if it's rotated.
+	Steer(Direction),
+	// Go this way on the board, however it's turned on the screen. For the remote control
+	// and the mouse, which see the board rather than the screen.
+	SteerBoard(Direction),
+	Pause,
+}
+
+// The touches that have already done something, so lifting them isn't taken as a tap.
+#[derive(Resource, Default)]
+struct TouchTracker {
+	used : Vec<u64>,
+}
+
+// The D-pad, so it can be cleaned up.
+#[derive(Component)]
+struct DpadUI;
+
+// The text that shows while the run is paused.
+#[derive(Component)]
+struct PausedUI;
+
+
+
+// The arrow keys or WASD, and P to pause.
+fn keyboard_actions_sys(
+	keyboard_input : Res<ButtonInput<KeyCode>>,
+	settings : Res<Settings>,
+	mut actions : MessageWriter<PlayerAction>,
+) {
+	if keyboard_input.just_pressed(KeyCode::KeyP) {
+		actions.write(PlayerAction::Pause);
+	}
+	let [up, down, left, right] = settings.controls.keys();
+	let pressed = if keyboard_input.pressed(up) {
+		Direction::Up
+	}
+	else if keyboard_input.pressed(down) {
+		Direction::Down
+	}
+	else if keyboard_input.pressed(left) {
+		Direction::Left
+	}
+	else if keyboard_input.pressed(right) {
+		Direction::Right
+	}
+	else {
+		return;
+	};
+	actions.write(PlayerAction::Steer(pressed));
+}
+
+
+
+// Swipes, taps, and the D-pad.
+fn touch_actions_sys(
+	touches : Res<Touches>,
+	settings : Res<Settings>,
+	window : Query<&Window, With<PrimaryWindow>>,
+	mut tracker : ResMut<TouchTracker>,
+	mut actions : MessageWriter<PlayerAction>,
+) {
+	if settings.dpad && let Ok(window) = window.single() {
+		for touch in touches.iter_just_pressed() {
+			if let Some(dir) = dpad_direction(window, touch.position()) {
+				actions.write(PlayerAction::Steer(dir));
+				tracker.used.push(touch.id());
+			}
+		}
+	}
+	for touch in touches.iter() {
+		if !tracker.used.contains(&touch.id()) && touch.distance().length() >= SWIPE_PIXELS {
+			actions.write(PlayerAction::Steer(screen_direction(touch.distance())));
+			tracker.used.push(touch.id());
+		}
+	}
+	for touch in touches.iter_just_released() {
+		if !tracker.used.contains(&touch.id()) {
+			actions.write(PlayerAction::Pause);
+		}
+	}
+	for touch in touches.iter_just_released().chain(touches.iter_just_canceled()) {
+		tracker.used.retain(|&id| id != touch.id());
+	}
+}
+
+fn mouse_steering(settings : Res<Settings>) -> bool {
+	settings.controls == Controls::Mouse
+}
+
+// Steers towards the cursor, going by where it is on the board. Nothing happens while the
+// cursor's over the head, or off the window.
+fn mouse_actions_sys(
+	snake : Res<SnakeState>,
+	window : Query<&Window, With<PrimaryWindow>>,
+	camera : Query<(&Camera, &GlobalTransform)>,
+	head : Query<&GridPosition, With<SnakeHead>>,
+	mut actions : MessageWriter<PlayerAction>,
+) {
+	let (Ok(window), Ok((camera, camera_transform)), Ok(&head)) = (window.single(), camera.single(), head.single()) else {
+		return;
+	};
+	let Some(cursor) = window.cursor_position().and_then(|pos| camera.viewport_to_world_2d(camera_transform, pos).ok()) else {
+		return;
+	};
+	let offset = cursor - crate::grid_to_world(head);
+	if offset.abs().max_element() < CELL_SIZE / 2.0 {
+		return;
+	}
+	let horizontal = if offset.x > 0.0 { Direction::Right } else { Direction::Left };
+	let vertical = if offset.y > 0.0 { Direction::Up } else { Direction::Down };
+	let (towards, aside) = if offset.x.abs() > offset.y.abs() { (horizontal, vertical) } else { (vertical, horizontal) };
+	let dir = if snake.dir.is_opposite(towards) { aside } else { towards };
+	actions.write(PlayerAction::SteerBoard(dir));
+}
+
+// Which way something is pointing on the screen. The screen's y goes down.
+fn screen_direction(offset : Vec2) -> Direction {
+	if offset.x.abs() > offset.y.abs() {
+		if offset.x > 0.0 { Direction::Right } else { Direction::Left }
+	} else if offset.y > 0.0 {
+		Direction::Down
+	} else {
+		Direction::Up
+	}
+}
+
+// Which arrow on the D-pad is under a point on the screen, if any.
+fn dpad_direction(window : &Window, pos : Vec2) -> Option<Direction> {
+	let center = Vec2::new(MARGIN as f32 + DPAD_SIZE / 2.0, window.height() - MARGIN as f32 - DPAD_SIZE / 2.0);
+	let offset = pos - center;
+	(offset.abs().max_element() <= DPAD_SIZE / 2.0).then(|| screen_direction(offset))
+}
+
+
+
+// Points the snake the way the player asked, unless that would turn it straight back on
+// itself. Nothing steers while the run is paused, unless it's only stopped for frame
+// stepping.
+pub fn steer_snake_sys(
+	settings : Res<Settings>,
+	map : Res<ArenaMap>,
+	time : Res<Time<Virtual>>,
+	step : Res<FrameStep>,
+	mut snake : ResMut<SnakeState>,
+	mut actions : MessageReader<PlayerAction>,
+) {
+	for action in actions.read() {
+		// On a rotated board, up on the screen is left on the board.
+		let pressed = match *action {
+			PlayerAction::Steer(pressed) => {
+				let mirrored = map.mirror == Mirror::Controls || map.mutators.has(Mutator::MirroredControls);
+				let pressed = if mirrored { pressed.mirrored() } else { pressed };
+				if settings.rotated { pressed.turned_anticlockwise() } else { pressed }
+			}
+			PlayerAction::SteerBoard(pressed) => pressed,
+			PlayerAction::Pause => continue,
+		};
+		if time.is_paused() && !step.0 {
+			continue;
+		}
+
+		// Before the snake has started moving, the last key pressed during the countdown is
+		// the one it sets off in.
+		if snake.dir == Direction::None || (!snake.dir.is_opposite(pressed) && snake.dir == snake.next_dir) {
+			snake.next_dir = pressed;
+		}
+	}
+}
+
+
+
+// Stops the clock, which stops the snake and every timer in the run, or starts it again.
+fn pause_sys(
+	mut commands : Commands,
+	mut time : ResMut<Time<Virtual>>,
+	mut actions : MessageReader<PlayerAction>,
+	paused_ui : Query<Entity, With<PausedUI>>,
+) {
+	let toggles = actions.read().filter(|action| matches!(action, PlayerAction::Pause)).count();
+	if toggles.is_multiple_of(2) {
+		return;
+	}
+	if time.is_paused() {
+		time.unpause();
+		for e in &paused_ui {
+			commands.entity(e).despawn();
+		}
+		return;
+	}
+	time.pause();
+	commands.spawn((
+		PausedUI,
+		Text::new("PAUSED\nTap or press P to carry on\n[ and ] change the speed"),
+		TextFont {
+			font_size : 48.0,
+			..default()
+		},
+		TextColor(WHITE),
+		TextLayout::new_with_justify(Justify::Center),
+		Node {
+			position_type : PositionType::Absolute,
+			top : Val::Percent(40.0),
+			width : Val::Percent(100.0),
+			..default()
+		},
+		BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+	));
+}
+
+// A run never stays paused once it's over.
+fn unpause_sys(mut commands : Commands, mut time : ResMut<Time<Virtual>>, paused_ui : Query<Entity, With<PausedUI>>) {
+	time.unpause();
+	for e in &paused_ui {
+		commands.entity(e).despawn();
+	}
+}
+
+
+
+fn spawn_dpad_sys(mut commands : Commands, settings : Res<Settings>) {
+	if !settings.dpad {
+		return;
+	}
+	commands.spawn((
+		DpadUI,
+		Node {
+			position_type : PositionType::Absolute,
+			left : Val::Px(MARGIN as f32),
+			bottom : Val::Px(MARGIN as f32),
+			width : Val::Px(DPAD_SIZE),
+			height : Val::Px(DPAD_SIZE),
+			..default()
+		},
+	))
+	.with_children(|parent| {
+		// The arrows, in a plus shape. Each one takes up a third of the pad each way.
+		for (label, column, row) in [("^", 1.0, 0.0), ("<", 0.0, 1.0), (">", 2.0, 1.0), ("v", 1.0, 2.0)] {
+			parent.spawn((
+				Node {
+					position_type : PositionType::Absolute,
+					left : Val::Percent(column * 100.0 / 3.0),
+					top : Val::Percent(row * 100.0 / 3.0),
+					width : Val::Percent(100.0 / 3.0),
+					height : Val::Percent(100.0 / 3.0),
+					justify_content : JustifyContent::Center,
+					align_items : AlignItems::Center,
+					..default()
+				},
+				BackgroundColor(DPAD_COLOR),
+			))
+			.with_child((
+				Text::new(label),
+				TextFont {
+					font_size : 32.0,
+					..default()
+				},
+				TextColor(WHITE),
+			));
+		}
+	});
+}
+
+
+
+fn cleanup_dpad_sys(mut commands : Commands, ui : Query<Entity, With<DpadUI>>) {
+	for e in &ui {
+		commands.entity(e).despawn();
+	}
+}
@@ -0,0 +1,259 @@
+// The local leaderboard, with the best scores in each mode and the names that got them.
+//
+// When a run makes it onto the board, the game over screen asks for a name before it can
+// be restarted. Otherwise it just shows the top of the board for the mode. Tron is scored
+// by time, zen can't be lost, and hotseat is shared, so none of them have a board. Each set
+// of mutators gets its own board in every mode, so runs are only up against others played
+// by the same rules. The file is plain text with one "mode score name" line per entry, and
+// any mutators go on the end of the mode with a + in front of each, like "classic+fog".
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+
+use crate::mutators::Mutators;
+use crate::storage;
+use crate::{ArenaMap, GameMode, GameOverUI, GameState, Score, WHITE};
+
+
+// Where the leaderboard gets saved.
+pub const LEADERBOARD_FILE : &str = "leaderboard.txt";
+// How many entries each mode keeps, and how many of them show on the game over screen.
+const BOARD_SIZE : usize = 10;
+const SHOWN_ENTRIES : usize = 5;
+const MAX_NAME_LENGTH : usize = 12;
+// What an entry gets called if no name is typed in.
+const DEFAULT_NAME : &str = "Player";
+
+
+
+// Shows the leaderboard on the game over screen, and takes names for it.
+pub struct LeaderboardPlugin;
+impl Plugin for LeaderboardPlugin {
+	fn build(&self, app : &mut App) {
+		app.insert_resource(Leaderboard::load())
+			.init_resource::<NameEntry>()
+			.add_systems(OnEnter(GameState::GameOver), spawn_leaderboard_ui.after(crate::spawn_game_over_ui))
+			.add_systems(Update, (type_name_sys, update_leaderboard_ui_sys)
+				.chain()
+				.run_if(in_state(GameState::GameOver)))
+			.add_systems(OnExit(GameState::GameOver), stop_typing_sys);
+	}
+}
+
+
+
+struct Entry {
+	mode : GameMode,
+	mutators : Mutators,
+	score : u32,
+	name : String,
+}
+
+// Every board's entries, with the best first.
+#[derive(Resource, Default)]
+pub struct Leaderboard {
+	entries : Vec<Entry>,
+}
+impl Leaderboard {
+	// Reads the leaderboard file, or starts out empty if there isn't one yet.
+	fn load() -> Self {
+		match storage::read(LEADERBOARD_FILE) {
+			Some(text) => Leaderboard::from_text(&text),
+			None => Leaderboard::default(),
+		}
+	}
+
+	// The board for a mode with a set of mutators.
+	fn top(&self, mode : GameMode, mutators : Mutators) -> impl Iterator<Item = &Entry> {
+		self.entries.iter().filter(move |entry| entry.mode == mode && entry.mutators == mutators)
+	}
+
+	// Whether a score beats everything on its board.
+	pub fn is_high_score(&self, mode : GameMode, mutators : Mutators, score : u32) -> bool {
+		has_board(mode) && score > 0 && self.top(mode, mutators).next().is_none_or(|best| score > best.score)
+	}
+
+	// Whether a score is good enough to go on its board.
+	fn makes_board(&self, mode : GameMode, mutators : Mutators, score : u32) -> bool {
+		let top = || self.top(mode, mutators);
+		has_board(mode) && score > 0 && (top().count() < BOARD_SIZE || top().last().is_some_and(|worst| score > worst.score))
+	}
+
+	// Puts a score on the board, under any that are just as good, and drops whatever falls
+	// off the bottom.
+	fn add(&mut self, mode : GameMode, mutators : Mutators, score : u32, name : String) {
+		let at = self.entries.iter().position(|entry| entry.score < score).unwrap_or(self.entries.len());
+		self.entries.insert(at, Entry { mode, mutators, score, name });
+		let mut kept = 0;
+		self.entries.retain(|entry| {
+			if entry.mode != mode || entry.mutators != mutators {
+				return true;
+			}
+			kept += 1;
+			kept <= BOARD_SIZE
+		});
+	}
+
+	fn to_text(&self) -> String {
+		self.entries
+			.iter()
+			.map(|entry| {
+				let board = std::iter::once(entry.mode.to_name()).chain(entry.mutators.to_names()).collect::<Vec<_>>().join("+");
+				format!("{board} {} {}\n", entry.score, entry.name)
+			})
+			.collect()
+	}
+
+	fn from_text(text : &str) -> Self {
+		let mut board = Leaderboard::default();
+		for line in text.lines() {
+			let mut parts = line.trim().splitn(3, ' ');
+			let (Some(mode), Some(score), Some(name)) = (parts.next(), parts.next(), parts.next()) else {
+				continue;
+			};
+			let mut mode = mode.split('+');
+			let (mode, mutators) = (mode.next().and_then(GameMode::from_name), Mutators::from_names(mode));
+			if let (Some(mode), Some(mutators), Ok(score)) = (mode, mutators, score.parse()) {
+				board.add(mode, mutators, score, name.to_string());
+			}
+		}
+		board
+	}
+}
+
+fn has_board(mode : GameMode) -> bool {
+	!matches!(mode, GameMode::Tron | GameMode::Zen | GameMode::Hotseat)
+}
+
+
+
+// The name being typed in for the leaderboard.
+#[derive(Resource, Default)]
+pub struct NameEntry {
+	typing : bool,
+	name : String,
+}
+
+// The leaderboard on the game over screen, so it can be updated.
+#[derive(Component)]
+struct LeaderboardText;
+
+
+
+// Whether a name is being typed in, so the keys don't do anything else.
+pub fn typing_name(entry : Res<NameEntry>) -> bool {
+	entry.typing
+}
+
+
+
+fn spawn_leaderboard_ui(
+	mut commands : Commands,
+	mode : Res<GameMode>,
+	map : Res<ArenaMap>,
+	score : Res<Score>,
+	board : Res<Leaderboard>,
+	mut entry : ResMut<NameEntry>,
+	game_over : Query<Entity, With<GameOverUI>>,
+) {
+	if !has_board(*mode) {
+		return;
+	}
+	entry.typing = board.makes_board(*mode, map.mutators, score.0);
+	entry.name.clear();
+	for root in &game_over {
+		commands.entity(root).with_child((
+			LeaderboardText,
+			Text::new(leaderboard_text(*mode, map.mutators, &board, &entry)),
+			TextFont {
+				font_size : 28.0,
+				..default()
+			},
+			TextColor(WHITE),
+			TextLayout::new(Justify::Center, LineBreak::WordBoundary),
+		));
+	}
+}
+
+
+
+// Letters and numbers go on the end of the name, backspace takes them off, and enter puts
+// it on the board.
+fn type_name_sys(
+	mut keys : MessageReader<KeyboardInput>,
+	mode : Res<GameMode>,
+	map : Res<ArenaMap>,
+	score : Res<Score>,
+	mut board : ResMut<Leaderboard>,
+	mut entry : ResMut<NameEntry>,
+) {
+	if !entry.typing {
+		keys.clear();
+		return;
+	}
+	for key in keys.read() {
+		if !key.state.is_pressed() {
+			continue;
+		}
+		match &key.logical_key {
+			Key::Backspace => {
+				entry.name.pop();
+			}
+			Key::Enter => {
+				let name = if entry.name.is_empty() { DEFAULT_NAME.to_string() } else { entry.name.clone() };
+				board.add(*mode, map.mutators, score.0, name);
+				if let Err(err) = storage::write(LEADERBOARD_FILE, &board.to_text()) {
+					warn!("Couldn't save the leaderboard: {err}");
+				}
+				entry.typing = false;
+				return;
+			}
+			Key::Character(text) => {
+				for c in text.chars().filter(char::is_ascii_alphanumeric) {
+					if entry.name.len() < MAX_NAME_LENGTH {
+						entry.name.push(c);
+					}
+				}
+			}
+			_ => {}
+		}
+	}
+}
+
+
+
+fn update_leaderboard_ui_sys(
+	mode : Res<GameMode>,
+	map : Res<ArenaMap>,
+	board : Res<Leaderboard>,
+	entry : Res<NameEntry>,
+	mut text : Query<&mut Text, With<LeaderboardText>>,
+) {
+	if !board.is_changed() && !entry.is_changed() {
+		return;
+	}
+	for mut text in text.iter_mut() {
+		text.0 = leaderboard_text(*mode, map.mutators, &board, &entry);
+	}
+}
+
+
+
+fn stop_typing_sys(mut entry : ResMut<NameEntry>) {
+	entry.typing = false;
+}
+
+
+
+fn leaderboard_text(mode : GameMode, mutators : Mutators, board : &Leaderboard, entry : &NameEntry) -> String {
+	if entry.typing {
+		return format!("You made the leaderboard!\nName: {}_\nType your name and press ENTER", entry.name);
+	}
+	let mut text = format!("{} LEADERBOARD", mode.name().to_uppercase());
+	if !mutators.is_empty() {
+		text.push_str(&format!("\nWith {}", mutators.names()));
+	}
+	for (i, entry) in board.top(mode, mutators).take(SHOWN_ENTRIES).enumerate() {
+		text.push_str(&format!("\n{}. {} - {}", i + 1, entry.name, entry.score));
+	}
+	text
+}
@@ -0,0 +1,3862 @@
+use bevy::prelude::*; // Bevy
+use bevy::app::AppExit; // Used to close the app.
+use bevy::asset::RenderAssetUsages; // Used to build the snake's body pieces.
+use bevy::mesh::PrimitiveTopology; // Used to build the snake's body pieces.
+use bevy::ecs::system::SystemParam; // Used to bundle system parameters together.
+use bevy::window::PrimaryWindow; // Used to change the size of the screen.
+use bevy::winit::{UpdateMode, WinitSettings}; // Used to only redraw when something's changed.
+use bevy::diagnostic::FrameTimeDiagnosticsPlugin; // Used to show the frame rate on the F3 panel.
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel}; // Used to scroll the menu.
+use rand::{Rng, RngCore, SeedableRng}; // Used to generate food spawn position.
+use rand::rngs::StdRng; // A seedable RNG, so runs can be replayed exactly.
+use rand_chacha::ChaCha12Rng; // The same generator as StdRng, but it can jump ahead.
+use std::collections::VecDeque; // Used to hold the AI's delayed decisions.
+use std::time::Duration; // Used for the timers.
+
+mod replay; // Recording, signing, and checking replays of finished runs.
+#[cfg(feature = "audio")]
+mod sfx; // Sound effects.
+#[cfg(feature = "audio")]
+mod music; // Background music.
+mod settings; // The settings file.
+mod diagnostics; // The F3 panel that shows memory use and the snake's internals.
+mod progress; // The prestige ladder and its perks.
+mod featured; // The featured seeds on the menu.
+mod sprites; // Pictures for the snake and food.
+#[cfg(feature = "native")]
+mod report; // Bug reports.
+#[cfg(feature = "native")]
+mod clip; // Saving the end of a run as a GIF.
+#[cfg(feature = "native")]
+mod mods; // Custom rules, written in Rhai.
+#[cfg(feature = "native")]
+mod remote; // Driving the snake from another program over a WebSocket.
+mod storage; // Saving files, or local storage in the browser.
+mod theme; // Colour palettes.
+mod effects; // Particle bursts.
+mod death; // The death animation, and the blinking after coming back.
+mod camera; // Following the snake around the big arena.
+mod minimap; // The little map of the big arena.
+mod input; // The keyboard and touch controls.
+mod stats; // Lifetime statistics and the stats screen.
+mod skins; // Unlockable snake skins and the skins screen.
+mod daily; // The daily challenge.
+mod ghost; // Racing a ghost of the best run on a seed.
+mod leaderboard; // The local leaderboard and name entry.
+mod history; // The log of finished runs, and exporting it.
+mod snapshot; // Saving a run partway through and loading it again.
+mod logging; // The --log-level and --log-file flags.
+mod screenshot; // Taking screenshots with F12.
+pub mod sim; // Playing lots of runs headless, for the snake-sim binary.
+pub mod headless; // Playing a run without a window, one tick at a time, for the tests.
+pub mod snake_core; // The classic rules without Bevy, for replays and the Python bindings.
+mod cycle; // The perfect player, which can fill the whole board.
+mod pathfinding; // The F8 overlay showing the way to the food.
+mod boost; // Holding shift for a burst of speed, and ctrl to slow down.
+mod tiles; // Ice, mud, and the floors they go on.
+mod hazards; // Bombs, lasers, and the enemy that chases the snake.
+mod boss; // Boss mode and its giant fruit.
+mod shop; // Coins, the wallet, and the shop.
+mod events; // Food frenzies, speed storms, and blackouts.
+mod mutators; // Challenge rules that can be stacked up, and the mutators screen.
+
+
+// Margin of the grid from the edge of the screen.
+const MARGIN : i32 = 16;
+// The number of cells in the grid. Can be changed freely.
+const NUM_CELLS : i32 = 20;
+// The number of cells each way in the big arena. It's bigger than the screen, so the camera
+// follows the snake around it.
+const BIG_ARENA_CELLS : i32 = 100;
+// The grid is drawn in squares this many cells across, so the ones the camera can't see
+// on the big arena get left out. The normal arena fits in one.
+const BOARD_CHUNK_CELLS : i32 = 25;
+// Size of the screen basically.
+const WORLD_SIZE : i32 = 700;
+// Size of the grid cells is calculated dynamically using the number of cells. The big
+// arena uses the same size cells, and just has more of them.
+const CELL_SIZE : f32 = (WORLD_SIZE as f32 - MARGIN as f32 * 2.0) / NUM_CELLS as f32;
+// How many seconds between the snake moving, unless it's been changed in the settings.
+const MOVE_PERIOD : f32 = 0.15;
+// How many seconds the main menu has to sit idle before the demo starts playing.
+const DEMO_IDLE_SECONDS : f32 = 10.0;
+// How tall the buttons on the main menu are, and the space between them. There are more
+// buttons than fit on the screen, so only this many show at once and the rest scroll.
+const MENU_ENTRY_HEIGHT : f32 = 50.0;
+const MENU_GAP : f32 = 6.0;
+const MENU_VISIBLE_ENTRIES : usize = 8;
+const MENU_LIST_HEIGHT : f32 = MENU_VISIBLE_ENTRIES as f32 * (MENU_ENTRY_HEIGHT + MENU_GAP) - MENU_GAP;
+// How long a time attack round lasts, in seconds.
+const TIME_ATTACK_SECONDS : f32 = 90.0;
+// How many seconds dying knocks off the clock in time attack.
+const TIME_ATTACK_DEATH_PENALTY : f32 = 5.0;
+// How many seconds between the arena shrinking in survival mode.
+const SURVIVAL_SHRINK_SECONDS : f32 = 15.0;
+// How wide the arena is allowed to shrink down to in survival mode.
+const SURVIVAL_MIN_CELLS : i32 = 4;
+// How many lives the snake gets in the modes where dying ends the run.
+const STARTING_LIVES : u32 = 3;
+// How many seconds the countdown before the snake starts moving lasts. It runs at the
+// start of every run, and again after losing a life.
+const COUNTDOWN_SECONDS : f32 = 3.0;
+// How long "GO!" stays up once the countdown is over.
+const COUNTDOWN_GO_SECONDS : f32 = 0.5;
+// How many ticks the player has to eat the next food to keep a combo going.
+const COMBO_WINDOW_TICKS : u32 = 25;
+// How long the "+1" that pops up after eating food hangs around for.
+const SCORE_POPUP_SECONDS : f32 = 0.5;
+// How many moves each player gets in hotseat mode before handing over the controls.
+const HOTSEAT_TURN_TICKS : u32 = 10;
+// How many ticks the bank stays shut for in banking mode, and how long it stays open once
+// it shows up.
+const BANK_CLOSED_TICKS : u32 = 60;
+const BANK_OPEN_TICKS : u32 = 40;
+// Banking at least this many points at once shakes the camera, this hard and this long.
+const BIG_BANK_POINTS : u32 = 10;
+const BIG_BANK_SHAKE : effects::ShakeCamera = effects::ShakeCamera { strength : 4.0, seconds : 0.2 };
+// How many points a run needs before restarting it with R asks to make sure first.
+const RESTART_CONFIRM_SCORE : u32 = 10;
+// How long the player has to press R again to confirm a restart, in seconds.
+const RESTART_CONFIRM_SECONDS : f32 = 2.0;
+// How long a toast message stays on screen for, in seconds.
+const TOAST_SECONDS : f32 = 4.0;
+// How far out from the middle the arms of the cross-shaped arena go, and how big the hole
+// in the donut is, as fractions of the way to the edge.
+const CROSS_ARM : f32 = 1.0 / 3.0;
+const DONUT_HOLE : f32 = 0.4;
+// How many cells across and up each room is in the rooms layout, walls included. The last
+// room in a row or column gets whatever's left over too.
+const ROOM_CELLS : i32 = 10;
+// How many free cells get tried for new food before it settles for one under the snake,
+// so a board that's nearly full can't keep it rolling forever.
+const FOOD_SPAWN_TRIES : u32 = 1000;
+// How many pairs of obstacles get placed on a symmetric map.
+const MAP_OBSTACLE_PAIRS : usize = 12;
+// How many segments come off the tail for running into an obstacle, when they chop.
+const OBSTACLE_CHOP : usize = 3;
+// In low power mode, how long to wait between redraws when nothing is moving (menus, game over).
+const LOW_POWER_IDLE_SECONDS : f32 = 1.0;
+// Width of the outlines on the grids.
+const OUTLINE_WIDTH : f32 = 1.0;
+// Starting position of the snake.
+const SNAKE_START_POS : GridPosition = GridPosition{x : NUM_CELLS / 2, y : NUM_CELLS / 2};
+// Mixed into a run's seed for its side generator, so it doesn't hand out the same numbers
+// as the food's.
+const SIDE_RNG_SALT : u64 = 0x9E37_79B9_7F4A_7C15;
+// Colors for the menus and screens drawn over the board. The board itself gets its
+// colors from the theme.
+const WHITE : Color = Color::srgb(1.0, 1.0, 1.0);
+const GREEN : Color = Color::srgb(0.25, 0.75, 0.25);
+const DARK_GREY : Color = Color::srgb(0.3, 0.3, 0.3);
+
+
+
+#[derive(States, Debug, Clone, Eq, PartialEq, Hash, Default)]
+enum GameState {
+    #[default]
+    Menu,
+    Demo,
+    Playing,
+    // The death animation is playing out, on the way to GameOver.
+    Dying,
+    GameOver,
+    Settings,
+    Progress,
+    Stats,
+    // The imported replays and how they checked out.
+    Replays,
+    // Picking a skin, reached from the progress screen.
+    Skins,
+    // Spending coins, reached from the progress screen too.
+    Shop,
+    // Switching mutators on and off, reached from the menu.
+    Mutators,
+    // Passed through for a moment when a run gets restarted, on the way back to Playing.
+    Restarting,
+}
+
+
+
+#[derive(Component)]
+struct GameOverUI;
+
+#[derive(Component)]
+struct MenuUI;
+
+#[derive(Component)]
+struct DemoUI;
+
+// The steps every FixedUpdate tick goes through, in this order. The player's input has
+// already come in through Update by the time a tick starts.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+enum TickSet {
+	// The demo's AI picks a direction, and everything on the board remembers where it was.
+	Input,
+	// The snake takes a step. The replay writes it down.
+	Move,
+	// The wall and the obstacles, then the snake's own body, then the food.
+	Collide,
+	// New segments for food eaten on earlier ticks.
+	Grow,
+	// Everything that follows on from what happened: scoring, turns, the bank, and new food.
+	Spawn,
+}
+
+// The different ways the game can be played.
+#[derive(Resource, Copy, Clone, Eq, PartialEq, Default)]
+enum GameMode {
+	// Eat food, grow, and try not to crash.
+	#[default]
+	Classic,
+	// The snake never shrinks and leaves a permanent trail behind it. There's no food,
+	// the goal is just to survive as long as possible.
+	Tron,
+	// Eat as much food as you can before the clock runs out. Dying costs time instead of
+	// ending the run.
+	TimeAttack,
+	// Every so often the outer ring of the arena becomes deadly, until there's hardly any
+	// room left. Survive as long as possible.
+	Survival,
+	// A relaxed practice mode with no dying. The walls wrap around to the other side, and
+	// biting yourself just bites off the tail.
+	Zen,
+	// Two players share one snake and one keyboard, taking turns every few moves. Whoever
+	// has the controls when it crashes loses.
+	Hotseat,
+	// Food only goes into a pot, and the pot only counts once it's taken to the bank that
+	// shows up every so often. Dying loses everything that hasn't been banked.
+	Banking,
+	// A giant fruit moves around the arena, and has to be hit a few times to clear the
+	// stage.
+	Boss,
+}
+impl GameMode {
+	fn name(self) -> &'static str {
+		match self {
+			GameMode::Classic => "Classic",
+			GameMode::Tron => "Tron",
+			GameMode::TimeAttack => "Time Attack",
+			GameMode::Survival => "Survival",
+			GameMode::Zen => "Zen",
+			GameMode::Hotseat => "Hotseat",
+			GameMode::Banking => "Banking",
+			GameMode::Boss => "Boss",
+		}
+	}
+
+	fn from_name(name : &str) -> Option<Self> {
+		match name {
+			"classic" => Some(GameMode::Classic),
+			"tron" => Some(GameMode::Tron),
+			"time_attack" => Some(GameMode::TimeAttack),
+			"survival" => Some(GameMode::Survival),
+			"zen" => Some(GameMode::Zen),
+			"hotseat" => Some(GameMode::Hotseat),
+			"banking" => Some(GameMode::Banking),
+			"boss" => Some(GameMode::Boss),
+			_ => None,
+		}
+	}
+
+	fn to_name(self) -> &'static str {
+		match self {
+			GameMode::Classic => "classic",
+			GameMode::Tron => "tron",
+			GameMode::TimeAttack => "time_attack",
+			GameMode::Survival => "survival",
+			GameMode::Zen => "zen",
+			GameMode::Hotseat => "hotseat",
+			GameMode::Banking => "banking",
+			GameMode::Boss => "boss",
+		}
+	}
+}
+
+// Sent whenever the snake eats a food.
+#[derive(Message)]
+struct FoodEaten {
+	// Where the food was.
+	pos : GridPosition,
+}
+
+// Sent whenever the snake dies, even if it still has lives left.
+#[derive(Message)]
+struct SnakeDied {
+	cause : stats::DeathCause,
+	// How long the snake was, head included.
+	length : usize,
+}
+
+// Sent whenever a moving snake changes direction.
+#[derive(Message)]
+struct DirectionChanged;
+
+// Sent whenever the snake gets a new segment.
+#[derive(Message)]
+struct SnakeGrew {
+	// How long it is now, head included.
+	length : usize,
+}
+
+// Sent when one of the game's files is missing or broken, and the game is carrying on
+// without it.
+#[derive(Message)]
+struct AssetMissing {
+	path : String,
+}
+impl AssetMissing {
+	fn from_handle<A : Asset>(handle : &Handle<A>) -> Self {
+		let path = handle.path().map(|path| path.to_string()).unwrap_or_default();
+		AssetMissing { path }
+	}
+}
+
+// Eating food quickly one after another builds up a combo, and each food is worth the
+// current multiplier. Taking too long, or dying, breaks the streak.
+#[derive(Resource, Clone)]
+struct Combo {
+	multiplier : u32,
+	// How many ticks are left to eat the next food before the combo is lost.
+	ticks_left : u32,
+}
+impl Default for Combo {
+	fn default() -> Self {
+		Combo { multiplier : 1, ticks_left : 0 }
+	}
+}
+impl Combo {
+	// Called once per movement tick to run the combo clock down.
+	fn tick(&mut self) {
+		if self.ticks_left > 0 {
+			self.ticks_left -= 1;
+			if self.ticks_left == 0 {
+				self.multiplier = 1;
+			}
+		}
+	}
+
+	// Called when a food gets eaten. Returns how many points it was worth.
+	fn eat(&mut self) -> u32 {
+		if self.ticks_left > 0 {
+			self.multiplier += 1;
+		}
+		else {
+			self.multiplier = 1;
+		}
+		self.ticks_left = COMBO_WINDOW_TICKS;
+		self.multiplier
+	}
+}
+
+// The little "+N" that floats up from a food after it's eaten.
+#[derive(Component)]
+struct ScorePopup(Timer);
+
+// A short message at the bottom of the screen that fades away by itself.
+#[derive(Component)]
+struct Toast(Timer);
+
+// Read-only access to everything that gets shown about the current run.
+#[derive(SystemParam)]
+struct RunInfo<'w> {
+	mode : Res<'w, GameMode>,
+	snake : Res<'w, SnakeState>,
+	lives : Res<'w, Lives>,
+	score : Res<'w, Score>,
+	survived : Res<'w, SurvivalTime>,
+	round : Res<'w, RoundTimer>,
+	combo : Res<'w, Combo>,
+	fixed : Res<'w, Time<Fixed>>,
+	hotseat : Res<'w, Hotseat>,
+	pot : Res<'w, Pot>,
+	boss : Res<'w, boss::BossFight>,
+}
+
+// Whether to only redraw when the game has actually moved, to save battery.
+// On by default on the web and on phones, or with --low-power anywhere else.
+#[derive(Resource, PartialEq)]
+struct LowPower(bool);
+impl LowPower {
+	fn from_args() -> Self {
+		let mobile = cfg!(any(target_arch = "wasm32", target_os = "android", target_os = "ios"));
+		LowPower(mobile || std::env::args().any(|arg| arg == "--low-power"))
+	}
+}
+
+// Whose turn it is in hotseat mode, and how many moves they have left.
+#[derive(Resource)]
+struct Hotseat {
+	// Either 1 or 2.
+	player : u32,
+	ticks_left : u32,
+}
+impl Default for Hotseat {
+	fn default() -> Self {
+		Hotseat { player : 1, ticks_left : HOTSEAT_TURN_TICKS }
+	}
+}
+impl Hotseat {
+	fn other_player(&self) -> u32 {
+		3 - self.player
+	}
+}
+
+// The points in banking mode that haven't been banked yet, and the clock for the bank
+// coming and going.
+#[derive(Resource)]
+struct Pot {
+	points : u32,
+	// Ticks until the bank opens, or until it closes again while it's open.
+	ticks_left : u32,
+	open : bool,
+}
+impl Default for Pot {
+	fn default() -> Self {
+		Pot { points : 0, ticks_left : BANK_CLOSED_TICKS, open : false }
+	}
+}
+
+// How many lives are left this run, counting the one being played.
+#[derive(Resource)]
+struct Lives(u32);
+
+// How many seconds are left before the snake starts moving. The game holds still while
+// it's above zero. It keeps going below zero for a moment so "GO!" can be shown.
+#[derive(Resource, Default)]
+struct Countdown(f32);
+
+// How much food has been eaten this run.
+#[derive(Resource, Default)]
+struct Score(u32);
+
+// How many seconds the snake has survived this run. This is the score in Tron mode.
+#[derive(Resource, Default)]
+struct SurvivalTime(f32);
+
+// The countdown for time attack mode.
+#[derive(Resource)]
+struct RoundTimer(Timer);
+
+// The random number generator used for spawning food. It gets a fresh seed at the start
+// of every run, and the seed is saved with the replay so the run can be re-simulated.
+#[derive(Resource)]
+struct GameRng {
+	seed : u64,
+	rng : CountedRng,
+	// For the extras replays don't know about, like coins. It's seeded off the same seed, so
+	// a seed always plays out the same, but drawing from it doesn't move the food around.
+	side : StdRng,
+}
+impl GameRng {
+	fn from_seed(seed : u64) -> Self {
+		GameRng {
+			seed,
+			rng : CountedRng { rng : ChaCha12Rng::seed_from_u64(seed), draws : 0 },
+			side : StdRng::seed_from_u64(seed ^ SIDE_RNG_SALT),
+		}
+	}
+
+	// Picks up where a run's generator was after handing out this many numbers. It jumps
+	// straight there, so a save file with a huge count can't keep it busy. The side
+	// generator starts over, since nothing that comes from it gets saved.
+	fn resume(seed : u64, draws : u64) -> Self {
+		let mut resumed = GameRng::from_seed(seed);
+		resumed.rng.rng.set_word_pos(u128::from(draws));
+		resumed.rng.draws = draws;
+		resumed
+	}
+}
+
+// A seeded generator that keeps count of how much it's handed out, in 32 bit numbers.
+// That's all it takes to get another one to the same place, so a saved game carries on
+// with exactly the food it would've had. It's StdRng's own generator, so seeds play out
+// the same as they always have, and the count is exactly its position in the stream.
+#[derive(Clone)]
+struct CountedRng {
+	rng : ChaCha12Rng,
+	draws : u64,
+}
+impl RngCore for CountedRng {
+	fn next_u32(&mut self) -> u32 {
+		self.draws += 1;
+		self.rng.next_u32()
+	}
+
+	fn next_u64(&mut self) -> u64 {
+		self.draws += 2;
+		self.rng.next_u64()
+	}
+
+	fn fill_bytes(&mut self, dest : &mut [u8]) {
+		self.draws += dest.len().div_ceil(4) as u64;
+		self.rng.fill_bytes(dest);
+	}
+
+	fn try_fill_bytes(&mut self, dest : &mut [u8]) -> Result<(), rand::Error> {
+		self.draws += dest.len().div_ceil(4) as u64;
+		self.rng.try_fill_bytes(dest)
+	}
+}
+
+// The part of the arena that's deadly to touch, as a number of rings in from the edge.
+// It's always empty outside of survival mode.
+#[derive(Resource)]
+struct DangerZone {
+	rings : i32,
+	// Counts down to the next ring becoming deadly.
+	timer : Timer,
+}
+impl Default for DangerZone {
+	fn default() -> Self {
+		DangerZone {
+			rings : 0,
+			timer : Timer::from_seconds(SURVIVAL_SHRINK_SECONDS, TimerMode::Repeating),
+		}
+	}
+}
+impl DangerZone {
+	// Whether a cell is inside the danger zone, on an arena this many cells across and up.
+	fn is_deadly(&self, pos : GridPosition, width : i32, height : i32) -> bool {
+		pos.x < self.rings ||
+		pos.x >= width - self.rings ||
+		pos.y < self.rings ||
+		pos.y >= height - self.rings
+	}
+}
+
+// How the obstacles on the map are laid out. The symmetric layouts are there for fair
+// versus play, since whatever one side of the board has, the other side has too.
+#[derive(Copy, Clone, Eq, PartialEq, Default)]
+enum MapSymmetry {
+	// No obstacles at all, just the open arena.
+	#[default]
+	Open,
+	// The board looks the same after being turned halfway around.
+	Rotational,
+	// The left half of the board is a reflection of the right half.
+	Mirror,
+}
+impl MapSymmetry {
+	// The layout after this one, for flipping through them on the menu.
+	fn next(self) -> Self {
+		match self {
+			MapSymmetry::Open => MapSymmetry::Rotational,
+			MapSymmetry::Rotational => MapSymmetry::Mirror,
+			MapSymmetry::Mirror => MapSymmetry::Open,
+		}
+	}
+
+	fn name(self) -> &'static str {
+		match self {
+			MapSymmetry::Open => "Open",
+			MapSymmetry::Rotational => "Rotational",
+			MapSymmetry::Mirror => "Mirror",
+		}
+	}
+
+	fn from_name(name : &str) -> Option<Self> {
+		match name {
+			"open" => Some(MapSymmetry::Open),
+			"rotational" => Some(MapSymmetry::Rotational),
+			"mirror" => Some(MapSymmetry::Mirror),
+			_ => None,
+		}
+	}
+
+	fn to_name(self) -> &'static str {
+		match self {
+			MapSymmetry::Open => "open",
+			MapSymmetry::Rotational => "rotational",
+			MapSymmetry::Mirror => "mirror",
+		}
+	}
+
+	// The cell that matches this one on the other side of a board this many cells across
+	// and up, if the layout has one. It can be the same cell if it sits right on the line of
+	// symmetry.
+	fn partner(self, pos : GridPosition, width : i32, height : i32) -> Option<GridPosition> {
+		match self {
+			MapSymmetry::Open => None,
+			MapSymmetry::Rotational => Some(GridPosition { x : width - 1 - pos.x, y : height - 1 - pos.y }),
+			MapSymmetry::Mirror => Some(GridPosition { x : width - 1 - pos.x, y : pos.y }),
+		}
+	}
+}
+
+// How big the arena is. The wide one is twice as wide as it's tall, for widescreens, and
+// the big one's bigger than the screen both ways.
+#[derive(Copy, Clone, Eq, PartialEq, Default)]
+enum ArenaSize {
+	#[default]
+	Normal,
+	Wide,
+	Big,
+}
+impl ArenaSize {
+	// The size after this one, for flipping through them on the menu.
+	fn next(self) -> Self {
+		match self {
+			ArenaSize::Normal => ArenaSize::Wide,
+			ArenaSize::Wide => ArenaSize::Big,
+			ArenaSize::Big => ArenaSize::Normal,
+		}
+	}
+
+	fn name(self) -> &'static str {
+		match self {
+			ArenaSize::Normal => "Normal",
+			ArenaSize::Wide => "Wide",
+			ArenaSize::Big => "Big",
+		}
+	}
+
+	fn from_name(name : &str) -> Option<Self> {
+		match name {
+			"normal" => Some(ArenaSize::Normal),
+			"wide" => Some(ArenaSize::Wide),
+			"big" => Some(ArenaSize::Big),
+			_ => None,
+		}
+	}
+
+	fn to_name(self) -> &'static str {
+		match self {
+			ArenaSize::Normal => "normal",
+			ArenaSize::Wide => "wide",
+			ArenaSize::Big => "big",
+		}
+	}
+
+	// How many cells across the arena is.
+	fn width(self) -> i32 {
+		match self {
+			ArenaSize::Normal => NUM_CELLS,
+			ArenaSize::Wide => NUM_CELLS * 2,
+			ArenaSize::Big => BIG_ARENA_CELLS,
+		}
+	}
+
+	// How many cells up the arena is.
+	fn height(self) -> i32 {
+		match self {
+			ArenaSize::Normal | ArenaSize::Wide => NUM_CELLS,
+			ArenaSize::Big => BIG_ARENA_CELLS,
+		}
+	}
+}
+
+// Which of the cells inside the arena's edges are part of it. Everything else is as good as
+// a wall. The shapes stretch to fill the arena, so the circle's an oval on the wide one.
+#[derive(Copy, Clone, Eq, PartialEq, Default)]
+enum ArenaShape {
+	#[default]
+	Rectangle,
+	Circle,
+	// A plus sign, with arms a third of the way across.
+	Cross,
+	// A circle with a hole in the middle.
+	Donut,
+	// Rooms with walls between them, and a doorway in the middle of each wall.
+	Rooms,
+}
+impl ArenaShape {
+	// The shape after this one, for flipping through them on the menu.
+	fn next(self) -> Self {
+		match self {
+			ArenaShape::Rectangle => ArenaShape::Circle,
+			ArenaShape::Circle => ArenaShape::Cross,
+			ArenaShape::Cross => ArenaShape::Donut,
+			ArenaShape::Donut => ArenaShape::Rooms,
+			ArenaShape::Rooms => ArenaShape::Rectangle,
+		}
+	}
+
+	fn name(self) -> &'static str {
+		match self {
+			ArenaShape::Rectangle => "Rectangle",
+			ArenaShape::Circle => "Circle",
+			ArenaShape::Cross => "Cross",
+			ArenaShape::Donut => "Donut",
+			ArenaShape::Rooms => "Rooms",
+		}
+	}
+
+	fn from_name(name : &str) -> Option<Self> {
+		match name {
+			"rectangle" => Some(ArenaShape::Rectangle),
+			"circle" => Some(ArenaShape::Circle),
+			"cross" => Some(ArenaShape::Cross),
+			"donut" => Some(ArenaShape::Donut),
+			"rooms" => Some(ArenaShape::Rooms),
+			_ => None,
+		}
+	}
+
+	fn to_name(self) -> &'static str {
+		match self {
+			ArenaShape::Rectangle => "rectangle",
+			ArenaShape::Circle => "circle",
+			ArenaShape::Cross => "cross",
+			ArenaShape::Donut => "donut",
+			ArenaShape::Rooms => "rooms",
+		}
+	}
+
+	// Whether a cell that's inside the edges of an arena this many cells across and up is
+	// part of this shape.
+	fn contains(self, pos : GridPosition, width : i32, height : i32) -> bool {
+		// Where the middle of the cell is, from -1 at the left or bottom edge to 1 at the
+		// other one.
+		let across = (pos.x as f32 + 0.5) / width as f32 * 2.0 - 1.0;
+		let up = (pos.y as f32 + 0.5) / height as f32 * 2.0 - 1.0;
+		let distance = across.hypot(up);
+		match self {
+			ArenaShape::Rectangle => true,
+			ArenaShape::Circle => distance <= 1.0,
+			ArenaShape::Cross => across.abs() <= CROSS_ARM || up.abs() <= CROSS_ARM,
+			ArenaShape::Donut => (DONUT_HOLE..=1.0).contains(&distance),
+			ArenaShape::Rooms => {
+				let (first, last) = room_bounds(pos, width, height);
+				// The edges of the arena are walls already, so only the sides with another
+				// room on the other side get one.
+				let side_wall = (pos.x == first.x && first.x > 0) || (pos.x == last.x && last.x < width - 1);
+				let end_wall = (pos.y == first.y && first.y > 0) || (pos.y == last.y && last.y < height - 1);
+				let (middle_x, middle_y) = ((first.x + last.x) / 2, (first.y + last.y) / 2);
+				let door_across = pos.x == middle_x || pos.x == middle_x + 1;
+				let door_up = pos.y == middle_y || pos.y == middle_y + 1;
+				(!side_wall || door_up) && (!end_wall || door_across)
+			}
+		}
+	}
+
+	// The first and last cells of the room a cell's in, walls included, on the rooms
+	// layout. Nothing on the other shapes.
+	fn room(self, pos : GridPosition, width : i32, height : i32) -> Option<(GridPosition, GridPosition)> {
+		(self == ArenaShape::Rooms).then(|| room_bounds(pos, width, height))
+	}
+}
+
+// The first and last cells of the room a cell would be in, on the rooms layout of an arena
+// this many cells across and up.
+fn room_bounds(pos : GridPosition, width : i32, height : i32) -> (GridPosition, GridPosition) {
+	let span = |at : i32, size : i32| {
+		let rooms = (size / ROOM_CELLS).max(1);
+		let room = (at / ROOM_CELLS).clamp(0, rooms - 1);
+		let first = room * ROOM_CELLS;
+		(first, if room == rooms - 1 { size - 1 } else { first + ROOM_CELLS - 1 })
+	};
+	let (first_x, last_x) = span(pos.x, width);
+	let (first_y, last_y) = span(pos.y, height);
+	(GridPosition { x : first_x, y : first_y }, GridPosition { x : last_x, y : last_y })
+}
+
+// What running into an obstacle does. Chopping takes the end of the tail off and knocks
+// the snake back to where it was, and it's only a death when there's not enough tail left.
+#[derive(Copy, Clone, Eq, PartialEq, Default)]
+enum ObstacleHits {
+	#[default]
+	Deadly,
+	Chop,
+}
+impl ObstacleHits {
+	fn next(self) -> Self {
+		match self {
+			ObstacleHits::Deadly => ObstacleHits::Chop,
+			ObstacleHits::Chop => ObstacleHits::Deadly,
+		}
+	}
+
+	fn name(self) -> &'static str {
+		match self {
+			ObstacleHits::Deadly => "Deadly",
+			ObstacleHits::Chop => "Chop Tail",
+		}
+	}
+
+	fn from_name(name : &str) -> Option<Self> {
+		match name {
+			"deadly" => Some(ObstacleHits::Deadly),
+			"chop" => Some(ObstacleHits::Chop),
+			_ => None,
+		}
+	}
+
+	fn to_name(self) -> &'static str {
+		match self {
+			ObstacleHits::Deadly => "deadly",
+			ObstacleHits::Chop => "chop",
+		}
+	}
+}
+
+// Whether a run is mirrored, and how. A mirrored screen shows the board flipped left to
+// right, and mirrored controls swap left and right on the keys, swipes and D-pad.
+#[derive(Copy, Clone, Eq, PartialEq, Default)]
+enum Mirror {
+	#[default]
+	Off,
+	Controls,
+	Screen,
+}
+impl Mirror {
+	fn next(self) -> Self {
+		match self {
+			Mirror::Off => Mirror::Controls,
+			Mirror::Controls => Mirror::Screen,
+			Mirror::Screen => Mirror::Off,
+		}
+	}
+
+	fn name(self) -> &'static str {
+		match self {
+			Mirror::Off => "Off",
+			Mirror::Controls => "Controls",
+			Mirror::Screen => "Screen",
+		}
+	}
+
+	fn from_name(name : &str) -> Option<Self> {
+		match name {
+			"off" => Some(Mirror::Off),
+			"controls" => Some(Mirror::Controls),
+			"screen" => Some(Mirror::Screen),
+			_ => None,
+		}
+	}
+
+	fn to_name(self) -> &'static str {
+		match self {
+			Mirror::Off => "off",
+			Mirror::Controls => "controls",
+			Mirror::Screen => "screen",
+		}
+	}
+}
+
+// The layout of the arena, and the cells that have obstacles on them.
+#[derive(Resource, Default)]
+struct ArenaMap {
+	symmetry : MapSymmetry,
+	arena : ArenaSize,
+	shape : ArenaShape,
+	floor : tiles::Floor,
+	hazards : hazards::Hazards,
+	obstacle_hits : ObstacleHits,
+	// Whether random events come along during runs.
+	events : bool,
+	mirror : Mirror,
+	mutators : mutators::Mutators,
+	obstacles : Vec<GridPosition>,
+}
+impl ArenaMap {
+	fn width(&self) -> i32 {
+		self.arena.width()
+	}
+
+	fn height(&self) -> i32 {
+		self.arena.height()
+	}
+
+	// Whether a cell is part of the arena.
+	fn contains(&self, pos : GridPosition) -> bool {
+		pos.x >= 0 && pos.x < self.width() && pos.y >= 0 && pos.y < self.height() && self.shape.contains(pos, self.width(), self.height())
+	}
+
+	// Where the snake starts. On the rectangle it's always the same cell, whatever the size,
+	// so runs on the normal arena play the same as they always have. The donut has a hole
+	// in the middle, so the snake starts on its left side, headed up around it, and with
+	// rooms it starts in the middle of the bottom left one.
+	fn start(&self) -> GridPosition {
+		match self.shape {
+			ArenaShape::Rooms => {
+				let (first, last) = room_bounds(GridPosition { x : 0, y : 0 }, self.width(), self.height());
+				GridPosition { x : (first.x + last.x + 1) / 2, y : (first.y + last.y + 1) / 2 }
+			}
+			ArenaShape::Rectangle => SNAKE_START_POS,
+			ArenaShape::Circle | ArenaShape::Cross => GridPosition { x : self.width() / 2, y : self.height() / 2 },
+			ArenaShape::Donut => GridPosition {
+				x : ((1.0 - DONUT_HOLE) / 4.0 * self.width() as f32) as i32,
+				y : self.height() / 2,
+			},
+		}
+	}
+}
+
+// What's in every cell of the grid, so checking a cell doesn't mean going through the
+// whole snake. It's kept up to date as the snake moves, grows, and dies, and as the
+// obstacles are laid out.
+//
+// The snake's cells are counted rather than just marked, since a new segment starts out
+// on top of the tail.
+//
+// It's also what knows how big the arena is at the moment, and what shape, for anything
+// that needs to know where the walls are.
+#[derive(Resource)]
+struct OccupancyGrid {
+	width : i32,
+	height : i32,
+	shape : ArenaShape,
+	// Which cells are part of the arena's shape.
+	inside : Vec<bool>,
+	segments : Vec<u16>,
+	obstacles : Vec<bool>,
+}
+impl Default for OccupancyGrid {
+	fn default() -> Self {
+		OccupancyGrid::new(NUM_CELLS, NUM_CELLS)
+	}
+}
+impl OccupancyGrid {
+	// An empty rectangular grid this many cells across and up.
+	fn new(width : i32, height : i32) -> Self {
+		OccupancyGrid::shaped(width, height, ArenaShape::Rectangle)
+	}
+
+	// An empty grid this many cells across and up, with only the cells in the shape in it.
+	fn shaped(width : i32, height : i32, shape : ArenaShape) -> Self {
+		let cells = (width * height) as usize;
+		let inside = (0..height)
+			.flat_map(|y| (0..width).map(move |x| GridPosition { x, y }))
+			.map(|pos| shape.contains(pos, width, height))
+			.collect();
+		OccupancyGrid { width, height, shape, inside, segments : vec![0; cells], obstacles : vec![false; cells] }
+	}
+
+	// Whether a cell is part of the arena.
+	fn contains(&self, pos : GridPosition) -> bool {
+		self.index(pos).is_some()
+	}
+
+	// Where a cell is in the lists, or nothing if it's off the grid or outside the shape.
+	fn index(&self, pos : GridPosition) -> Option<usize> {
+		let on_grid = pos.x >= 0 && pos.x < self.width && pos.y >= 0 && pos.y < self.height;
+		let index = (pos.y * self.width + pos.x) as usize;
+		(on_grid && self.inside[index]).then_some(index)
+	}
+
+	fn add_segment(&mut self, pos : GridPosition) {
+		if let Some(i) = self.index(pos) {
+			self.segments[i] += 1;
+		}
+	}
+
+	fn remove_segment(&mut self, pos : GridPosition) {
+		if let Some(i) = self.index(pos) {
+			self.segments[i] = self.segments[i].saturating_sub(1);
+		}
+	}
+
+	// Forgets the whole snake, for when it's been despawned.
+	fn clear_segments(&mut self) {
+		self.segments.fill(0);
+	}
+
+	fn has_segment(&self, pos : GridPosition) -> bool {
+		self.index(pos).is_some_and(|i| self.segments[i] > 0)
+	}
+
+	fn add_obstacle(&mut self, pos : GridPosition) {
+		if let Some(i) = self.index(pos) {
+			self.obstacles[i] = true;
+		}
+	}
+
+	fn clear_obstacles(&mut self) {
+		self.obstacles.fill(false);
+	}
+
+	fn has_obstacle(&self, pos : GridPosition) -> bool {
+		self.index(pos).is_some_and(|i| self.obstacles[i])
+	}
+
+	// Whether moving into a cell would kill the snake. Going off the grid isn't counted.
+	fn is_blocked(&self, pos : GridPosition) -> bool {
+		self.has_segment(pos) || self.has_obstacle(pos)
+	}
+}
+
+// Starts the occupancy grid afresh when a different sized or shaped arena is picked,
+// keeping any obstacles that are already out. That only happens on the menu, where the
+// snake is always fresh.
+fn resize_occupancy_sys(map : Res<ArenaMap>, mut occupancy : ResMut<OccupancyGrid>) {
+	if occupancy.width == map.width() && occupancy.height == map.height() && occupancy.shape == map.shape {
+		return;
+	}
+	*occupancy = OccupancyGrid::shaped(map.width(), map.height(), map.shape);
+	for &pos in map.obstacles.iter() {
+		occupancy.add_obstacle(pos);
+	}
+}
+
+// Everything needed to put new food on the board. On a symmetric map the food comes in
+// matching pairs, so neither side of the board gets it closer.
+#[derive(SystemParam)]
+struct FoodSpawner<'w, 's> {
+	rng : ResMut<'w, GameRng>,
+	danger : Res<'w, DangerZone>,
+	map : Res<'w, ArenaMap>,
+	occupancy : Res<'w, OccupancyGrid>,
+	cells : Res<'w, CellMeshes>,
+	head : Query<'w, 's, &'static GridPosition, With<SnakeHead>>,
+}
+impl FoodSpawner<'_, '_> {
+	fn spawn(&mut self, commands : &mut Commands) {
+		// Keep rolling until we land on a cell in the arena without an obstacle. Obstacles
+		// and the shapes are symmetric, so the partner cell is in the arena too. The snake
+		// isn't though, so the partner has to be off the snake as well.
+		let (width, height) = (self.map.width(), self.map.height());
+		let on_snake = |pos : GridPosition| self.occupancy.has_segment(pos) || self.head.iter().any(|head| *head == pos);
+		let taken = |pos : GridPosition| {
+			on_snake(pos) || self.map.symmetry.partner(pos, width, height).is_some_and(on_snake)
+		};
+		let usable = |pos : GridPosition| self.map.contains(pos) && !self.occupancy.has_obstacle(pos);
+		let pos = roll_food_pos(&mut self.rng.rng, &self.danger, width, height, usable, taken);
+		spawn_food_at(commands, &self.cells, pos);
+		if let Some(partner) = self.map.symmetry.partner(pos, self.map.width(), self.map.height()) && partner != pos {
+			spawn_food_at(commands, &self.cells, partner);
+		}
+	}
+}
+
+// Shared meshes and materials for the things there can be a lot of: snake segments and food.
+// Every segment uses the same handles, so Bevy draws all of them in one instanced batch,
+// no matter how long the snake gets.
+//
+// The body pieces are drawn joining up with the top edge of the cell (and the right edge
+// for the corner), and get turned to face whichever way they need to.
+#[derive(Resource)]
+struct CellMeshes {
+	outline : Handle<Mesh>,
+	segment_fill : Handle<Mesh>,
+	body_straight : Handle<Mesh>,
+	body_corner : Handle<Mesh>,
+	body_tail : Handle<Mesh>,
+	// These three get swapped for different shapes when the shapes setting is on.
+	food_fill : Handle<Mesh>,
+	bank_fill : Handle<Mesh>,
+	obstacle_fill : Handle<Mesh>,
+	// The materials get recoloured to match the theme.
+	line : Handle<ColorMaterial>,
+	snake : Handle<ColorMaterial>,
+	food : Handle<ColorMaterial>,
+	bank : Handle<ColorMaterial>,
+	obstacle : Handle<ColorMaterial>,
+	// The board's cells have their colours in their mesh, so this stays white, and they
+	// come out in exactly their own colours.
+	board : Handle<ColorMaterial>,
+}
+impl FromWorld for CellMeshes {
+	fn from_world(world : &mut World) -> Self {
+		let mut meshes = world.resource_mut::<Assets<Mesh>>();
+		let outline = meshes.add(Rectangle::from_length(CELL_SIZE));
+		let segment_fill = meshes.add(Rectangle::from_length(CELL_SIZE - OUTLINE_WIDTH * 4.0));
+
+		// The body is as wide as a segment's fill, and reaches right up to the edge of the
+		// cell on the sides it's joined on.
+		let half = (CELL_SIZE - OUTLINE_WIDTH * 4.0) / 2.0;
+		let edge = CELL_SIZE / 2.0;
+		let body_straight = meshes.add(Rectangle::new(half * 2.0, CELL_SIZE));
+		let body_corner = meshes.add(quads_mesh(&[
+			rect_corners(Vec2::new(-half, -half), Vec2::new(half, half)),
+			rect_corners(Vec2::new(-half, half), Vec2::new(half, edge)),
+			rect_corners(Vec2::new(half, -half), Vec2::new(edge, half)),
+		]));
+		// The tail narrows to a blunt point.
+		let body_tail = meshes.add(quads_mesh(&[[
+			Vec2::new(-half / 3.0, -half),
+			Vec2::new(half / 3.0, -half),
+			Vec2::new(half, edge),
+			Vec2::new(-half, edge),
+		]]));
+		let food_fill = meshes.add(CellMeshes::food_mesh(false));
+		let bank_fill = meshes.add(CellMeshes::bank_mesh(false));
+		let obstacle_fill = meshes.add(CellMeshes::obstacle_mesh(false));
+
+		let theme = *world.resource::<theme::Theme>();
+		let mut materials = world.resource_mut::<Assets<ColorMaterial>>();
+		CellMeshes {
+			outline,
+			segment_fill,
+			body_straight,
+			body_corner,
+			body_tail,
+			food_fill,
+			bank_fill,
+			obstacle_fill,
+			line : materials.add(theme.line),
+			snake : materials.add(theme.snake),
+			food : materials.add(theme.food),
+			bank : materials.add(theme.bank),
+			obstacle : materials.add(theme.obstacle),
+			board : materials.add(WHITE),
+		}
+	}
+}
+impl CellMeshes {
+	// Food is a circle with shapes on, and a square without.
+	fn food_mesh(shapes : bool) -> Mesh {
+		if shapes {
+			Mesh::from(Circle::new((CELL_SIZE - 10.0) / 2.0))
+		} else {
+			Mesh::from(Rectangle::from_length(CELL_SIZE - 12.0))
+		}
+	}
+
+	// The bank is a diamond with shapes on, and a square like the food without.
+	fn bank_mesh(shapes : bool) -> Mesh {
+		if shapes {
+			Mesh::from(Rhombus::new(CELL_SIZE - 6.0, CELL_SIZE - 6.0))
+		} else {
+			Mesh::from(Rectangle::from_length(CELL_SIZE - 12.0))
+		}
+	}
+
+	// Obstacles are a hollow square with shapes on, and a filled one without.
+	fn obstacle_mesh(shapes : bool) -> Mesh {
+		let outer = (CELL_SIZE - OUTLINE_WIDTH * 6.0) / 2.0;
+		if shapes {
+			let inner = outer - 4.0;
+			quads_mesh(&[
+				rect_corners(Vec2::new(-outer, -outer), Vec2::new(outer, -inner)),
+				rect_corners(Vec2::new(-outer, inner), Vec2::new(outer, outer)),
+				rect_corners(Vec2::new(-outer, -inner), Vec2::new(-inner, inner)),
+				rect_corners(Vec2::new(inner, -inner), Vec2::new(outer, inner)),
+			])
+		} else {
+			Mesh::from(Rectangle::from_length(outer * 2.0))
+		}
+	}
+}
+impl CellMeshes {
+	fn body_mesh(&self, piece : BodyPiece) -> Handle<Mesh> {
+		match piece {
+			BodyPiece::Lone => self.segment_fill.clone(),
+			BodyPiece::Straight => self.body_straight.clone(),
+			BodyPiece::Corner => self.body_corner.clone(),
+			BodyPiece::Tail => self.body_tail.clone(),
+		}
+	}
+}
+
+// The shapes a segment of the snake can be drawn as, depending on its neighbours.
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum BodyPiece {
+	// A segment that was just grown sits on top of the one before it, so it doesn't join
+	// onto anything yet.
+	Lone,
+	Straight,
+	Corner,
+	Tail,
+}
+impl BodyPiece {
+	// The piece for a segment, given which ways the pieces in front of and behind it are,
+	// and how far it needs turning. The pieces are all drawn facing up.
+	fn between(front : Direction, back : Direction) -> (BodyPiece, Quat) {
+		match (front, back) {
+			(Direction::None, Direction::None) => (BodyPiece::Lone, Quat::IDENTITY),
+			(dir, Direction::None) | (Direction::None, dir) => (BodyPiece::Tail, dir.facing()),
+			_ if front.is_opposite(back) => (BodyPiece::Straight, front.facing()),
+			// The corner joins up and right, so it's turned to face whichever of the two
+			// ways is anticlockwise of the other.
+			_ if back.turned_anticlockwise() == front => (BodyPiece::Corner, front.facing()),
+			_ => (BodyPiece::Corner, back.facing()),
+		}
+	}
+}
+
+// Builds a flat mesh out of four sided shapes, each given as its corners going around
+// anticlockwise.
+fn quads_mesh(quads : &[[Vec2; 4]]) -> Mesh {
+	let mut positions = Vec::new();
+	for quad in quads {
+		for i in [0, 1, 2, 0, 2, 3] {
+			positions.push([quad[i].x, quad[i].y, 0.0]);
+		}
+	}
+	let normals = vec![[0.0, 0.0, 1.0]; positions.len()];
+	let uvs : Vec<[f32; 2]> = positions.iter()
+		.map(|p| [p[0] / CELL_SIZE + 0.5, 0.5 - p[1] / CELL_SIZE])
+		.collect();
+	Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+		.with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+		.with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+		.with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+}
+
+// The corners of a rectangle, going around anticlockwise from the bottom left.
+fn rect_corners(min : Vec2, max : Vec2) -> [Vec2; 4] {
+	[min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)]
+}
+
+// An obstacle on the map. Running into one is just like running into the wall.
+#[derive(Component)]
+struct Obstacle;
+
+// The hint line at the bottom of the menu, which also shows when the game is muted.
+#[derive(Component)]
+struct MenuText;
+
+// The scrolling column the menu's buttons sit in.
+#[derive(Component)]
+struct MenuList;
+
+// The entries on the main menu, from top to bottom.
+#[derive(Component, Copy, Clone, Eq, PartialEq)]
+enum MenuEntry {
+	Play(GameMode),
+	// Plays one of the featured seeds in classic mode.
+	Featured,
+	Daily,
+	// Picks up the run that was going when the game was last quit.
+	Resume,
+	Map,
+	// Flips through the sizes of arena.
+	Arena,
+	Shape,
+	// Flips through the floors, plain, icy or muddy.
+	Floor,
+	Hazards,
+	// Whether obstacles kill the snake or just chop its tail.
+	Obstacles,
+	Events,
+	// Mirrors the screen or the controls, or neither.
+	Mirror,
+	// Opens the mutators screen.
+	Mutators,
+	Settings,
+	Progress,
+	Stats,
+	Replays,
+	// Saves a bug report.
+	Report,
+	Quit,
+}
+const MENU_ENTRIES : [MenuEntry; 26] = [
+	MenuEntry::Play(GameMode::Classic),
+	MenuEntry::Play(GameMode::Tron),
+	MenuEntry::Play(GameMode::TimeAttack),
+	MenuEntry::Play(GameMode::Survival),
+	MenuEntry::Play(GameMode::Zen),
+	MenuEntry::Play(GameMode::Hotseat),
+	MenuEntry::Play(GameMode::Banking),
+	MenuEntry::Play(GameMode::Boss),
+	MenuEntry::Featured,
+	MenuEntry::Daily,
+	MenuEntry::Resume,
+	MenuEntry::Map,
+	MenuEntry::Arena,
+	MenuEntry::Shape,
+	MenuEntry::Floor,
+	MenuEntry::Hazards,
+	MenuEntry::Obstacles,
+	MenuEntry::Events,
+	MenuEntry::Mirror,
+	MenuEntry::Mutators,
+	MenuEntry::Settings,
+	MenuEntry::Progress,
+	MenuEntry::Stats,
+	MenuEntry::Replays,
+	MenuEntry::Report,
+	MenuEntry::Quit,
+];
+impl MenuEntry {
+	fn label(self, map : &ArenaMap, featured : &featured::FeaturedSeeds) -> String {
+		match self {
+			MenuEntry::Play(mode) => mode.name().to_string(),
+			MenuEntry::Featured => match featured.picked() {
+				Some(picked) => format!("Featured: {}", picked.name),
+				None => "Featured: none".to_string(),
+			},
+			MenuEntry::Daily => "Daily Challenge".to_string(),
+			MenuEntry::Resume => "Resume Last Game".to_string(),
+			MenuEntry::Map => format!("Map: {}", map.symmetry.name()),
+			MenuEntry::Arena => format!("Arena: {}", map.arena.name()),
+			MenuEntry::Shape => format!("Shape: {}", map.shape.name()),
+			MenuEntry::Floor => format!("Floor: {}", map.floor.name()),
+			MenuEntry::Hazards => format!("Hazards: {}", map.hazards.name()),
+			MenuEntry::Obstacles => format!("Obstacles: {}", map.obstacle_hits.name()),
+			MenuEntry::Events => format!("Events: {}", if map.events { "On" } else { "Off" }),
+			MenuEntry::Mirror => format!("Mirror: {}", map.mirror.name()),
+			MenuEntry::Mutators => format!("Mutators: {}", map.mutators.summary()),
+			MenuEntry::Settings => "Settings".to_string(),
+			MenuEntry::Progress => "Progress".to_string(),
+			MenuEntry::Stats => "Stats".to_string(),
+			MenuEntry::Replays => "Imported Replays".to_string(),
+			MenuEntry::Report => "Save Bug Report".to_string(),
+			MenuEntry::Quit => "Quit".to_string(),
+		}
+	}
+}
+
+// Everything the menu can change about the next run.
+#[derive(SystemParam)]
+struct MenuPicks<'w> {
+	mode : ResMut<'w, GameMode>,
+	map : ResMut<'w, ArenaMap>,
+	featured : ResMut<'w, featured::FeaturedSeeds>,
+	daily : ResMut<'w, daily::Daily>,
+	snapshot : ResMut<'w, snapshot::PendingSnapshot>,
+}
+
+// The text inside a menu button, so the map and featured buttons can be relabeled.
+#[derive(Component)]
+struct MenuLabel(MenuEntry);
+
+// Which menu entry is picked. Stays put between visits to the menu.
+#[derive(Resource, Default)]
+struct MenuCursor(usize);
+
+// The fills of one chunk of the grid's cells, drawn as one mesh. Every cell gets its own
+// colour in the mesh, so cells can be recolored when they become deadly. Holds which size
+// and shape of arena it's for, and the chunk's bottom left cell.
+#[derive(Component)]
+struct BoardFill(ArenaSize, ArenaShape, GridPosition);
+
+// The text at the top of the screen that shows the score and timers while playing.
+#[derive(Component)]
+struct HudUI;
+
+// The big 3-2-1-GO in the middle of the screen.
+#[derive(Component)]
+struct CountdownUI;
+
+// The "Restart?" prompt, and how long it has left before it gives up waiting.
+#[derive(Component)]
+struct RestartPromptUI(Timer);
+
+// Query filter for everything on screen during a run that goes away when it ends.
+type RunUI = Or<(With<HudUI>, With<CountdownUI>, With<RestartPromptUI>)>;
+
+// Counts how long the main menu has been sitting idle, so we know when to start the demo.
+#[derive(Resource)]
+struct IdleTimer(Timer);
+
+
+
+// An enum that represents the possible directions.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)] // Allows us to use equality operators.
+pub enum Direction { None, Up, Down, Left, Right }
+impl Direction {
+	// The values for each of the enumerations. These can be of mixed types by the way!!
+	fn delta(self) -> (i32, i32) {
+		match self {
+			Direction::None => (0, 0),
+			Direction::Up => (0, 1),
+			Direction::Down => (0, -1),
+			Direction::Left => (-1, 0),
+			Direction::Right => (1, 0),
+		}
+	}
+	
+	// The direction on the other side, left to right.
+	fn mirrored(self) -> Direction {
+		match self {
+			Direction::Left => Direction::Right,
+			Direction::Right => Direction::Left,
+			other => other,
+		}
+	}
+
+	// The direction a quarter turn anticlockwise from this one.
+	fn turned_anticlockwise(self) -> Direction {
+		match self {
+			Direction::None => Direction::None,
+			Direction::Up => Direction::Left,
+			Direction::Left => Direction::Down,
+			Direction::Down => Direction::Right,
+			Direction::Right => Direction::Up,
+		}
+	}
+
+	// Which way you'd have to go from one cell to get to the cell next to it. Wrapping
+	// around the edge in Zen mode still counts as next to it.
+	fn between(from : GridPosition, to : GridPosition) -> Direction {
+		let step = |d : i32| if d.abs() > 1 { -d.signum() } else { d };
+		match (step(to.x - from.x), step(to.y - from.y)) {
+			(0, 1) => Direction::Up,
+			(0, -1) => Direction::Down,
+			(-1, 0) => Direction::Left,
+			(1, 0) => Direction::Right,
+			_ => Direction::None,
+		}
+	}
+
+	// The rotation that turns something drawn facing up to face this way.
+	fn facing(self) -> Quat {
+		let (dx, dy) = self.delta();
+		Quat::from_rotation_z((dy as f32).atan2(dx as f32) - std::f32::consts::FRAC_PI_2)
+	}
+
+	// Determines if this direction is opposite of the other direction.
+	// Used to determine if a player's movement input should be blocked or not.
+	// Eg. If you're going up and press down, you shouldn't be able to go straight down.
+	fn is_opposite(self, other : Direction) -> bool {
+		matches!(
+			(self, other),
+			(Direction::Up, Direction::Down) | 
+			(Direction::Down, Direction::Up) |
+			(Direction::Left, Direction::Right) | 
+			(Direction::Right, Direction::Left)
+		)
+	}
+}
+
+
+
+// The head of the snake. Used to sense collisions.
+#[derive(Component)]
+struct SnakeHead;
+
+// A segment of a snake that isn't the head.
+#[derive(Component)]
+struct SnakeSegment;
+
+// The green part of a segment, which changes shape to join up with its neighbours.
+#[derive(Component)]
+struct BodyFill;
+
+// The parts of a body fill that get reshaped. It's a mesh, or a sprite off the sheet.
+type FillParts<'a> = (Option<&'a mut Mesh2d>, Option<&'a mut Sprite>, &'a mut Transform);
+
+// Query filter for the head, but only on ticks where it actually moved.
+type HeadMoved = (With<SnakeHead>, Changed<GridPosition>);
+
+// Query filter for the food that's always there, and not the extra food from a frenzy.
+type NormalFood = (With<Food>, Without<events::FrenzyFood>);
+
+// A position on the main grid, instead of using pixel locations.
+#[derive(Component, Copy, Clone, Eq, PartialEq, Debug)]
+pub struct GridPosition {
+	pub x : i32,
+	pub y : i32,
+}
+
+// Where a piece of the snake was before the last tick, so it can be drawn sliding
+// smoothly between cells instead of jumping a whole cell at a time.
+#[derive(Component, Copy, Clone)]
+struct PreviousGridPosition(GridPosition);
+
+// Query filter for things that jump straight to their cell when they move, instead of sliding.
+type SnapsToGrid = (Changed<GridPosition>, Without<PreviousGridPosition>);
+
+// The food object.
+#[derive(Component)]
+struct Food;
+
+// The tile that banks the pot in banking mode.
+#[derive(Component)]
+struct Bank;
+
+// The bot playing the demo. Its reaction delay comes from the settings when the demo starts.
+#[derive(Resource, Default)]
+struct AiController {
+	reaction : Reaction,
+	// What a scripted bot decided this tick. The mods fill this in before the AI steers.
+	scripted : Option<Direction>,
+}
+
+// A bot's reaction time. Its decisions only go through this many ticks after they're
+// made, so it plays against the board as it was a few ticks ago, the same way a human has
+// to. Each bot has its own, so bots with different delays can be played against each other.
+#[derive(Default)]
+struct Reaction {
+	ticks : u32,
+	// Decisions waiting for their turn, oldest first.
+	pending : VecDeque<Direction>,
+}
+impl Reaction {
+	fn new(ticks : u32) -> Self {
+		Reaction { ticks, pending : VecDeque::new() }
+	}
+
+	// Hands over this tick's decision, and gives back the one that's due, if there is one.
+	// Facing is the way the snake's going now.
+	fn delay(&mut self, facing : Direction, decision : Direction) -> Option<Direction> {
+		// A snake that isn't moving yet has just been (re)spawned, so anything still
+		// waiting was decided for the old snake. Its first move goes straight through, or
+		// it would sit there forever waiting for decisions that keep getting thrown out.
+		if facing == Direction::None {
+			self.pending.clear();
+			return Some(decision);
+		}
+		self.pending.push_back(decision);
+		if self.pending.len() <= self.ticks as usize {
+			return None;
+		}
+		// An old decision can point straight back into the snake by now, so it has to pass
+		// the same check as the player's input.
+		self.pending.pop_front().filter(|&dir| !facing.is_opposite(dir))
+	}
+}
+
+// Everything the collision systems need to decide what happens once the snake dies.
+// Most of the time that's game over, but some modes just carry on with a fresh snake.
+#[derive(SystemParam)]
+struct DeathRules<'w> {
+	state : Res<'w, State<GameState>>,
+	map : Res<'w, ArenaMap>,
+	next_state : ResMut<'w, NextState<GameState>>,
+	mode : Res<'w, GameMode>,
+	round : ResMut<'w, RoundTimer>,
+	lives : ResMut<'w, Lives>,
+	countdown : ResMut<'w, Countdown>,
+	invincible : ResMut<'w, death::Invincible>,
+	died : MessageWriter<'w, SnakeDied>,
+}
+impl DeathRules<'_> {
+	// Whether the snake's just come back and can't be killed yet, other than by the walls
+	// or itself.
+	fn invincible(&self) -> bool {
+		self.invincible.active()
+	}
+
+	// Called after the dead snake has been left behind and a new one has been spawned.
+	// Length is how long the snake was when it died, head included.
+	fn snake_died(&mut self, snake : &mut SnakeState, length : usize, cause : stats::DeathCause) {
+		debug!(cause = cause.name(), length, "Snake died");
+		self.died.write(SnakeDied { cause, length });
+
+		// The demo just keeps going with a fresh snake.
+		if *self.state.get() == GameState::Demo {
+			return;
+		}
+
+		// In hotseat there's no second chance, whoever crashed has lost.
+		if *self.mode == GameMode::Hotseat {
+			self.lives.0 = 0;
+			self.next_state.set(GameState::Dying);
+			return;
+		}
+
+		// Time attack takes a bite out of the clock instead of ending the run.
+		if *self.mode == GameMode::TimeAttack {
+			let penalty = Duration::from_secs_f32(TIME_ATTACK_DEATH_PENALTY);
+			let elapsed = (self.round.0.elapsed() + penalty).min(self.round.0.duration());
+			self.round.0.set_elapsed(elapsed);
+			self.invincible.start();
+			return;
+		}
+
+		// With a life to spare, count down again and then carry on from the start
+		// with half the length. The new snake grows back into it once it starts moving.
+		if self.lives.0 > 1 {
+			self.lives.0 -= 1;
+			self.countdown.0 = COUNTDOWN_SECONDS;
+			snake.grow = respawn_growth(length);
+			self.invincible.start();
+			return;
+		}
+
+		// The game over screen waits for the death animation.
+		self.lives.0 = 0;
+		self.next_state.set(GameState::Dying);
+	}
+}
+
+// How many segments a respawned snake gets back, given how long it was when it died.
+fn respawn_growth(length : usize) -> u32 {
+	(length / 2).saturating_sub(1) as u32
+}
+
+// The information about the snake, such as it's direction, references to all of its
+// segments, and the number of segments that need to be added.
+// Works similarly to a global/static variable, stores a single copy of the data.
+#[derive(Resource)]
+struct SnakeState {
+	// The direction the snake is currently facing.
+	dir : Direction,
+	// The direction the snake should face on the next movement tick.
+	next_dir : Direction,
+	// References to the segments.
+	segments : Vec<Entity>,
+	// How many segments need to be added on the next tick.
+	grow : u32,
+}
+impl SnakeState {
+	// Cuts the snake off at the given segment, so that segment and everything behind it
+	// is gone. Hands back the segments that were cut off so they can be despawned.
+	fn truncate(&mut self, index : usize) -> Vec<Entity> {
+		if index >= self.segments.len() {
+			return Vec::new();
+		}
+		self.segments.split_off(index)
+	}
+}
+
+
+
+// Sets the game up and runs it. This is all the game's binary does, so the simulator can
+// be its own binary that shares everything else.
+pub fn run() {
+	/*
+	So, what are systems? They are a special feature of Bevy that helps us implement
+	the ECS structure. Instead of having normal functions, which are sent data when
+	they're called somewhere else, systems are called only when scheduled, and are
+	provided data from the ECS that matches their parameters.
+	
+	Eg. In the collision systems, they generally use the snake state, the head, and 
+	then provide a query to fetch the segments of the snake. 
+	
+	This allows us to do the following:
+	1) We use the position of the head compared to some other entity to see if it's
+	   collided with something.
+	2) If it has collided, we use the head and segment values in order to despawn all of
+	   their entities.
+	3) We update the properties of the snake state to be reset after the snake dies!
+	*/
+    // A crash saves a bug report on its way out.
+    #[cfg(feature = "native")]
+    report::install_crash_hook();
+    let settings = settings::Settings::load();
+    let mut app = App::new();
+    app
+    	// Default plugins provide us base rendering, physics, etc.
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title : "Rusty Snake".into(), // Set the window title here
+                // In the browser, the canvas fills whatever the page gives it.
+                fit_canvas_to_parent : true,
+                // other window settings can be added here
+                ..default()
+            }),
+            ..default()
+        }).set(logging::log_plugin()))
+        // Add the fixed timer that will be used when rendering objects and handle physics.
+        .insert_resource(Time::<Fixed>::from_duration(settings.move_period()))
+        .insert_resource(IdleTimer(Timer::from_seconds(DEMO_IDLE_SECONDS, TimerMode::Once)))
+        .insert_resource(settings)
+        .init_resource::<settings::MoveSpeed>()
+        // The snake, the food, and every step of a tick. Everything after this is drawing
+        // it, the screens around it, and the extra modes.
+        .add_plugins(RulesPlugin)
+        .init_resource::<settings::SettingsCursor>()
+        .insert_resource(progress::Progress::load())
+        .init_resource::<progress::SlowStart>()
+        .add_message::<AssetMissing>()
+        .init_resource::<AiController>()
+        // Startup systems to initialize the game and spawn starting objects.
+        .add_systems(Startup, (setup_camera_sys, setup_screen_sys))
+        // Each frame we need to align objects to the grid and get the user's input.
+        .add_systems(Update, (align_grid_to_world_sys, interpolate_snake_sys, shape_snake_body_sys, float_score_popups_sys))
+        // Allows us to close the game with the esc key.
+        .add_systems(Update, exit_sys)
+        // The sprite sheet takes over from the plain squares once it's loaded.
+        .init_resource::<sprites::SpriteSheet>()
+        .add_systems(Update, (sprites::check_sheet_sys, sprites::apply_sheet_sys, sprites::turn_head_sprite_sys).chain())
+        // Missing files don't stop the game, but the player gets told about them.
+        .add_systems(Update, (warn_missing_assets_sys, fade_toasts_sys))
+        // F3 shows what the game is holding on to, and what the snake is doing.
+        .add_plugins(FrameTimeDiagnosticsPlugin::default())
+        .add_systems(Update, (diagnostics::toggle_diagnostics_on_input, diagnostics::update_diagnostics_ui).chain())
+        // F4 and F5 step through a run one tick at a time.
+        .init_resource::<diagnostics::FrameStep>()
+        .add_systems(Update, (
+        		diagnostics::toggle_frame_step_on_input,
+        		diagnostics::step_tick_on_input.run_if(diagnostics::frame_stepping),
+        	).chain().run_if(in_state(GameState::Playing)))
+        .add_systems(OnExit(GameState::Playing), diagnostics::stop_frame_step_sys)
+        // The keys for muting and fullscreen, and putting the settings into effect.
+        .add_systems(Update, (
+        		settings::mute_on_input.run_if(not(leaderboard::typing_name)),
+        		settings::fullscreen_on_input,
+        		settings::speed_on_input.run_if(in_state(GameState::Playing).and(not(daily::playing_daily))),
+        		settings::reset_move_speed_sys,
+        		settings::apply_window_mode_sys,
+        		settings::apply_board_rotation_sys,
+        		settings::save_settings_sys,
+        	).chain())
+        // Recolours everything when a different palette is picked.
+        // The grid and the occupancy grid get rebuilt when a different sized arena is picked.
+        .add_systems(Update, (spawn_grid_sys, resize_occupancy_sys).before(theme::restyle_sys))
+        .add_systems(Update, (theme::apply_palette_sys, theme::apply_shapes_sys, theme::restyle_materials_sys, theme::restyle_sys, color_danger_zone_sys)
+        	.chain()
+        	.after(settings::save_settings_sys))
+        // Hotseat turns only count down after the collisions, so a crash is blamed on whoever
+        // made the move.
+        .add_systems(
+        	FixedUpdate, hotseat_turn_sys
+        	.in_set(TickSet::Spawn)
+        	.run_if(in_state(GameState::Playing).and(resource_equals(GameMode::Hotseat)))
+        	.run_if(countdown_done),
+        )
+        // The bank is checked after the food, so food eaten on the bank's cell goes straight
+        // into the bank.
+        .add_systems(
+        	FixedUpdate, (bank_collision_sys, bank_tick_sys)
+        	.chain()
+        	.in_set(TickSet::Spawn)
+        	.after(score_food_sys)
+        	.run_if(in_state(GameState::Playing).and(resource_equals(GameMode::Banking)))
+        	.run_if(countdown_done),
+        )
+        // The demo snake is steered by the AI instead of the keyboard.
+        .add_systems(OnEnter(GameState::Demo), start_demo_bot_sys)
+        .add_systems(FixedUpdate, ai_steer_sys.in_set(TickSet::Input).run_if(in_state(GameState::Demo)))
+        .init_resource::<MenuCursor>()
+        .add_systems(Update, (menu_input_sys, menu_idle_sys, (highlight_menu_sys, update_menu_text_sys))
+        	.chain()
+        	.run_if(in_state(GameState::Menu)))
+        .add_systems(Update, scroll_menu_sys.run_if(in_state(GameState::Menu)))
+        .add_systems(Update, exit_demo_on_input.run_if(in_state(GameState::Demo)))
+        .add_systems(OnEnter(GameState::Menu), (spawn_menu_ui, reset_idle_timer))
+        .add_systems(OnExit(GameState::Menu), cleanup_menu_ui)
+        .add_systems(OnEnter(GameState::Demo), spawn_demo_ui)
+        .add_systems(OnExit(GameState::Demo), cleanup_demo_ui)
+        .add_systems(Update, (settings::settings_menu_input, settings::update_settings_text_sys)
+        	.chain()
+        	.run_if(in_state(GameState::Settings)))
+        .add_systems(OnEnter(GameState::Settings), settings::spawn_settings_ui)
+        .add_systems(OnExit(GameState::Settings), settings::cleanup_settings_ui)
+        .add_systems(Update, (progress::progress_menu_input, progress::update_progress_text_sys)
+        	.chain()
+        	.run_if(in_state(GameState::Progress)))
+        .add_systems(OnEnter(GameState::Progress), progress::spawn_progress_ui)
+        .add_systems(OnExit(GameState::Progress), progress::cleanup_progress_ui)
+        // The slow start perk has the last say on how fast the snake goes.
+        .add_systems(Update, (progress::slow_start_sys.after(settings::reset_move_speed_sys), progress::save_progress_sys))
+        // Zen can't be lost and hotseat is shared, so neither of them count towards the ladder.
+        .add_systems(OnEnter(GameState::GameOver), progress::record_score_sys
+        	.run_if(not(resource_equals(GameMode::Zen).or(resource_equals(GameMode::Hotseat)))))
+        // Space can't restart while a name is being typed in for the leaderboard.
+        .add_systems(Update, restart_on_input.run_if(in_state(GameState::GameOver).and(not(leaderboard::typing_name))))
+        .add_systems(Update, restart_hotkey_sys.run_if(in_state(GameState::Playing)))
+        .add_systems(OnEnter(GameState::Restarting), restart_run_sys)
+        // Tron mode keeps score by how long you've been alive, and time attack counts down.
+        // None of the clocks run during the countdown.
+        .add_systems(Update, (
+        		(
+        			survival_time_sys.run_if(resource_equals(GameMode::Tron).or(resource_equals(GameMode::Survival))),
+        			shrink_arena_sys.run_if(resource_equals(GameMode::Survival)),
+        			round_timer_sys.run_if(resource_equals(GameMode::TimeAttack)),
+        		).run_if(countdown_done).before(countdown_sys),
+        		(update_hud_ui, update_countdown_ui).after(countdown_sys),
+        	)
+        	.chain()
+        	.run_if(in_state(GameState::Playing)))
+        // The perks go on after the last run's been cleared out, and before the run is seeded.
+        .add_systems(OnEnter(GameState::Playing), (progress::reset_slow_start_sys, progress::apply_start_growth_sys.run_if(not(daily::playing_daily)))
+        	.after(reset_hotseat_sys)
+        	.before(reseed_run_sys))
+        .add_systems(OnEnter(GameState::GameOver), replay::save_replay_sys
+        	.run_if(resource_equals(GameMode::Classic).and(map_is_open)))
+        .insert_resource(LowPower::from_args())
+        // Everything that changes the speed has had its say by the end of the frame, and the
+        // boost, storms, double speed and mods can change it at any time during a run, so the
+        // low power wait gets worked out again every frame after that.
+        .add_systems(PostUpdate, (
+        		settings::apply_move_speed_sys,
+        		low_power_schedule_sys.run_if(resource_equals(LowPower(true))),
+        	).chain())
+        .add_systems(OnExit(GameState::Playing), (cleanup_hud_ui, reset_bank_sys))
+        .add_systems(OnEnter(GameState::GameOver), spawn_game_over_ui)
+        .add_systems(OnExit(GameState::GameOver), cleanup_game_over_ui);
+
+    app.add_plugins((effects::EffectsPlugin, death::DeathPlugin, camera::FollowCameraPlugin, minimap::MinimapPlugin, input::InputPlugin, stats::StatsPlugin, skins::SkinsPlugin, daily::DailyPlugin, ghost::GhostPlugin, leaderboard::LeaderboardPlugin, history::HistoryPlugin, snapshot::SnapshotPlugin, logging::LoggingPlugin, screenshot::ScreenshotPlugin, pathfinding::PathOverlayPlugin));
+
+    // Shift for a burst of speed, while the stamina lasts, and ctrl to brake.
+    app.add_plugins(boost::BoostPlugin);
+
+    // Patches of ice or mud on the floor, when one is picked on the menu.
+    app.add_plugins(tiles::TilesPlugin);
+
+    // Bombs, the chaser, or lasers, when they're picked on the menu.
+    app.add_plugins(hazards::HazardsPlugin);
+
+    // The giant fruit in boss mode.
+    app.add_plugins(boss::BossPlugin);
+
+    // Coins to pick up during runs, and the shop to spend them in.
+    app.add_plugins(shop::ShopPlugin);
+
+    // Food frenzies, speed storms and blackouts, when events are switched on from the menu.
+    app.add_plugins(events::EventsPlugin);
+
+    // No walls, double speed, fog and the rest, when they're switched on from their screen.
+    app.add_plugins(mutators::MutatorsPlugin);
+
+    // Checking the imported replays on startup, and the screen that shows how they did.
+    app.add_plugins(replay::ReplaysPlugin);
+
+    // Music and sound effects are only there when the audio feature is.
+    #[cfg(feature = "audio")]
+    app.add_plugins((sfx::SfxPlugin, music::MusicPlugin));
+
+    // F9 saves a bug report with a screenshot in the middle of a run.
+    #[cfg(feature = "native")]
+    app.add_systems(Update, report::capture_bundle_on_input.run_if(in_state(GameState::Playing)));
+
+    // Runs that set a high score can be saved as a GIF from the game over screen.
+    #[cfg(feature = "native")]
+    app.add_plugins(clip::ClipPlugin);
+
+    // Mods are loaded from a folder, so they're only there on desktop.
+    #[cfg(feature = "native")]
+    app.add_plugins(mods::ModsPlugin);
+
+    // `--remote <address>` lets other programs play over a WebSocket.
+    #[cfg(feature = "native")]
+    app.add_plugins(remote::RemotePlugin);
+
+    app.run();
+}
+
+
+
+// The rules of the game, on their own. That's the snake, the food, and every step of a
+// tick, along with setting up and clearing out a run. Nothing in here needs a window, so
+// the tests can run it headless (see the headless module).
+struct RulesPlugin;
+impl Plugin for RulesPlugin {
+    fn build(&self, app : &mut App) {
+        app
+            .init_state::<GameState>()
+            // Adding the SnakeState to the project so it can be accessed.
+            // Kinda works like a global/static variable in a way.
+            .insert_resource(SnakeState {
+                dir : Direction::None,
+                next_dir : Direction::None,
+                segments : Vec::new(),
+                grow : 0,
+            })
+            .init_resource::<GameMode>()
+            .init_resource::<SurvivalTime>()
+            .init_resource::<Score>()
+            .init_resource::<Combo>()
+            .init_resource::<Hotseat>()
+            .init_resource::<Pot>()
+            .add_message::<FoodEaten>()
+            .insert_resource(Lives(STARTING_LIVES))
+            .init_resource::<Countdown>()
+            .insert_resource(RoundTimer(Timer::from_seconds(TIME_ATTACK_SECONDS, TimerMode::Once)))
+            .insert_resource(GameRng::from_seed(rand::random()))
+            .init_resource::<replay::ReplayRecorder>()
+            .init_resource::<DangerZone>()
+            .init_resource::<ArenaMap>()
+            .init_resource::<OccupancyGrid>()
+            // The meshes' materials start out in the theme's colours.
+            .init_resource::<theme::Theme>()
+            .init_resource::<CellMeshes>()
+            // These belong to other parts of the game, but the rules look at them. The game
+            // puts its own in, so these defaults only get used when running headless.
+            .init_resource::<settings::Settings>()
+            .init_resource::<featured::FeaturedSeeds>()
+            .init_resource::<daily::Daily>()
+            .init_resource::<death::Invincible>()
+            .add_message::<SnakeDied>()
+            .add_message::<DirectionChanged>()
+            .add_message::<SnakeGrew>()
+            .add_systems(Startup, (spawn_snake_sys, spawn_food_sys))
+            // Every tick goes through the same steps in the same order. See TickSet.
+            .configure_sets(FixedUpdate, (TickSet::Input, TickSet::Move, TickSet::Collide, TickSet::Grow, TickSet::Spawn).chain())
+            // This runs on every tick, even when the snake is held still, so a snake that isn't
+            // moving isn't drawn sliding.
+            .add_systems(FixedUpdate, remember_snake_positions_sys.in_set(TickSet::Input))
+            // Everything else that should be updated when the timer loops.
+            .add_systems(FixedUpdate, move_snake_sys
+            	.in_set(TickSet::Move)
+            	.run_if(in_state(GameState::Playing).or(in_state(GameState::Demo)))
+            	.run_if(countdown_done))
+            // A snake that just hit the wall has already been replaced by the time the rest get
+            // checked, so a dead snake can't bite itself or eat.
+            .add_systems(
+            	FixedUpdate, (
+            		wall_collision_sys,
+            		snake_collision_sys,
+            		food_collision_sys.run_if(not(resource_equals(GameMode::Tron))),
+            	)
+            	.chain()
+            	.in_set(TickSet::Collide)
+            	.run_if(in_state(GameState::Playing).or(in_state(GameState::Demo)))
+            	.run_if(countdown_done),
+            )
+            // A new segment starts out on the head when the snake had none, so the snake has
+            // to be checked before it grows. Food eaten this tick only adds a segment on the next
+            // one, once the snake has moved off it.
+            .add_systems(
+            	FixedUpdate, (grow_snake_sys, grow_on_food_sys)
+            	.chain()
+            	.in_set(TickSet::Grow)
+            	.run_if(in_state(GameState::Playing).or(in_state(GameState::Demo)))
+            	.run_if(countdown_done),
+            )
+            // Scoring waits until the collisions are done, so a death on the same tick has
+            // already broken the combo. Losing a life starts the countdown again straight
+            // away, so the forfeit can't wait for the countdown like the rest, or it'd never
+            // happen.
+            .add_systems(
+            	FixedUpdate, forfeit_on_death_sys
+            	.in_set(TickSet::Spawn)
+            	.before(combo_tick_sys)
+            	.run_if(in_state(GameState::Playing).or(in_state(GameState::Demo))),
+            )
+            .add_systems(
+            	FixedUpdate, ((combo_tick_sys, score_food_sys).chain(), replace_eaten_food_sys)
+            	.in_set(TickSet::Spawn)
+            	.run_if(in_state(GameState::Playing).or(in_state(GameState::Demo)))
+            	.run_if(countdown_done),
+            )
+            .add_systems(Update, countdown_sys.run_if(in_state(GameState::Playing)))
+            .add_systems(OnEnter(GameState::Playing), (
+            		reset_run_sys,
+            		reset_hotseat_sys,
+            		reseed_run_sys,
+            		build_arena_sys,
+            		place_snake_at_start_sys,
+            		respawn_food_sys,
+            		apply_mode_sys,
+            		replay::start_recording_sys,
+            	).chain())
+            // Replays are only kept for classic runs, since the other modes depend on the clock.
+            // The replay simulation doesn't know about obstacles either, so only the open map counts.
+            // The direction is written down straight after the move, before a death can reset it.
+            .add_systems(FixedUpdate, replay::record_tick_sys
+            	.in_set(TickSet::Move)
+            	.after(move_snake_sys)
+            	.run_if(in_state(GameState::Playing).and(resource_equals(GameMode::Classic)).and(map_is_open))
+            	.run_if(countdown_done));
+    }
+}
+
+
+
+// Spawns the camera. Not much else to say lol.
+fn setup_camera_sys(mut commands : Commands) {
+	commands.spawn(Camera2d);
+}
+
+
+
+// Create the window and set its starting dimensions. It can be resized after, and the
+// camera scales the board to fit. In the browser the page decides how big it is.
+fn setup_screen_sys(mut windows : Query<&mut Window, With<PrimaryWindow>>) {
+    if cfg!(target_arch = "wasm32") {
+        return;
+    }
+    let Ok(mut window) = windows.single_mut() else {
+        return;
+    };
+    let size = WORLD_SIZE as f32;
+    window.resolution.set(size, size);
+}
+
+
+// Basically takes all objects that have grid positions and moves them to the grid. 
+// This system is only called when an entities grid position changes.
+fn align_grid_to_world_sys(
+    mut query : Query<(&GridPosition, &mut Transform), SnapsToGrid>,
+) {
+    for (grid_pos, mut transform) in query.iter_mut() {
+        let world = grid_to_world(*grid_pos);
+        transform.translation = world.extend(transform.translation.z);
+    }
+}
+
+
+
+// The middle of a grid cell, in world coordinates.
+fn grid_to_world(grid_pos : GridPosition) -> Vec2 {
+    let x = -WORLD_SIZE as f32 / 2.0 + MARGIN as f32 + (grid_pos.x as f32 + 0.5) * CELL_SIZE;
+    let y = -WORLD_SIZE as f32 / 2.0 + MARGIN as f32 + (grid_pos.y as f32 + 0.5) * CELL_SIZE;
+    Vec2::new(x, y)
+}
+
+
+
+// Writes down where every piece of the snake is before the tick moves it.
+fn remember_snake_positions_sys(mut query : Query<(&GridPosition, &mut PreviousGridPosition)>) {
+    for (grid_pos, mut previous) in query.iter_mut() {
+        previous.0 = *grid_pos;
+    }
+}
+
+
+
+// Draws the snake part of the way from where it was to where it is now, depending on how
+// far along we are to the next tick. The logic still only moves a whole cell per tick.
+fn interpolate_snake_sys(
+    fixed : Res<Time<Fixed>>,
+    mut query : Query<(&GridPosition, &PreviousGridPosition, &mut Transform)>,
+) {
+    let t = fixed.overstep_fraction();
+    for (grid_pos, previous, mut transform) in query.iter_mut() {
+        // Wrapping around the edge in Zen mode jumps across the board, so don't slide
+        // the whole way over.
+        let jumped = (grid_pos.x - previous.0.x).abs() > 1 || (grid_pos.y - previous.0.y).abs() > 1;
+        let from = if jumped { *grid_pos } else { previous.0 };
+        let world = grid_to_world(from).lerp(grid_to_world(*grid_pos), t);
+        transform.translation = world.extend(transform.translation.z);
+    }
+}
+
+
+
+// Picks the body piece for every segment based on where the pieces either side of it are,
+// so the snake is drawn as one joined up body with corners where it turns and a tail on
+// the end. Works for both the plain meshes and the sprite sheet. Only needs doing when
+// the snake has moved.
+fn shape_snake_body_sys(
+    snake : Res<SnakeState>,
+    cells : Res<CellMeshes>,
+    head : Query<&GridPosition, HeadMoved>,
+    segments : Query<(&GridPosition, &Children), With<SnakeSegment>>,
+    mut fills : Query<FillParts, With<BodyFill>>,
+) {
+    let Ok(head_pos) = head.single() else {
+        return;
+    };
+    // A segment that's only just been spawned won't be here yet, so wait for it.
+    let Ok(body) = snake.segments.iter().map(|&e| segments.get(e)).collect::<Result<Vec<_>, _>>() else {
+        return;
+    };
+    for (i, (pos, children)) in body.iter().enumerate() {
+        let front = if i == 0 { *head_pos } else { *body[i - 1].0 };
+        let back = match body.get(i + 1) {
+            Some((back, _)) => Direction::between(**pos, **back),
+            None => Direction::None,
+        };
+        let (piece, rotation) = BodyPiece::between(Direction::between(**pos, front), back);
+        for child in children.iter() {
+            let Ok((mesh, sprite, mut transform)) = fills.get_mut(child) else {
+                continue;
+            };
+            if let Some(mut mesh) = mesh {
+                mesh.0 = cells.body_mesh(piece);
+            }
+            if let Some(atlas) = sprite.and_then(|sprite| sprite.into_inner().texture_atlas.as_mut()) {
+                atlas.index = sprites::piece_index(piece);
+            }
+            transform.rotation = rotation;
+        }
+    }
+}
+
+
+
+// Creates the grid, and makes it again whenever the arena changes size. Each chunk of it is
+// just two things: one mesh with the fills of its cells, and a backdrop in the outline
+// colour behind it, so the gaps between the fills make the lines. That keeps the number of
+// entities down however big the grid gets, and Bevy skips drawing the chunks that are off
+// the screen when the camera's following the snake around the big arena.
+fn spawn_grid_sys(
+	mut commands : Commands,
+	mut meshes : ResMut<Assets<Mesh>>,
+	danger : Res<DangerZone>,
+	theme : Res<theme::Theme>,
+	map : Res<ArenaMap>,
+	cells : Res<CellMeshes>,
+	boards : Query<(Entity, &BoardFill)>,
+) {
+	if boards.iter().any(|(_, board)| board.0 == map.arena && board.1 == map.shape) {
+		return;
+	}
+	for (e, _) in &boards {
+		commands.entity(e).despawn();
+	}
+
+	// Fills, coloured in by color_danger_zone_sys. The outline goes underneath them, with
+	// each cell's bit of it a little bigger than its fill, and only where there are cells.
+	let half = (CELL_SIZE - OUTLINE_WIDTH * 2.0) / 2.0;
+	for y in (0..map.height()).step_by(BOARD_CHUNK_CELLS as usize) {
+		for x in (0..map.width()).step_by(BOARD_CHUNK_CELLS as usize) {
+			let first = GridPosition { x, y };
+			let mut fills = Vec::new();
+			let mut outlines = Vec::new();
+			for pos in chunk_cells(map.arena, map.shape, first) {
+				let center = grid_to_world(pos);
+				fills.push(rect_corners(center - Vec2::splat(half), center + Vec2::splat(half)));
+				outlines.push(rect_corners(center - Vec2::splat(CELL_SIZE / 2.0), center + Vec2::splat(CELL_SIZE / 2.0)));
+			}
+			// Some shapes leave whole chunks out.
+			if fills.is_empty() {
+				continue;
+			}
+			let colors = board_colors(&danger, &theme, map.arena, map.shape, first);
+			commands.spawn((
+				BoardFill(map.arena, map.shape, first),
+				Mesh2d(meshes.add(quads_mesh(&fills).with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors))),
+				MeshMaterial2d(cells.board.clone()),
+				Transform::from_xyz(0.0, 0.0, 1.0),
+				Visibility::default(),
+			))
+			.with_children(|parent| {
+				parent.spawn((
+					Mesh2d(meshes.add(quads_mesh(&outlines))),
+					MeshMaterial2d(cells.line.clone()),
+					Transform::from_xyz(0.0, 0.0, -1.0),
+				));
+			});
+		}
+	}
+}
+
+// Every cell that's part of an arena, a row at a time from the bottom.
+fn arena_cells(arena : ArenaSize, shape : ArenaShape) -> impl Iterator<Item = GridPosition> {
+	let (width, height) = (arena.width(), arena.height());
+	(0..height)
+		.flat_map(move |y| (0..width).map(move |x| GridPosition { x, y }))
+		.filter(move |&pos| shape.contains(pos, width, height))
+}
+
+// The cells of the arena in the chunk that starts at this cell, a row at a time from the
+// bottom.
+fn chunk_cells(arena : ArenaSize, shape : ArenaShape, first : GridPosition) -> impl Iterator<Item = GridPosition> {
+	let (width, height) = (arena.width(), arena.height());
+	(first.y..(first.y + BOARD_CHUNK_CELLS).min(height))
+		.flat_map(move |y| (first.x..(first.x + BOARD_CHUNK_CELLS).min(width)).map(move |x| GridPosition { x, y }))
+		.filter(move |&pos| shape.contains(pos, width, height))
+}
+
+// The colour of every corner of every cell's fill in a chunk, in the same order
+// spawn_grid_sys puts the cells in.
+fn board_colors(danger : &DangerZone, theme : &theme::Theme, arena : ArenaSize, shape : ArenaShape, first : GridPosition) -> Vec<[f32; 4]> {
+	let mut colors = Vec::new();
+	for pos in chunk_cells(arena, shape, first) {
+		let color = if danger.is_deadly(pos, arena.width(), arena.height()) { theme.danger } else { theme.cell };
+		// Each cell is two triangles.
+		colors.extend([color.to_linear().to_f32_array(); 6]);
+	}
+	colors
+}
+
+
+
+// Spawns the snake into the game at the arena's starting position.
+fn spawn_snake_sys(mut commands : Commands, map : Res<ArenaMap>) {
+	spawn_snake(&mut commands, map.start());
+}
+
+fn spawn_snake(commands : &mut Commands, start : GridPosition) {
+	let _head = commands.spawn((
+		SnakeHead, 
+		start,
+		PreviousGridPosition(start),
+		Transform::default(),
+		Visibility::default(),
+	))
+	// Drawing the snake.
+	.with_children(|parent| {
+		// Outline
+		parent.spawn((
+			theme::Themed(theme::ThemeColor::Line),
+			Sprite {
+				custom_size : Some(Vec2::splat(CELL_SIZE)),
+				..default()
+			},
+			Transform::from_xyz(0.0, 0.0, 0.0),
+		));
+		// Fill
+		parent.spawn((
+			theme::Themed(theme::ThemeColor::Snake),
+			Sprite {
+				custom_size : Some(Vec2::splat(CELL_SIZE - OUTLINE_WIDTH * 4.0)),
+				..default()
+			},
+			Transform::from_xyz(0.0, 0.0, 2.0),
+		));
+	});
+}
+
+
+
+// Spawns the food at a random position.
+fn spawn_food_sys(mut commands : Commands, mut spawner : FoodSpawner) {
+	spawner.spawn(&mut commands);
+}
+
+
+
+// Spawns a single food at the given position.
+fn spawn_food_at(commands : &mut Commands, cells : &CellMeshes, pos : GridPosition) -> Entity {
+	debug!(x = pos.x, y = pos.y, "Spawned food");
+	commands.spawn((
+		Food, 
+		pos,
+		Transform::default(),
+		Visibility::default(),
+	))
+	// Drawing the food.
+	.with_children(|parent| {
+		// Outline
+		parent.spawn((
+			Mesh2d(cells.outline.clone()),
+			MeshMaterial2d(cells.line.clone()),
+			Transform::from_xyz(0.0, 0.0, 0.0),
+		));
+		// Fill. It has to sit above the grid's fill, which is at 1.
+		parent.spawn((
+			effects::Pulse,
+			Mesh2d(cells.food_fill.clone()),
+			MeshMaterial2d(cells.food.clone()),
+			Transform::from_xyz(0.0, 0.0, 2.0),
+		));
+	})
+	.id()
+}
+
+
+
+// Sums up the run that just ended. The leaderboard adds itself underneath.
+fn spawn_game_over_ui(
+    mut commands: Commands,
+    run : RunInfo,
+    run_stats : Res<stats::RunStats>,
+    daily : Res<daily::Daily>,
+    board : Res<leaderboard::Leaderboard>,
+    map : Res<ArenaMap>,
+) {
+    let headline = match *run.mode {
+        GameMode::TimeAttack => "TIME'S UP".to_string(),
+        GameMode::Hotseat => format!("PLAYER {} CRASHED\nPlayer {} wins", run.hotseat.player, run.hotseat.other_player()),
+        GameMode::Boss if run.boss.beaten => "BOSS BEATEN".to_string(),
+        _ => "GAME OVER".to_string(),
+    };
+    let headline = if daily.playing { format!("DAILY CHALLENGE\n{headline}") } else { headline };
+    let score = match *run.mode {
+        GameMode::Tron => format!("You survived {:.1}s", run.survived.0),
+        GameMode::Survival => format!("You survived {:.1}s and scored {}", run.survived.0, run.score.0),
+        GameMode::Banking => format!("You banked {}", run.score.0),
+        _ => format!("You scored {}", run.score.0),
+    };
+    let mut message = format!("{headline}\n{score}\n");
+    if board.is_high_score(*run.mode, map.mutators, run.score.0) {
+        message.push_str("New high score!\n");
+    }
+    if daily.playing {
+        message.push_str(&format!("Best today: {}\n", daily.best_today()));
+    }
+    message.push_str(&format!(
+        "Length: {}   Time: {:.1}s\nFood eaten: {}   Turns: {}\n",
+        run_stats.length,
+        run_stats.seconds,
+        run_stats.food_eaten,
+        run_stats.turns,
+    ));
+    // Time attack ends on the clock, not a death.
+    if let Some(cause) = run_stats.cause && *run.mode != GameMode::TimeAttack {
+        message.push_str(&format!("{}\n", cause.name()));
+    }
+    message.push_str("Press SPACE to restart");
+
+    commands.spawn((
+        GameOverUI,
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(24.0),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+    ))
+    .with_children(|parent| {
+        parent.spawn((
+    		Text::new(message),
+    		TextFont {
+        		font_size : 36.0,
+        		..default()
+    		},
+    		TextColor(WHITE),
+    		TextLayout::new(Justify::Center, LineBreak::WordBoundary),
+		));
+    });
+}
+
+
+
+fn cleanup_game_over_ui(
+    mut commands: Commands,
+    ui: Query<Entity, With<GameOverUI>>,
+) {
+    for e in &ui {
+        commands.entity(e).despawn();
+    }
+}
+
+
+
+fn restart_on_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut next_state : ResMut<NextState<GameState>>,
+) {
+    if keys.just_pressed(KeyCode::Space) {
+        next_state.set(GameState::Restarting);
+    }
+}
+
+
+
+// R restarts the run without having to die first. Once the run is worth something, the
+// first press only asks "Restart?", and it takes a second press to go through with it.
+fn restart_hotkey_sys(
+    mut commands : Commands,
+    keys : Res<ButtonInput<KeyCode>>,
+    time : Res<Time>,
+    score : Res<Score>,
+    mut prompt : Query<(Entity, &mut RestartPromptUI)>,
+    mut next_state : ResMut<NextState<GameState>>,
+) {
+    let mut asking = false;
+    for (e, mut timer) in prompt.iter_mut() {
+        timer.0.tick(time.delta());
+        if timer.0.is_finished() {
+            commands.entity(e).despawn();
+        } else {
+            asking = true;
+        }
+    }
+
+    if !keys.just_pressed(KeyCode::KeyR) {
+        return;
+    }
+    if asking || score.0 < RESTART_CONFIRM_SCORE {
+        next_state.set(GameState::Restarting);
+        return;
+    }
+    commands.spawn((
+        RestartPromptUI(Timer::from_seconds(RESTART_CONFIRM_SECONDS, TimerMode::Once)),
+        Text::new("Restart? Press R again"),
+        TextFont {
+            font_size : 48.0,
+            ..default()
+        },
+        theme::Themed(theme::ThemeColor::Text),
+        TextLayout::new_with_justify(Justify::Center),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(25.0),
+            width: Val::Percent(100.0),
+            ..default()
+        },
+    ));
+}
+
+
+
+// Swaps the old snake for a fresh one at the start, then heads back into Playing, which
+// sets up everything else for the new run.
+fn restart_run_sys(
+    mut commands : Commands,
+    mut next_state : ResMut<NextState<GameState>>,
+    mut fresh : FreshSnake,
+) {
+    fresh.respawn(&mut commands);
+    next_state.set(GameState::Playing);
+}
+
+// Everything that goes into swapping the snake for a fresh one at the start.
+#[derive(SystemParam)]
+struct FreshSnake<'w, 's> {
+    map : Res<'w, ArenaMap>,
+    snake : ResMut<'w, SnakeState>,
+    occupancy : ResMut<'w, OccupancyGrid>,
+    head : Query<'w, 's, Entity, With<SnakeHead>>,
+    segments : Query<'w, 's, Entity, With<SnakeSegment>>,
+}
+impl FreshSnake<'_, '_> {
+    fn respawn(&mut self, commands : &mut Commands) {
+        // Despawn snake
+        if let Ok(e) = self.head.single() {
+            commands.entity(e).despawn();
+        }
+        for e in self.segments.iter() {
+            commands.entity(e).despawn();
+        }
+
+        // Reset state
+        self.snake.segments.clear();
+        self.snake.dir = Direction::None;
+        self.snake.next_dir = Direction::None;
+        self.snake.grow = 0;
+        self.occupancy.clear_segments();
+
+        // Respawn
+        spawn_snake(commands, self.map.start());
+    }
+}
+
+
+
+// The title screen that shows up when the game is launched. It's a column of buttons
+// that can be picked with the arrow keys and enter, or clicked on. The column scrolls,
+// since they don't all fit on the screen.
+fn spawn_menu_ui(
+    mut commands: Commands,
+    map : Res<ArenaMap>,
+    settings : Res<settings::Settings>,
+    cursor : Res<MenuCursor>,
+    featured : Res<featured::FeaturedSeeds>,
+    daily : Res<daily::Daily>,
+) {
+    commands.spawn((
+        MenuUI,
+        Node {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(6.0),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+    ))
+    .with_children(|parent| {
+        parent.spawn((
+    		Text::new("RUSTY SNAKE"),
+    		TextFont {
+        		font_size : 56.0,
+        		..default()
+    		},
+    		TextColor(WHITE),
+		));
+        parent.spawn((
+            MenuList,
+            Node {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(MENU_GAP),
+                height: Val::Px(MENU_LIST_HEIGHT),
+                overflow: Overflow::scroll_y(),
+                ..default()
+            },
+            ScrollPosition(Vec2::new(0.0, menu_scroll_to(cursor.0, 0.0))),
+        ))
+        .with_children(|list| {
+            for (i, entry) in MENU_ENTRIES.into_iter().enumerate() {
+                list.spawn((
+                    Button,
+                    entry,
+                    Node {
+                        width: Val::Px(360.0),
+                        height: Val::Px(MENU_ENTRY_HEIGHT),
+                        // Without this the buttons get squashed to fit instead of scrolling.
+                        flex_shrink: 0.0,
+                        padding: UiRect::all(Val::Px(6.0)),
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    BackgroundColor(menu_entry_color(i == cursor.0)),
+                ))
+                .with_children(|button| {
+                    button.spawn((
+                        MenuLabel(entry),
+                        Text::new(entry.label(&map, &featured)),
+                        TextFont {
+                            font_size : 32.0,
+                            ..default()
+                        },
+                        TextColor(WHITE),
+                    ));
+                });
+            }
+        });
+        parent.spawn((
+    		MenuText,
+    		Text::new(menu_hint(&settings, MENU_ENTRIES[cursor.0], &featured, &daily)),
+    		TextFont {
+        		font_size : 20.0,
+        		..default()
+    		},
+    		TextColor(WHITE),
+		));
+    });
+}
+
+
+
+fn cleanup_menu_ui(
+    mut commands: Commands,
+    ui: Query<Entity, With<MenuUI>>,
+) {
+    for e in &ui {
+        commands.entity(e).despawn();
+    }
+}
+
+
+
+// A small banner so people know the snake isn't being played by a ghost.
+fn spawn_demo_ui(mut commands: Commands) {
+    commands.spawn((
+        DemoUI,
+        Node {
+            width: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            top: Val::Px(MARGIN as f32),
+            ..default()
+        },
+    ))
+    .with_children(|parent| {
+        parent.spawn((
+    		Text::new("DEMO - Press any key"),
+    		TextFont {
+        		font_size : 32.0,
+        		..default()
+    		},
+    		theme::Themed(theme::ThemeColor::Text),
+		));
+    });
+}
+
+
+
+fn cleanup_demo_ui(
+    mut commands: Commands,
+    ui: Query<Entity, With<DemoUI>>,
+) {
+    for e in &ui {
+        commands.entity(e).despawn();
+    }
+}
+
+
+
+// Moves around the menu and picks entries, with either the keyboard or the mouse.
+fn menu_input_sys(
+    mut commands : Commands,
+    keys : Res<ButtonInput<KeyCode>>,
+    buttons : Query<(&Interaction, &MenuEntry), Changed<Interaction>>,
+    mut cursor : ResMut<MenuCursor>,
+    mut picks : MenuPicks,
+    mut next_state : ResMut<NextState<GameState>>,
+    mut exit : MessageWriter<AppExit>,
+) {
+    let mut chosen = None;
+    for (interaction, entry) in buttons.iter() {
+        let index = MENU_ENTRIES.iter().position(|e| e == entry).unwrap_or(0);
+        match interaction {
+            Interaction::Hovered => cursor.0 = index,
+            Interaction::Pressed => {
+                cursor.0 = index;
+                chosen = Some(*entry);
+            }
+            Interaction::None => {}
+        }
+    }
+
+    if keys.just_pressed(KeyCode::ArrowUp) {
+        cursor.0 = (cursor.0 + MENU_ENTRIES.len() - 1) % MENU_ENTRIES.len();
+    }
+    if keys.just_pressed(KeyCode::ArrowDown) {
+        cursor.0 = (cursor.0 + 1) % MENU_ENTRIES.len();
+    }
+    if settings::enter_pressed(&keys) || keys.just_pressed(KeyCode::Space) {
+        chosen = Some(MENU_ENTRIES[cursor.0]);
+    }
+    // Left and right flip through the featured seeds.
+    if MENU_ENTRIES[cursor.0] == MenuEntry::Featured {
+        if keys.just_pressed(KeyCode::ArrowLeft) {
+            picks.featured.cycle(-1);
+        }
+        if keys.just_pressed(KeyCode::ArrowRight) {
+            picks.featured.cycle(1);
+        }
+    }
+
+    match chosen {
+        Some(MenuEntry::Play(picked)) => {
+            *picks.mode = picked;
+            picks.featured.playing = false;
+            picks.daily.playing = false;
+            next_state.set(GameState::Playing);
+        }
+        // A featured seed is always played with the classic rules on the open map, so
+        // everyone gets the same run.
+        Some(MenuEntry::Featured) if picks.featured.picked().is_some() => {
+            *picks.mode = GameMode::Classic;
+            picks.map.symmetry = MapSymmetry::Open;
+            picks.map.arena = ArenaSize::Normal;
+            picks.map.shape = ArenaShape::Rectangle;
+            picks.map.floor = tiles::Floor::Plain;
+            picks.map.hazards = hazards::Hazards::Off;
+            picks.map.obstacle_hits = ObstacleHits::Deadly;
+            picks.map.events = false;
+            picks.map.mirror = Mirror::Off;
+            picks.map.mutators = mutators::Mutators::default();
+            picks.featured.playing = true;
+            picks.daily.playing = false;
+            next_state.set(GameState::Playing);
+        }
+        // The daily challenge picks its own map, so that goes with it.
+        Some(MenuEntry::Daily) => {
+            picks.daily.start();
+            *picks.mode = GameMode::Classic;
+            picks.map.symmetry = picks.daily.map();
+            picks.map.arena = ArenaSize::Normal;
+            picks.map.shape = ArenaShape::Rectangle;
+            picks.map.floor = tiles::Floor::Plain;
+            picks.map.hazards = hazards::Hazards::Off;
+            picks.map.obstacle_hits = ObstacleHits::Deadly;
+            picks.map.events = false;
+            picks.map.mirror = Mirror::Off;
+            picks.map.mutators = mutators::Mutators::default();
+            picks.featured.playing = false;
+            next_state.set(GameState::Playing);
+        }
+        Some(MenuEntry::Resume) => match snapshot::resume_last_game(&mut picks) {
+            Ok(()) => next_state.set(GameState::Playing),
+            Err(err) => spawn_toast(&mut commands, format!("Couldn't resume the last game: {err}")),
+        },
+        Some(MenuEntry::Map) => picks.map.symmetry = picks.map.symmetry.next(),
+        Some(MenuEntry::Arena) => picks.map.arena = picks.map.arena.next(),
+        Some(MenuEntry::Shape) => picks.map.shape = picks.map.shape.next(),
+        Some(MenuEntry::Floor) => picks.map.floor = picks.map.floor.next(),
+        Some(MenuEntry::Hazards) => picks.map.hazards = picks.map.hazards.next(),
+        Some(MenuEntry::Obstacles) => picks.map.obstacle_hits = picks.map.obstacle_hits.next(),
+        Some(MenuEntry::Events) => picks.map.events = !picks.map.events,
+        Some(MenuEntry::Mirror) => picks.map.mirror = picks.map.mirror.next(),
+        Some(MenuEntry::Mutators) => next_state.set(GameState::Mutators),
+        Some(MenuEntry::Settings) => next_state.set(GameState::Settings),
+        Some(MenuEntry::Progress) => next_state.set(GameState::Progress),
+        Some(MenuEntry::Stats) => next_state.set(GameState::Stats),
+        Some(MenuEntry::Replays) => next_state.set(GameState::Replays),
+        #[cfg(feature = "native")]
+        Some(MenuEntry::Report) => match report::write_report(None) {
+            Ok(path) => spawn_toast(&mut commands, format!("Saved a bug report to {}", path.display())),
+            Err(err) => {
+                warn!("Couldn't save a bug report: {err}");
+                spawn_toast(&mut commands, "Couldn't save a bug report");
+            }
+        },
+        // Reports are files for attaching to an issue, so there's nowhere to put one in the browser.
+        #[cfg(not(feature = "native"))]
+        Some(MenuEntry::Report) => spawn_toast(&mut commands, "Bug reports can only be saved on desktop"),
+        Some(MenuEntry::Quit) => {
+            exit.write(AppExit::Success);
+        }
+        // Nothing picked, or the featured entry with no seed to play.
+        Some(MenuEntry::Featured) | None => {}
+    }
+}
+
+
+
+// Lights up whichever menu entry is picked, and scrolls the menu so it can be seen.
+fn highlight_menu_sys(
+    cursor : Res<MenuCursor>,
+    mut buttons : Query<(&MenuEntry, &mut BackgroundColor)>,
+    mut list : Query<&mut ScrollPosition, With<MenuList>>,
+) {
+    if !cursor.is_changed() {
+        return;
+    }
+    for (entry, mut color) in buttons.iter_mut() {
+        let picked = MENU_ENTRIES[cursor.0] == *entry;
+        color.0 = menu_entry_color(picked);
+    }
+    for mut scroll in list.iter_mut() {
+        scroll.y = menu_scroll_to(cursor.0, scroll.y);
+    }
+}
+
+
+
+// How far down the menu has to be scrolled for the entry at this index to be on screen.
+// It only moves as far as it needs to from where it is now.
+fn menu_scroll_to(index : usize, scroll : f32) -> f32 {
+    let top = index as f32 * (MENU_ENTRY_HEIGHT + MENU_GAP);
+    scroll.clamp(top + MENU_ENTRY_HEIGHT - MENU_LIST_HEIGHT, top)
+}
+
+
+
+// Scrolls the menu with the mouse wheel.
+fn scroll_menu_sys(mut wheel : MessageReader<MouseWheel>, mut list : Query<&mut ScrollPosition, With<MenuList>>) {
+    let scrolled : f32 = wheel.read().map(|event| match event.unit {
+        MouseScrollUnit::Line => event.y * (MENU_ENTRY_HEIGHT + MENU_GAP),
+        MouseScrollUnit::Pixel => event.y,
+    }).sum();
+    if scrolled == 0.0 {
+        return;
+    }
+    let bottom = (MENU_ENTRIES.len() - MENU_VISIBLE_ENTRIES) as f32 * (MENU_ENTRY_HEIGHT + MENU_GAP);
+    for mut scroll in list.iter_mut() {
+        scroll.y = (scroll.y - scrolled).clamp(0.0, bottom);
+    }
+}
+
+
+
+fn menu_entry_color(picked : bool) -> Color {
+    if picked { GREEN } else { DARK_GREY }
+}
+
+
+
+// Sets the board up for the chosen mode when a run starts. Tron doesn't have any food,
+// so it gets hidden (and food collisions are turned off in main).
+fn apply_mode_sys(
+    mut commands : Commands,
+    mode : Res<GameMode>,
+    mut food : Query<&mut Visibility, With<Food>>,
+) {
+    for mut visibility in food.iter_mut() {
+        *visibility = match *mode {
+            GameMode::Tron => Visibility::Hidden,
+            _ => Visibility::Inherited,
+        };
+    }
+
+    commands.spawn((
+        HudUI,
+        Text::new(""),
+        TextFont {
+            font_size : 32.0,
+            ..default()
+        },
+        theme::Themed(theme::ThemeColor::Text),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(MARGIN as f32),
+            left: Val::Px(MARGIN as f32 * 2.0),
+            ..default()
+        },
+    ));
+
+    commands.spawn((
+        CountdownUI,
+        Text::new(""),
+        TextFont {
+            font_size : 120.0,
+            ..default()
+        },
+        theme::Themed(theme::ThemeColor::Text),
+        TextLayout::new_with_justify(Justify::Center),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Percent(40.0),
+            width: Val::Percent(100.0),
+            ..default()
+        },
+    ));
+}
+
+
+
+// Gives every run its own seed, so the whole run can be replayed from the seed and the
+// player's inputs. Featured runs get their featured seed instead, and the daily challenge
+// gets the day's seed.
+fn reseed_run_sys(mut rng : ResMut<GameRng>, featured : Res<featured::FeaturedSeeds>, daily : Res<daily::Daily>) {
+    let seed = featured.seed_to_play().or(daily.seed_to_play());
+    *rng = GameRng::from_seed(seed.unwrap_or_else(rand::random));
+}
+
+
+
+// Puts the food somewhere new for the start of a run. Has to happen after the arena is
+// built so it doesn't land on an obstacle.
+fn respawn_food_sys(
+    mut commands : Commands,
+    spawner : FoodSpawner,
+    food : Query<Entity, With<Food>>,
+) {
+    for e in &food {
+        commands.entity(e).despawn();
+    }
+    spawn_food_sys(commands, spawner);
+}
+
+
+
+// Moves the snake that's waiting for the run to the arena's start, since the arena might
+// have changed shape since it was spawned.
+fn place_snake_at_start_sys(map : Res<ArenaMap>, mut head : Query<(&mut GridPosition, &mut PreviousGridPosition), With<SnakeHead>>) {
+    for (mut pos, mut previous) in head.iter_mut() {
+        pos.set_if_neq(map.start());
+        previous.0 = map.start();
+    }
+}
+
+
+
+// Lays out the obstacles for the chosen map. Obstacles are placed in matching pairs, and
+// the middle of the board is kept clear so the snake doesn't start next to a wall.
+fn build_arena_sys(
+    mut commands : Commands,
+    mut map : ResMut<ArenaMap>,
+    mut rng : ResMut<GameRng>,
+    mode : Res<GameMode>,
+    cells : Res<CellMeshes>,
+    mut occupancy : ResMut<OccupancyGrid>,
+    obstacles : Query<Entity, With<Obstacle>>,
+) {
+    for e in &obstacles {
+        commands.entity(e).despawn();
+    }
+    map.obstacles.clear();
+    occupancy.clear_obstacles();
+
+    // Zen is for practicing, and there's no dying in it, so it always gets an open arena.
+    // The open map has nothing to lay out, and mustn't take anything from the food's
+    // generator either, or replays of it would get different food.
+    if *mode == GameMode::Zen || map.symmetry == MapSymmetry::Open {
+        return;
+    }
+
+    let start = map.start();
+    let near_start = |pos : GridPosition| {
+        (pos.x - start.x).abs() <= 2 && (pos.y - start.y).abs() <= 2
+    };
+    let mut attempts = 0;
+    while map.obstacles.len() < MAP_OBSTACLE_PAIRS * 2 && attempts < 1000 {
+        attempts += 1;
+        let pos = get_random_pos(&mut rng.rng, &DangerZone::default(), map.width(), map.height());
+        let Some(partner) = map.symmetry.partner(pos, map.width(), map.height()) else {
+            break;
+        };
+        if !map.contains(pos) || near_start(pos) || near_start(partner) || map.obstacles.contains(&pos) || map.obstacles.contains(&partner) {
+            continue;
+        }
+        map.obstacles.push(pos);
+        if partner != pos {
+            map.obstacles.push(partner);
+        }
+    }
+
+    for &pos in map.obstacles.iter() {
+        occupancy.add_obstacle(pos);
+        spawn_obstacle_at(&mut commands, &cells, pos);
+    }
+    debug!(count = map.obstacles.len(), symmetry = map.symmetry.name(), "Laid out obstacles");
+}
+
+
+
+// Spawns a single obstacle at the given position.
+fn spawn_obstacle_at(commands : &mut Commands, cells : &CellMeshes, pos : GridPosition) {
+    commands.spawn((
+        Obstacle,
+        pos,
+        Transform::default(),
+        Visibility::default(),
+    ))
+    .with_children(|parent| {
+        // Outline
+        parent.spawn((
+            theme::Themed(theme::ThemeColor::Line),
+            Sprite {
+                custom_size : Some(Vec2::splat(CELL_SIZE)),
+                ..default()
+            },
+            Transform::from_xyz(0.0, 0.0, 0.0),
+        ));
+        // Fill
+        parent.spawn((
+            Mesh2d(cells.obstacle_fill.clone()),
+            MeshMaterial2d(cells.obstacle.clone()),
+            Transform::from_xyz(0.0, 0.0, 2.0),
+        ));
+    });
+}
+
+
+
+// The replay simulation only knows about the normal sized arena with nothing on it, and
+// the normal rules.
+fn map_is_open(map : Res<ArenaMap>) -> bool {
+    map.symmetry == MapSymmetry::Open && map.arena == ArenaSize::Normal && map.shape == ArenaShape::Rectangle
+        && map.floor == tiles::Floor::Plain && map.hazards == hazards::Hazards::Off && !map.events && map.mutators.is_empty()
+}
+
+
+
+// The line under the menu. On the featured entry it describes the picked seed, and on the
+// daily entry it describes today's board.
+fn menu_hint(settings : &settings::Settings, entry : MenuEntry, featured : &featured::FeaturedSeeds, daily : &daily::Daily) -> String {
+    let mute = format!("M to mute{}", if settings.muted { " (muted)" } else { "" });
+    match (entry, featured.picked()) {
+        (MenuEntry::Featured, Some(picked)) => {
+            format!("{}\nLEFT/RIGHT for another seed, ENTER to play it. {mute}", picked.description)
+        }
+        (MenuEntry::Daily, _) => format!("{}\nENTER to play it. {mute}", daily.describe()),
+        (MenuEntry::Resume, _) => format!("Carry on from where you quit last time. {mute}"),
+        _ => format!("UP/DOWN and ENTER, or click. {mute}"),
+    }
+}
+
+
+
+// Keeps the map and featured buttons and the hint up to date.
+fn update_menu_text_sys(
+    map : Res<ArenaMap>,
+    settings : Res<settings::Settings>,
+    cursor : Res<MenuCursor>,
+    featured : Res<featured::FeaturedSeeds>,
+    daily : Res<daily::Daily>,
+    mut labels : Query<(&MenuLabel, &mut Text), Without<MenuText>>,
+    mut hint : Query<&mut Text, With<MenuText>>,
+) {
+    if map.is_changed() || featured.is_changed() {
+        for (label, mut text) in labels.iter_mut() {
+            text.0 = label.0.label(&map, &featured);
+        }
+    }
+    if settings.is_changed() || cursor.is_changed() || featured.is_changed() {
+        for mut text in hint.iter_mut() {
+            text.0 = menu_hint(&settings, MENU_ENTRIES[cursor.0], &featured, &daily);
+        }
+    }
+}
+
+
+
+// Clears out the score and timers from the last run.
+fn reset_run_sys(
+    mut survived : ResMut<SurvivalTime>,
+    mut score : ResMut<Score>,
+    mut round : ResMut<RoundTimer>,
+    mut danger : ResMut<DangerZone>,
+    mut lives : ResMut<Lives>,
+    mut countdown : ResMut<Countdown>,
+    mut combo : ResMut<Combo>,
+) {
+    survived.0 = 0.0;
+    score.0 = 0;
+    round.0.reset();
+    *danger = DangerZone::default();
+    lives.0 = STARTING_LIVES;
+    countdown.0 = COUNTDOWN_SECONDS;
+    *combo = Combo::default();
+}
+
+
+
+// Runs the countdown down. When it runs out, a snake that hasn't been pointed anywhere
+// yet sets off upwards.
+fn countdown_sys(time : Res<Time>, mut countdown : ResMut<Countdown>, mut snake : ResMut<SnakeState>) {
+    if countdown.0 <= -COUNTDOWN_GO_SECONDS {
+        return;
+    }
+    let was_counting = countdown.0 > 0.0;
+    countdown.0 = (countdown.0 - time.delta_secs()).max(-COUNTDOWN_GO_SECONDS);
+    if was_counting && countdown.0 <= 0.0 && snake.next_dir == Direction::None {
+        snake.next_dir = Direction::Up;
+    }
+}
+
+
+
+// Run condition that holds the game still while the countdown is going.
+fn countdown_done(countdown : Res<Countdown>) -> bool {
+    countdown.0 <= 0.0
+}
+
+
+
+// The clock only starts once the snake does, so nobody gets punished for taking a
+// moment before the first key press.
+fn survival_time_sys(
+    time : Res<Time>,
+    snake : Res<SnakeState>,
+    mut survived : ResMut<SurvivalTime>,
+) {
+    if snake.dir != Direction::None {
+        survived.0 += time.delta_secs();
+    }
+}
+
+
+
+// Counts the time attack clock down. Like the survival clock it waits for the snake's
+// first move, but after that it keeps running, even while a respawned snake sits still.
+fn round_timer_sys(
+    time : Res<Time>,
+    snake : Res<SnakeState>,
+    mut round : ResMut<RoundTimer>,
+    mut next_state : ResMut<NextState<GameState>>,
+) {
+    if snake.dir == Direction::None && round.0.elapsed().is_zero() {
+        return;
+    }
+    if round.0.tick(time.delta()).is_finished() {
+        next_state.set(GameState::GameOver);
+    }
+}
+
+
+
+// Makes the next ring of the arena deadly every so often, and moves the food out of the
+// way if it's been caught in it. Waits for the snake's first move like the other clocks.
+fn shrink_arena_sys(
+    time : Res<Time>,
+    snake : Res<SnakeState>,
+    mut danger : ResMut<DangerZone>,
+    mut rng : ResMut<GameRng>,
+    map : Res<ArenaMap>,
+    mut food : Query<&mut GridPosition, With<Food>>,
+) {
+    if snake.dir == Direction::None {
+        return;
+    }
+    let max_rings = (map.width().min(map.height()) - SURVIVAL_MIN_CELLS) / 2;
+    if !danger.timer.tick(time.delta()).just_finished() || danger.rings >= max_rings {
+        return;
+    }
+    // The middle of the donut is its hole, so it runs out of room before it gets that far.
+    let next = DangerZone { rings : danger.rings + 1, ..default() };
+    let left = arena_cells(map.arena, map.shape).filter(|&pos| !next.is_deadly(pos, map.width(), map.height())).count();
+    if left < (SURVIVAL_MIN_CELLS * SURVIVAL_MIN_CELLS) as usize {
+        return;
+    }
+
+    danger.rings += 1;
+    for mut food_pos in food.iter_mut() {
+        if danger.is_deadly(*food_pos, map.width(), map.height()) {
+            *food_pos = loop {
+                let pos = get_random_pos(&mut rng.rng, &danger, map.width(), map.height());
+                if map.contains(pos) {
+                    break pos;
+                }
+            };
+        }
+    }
+}
+
+
+
+// Darkens the cells that are in the danger zone. Only does anything when the zone or the
+// theme changes.
+fn color_danger_zone_sys(
+    danger : Res<DangerZone>,
+    theme : Res<theme::Theme>,
+    board : Query<(&Mesh2d, &BoardFill)>,
+    mut meshes : ResMut<Assets<Mesh>>,
+) {
+    if !danger.is_changed() && !theme.is_changed() {
+        return;
+    }
+    for (fill, board) in &board {
+        if let Some(mesh) = meshes.get_mut(&fill.0) {
+            mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, board_colors(&danger, &theme, board.0, board.1, board.2));
+        }
+    }
+}
+
+
+
+fn update_hud_ui(run : RunInfo, mut ui : Query<&mut Text, With<HudUI>>) {
+    let mut hud = match *run.mode {
+        GameMode::Classic => format!("Score: {}   Lives: {}", run.score.0, run.lives.0),
+        GameMode::Tron => format!("Time: {:.1}s   Lives: {}", run.survived.0, run.lives.0),
+        GameMode::TimeAttack => format!("Time left: {:.1}s   Score: {}", run.round.0.remaining_secs(), run.score.0),
+        GameMode::Survival => format!("Time: {:.1}s   Score: {}   Lives: {}", run.survived.0, run.score.0, run.lives.0),
+        GameMode::Zen => format!("Score: {}   Length: {}", run.score.0, run.snake.segments.len() + 1),
+        GameMode::Hotseat => format!("Player {}'s turn: {} moves left   Score: {}", run.hotseat.player, run.hotseat.ticks_left, run.score.0),
+        GameMode::Banking => format!("Banked: {}   Pot: {}   Lives: {}", run.score.0, run.pot.points, run.lives.0),
+        GameMode::Boss => format!("Boss: {} hits left   Score: {}   Lives: {}", run.boss.hits_left, run.score.0, run.lives.0),
+    };
+
+    // Let the player know when the bank is coming, or how long they've got to reach it.
+    if *run.mode == GameMode::Banking {
+        let seconds_left = run.pot.ticks_left as f32 * run.fixed.timestep().as_secs_f32();
+        let status = if run.pot.open { "Bank closes in" } else { "Bank opens in" };
+        hud.push_str(&format!("\n{status} {seconds_left:.1}s"));
+    }
+
+    // Show the combo, and how long is left to keep it going.
+    if run.combo.multiplier > 1 {
+        let seconds_left = run.combo.ticks_left as f32 * run.fixed.timestep().as_secs_f32();
+        hud.push_str(&format!("\nCombo x{} ({:.1}s)", run.combo.multiplier, seconds_left));
+    }
+
+    for mut text in ui.iter_mut() {
+        text.0 = hud.clone();
+    }
+}
+
+
+
+fn update_countdown_ui(countdown : Res<Countdown>, mut ui : Query<&mut Text, With<CountdownUI>>) {
+    if !countdown.is_changed() {
+        return;
+    }
+    let shown = if countdown.0 > 0.0 {
+        format!("{}", countdown.0.ceil() as u32)
+    } else if countdown.0 > -COUNTDOWN_GO_SECONDS {
+        "GO!".to_string()
+    } else {
+        String::new()
+    };
+    for mut text in ui.iter_mut() {
+        text.0 = shown.clone();
+    }
+}
+
+
+
+fn cleanup_hud_ui(
+    mut commands: Commands,
+    ui: Query<Entity, RunUI>,
+) {
+    for e in &ui {
+        commands.entity(e).despawn();
+    }
+}
+
+
+
+fn reset_idle_timer(mut idle : ResMut<IdleTimer>) {
+    idle.0.reset();
+}
+
+
+
+// Counts down while nobody is touching the keyboard on the menu, and starts the demo
+// once the timer runs out. Any key press starts the countdown over.
+fn menu_idle_sys(
+    time : Res<Time>,
+    keys : Res<ButtonInput<KeyCode>>,
+    mut idle : ResMut<IdleTimer>,
+    mut next_state : ResMut<NextState<GameState>>,
+) {
+    if keys.get_just_pressed().next().is_some() {
+        idle.0.reset();
+        return;
+    }
+    if idle.0.tick(time.delta()).just_finished() {
+        next_state.set(GameState::Demo);
+    }
+}
+
+
+
+// Any key stops the demo, throws away the AI's snake, and goes back to the menu.
+fn exit_demo_on_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut commands : Commands,
+    mut next_state : ResMut<NextState<GameState>>,
+    mut fresh : FreshSnake,
+) {
+    if keys.get_just_pressed().next().is_some() {
+        fresh.respawn(&mut commands);
+        next_state.set(GameState::Menu);
+    }
+}
+
+
+
+// Gets a random cell position based on the number of cells in the grid, skipping over
+// the danger zone.
+fn get_random_pos(rng : &mut impl Rng, danger : &DangerZone, width : i32, height : i32) -> GridPosition {
+	let col = rng.gen_range(danger.rings..width - danger.rings);
+	let row = rng.gen_range(danger.rings..height - danger.rings);
+    GridPosition{ x : col, y : row }
+}
+
+// Rolls cells for new food until one is usable and not taken. Only the usable rolls count
+// as tries, so the replay simulation, where every cell is usable, rolls the same way.
+fn roll_food_pos(
+	rng : &mut impl Rng,
+	danger : &DangerZone,
+	width : i32,
+	height : i32,
+	usable : impl Fn(GridPosition) -> bool,
+	taken : impl Fn(GridPosition) -> bool,
+) -> GridPosition {
+	let mut tries = 0;
+	loop {
+		let pos = get_random_pos(rng, danger, width, height);
+		if !usable(pos) {
+			continue;
+		}
+		tries += 1;
+		if !taken(pos) || tries >= FOOD_SPAWN_TRIES {
+			return pos;
+		}
+	}
+}
+
+
+
+// A very simple AI controller. Each tick it picks whichever direction gets the head closest
+// to the food without running into a wall or the snake's own body. It isn't smart enough
+// to avoid trapping itself, but it's good enough to show off the game on the menu. The
+// settings can swap it for the perfect player, which is, or for a bot a mod has written.
+//
+// The AI's decisions are held back by its reaction delay.
+fn ai_steer_sys(
+    mut snake : ResMut<SnakeState>,
+    mut ai : ResMut<AiController>,
+    head_query : Query<&GridPosition, With<SnakeHead>>,
+    seg_query : Query<&GridPosition, With<SnakeSegment>>,
+    food_query : Query<&GridPosition, With<Food>>,
+    occupancy : Res<OccupancyGrid>,
+    settings : Res<settings::Settings>,
+) {
+    let Ok(head_pos) = head_query.single() else {
+        return;
+    };
+    // There can be more than one food on symmetric maps, so go for the closest.
+    let distance = |pos : &&GridPosition| (pos.x - head_pos.x).abs() + (pos.y - head_pos.y).abs();
+    let Some(food_pos) = food_query.iter().min_by_key(distance) else {
+        return;
+    };
+
+    let scripted = ai.scripted.take();
+    let decision = match (settings.demo_bot, scripted) {
+        (settings::DemoBot::Perfect, _) => {
+            let tail = snake.segments.last().and_then(|&e| seg_query.get(e).ok()).copied();
+            cycle::choose_cycle_direction(snake.dir, *head_pos, tail, *food_pos, &occupancy)
+        }
+        // The mods have already had their budget checked.
+        (settings::DemoBot::Script, Some(dir)) => dir,
+        // No mod to play means the greedy bot does.
+        (settings::DemoBot::Greedy | settings::DemoBot::Script, _) => choose_ai_direction(snake.dir, *head_pos, *food_pos, &occupancy),
+    };
+    if let Some(dir) = ai.reaction.delay(snake.dir, decision) {
+        snake.next_dir = dir;
+    }
+}
+
+// The demo's bot reacts as slowly as the settings say.
+fn start_demo_bot_sys(settings : Res<settings::Settings>, mut ai : ResMut<AiController>) {
+    ai.reaction = Reaction::new(settings.bot_reaction_ticks);
+}
+
+
+
+// Picks the next direction for the AI. Kept separate from the system so it only deals
+// with plain positions. Blocked cells are anything the snake can't go into, like its own
+// body or obstacles.
+fn choose_ai_direction(
+    dir : Direction,
+    head : GridPosition,
+    food : GridPosition,
+    occupancy : &OccupancyGrid,
+) -> Direction {
+    let mut best : Option<(i32, Direction)> = None;
+    for candidate in [Direction::Up, Direction::Down, Direction::Left, Direction::Right] {
+        if dir.is_opposite(candidate) {
+            continue;
+        }
+        let (dx, dy) = candidate.delta();
+        let next = GridPosition { x : head.x + dx, y : head.y + dy };
+        if !occupancy.contains(next) || occupancy.is_blocked(next) {
+            continue;
+        }
+        // Prefer going straight when two directions are just as good, so it wiggles less.
+        let distance = (food.x - next.x).abs() + (food.y - next.y).abs();
+        let better = match best {
+            None => true,
+            Some((best_distance, _)) => distance < best_distance || (distance == best_distance && candidate == dir),
+        };
+        if better {
+            best = Some((distance, candidate));
+        }
+    }
+
+    // Nowhere safe to go, so just keep going and accept our fate.
+    match best {
+        Some((_, candidate)) => candidate,
+        None if dir == Direction::None => Direction::Up,
+        None => dir,
+    }
+}
+
+
+
+// Moves the snake by first moving the head, and then, moving every segment after to the
+// previous position of the segment in front of it.
+//
+// In Tron mode the snake grows on every tick, so the tail never moves and leaves a trail.
+// In Zen mode the head wraps around to the other side of the arena instead of hitting
+// the wall.
+fn move_snake_sys(
+    mut snake : ResMut<SnakeState>,
+    mode : Res<GameMode>,
+    map : Res<ArenaMap>,
+    mut head_query : Query<&mut GridPosition, (With<SnakeHead>, Without<SnakeSegment>)>,
+    mut seg_query : Query<&mut GridPosition, With<SnakeSegment>>,
+    mut occupancy : ResMut<OccupancyGrid>,
+    mut turned : MessageWriter<DirectionChanged>,
+) {
+    // There's no head to move for the moment between a snake dying and the new one
+    // being spawned.
+    let Ok(mut head_pos) = head_query.single_mut() else {
+        return;
+    };
+    // Move head
+    if snake.dir != Direction::None && snake.next_dir != snake.dir {
+        turned.write(DirectionChanged);
+    }
+    snake.dir = snake.next_dir;
+    let (dx, dy) = snake.dir.delta();
+    let old_head_pos = *head_pos;
+    head_pos.x += dx;
+    head_pos.y += dy;
+    if *mode == GameMode::Zen || map.mutators.has(mutators::Mutator::NoWalls) {
+        // On a shaped arena it carries on across the gap to the other side of it.
+        loop {
+            head_pos.x = head_pos.x.rem_euclid(occupancy.width);
+            head_pos.y = head_pos.y.rem_euclid(occupancy.height);
+            if occupancy.contains(*head_pos) {
+                break;
+            }
+            head_pos.x += dx;
+            head_pos.y += dy;
+        }
+    }
+
+    // Move each segment to the previous position
+    let mut prev_pos = old_head_pos;
+    for &seg_entity in snake.segments.iter() {
+        if let Ok(mut seg_pos) = seg_query.get_mut(seg_entity) {
+            std::mem::swap(&mut *seg_pos, &mut prev_pos);
+        }
+    }
+    // All that's changed about where the body is, is that it now covers where the head
+    // was, and doesn't cover where the tail was. That's now in prev_pos.
+    if !snake.segments.is_empty() {
+        occupancy.add_segment(old_head_pos);
+        occupancy.remove_segment(prev_pos);
+    }
+
+    if *mode == GameMode::Tron && snake.dir != Direction::None {
+        snake.grow += 1;
+    }
+    trace!(x = head_pos.x, y = head_pos.y, dir = ?snake.dir, "Tick");
+}
+
+
+
+// Checks if the snake needs a new segment. If it does, we need to determine the position 
+// and then spawn the new segment. Finally, decrement the grow property by one.
+fn grow_snake_sys(
+    mut commands : Commands,
+    mut snake : ResMut<SnakeState>,
+    cells : Res<CellMeshes>,
+    mut occupancy : ResMut<OccupancyGrid>,
+    seg_query : Query<&GridPosition, With<SnakeSegment>>,
+    head_query : Query<&GridPosition, (With<SnakeHead>, Without<SnakeSegment>)>,
+    mut grew : MessageWriter<SnakeGrew>,
+) {
+    // A snake that isn't moving yet would just pile its new segments on top of its head
+    // and bite itself, so wait until it's going.
+    if snake.grow == 0 || snake.dir == Direction::None {
+        return;
+    }
+
+    // Determine spawn position by either the last segment of the snake, or the head if
+    // there are no additional segments. If that's not there yet, it can wait a tick.
+    let spawn_pos = match snake.segments.last() {
+        Some(&tail_entity) => seg_query.get(tail_entity).ok(),
+        None => head_query.single().ok(),
+    };
+    let Some(&spawn_pos) = spawn_pos else {
+        return;
+    };
+    
+
+    // Spawn new segment.
+    let new_segment = spawn_segment_at(&mut commands, &cells, spawn_pos);
+
+    // Add the new segment to the reference list, and decrement the grow counter.
+    snake.segments.push(new_segment);
+    occupancy.add_segment(spawn_pos);
+    snake.grow -= 1;
+    debug!(length = snake.segments.len() + 1, "Snake grew");
+    grew.write(SnakeGrew { length : snake.segments.len() + 1 });
+}
+
+
+
+// Spawns a single body segment at the given position. The snake's list of segments is
+// left for the caller to add it to.
+fn spawn_segment_at(commands : &mut Commands, cells : &CellMeshes, pos : GridPosition) -> Entity {
+    commands
+        .spawn((
+            SnakeSegment,
+            pos,
+            PreviousGridPosition(pos),
+            Transform::default(),
+            Visibility::default(),
+        ))
+        .with_children(|parent| {
+            // Outline
+            parent.spawn((
+                Mesh2d(cells.outline.clone()),
+                MeshMaterial2d(cells.line.clone()),
+                Transform::from_xyz(0.0, 0.0, 0.0),
+            ));
+            // Fill, above the grid's fill like the head's. It gets its proper shape once
+            // the snake moves on.
+            parent.spawn((
+                BodyFill,
+                Mesh2d(cells.segment_fill.clone()),
+                MeshMaterial2d(cells.snake.clone()),
+                Transform::from_xyz(0.0, 0.0, 2.0),
+            ));
+        })
+        .id()
+}
+
+
+
+// Checks if the snake has "collided" with the wall (going out of bounds). If it does,
+// resets the game by despawning the entities and resetting the properties.
+fn wall_collision_sys(
+	mut commands : Commands,
+	mut snake : ResMut<SnakeState>,
+	mut head : Query<(Entity, &mut GridPosition, &PreviousGridPosition), HeadMoved>,
+	mut segments : Query<(&mut GridPosition, &PreviousGridPosition), Without<SnakeHead>>,
+	danger : Res<DangerZone>,
+	mut occupancy : ResMut<OccupancyGrid>,
+	mut death : DeathRules,
+) {
+	let Ok((head_entity, mut head_pos, head_prev)) = head.single_mut() else {
+		return;
+	};
+
+	let cause = if !occupancy.contains(*head_pos) {
+		stats::DeathCause::Wall
+	} else if death.invincible() {
+		return;
+	} else if danger.is_deadly(*head_pos, occupancy.width, occupancy.height) {
+		stats::DeathCause::Danger
+	} else if occupancy.has_obstacle(*head_pos) {
+		stats::DeathCause::Obstacle
+	} else {
+		return;
+	};
+
+	// With obstacles that chop, the whole snake goes back to where it was before this move
+	// and stops there, and the end of its tail comes off.
+	let chops = death.map.obstacle_hits == ObstacleHits::Chop;
+	if cause == stats::DeathCause::Obstacle && chops && snake.segments.len() >= OBSTACLE_CHOP {
+		*head_pos = head_prev.0;
+		for &e in &snake.segments {
+			if let Ok((mut pos, prev)) = segments.get_mut(e) {
+				*pos = prev.0;
+			}
+		}
+		let keep = snake.segments.len() - OBSTACLE_CHOP;
+		for e in snake.truncate(keep) {
+			commands.entity(e).despawn();
+		}
+		occupancy.clear_segments();
+		for &e in &snake.segments {
+			if let Ok((pos, _)) = segments.get(e) {
+				occupancy.add_segment(*pos);
+			}
+		}
+		snake.dir = Direction::None;
+		snake.next_dir = Direction::None;
+		debug!(length = snake.segments.len() + 1, "Chopped on an obstacle");
+		return;
+	}
+
+	let length = snake.segments.len() + 1;
+	death::leave_corpse(&mut commands, head_entity, &snake.segments);
+	snake.dir = Direction::None;
+	snake.next_dir = Direction::None;
+	snake.segments.clear();
+	snake.grow = 0;
+	occupancy.clear_segments();
+	
+	spawn_snake(&mut commands, death.map.start());
+	death.snake_died(&mut snake, length, cause);
+}
+
+
+
+// Checks if the snake has collided with a food, and lets everything else know if it has!
+fn food_collision_sys(
+	head : Query<&GridPosition, With<SnakeHead>>,
+	food : Query<&GridPosition, With<Food>>,
+	mut eaten : MessageWriter<FoodEaten>,
+) {
+	let Ok(head_position) = head.single() else {
+		return;
+	};
+	if food.iter().any(|food_position| food_position == head_position) {
+		debug!(x = head_position.x, y = head_position.y, "Ate food");
+		eaten.write(FoodEaten { pos : *head_position });
+	}
+}
+
+
+
+// Puts out new food in place of food that's been eaten. On symmetric maps the food comes
+// in pairs, and eating either one replaces both so the pair stays matched up.
+fn replace_eaten_food_sys(
+	mut commands : Commands,
+	food : Query<(Entity, &GridPosition), NormalFood>,
+	mut eaten : MessageReader<FoodEaten>,
+	spawner : FoodSpawner,
+) {
+	// Food from a frenzy doesn't get replaced.
+	let eaten : Vec<GridPosition> = eaten.read().map(|eaten| eaten.pos).collect();
+	if !food.iter().any(|(_, pos)| eaten.contains(pos)) {
+		return;
+	}
+	for (food_entity, _) in &food {
+		commands.entity(food_entity).despawn();
+	}
+	spawn_food_sys(commands, spawner);
+}
+
+
+
+// Every food eaten is another segment on the next tick.
+fn grow_on_food_sys(mut snake : ResMut<SnakeState>, mut eaten : MessageReader<FoodEaten>) {
+	snake.grow += eaten.read().count() as u32;
+}
+
+
+
+// Hands the controls back to the first player at the start of a run.
+fn reset_hotseat_sys(mut hotseat : ResMut<Hotseat>) {
+	*hotseat = Hotseat::default();
+}
+
+
+
+// Counts down the current player's moves in hotseat mode. When they run out, the game
+// holds still for a countdown while the keyboard gets passed over.
+fn hotseat_turn_sys(
+	snake : Res<SnakeState>,
+	mut hotseat : ResMut<Hotseat>,
+	mut countdown : ResMut<Countdown>,
+) {
+	// A snake that isn't moving, including one that just crashed, isn't using up a turn.
+	if snake.dir == Direction::None {
+		return;
+	}
+	hotseat.ticks_left -= 1;
+	if hotseat.ticks_left == 0 {
+		hotseat.player = hotseat.other_player();
+		hotseat.ticks_left = HOTSEAT_TURN_TICKS;
+		countdown.0 = COUNTDOWN_SECONDS;
+	}
+}
+
+
+
+// Dying breaks the combo, and whatever hadn't been banked yet is gone.
+fn forfeit_on_death_sys(mut combo : ResMut<Combo>, mut pot : ResMut<Pot>, mut died : MessageReader<SnakeDied>) {
+	if died.read().count() > 0 {
+		*combo = Combo::default();
+		pot.points = 0;
+	}
+}
+
+
+
+// Runs the combo clock down once per tick.
+fn combo_tick_sys(mut combo : ResMut<Combo>) {
+	combo.tick();
+}
+
+
+
+// Adds the points for every food eaten this tick, using the combo multiplier.
+fn score_food_sys(
+	mut commands : Commands,
+	mut eaten : MessageReader<FoodEaten>,
+	mut combo : ResMut<Combo>,
+	mut score : ResMut<Score>,
+	mut pot : ResMut<Pot>,
+	mode : Res<GameMode>,
+	settings : Res<settings::Settings>,
+) {
+	for food in eaten.read() {
+		let points = combo.eat();
+		// In banking mode the points aren't safe until they've been banked.
+		if *mode == GameMode::Banking {
+			pot.points += points;
+		} else {
+			score.0 += points;
+		}
+
+		// Show how many points it was worth where the food used to be.
+		spawn_score_popup(&mut commands, &settings, food.pos, format!("+{points}"));
+	}
+}
+
+
+
+// Pops up some text over a cell. It's turned along with the camera so it reads the right
+// way up on a rotated board.
+fn spawn_score_popup(commands : &mut Commands, settings : &settings::Settings, pos : GridPosition, text : String) {
+	commands.spawn((
+		ScorePopup(Timer::from_seconds(SCORE_POPUP_SECONDS, TimerMode::Once)),
+		pos,
+		Text2d::new(text),
+		TextFont { font_size : 24.0, ..default() },
+		theme::Themed(theme::ThemeColor::Text),
+		Transform::from_xyz(0.0, 0.0, 10.0).with_rotation(settings.board_rotation()),
+	));
+}
+
+
+
+// Banks the pot if the snake has reached the bank. The bank closes up behind it.
+fn bank_collision_sys(
+	mut commands : Commands,
+	head : Query<&GridPosition, With<SnakeHead>>,
+	banks : Query<(Entity, &GridPosition), With<Bank>>,
+	mut pot : ResMut<Pot>,
+	mut score : ResMut<Score>,
+	settings : Res<settings::Settings>,
+	mut shake : MessageWriter<effects::ShakeCamera>,
+) {
+	let Ok(head_position) = head.single() else {
+		return;
+	};
+	let Some((bank_entity, bank_position)) = banks.iter().find(|(_, pos)| *pos == head_position) else {
+		return;
+	};
+	commands.entity(bank_entity).despawn();
+	if pot.points > 0 {
+		spawn_score_popup(&mut commands, &settings, *bank_position, format!("Banked {}", pot.points));
+	}
+	// A big pot gets a bit of a shake.
+	if pot.points >= BIG_BANK_POINTS {
+		shake.write(BIG_BANK_SHAKE);
+	}
+	score.0 += pot.points;
+	*pot = Pot::default();
+}
+
+
+
+// Opens and closes the bank. It waits for the snake's first move like the other clocks,
+// and never opens on top of an obstacle or the food.
+fn bank_tick_sys(
+	mut commands : Commands,
+	snake : Res<SnakeState>,
+	mut pot : ResMut<Pot>,
+	mut spawner : FoodSpawner,
+	food : Query<&GridPosition, With<Food>>,
+	banks : Query<Entity, With<Bank>>,
+) {
+	if snake.dir == Direction::None {
+		return;
+	}
+	pot.ticks_left = pot.ticks_left.saturating_sub(1);
+	if pot.ticks_left > 0 {
+		return;
+	}
+
+	if pot.open {
+		for e in &banks {
+			commands.entity(e).despawn();
+		}
+		pot.open = false;
+		pot.ticks_left = BANK_CLOSED_TICKS;
+	} else {
+		let pos = loop {
+			let pos = get_random_pos(&mut spawner.rng.rng, &spawner.danger, spawner.map.width(), spawner.map.height());
+			if spawner.map.contains(pos) && !spawner.occupancy.has_obstacle(pos) && !food.iter().any(|food_pos| *food_pos == pos) {
+				break pos;
+			}
+		};
+		spawn_bank_at(&mut commands, &spawner.cells, pos);
+		pot.open = true;
+		pot.ticks_left = BANK_OPEN_TICKS;
+	}
+}
+
+
+
+// Spawns the bank at the given position. It's drawn like the food, but in its own colour.
+fn spawn_bank_at(commands : &mut Commands, cells : &CellMeshes, pos : GridPosition) {
+	commands.spawn((
+		Bank,
+		pos,
+		Transform::default(),
+		Visibility::default(),
+	))
+	.with_children(|parent| {
+		parent.spawn((
+			Mesh2d(cells.outline.clone()),
+			MeshMaterial2d(cells.line.clone()),
+			Transform::from_xyz(0.0, 0.0, 0.0),
+		));
+		parent.spawn((
+			Mesh2d(cells.bank_fill.clone()),
+			MeshMaterial2d(cells.bank.clone()),
+			Transform::from_xyz(0.0, 0.0, 2.0),
+		));
+	});
+}
+
+
+
+// Empties the pot and shuts the bank once a run is over, so it doesn't hang around on
+// the menu.
+fn reset_bank_sys(mut commands : Commands, mut pot : ResMut<Pot>, banks : Query<Entity, With<Bank>>) {
+	for e in &banks {
+		commands.entity(e).despawn();
+	}
+	*pot = Pot::default();
+}
+
+
+
+// Floats the score popups upwards and fades them out, then gets rid of them.
+fn float_score_popups_sys(
+	mut commands : Commands,
+	time : Res<Time>,
+	mut popups : Query<(Entity, &mut ScorePopup, &mut Transform, &mut TextColor)>,
+) {
+	for (entity, mut popup, mut transform, mut color) in popups.iter_mut() {
+		popup.0.tick(time.delta());
+		if popup.0.is_finished() {
+			commands.entity(entity).despawn();
+			continue;
+		}
+		// Rises by about a cell over its lifetime, up the screen even on a rotated board.
+		let up = transform.up();
+		transform.translation += up * CELL_SIZE * time.delta_secs() / SCORE_POPUP_SECONDS;
+		color.0.set_alpha(1.0 - popup.0.fraction());
+	}
+}
+
+
+
+// Lets the player know that some of the game's files couldn't be loaded. Every file gets
+// logged, but there's only ever the one toast.
+fn warn_missing_assets_sys(
+	mut commands : Commands,
+	mut missing : MessageReader<AssetMissing>,
+	mut warned : Local<bool>,
+) {
+	for asset in missing.read() {
+		warn!("Couldn't load {}, carrying on without it", asset.path);
+		if *warned {
+			continue;
+		}
+		*warned = true;
+		spawn_toast(&mut commands, "Some game files are missing, so some sounds or pictures may be missing too");
+	}
+}
+
+
+
+// Pops up a short message in the corner, which fades away on its own.
+fn spawn_toast(commands : &mut Commands, message : impl Into<String>) {
+	commands.spawn((
+		Toast(Timer::from_seconds(TOAST_SECONDS, TimerMode::Once)),
+		Text::new(message),
+		TextFont { font_size : 20.0, ..default() },
+		TextColor(WHITE),
+		BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+		Node {
+			position_type: PositionType::Absolute,
+			bottom: Val::Px(MARGIN as f32),
+			right: Val::Px(MARGIN as f32 * 2.0),
+			padding: UiRect::all(Val::Px(8.0)),
+			..default()
+		},
+	));
+}
+
+
+
+// Fades the toasts out over their lifetime, then gets rid of them.
+fn fade_toasts_sys(
+	mut commands : Commands,
+	time : Res<Time>,
+	mut toasts : Query<(Entity, &mut Toast, &mut TextColor, &mut BackgroundColor)>,
+) {
+	for (entity, mut toast, mut text, mut background) in toasts.iter_mut() {
+		toast.0.tick(time.delta());
+		if toast.0.is_finished() {
+			commands.entity(entity).despawn();
+			continue;
+		}
+		let alpha = 1.0 - toast.0.fraction();
+		text.0.set_alpha(alpha);
+		background.0.set_alpha(alpha * 0.6);
+	}
+}
+
+
+
+// Checks if the snake has "collided" with itself. If it has, resets the game by 
+// despawning the entities and resetting the properties.
+fn snake_collision_sys(
+    mut commands : Commands,
+    mut snake : ResMut<SnakeState>,
+    mut head_query : Query<(Entity, &GridPosition), With<SnakeHead>>,
+    seg_query : Query<(Entity, &GridPosition), With<SnakeSegment>>,
+    mut occupancy : ResMut<OccupancyGrid>,
+    mode : Res<GameMode>,
+    mut death : DeathRules,
+) {
+    let Ok((head_entity, head_pos)) = head_query.single_mut() else {
+        return;
+    };
+
+    // Check if the head collides with any segment.
+    if !occupancy.has_segment(*head_pos) {
+        return;
+    }
+
+    // In Zen mode biting yourself just bites the tail off at that point.
+    if *mode == GameMode::Zen {
+        let bitten = snake.segments.iter().position(|&seg_entity| {
+            seg_query.get(seg_entity).is_ok_and(|(_, seg_pos)| seg_pos == head_pos)
+        });
+        if let Some(index) = bitten {
+            for e in snake.truncate(index) {
+                if let Ok((_, seg_pos)) = seg_query.get(e) {
+                    occupancy.remove_segment(*seg_pos);
+                }
+                commands.entity(e).despawn();
+            }
+        }
+        return;
+    }
+
+    // Otherwise it's dead, so leave the body behind for the death animation.
+    death::leave_corpse(&mut commands, head_entity, &snake.segments);
+
+    // Reset snake state.
+    let length = snake.segments.len() + 1;
+    snake.segments.clear();
+    snake.dir = Direction::None;
+    snake.next_dir = Direction::None;
+    snake.grow = 0;
+    occupancy.clear_segments();
+
+    // Spawn the new snake!
+    spawn_snake(&mut commands, death.map.start());
+    
+    death.snake_died(&mut snake, length, stats::DeathCause::Body);
+}
+
+
+
+// In low power mode the game only wakes up when there's input, or when the snake is due to
+// move. The board can only change on a tick, so there's no point drawing it any more often
+// than that, and on the menus it can wait even longer.
+fn low_power_schedule_sys(state : Res<State<GameState>>, fixed : Res<Time<Fixed>>, mut settings : ResMut<WinitSettings>) {
+	let wait = match state.get() {
+		GameState::Playing | GameState::Demo | GameState::Dying | GameState::Restarting => fixed.timestep().as_secs_f32(),
+		GameState::Menu | GameState::Settings | GameState::Progress | GameState::Stats | GameState::Replays | GameState::Skins | GameState::Shop | GameState::Mutators
+		| GameState::GameOver => LOW_POWER_IDLE_SECONDS,
+	};
+	let focused = UpdateMode::reactive(Duration::from_secs_f32(wait));
+	let unfocused = UpdateMode::reactive_low_power(Duration::from_secs_f32(wait.max(LOW_POWER_IDLE_SECONDS)));
+	// Only touch the settings when the wait's actually different.
+	if settings.focused_mode != focused || settings.unfocused_mode != unfocused {
+		settings.focused_mode = focused;
+		settings.unfocused_mode = unfocused;
+	}
+}
+
+
+
+// Exits the game if the user presses the esc key!
+fn exit_sys(
+	keys : Res<ButtonInput<KeyCode>>, 
+	mut exit : MessageWriter<AppExit>
+) {
+    if keys.just_pressed(KeyCode::Escape) {
+        exit.write(AppExit::Success);
+    }
+}
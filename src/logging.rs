@@ -0,0 +1,72 @@
+// Logging, for working out what went wrong from a player's log.
+//
+// `--log-level <level>` picks how much the game logs, out of error, warn, info, debug and
+// trace. Debug shows food being spawned and eaten, the snake growing, and collisions, and
+// trace adds every tick on top. Bevy's own logs never go past info, so they don't drown
+// the game's out. `--log-file <path>` writes a copy of the log to a file as well.
+use std::fs::File;
+use std::sync::Mutex;
+
+use bevy::log::tracing_subscriber::fmt;
+use bevy::log::{BoxedLayer, Level, LogPlugin, DEFAULT_FILTER};
+use bevy::prelude::*;
+use bevy::state::state::StateTransitionEvent;
+
+use crate::GameState;
+
+
+
+// Logs the game moving between screens.
+pub struct LoggingPlugin;
+impl Plugin for LoggingPlugin {
+	fn build(&self, app : &mut App) {
+		app.add_systems(Last, log_state_transitions_sys);
+	}
+}
+
+
+
+// The log plugin, set up from the command line.
+pub fn log_plugin() -> LogPlugin {
+	let level = arg_value("--log-level")
+		.and_then(|level| match level.parse() {
+			Ok(level) => Some(level),
+			Err(_) => {
+				eprintln!("Unknown log level {level}, using info");
+				None
+			}
+		})
+		.unwrap_or(Level::INFO);
+	LogPlugin {
+		level : level.min(Level::INFO),
+		filter : format!("{DEFAULT_FILTER},{}={level}", env!("CARGO_CRATE_NAME")),
+		custom_layer : log_file_layer,
+		..default()
+	}
+}
+
+// The value after a flag on the command line, like the path in `--log-file game.log`.
+fn arg_value(flag : &str) -> Option<String> {
+	let mut args = std::env::args().skip_while(|arg| arg != flag);
+	args.next()?;
+	args.next()
+}
+
+fn log_file_layer(_app : &mut App) -> Option<BoxedLayer> {
+	let path = arg_value("--log-file")?;
+	match File::create(&path) {
+		Ok(file) => Some(Box::new(fmt::layer().with_ansi(false).with_writer(Mutex::new(file)))),
+		Err(err) => {
+			eprintln!("Couldn't open the log file {path}: {err}");
+			None
+		}
+	}
+}
+
+
+
+fn log_state_transitions_sys(mut transitions : MessageReader<StateTransitionEvent<GameState>>) {
+	for transition in transitions.read() {
+		info!(from = ?transition.exited, to = ?transition.entered, "Game state changed");
+	}
+}
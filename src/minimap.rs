@@ -0,0 +1,128 @@
+// A little map of the whole arena in the corner of the screen, for when the arena is too
+// big to fit. Every cell is one pixel of a picture that gets redrawn as things move.
+use bevy::prelude::*;
+use bevy::asset::RenderAssetUsages;
+use bevy::image::ImageSampler;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+
+use crate::theme::Theme;
+use crate::{ArenaMap, ArenaSize, Bank, DangerZone, Food, GameState, GridPosition, OccupancyGrid, MARGIN};
+
+
+// How big the minimap is on the screen, in pixels, along its longer side.
+const MINIMAP_SIZE : f32 = 150.0;
+// How see-through the empty cells are, so the board shows through a little.
+const MINIMAP_ALPHA : f32 = 0.8;
+
+
+
+// Puts the minimap up for runs on the big arena, and keeps it up to date.
+pub struct MinimapPlugin;
+impl Plugin for MinimapPlugin {
+	fn build(&self, app : &mut App) {
+		app.add_systems(OnEnter(GameState::Playing), spawn_minimap_sys.after(crate::build_arena_sys))
+			.add_systems(OnExit(GameState::Playing), cleanup_minimap_sys)
+			.add_systems(Update, draw_minimap_sys.run_if(in_state(GameState::Playing)));
+	}
+}
+
+
+
+// The minimap, and the picture it shows.
+#[derive(Component)]
+struct Minimap(Handle<Image>);
+
+// Where each food and bank is, whether it's showing, and which of the two it is.
+type Pickup<'a> = (Ref<'a, GridPosition>, &'a Visibility, Has<Bank>);
+type IsPickup = Or<(With<Food>, With<Bank>)>;
+
+
+
+fn spawn_minimap_sys(mut commands : Commands, map : Res<ArenaMap>, mut images : ResMut<Assets<Image>>) {
+	if map.arena != ArenaSize::Big {
+		return;
+	}
+	let (width, height) = (map.width() as u32, map.height() as u32);
+	let pixels = MINIMAP_SIZE / width.max(height) as f32;
+	let mut image = Image::new_fill(
+		Extent3d { width, height, depth_or_array_layers : 1 },
+		TextureDimension::D2,
+		&[0, 0, 0, 0],
+		TextureFormat::Rgba8UnormSrgb,
+		RenderAssetUsages::default(),
+	);
+	// Keep the cells sharp instead of blurring them together.
+	image.sampler = ImageSampler::nearest();
+	let handle = images.add(image);
+
+	commands.spawn((
+		Minimap(handle.clone()),
+		ImageNode::new(handle),
+		Node {
+			position_type : PositionType::Absolute,
+			bottom : Val::Px(MARGIN as f32),
+			right : Val::Px(MARGIN as f32),
+			width : Val::Px(width as f32 * pixels),
+			height : Val::Px(height as f32 * pixels),
+			..default()
+		},
+	));
+}
+
+
+
+fn cleanup_minimap_sys(mut commands : Commands, minimaps : Query<Entity, With<Minimap>>) {
+	for entity in &minimaps {
+		commands.entity(entity).despawn();
+	}
+}
+
+
+
+// Redraws the minimap whenever the snake or the food has moved. Food that's hidden, like
+// in tron, stays off the map too.
+fn draw_minimap_sys(
+	theme : Res<Theme>,
+	danger : Res<DangerZone>,
+	occupancy : Res<OccupancyGrid>,
+	pickups : Query<Pickup, IsPickup>,
+	minimaps : Query<&Minimap>,
+	mut images : ResMut<Assets<Image>>,
+) {
+	let moved = pickups.iter().any(|(pos, _, _)| pos.is_changed());
+	if !occupancy.is_changed() && !danger.is_changed() && !theme.is_changed() && !moved {
+		return;
+	}
+	for minimap in &minimaps {
+		let Some(image) = images.get_mut(&minimap.0) else {
+			continue;
+		};
+		let (width, height) = (occupancy.width, occupancy.height);
+		let mut paint = |pos : GridPosition, color : Color| {
+			// The picture's rows go from the top down, and the grid's from the bottom up.
+			let _ = image.set_color_at(pos.x as u32, (height - 1 - pos.y) as u32, color);
+		};
+		for y in 0..height {
+			for x in 0..width {
+				let pos = GridPosition { x, y };
+				let color = if !occupancy.contains(pos) {
+					Color::NONE
+				} else if occupancy.has_segment(pos) {
+					theme.snake
+				} else if occupancy.has_obstacle(pos) {
+					theme.obstacle
+				} else if danger.is_deadly(pos, width, height) {
+					theme.danger.with_alpha(MINIMAP_ALPHA)
+				} else {
+					theme.cell.with_alpha(MINIMAP_ALPHA)
+				};
+				paint(pos, color);
+			}
+		}
+		for (pos, visibility, is_bank) in &pickups {
+			if *visibility != Visibility::Hidden {
+				paint(*pos, if is_bank { theme.bank } else { theme.food });
+			}
+		}
+	}
+}
@@ -0,0 +1,462 @@
+// Mods, written in Rhai and loaded from the mods folder when the game starts.
+//
+// A mod is a `.rhai` file that defines any of these, which get called as things happen in
+// a run:
+//
+//     on_run_start()
+//     on_tick(tick)
+//     on_food_eaten(x, y)
+//     on_snake_grew(length)
+//     on_snake_died(length)
+//
+// and changes the run by calling these:
+//
+//     add_score(points)     spawn_obstacle(x, y)    set_speed(factor)
+//     toast(message)        random(n)               score()
+//     board_width()         board_height()          set_tile(x, y, tile)
+//
+// `set_speed(2.0)` makes the snake go twice as fast until the next run, and `random(n)`
+// gives a number from 0 up to n. `set_tile` changes the floor of a cell, to "plain", "ice"
+// or "mud", so a mod can lay out a whole level of them from `on_run_start`. Modded runs
+// don't play by the normal rules, so they never get saved as replays.
+//
+// A mod can also play the demo on the menu, when it's picked in the settings, by defining
+//
+//     steer(head_x, head_y, food_x, food_y, dir)
+//
+// which gets called every tick and answers "up", "down", "left" or "right". It only gets
+// so long and so many operations to answer in. A bot that runs over gets stopped, keeps
+// going straight for that tick, and gets a strike.
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bevy::ecs::system::SystemParam;
+use bevy::platform::time::Instant;
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, FuncArgs, Scope, AST, INT};
+
+use crate::replay::{self, ReplayRecorder};
+use crate::settings::{DemoBot, MoveSpeed, Settings};
+use crate::tiles::{Tile, Tiles};
+use crate::{
+	AiController, ArenaMap, CellMeshes, Direction, Food, FoodEaten, GameRng, GameState, GridPosition, OccupancyGrid, Score, SnakeDied,
+	SnakeGrew, SnakeHead, SnakeState, TickSet,
+};
+
+
+// Where mods get loaded from.
+const MODS_DIR : &str = "mods";
+// The slowest and fastest a mod can make the snake go, as a multiple of the normal speed.
+const MIN_SPEED : f32 = 0.25;
+const MAX_SPEED : f32 = 4.0;
+// How long a mod's bot gets to pick its move each tick, and how many operations it can use
+// doing it. Whichever runs out first stops it.
+const STEER_BUDGET : Duration = Duration::from_millis(2);
+const STEER_MAX_OPERATIONS : u64 = 50_000;
+// How many operations go by between checks of the clock, since reading it isn't free.
+const CLOCK_CHECK_OPERATIONS : u64 = 256;
+
+
+
+// Loads the mods and hands them what happens in each run.
+pub struct ModsPlugin;
+impl Plugin for ModsPlugin {
+	fn build(&self, app : &mut App) {
+		app.insert_resource(Mods::load(MODS_DIR))
+			.init_resource::<ModSpeed>()
+			.add_systems(Startup, announce_mods_sys)
+			// The run has to be all set up, snapshot and all, before the mods get a look at it.
+			.add_systems(OnEnter(GameState::Playing), (start_run_sys, apply_mod_actions_sys)
+				.chain()
+				.after(replay::start_recording_sys)
+				.after(crate::snapshot::restore_snapshot_sys)
+				.run_if(mods_loaded))
+			.add_systems(FixedUpdate, (run_tick_hooks_sys, apply_mod_actions_sys)
+				.chain()
+				.after(TickSet::Spawn)
+				.run_if(in_state(GameState::Playing).and(crate::countdown_done).and(mods_loaded)))
+			.add_systems(PostUpdate, apply_mod_speed_sys
+				.before(crate::settings::apply_move_speed_sys)
+				.run_if(in_state(GameState::Playing)))
+			.add_systems(FixedUpdate, script_bot_sys
+				.in_set(TickSet::Input)
+				.before(crate::ai_steer_sys)
+				.run_if(in_state(GameState::Demo).and(script_bot_picked)));
+	}
+}
+
+
+
+// Something a mod asked for, done once the hooks have all had their turn.
+enum ModAction {
+	AddScore(INT),
+	SpawnObstacle(GridPosition),
+	SetTile(GridPosition, Tile),
+	SetSpeed(f32),
+	Toast(String),
+}
+
+// The bits of the run the mods can look at, kept up to date before they get called.
+#[derive(Default)]
+struct ModView {
+	score : INT,
+	board_width : INT,
+	board_height : INT,
+}
+
+// One loaded mod.
+struct Mod {
+	name : String,
+	ast : AST,
+	scope : Scope<'static>,
+}
+
+// Every loaded mod, and the engine that runs them.
+#[derive(Resource)]
+pub struct Mods {
+	engine : Engine,
+	mods : Vec<Mod>,
+	// The mods that couldn't be loaded, so the player can be told.
+	failed : Vec<String>,
+	actions : Arc<Mutex<Vec<ModAction>>>,
+	view : Arc<Mutex<ModView>>,
+	// When the script that's running has to stop by, and how many operations it gets.
+	// Only a bot steering has limits, the hooks can take as long as they need.
+	limits : Arc<Mutex<Option<(Instant, u64)>>>,
+	// How many times a mod's bot has run over its budget.
+	strikes : u32,
+	// Where `random` gets its numbers. It's seeded from the run's generator when the run
+	// starts, so a seed plays out the same with the same mods.
+	rng : Arc<Mutex<StdRng>>,
+	// How many ticks the run has been going.
+	tick : INT,
+}
+impl Mods {
+	// Loads every mod in the folder, in order of name. A folder that isn't there just
+	// means there aren't any mods.
+	fn load(dir : impl AsRef<Path>) -> Self {
+		let actions = Arc::new(Mutex::new(Vec::new()));
+		let view = Arc::new(Mutex::new(ModView::default()));
+		let limits = Arc::new(Mutex::new(None));
+		let rng = Arc::new(Mutex::new(StdRng::seed_from_u64(0)));
+		let mut mods = Mods {
+			engine : build_engine(&actions, &view, &limits, &rng),
+			mods : Vec::new(),
+			failed : Vec::new(),
+			actions,
+			view,
+			limits,
+			strikes : 0,
+			rng,
+			tick : 0,
+		};
+
+		let Ok(entries) = std::fs::read_dir(dir) else {
+			return mods;
+		};
+		let mut paths : Vec<_> = entries
+			.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+			.filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+			.collect();
+		paths.sort();
+		for path in paths {
+			let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+			let mut scope = Scope::new();
+			let loaded = mods.engine.compile_file(path.clone()).and_then(|ast| {
+				mods.engine.run_ast_with_scope(&mut scope, &ast)?;
+				Ok(ast)
+			});
+			match loaded {
+				Ok(ast) => {
+					info!("Loaded the {name} mod");
+					mods.mods.push(Mod { name, ast, scope });
+				}
+				Err(err) => {
+					warn!("Couldn't load the mod {}: {err}", path.display());
+					mods.failed.push(name);
+				}
+			}
+		}
+		// Anything the mods did while loading happened before there was a run to do it to.
+		mods.actions.lock().unwrap().clear();
+		mods
+	}
+
+	// Calls a hook on every mod that has it. A mod that goes wrong gets logged, and the rest
+	// still get their turn.
+	fn call(&mut self, hook : &str, args : impl FuncArgs + Clone) {
+		for m in self.mods.iter_mut() {
+			if !m.ast.iter_functions().any(|f| f.name == hook) {
+				continue;
+			}
+			// The top of the script was already run when it was loaded.
+			let options = CallFnOptions::new().eval_ast(false).rewind_scope(false);
+			if let Err(err) = self.engine.call_fn_with_options::<Dynamic>(options, &mut m.scope, &m.ast, hook, args.clone()) {
+				warn!("The {} mod went wrong in {hook}: {err}", m.name);
+			}
+		}
+	}
+
+	// Asks the first mod that can steer which way to go, within the bot's budget.
+	fn steer(&mut self, head : GridPosition, food : GridPosition, dir : Direction) -> Result<Direction, Box<EvalAltResult>> {
+		let Some(m) = self.mods.iter_mut().find(|m| m.ast.iter_functions().any(|f| f.name == "steer")) else {
+			return Ok(dir);
+		};
+		let args = (head.x as INT, head.y as INT, food.x as INT, food.y as INT, direction_name(dir).to_string());
+		*self.limits.lock().unwrap() = Some((Instant::now() + STEER_BUDGET, STEER_MAX_OPERATIONS));
+		let options = CallFnOptions::new().eval_ast(false).rewind_scope(false);
+		let answer = self.engine.call_fn_with_options::<Dynamic>(options, &mut m.scope, &m.ast, "steer", args);
+		*self.limits.lock().unwrap() = None;
+		let answer = answer?.into_string().unwrap_or_default();
+		Ok(direction_from_name(&answer).unwrap_or_else(|| {
+			warn!("The {} mod steered {answer:?}, which isn't a direction", m.name);
+			dir
+		}))
+	}
+
+	fn update_view(&self, score : &Score, map : &ArenaMap) {
+		let mut view = self.view.lock().unwrap();
+		view.score = score.0 as INT;
+		view.board_width = map.width() as INT;
+		view.board_height = map.height() as INT;
+	}
+}
+
+// An engine with the functions mods can call. They only write down what was asked for,
+// since the mods run in the middle of a system that can't change the world.
+fn build_engine(
+	actions : &Arc<Mutex<Vec<ModAction>>>,
+	view : &Arc<Mutex<ModView>>,
+	limits : &Arc<Mutex<Option<(Instant, u64)>>>,
+	rng : &Arc<Mutex<StdRng>>,
+) -> Engine {
+	let mut engine = Engine::new();
+	engine.on_print(|text| info!("Mod: {text}"));
+	engine.on_debug(|text, _, pos| debug!("Mod at {pos}: {text}"));
+	// Stops a script that's gone over its limits, if it has any.
+	let limits = limits.clone();
+	engine.on_progress(move |operations| match *limits.lock().unwrap() {
+		Some((_, most)) if operations > most => Some(Dynamic::UNIT),
+		Some((deadline, _)) if operations.is_multiple_of(CLOCK_CHECK_OPERATIONS) && Instant::now() > deadline => Some(Dynamic::UNIT),
+		_ => None,
+	});
+
+	let push = |actions : &Arc<Mutex<Vec<ModAction>>>| {
+		let actions = actions.clone();
+		move |action : ModAction| actions.lock().unwrap().push(action)
+	};
+	let act = push(actions);
+	engine.register_fn("add_score", move |points : INT| act(ModAction::AddScore(points)));
+	let act = push(actions);
+	engine.register_fn("spawn_obstacle", move |x : INT, y : INT| {
+		act(ModAction::SpawnObstacle(GridPosition { x : x as i32, y : y as i32 }));
+	});
+	let act = push(actions);
+	engine.register_fn("set_tile", move |x : INT, y : INT, name : &str| match Tile::from_name(name) {
+		Some(tile) => act(ModAction::SetTile(GridPosition { x : x as i32, y : y as i32 }, tile)),
+		None => warn!("A mod asked for an unknown tile {name:?}"),
+	});
+	let act = push(actions);
+	engine.register_fn("set_speed", move |factor : rhai::FLOAT| act(ModAction::SetSpeed(factor as f32)));
+	let act = push(actions);
+	engine.register_fn("toast", move |message : &str| act(ModAction::Toast(message.to_string())));
+
+	let rng = rng.clone();
+	engine.register_fn("random", move |n : INT| if n > 0 { rng.lock().unwrap().gen_range(0..n) } else { 0 });
+	let score_view = view.clone();
+	engine.register_fn("score", move || score_view.lock().unwrap().score);
+	let width_view = view.clone();
+	engine.register_fn("board_width", move || width_view.lock().unwrap().board_width);
+	let height_view = view.clone();
+	engine.register_fn("board_height", move || height_view.lock().unwrap().board_height);
+	engine
+}
+
+
+
+// How much faster than normal a mod has made the snake go.
+#[derive(Resource)]
+struct ModSpeed(f32);
+impl Default for ModSpeed {
+	fn default() -> Self {
+		ModSpeed(1.0)
+	}
+}
+
+// Where a mod's obstacles and tiles go, and what has to be kept clear of them.
+#[derive(SystemParam)]
+struct ModBoard<'w, 's> {
+	map : ResMut<'w, ArenaMap>,
+	tiles : ResMut<'w, Tiles>,
+	occupancy : ResMut<'w, OccupancyGrid>,
+	cells : Res<'w, CellMeshes>,
+	food : Query<'w, 's, &'static GridPosition, With<Food>>,
+}
+
+
+
+fn mods_loaded(mods : Res<Mods>) -> bool {
+	!mods.mods.is_empty()
+}
+
+// Whether the demo should be played by a mod, and there's a mod that can play it.
+fn script_bot_picked(settings : Res<Settings>, mods : Res<Mods>) -> bool {
+	settings.demo_bot == DemoBot::Script && mods.mods.iter().any(|m| m.ast.iter_functions().any(|f| f.name == "steer"))
+}
+
+
+
+fn direction_name(dir : Direction) -> &'static str {
+	match dir {
+		Direction::None => "none",
+		Direction::Up => "up",
+		Direction::Down => "down",
+		Direction::Left => "left",
+		Direction::Right => "right",
+	}
+}
+
+fn direction_from_name(name : &str) -> Option<Direction> {
+	match name.trim().to_lowercase().as_str() {
+		"up" => Some(Direction::Up),
+		"down" => Some(Direction::Down),
+		"left" => Some(Direction::Left),
+		"right" => Some(Direction::Right),
+		_ => None,
+	}
+}
+
+
+
+fn announce_mods_sys(mut commands : Commands, mods : Res<Mods>) {
+	if !mods.failed.is_empty() {
+		crate::spawn_toast(&mut commands, format!("Couldn't load some mods: {}", mods.failed.join(", ")));
+	} else if !mods.mods.is_empty() {
+		crate::spawn_toast(&mut commands, format!("Playing with {} mods", mods.mods.len()));
+	}
+}
+
+
+
+// Gets the mods going on a fresh run. The replay simulation only knows the normal rules,
+// so the recording's thrown away.
+fn start_run_sys(
+	mut mods : ResMut<Mods>,
+	mut rng : ResMut<GameRng>,
+	mut recorder : ResMut<ReplayRecorder>,
+	mut speed : ResMut<ModSpeed>,
+	score : Res<Score>,
+	map : Res<ArenaMap>,
+) {
+	recorder.discard();
+	speed.0 = 1.0;
+	mods.tick = 0;
+	*mods.rng.lock().unwrap() = StdRng::seed_from_u64(rng.side.next_u64());
+	mods.update_view(&score, &map);
+	mods.call("on_run_start", ());
+}
+
+
+
+// Tells the mods about the tick that's just been played.
+fn run_tick_hooks_sys(
+	mut mods : ResMut<Mods>,
+	score : Res<Score>,
+	map : Res<ArenaMap>,
+	mut eaten : MessageReader<FoodEaten>,
+	mut grew : MessageReader<SnakeGrew>,
+	mut died : MessageReader<SnakeDied>,
+) {
+	mods.tick += 1;
+	mods.update_view(&score, &map);
+	let tick = mods.tick;
+	mods.call("on_tick", (tick,));
+	for food in eaten.read() {
+		mods.call("on_food_eaten", (food.pos.x as INT, food.pos.y as INT));
+	}
+	for growth in grew.read() {
+		mods.call("on_snake_grew", (growth.length as INT,));
+	}
+	for death in died.read() {
+		mods.call("on_snake_died", (death.length as INT,));
+	}
+}
+
+
+
+// Lets a mod's bot pick the demo snake's move. The AI controller puts it through the
+// reaction delay after this, the same as the built in bots. A bot that runs over its
+// budget, or goes wrong, just keeps going straight.
+fn script_bot_sys(
+	mut mods : ResMut<Mods>,
+	mut ai : ResMut<AiController>,
+	snake : Res<SnakeState>,
+	head_query : Query<&GridPosition, With<SnakeHead>>,
+	food_query : Query<&GridPosition, With<Food>>,
+) {
+	let Ok(head) = head_query.single() else {
+		return;
+	};
+	let distance = |pos : &&GridPosition| (pos.x - head.x).abs() + (pos.y - head.y).abs();
+	let Some(food) = food_query.iter().min_by_key(distance) else {
+		return;
+	};
+	let decision = match mods.steer(*head, *food, snake.dir) {
+		Ok(dir) => dir,
+		Err(err) if matches!(*err, EvalAltResult::ErrorTerminated(..)) => {
+			mods.strikes += 1;
+			warn!("A mod's bot went over its budget ({} strikes)", mods.strikes);
+			snake.dir
+		}
+		Err(err) => {
+			warn!("A mod's bot went wrong in steer: {err}");
+			snake.dir
+		}
+	};
+	ai.scripted = Some(decision);
+}
+
+
+
+// Does what the mods asked for. Obstacles only go on empty cells, so a mod can't drop one
+// on the snake's head.
+fn apply_mod_actions_sys(
+	mut commands : Commands,
+	mods : Res<Mods>,
+	mut score : ResMut<Score>,
+	mut speed : ResMut<ModSpeed>,
+	mut board : ModBoard,
+) {
+	let actions = std::mem::take(&mut *mods.actions.lock().unwrap());
+	for action in actions {
+		match action {
+			ModAction::AddScore(points) => {
+				score.0 = (score.0 as INT).saturating_add(points).clamp(0, u32::MAX as INT) as u32;
+			}
+			ModAction::SpawnObstacle(pos) => {
+				let taken = board.occupancy.is_blocked(pos) || board.food.iter().any(|food| *food == pos);
+				if board.occupancy.contains(pos) && !taken {
+					board.map.obstacles.push(pos);
+					board.occupancy.add_obstacle(pos);
+					crate::spawn_obstacle_at(&mut commands, &board.cells, pos);
+				}
+			}
+			ModAction::SetTile(pos, tile) => if board.occupancy.contains(pos) {
+				board.tiles.set(pos, tile);
+			},
+			ModAction::SetSpeed(factor) => {
+				speed.0 = if factor.is_finite() { factor.clamp(MIN_SPEED, MAX_SPEED) } else { 1.0 };
+			}
+			ModAction::Toast(message) => crate::spawn_toast(&mut commands, message),
+		}
+	}
+}
+
+
+
+fn apply_mod_speed_sys(mod_speed : Res<ModSpeed>, mut speed : ResMut<MoveSpeed>) {
+	speed.mods = 1.0 / mod_speed.0;
+}
@@ -0,0 +1,153 @@
+// Background music that loops for as long as the game is open.
+//
+// There are two tracks that play in sync: a calm one that's always on, and a busier one
+// that fades in over the top as the run gets more intense, ie. the snake gets longer or
+// the arena closes in.
+use bevy::prelude::*;
+use bevy::audio::Volume;
+
+use crate::settings::Settings;
+use crate::sfx::{check_audio, AudioCheck};
+use crate::{ArenaMap, AssetMissing, DangerZone, GameState, SnakeState, SURVIVAL_MIN_CELLS};
+
+
+// The music tracks, loaded from the assets folder. They have to be the same length so
+// they stay lined up as they loop.
+const MUSIC_FILE : &str = "music/theme.wav";
+const INTENSE_MUSIC_FILE : &str = "music/theme_intense.wav";
+// How long the snake has to be, head included, for the music to be at its most intense.
+const MAX_INTENSITY_LENGTH : usize = 40;
+// How many seconds it takes the intense track to fade all the way in or out.
+const INTENSITY_FADE_SECONDS : f32 = 2.0;
+
+
+
+// Starts the music and keeps its volume up to date.
+pub struct MusicPlugin;
+impl Plugin for MusicPlugin {
+	fn build(&self, app : &mut App) {
+		app.init_resource::<MusicTracks>()
+			.init_resource::<MusicIntensity>()
+			.add_systems(Update, start_music_sys.run_if(not(any_with_component::<Music>)))
+			.add_systems(Update, (music_intensity_sys, music_volume_sys)
+				.chain()
+				.after(crate::settings::mute_on_input));
+	}
+}
+
+
+
+// The loaded music tracks.
+#[derive(Resource)]
+struct MusicTracks {
+	calm : Handle<AudioSource>,
+	intense : Handle<AudioSource>,
+}
+impl FromWorld for MusicTracks {
+	fn from_world(world : &mut World) -> Self {
+		let assets = world.resource::<AssetServer>();
+		MusicTracks {
+			calm : assets.load(MUSIC_FILE),
+			intense : assets.load(INTENSE_MUSIC_FILE),
+		}
+	}
+}
+
+// Which of the tracks an entity is playing, so its volume can be changed while it plays.
+#[derive(Component)]
+enum Music {
+	Calm,
+	Intense,
+}
+
+// How far the intense track has faded in, from 0 to 1.
+#[derive(Resource, Default, PartialEq)]
+struct MusicIntensity(f32);
+
+
+
+// Starts the music once both tracks have loaded, so they start playing on the same frame
+// and stay in sync. The intense track starts off silent. If either track is missing or
+// broken the game goes without music.
+fn start_music_sys(
+	mut commands : Commands,
+	assets : Res<AssetServer>,
+	sources : Res<Assets<AudioSource>>,
+	tracks : Res<MusicTracks>,
+	settings : Res<Settings>,
+	mut missing : MessageWriter<AssetMissing>,
+	mut gave_up : Local<bool>,
+) {
+	if *gave_up {
+		return;
+	}
+	let calm = check_audio(&assets, &sources, &tracks.calm);
+	let intense = check_audio(&assets, &sources, &tracks.intense);
+	if calm == AudioCheck::Loading || intense == AudioCheck::Loading {
+		return;
+	}
+	if calm == AudioCheck::Broken || intense == AudioCheck::Broken {
+		for (check, handle) in [(calm, &tracks.calm), (intense, &tracks.intense)] {
+			if check == AudioCheck::Broken {
+				missing.write(AssetMissing::from_handle(handle));
+			}
+		}
+		*gave_up = true;
+		return;
+	}
+	commands.spawn((
+		Music::Calm,
+		AudioPlayer::new(tracks.calm.clone()),
+		PlaybackSettings::LOOP.with_volume(Volume::Linear(settings.music())),
+	));
+	commands.spawn((
+		Music::Intense,
+		AudioPlayer::new(tracks.intense.clone()),
+		PlaybackSettings::LOOP.with_volume(Volume::Linear(0.0)),
+	));
+}
+
+
+
+// Works out how intense the music should be, and fades towards it. Whichever is further
+// along out of the snake's length and the arena shrinking wins. Off the playing screen
+// things calm back down.
+fn music_intensity_sys(
+	time : Res<Time>,
+	state : Res<State<GameState>>,
+	snake : Res<SnakeState>,
+	danger : Res<DangerZone>,
+	map : Res<ArenaMap>,
+	mut intensity : ResMut<MusicIntensity>,
+) {
+	let target = if *state.get() == GameState::Playing {
+		let length = (snake.segments.len() + 1) as f32 / MAX_INTENSITY_LENGTH as f32;
+		let shrink = danger.rings as f32 / ((map.width().min(map.height()) - SURVIVAL_MIN_CELLS) / 2) as f32;
+		length.max(shrink).min(1.0)
+	} else {
+		0.0
+	};
+	let step = time.delta_secs() / INTENSITY_FADE_SECONDS;
+	let faded = intensity.0 + (target - intensity.0).clamp(-step, step);
+	intensity.set_if_neq(MusicIntensity(faded));
+}
+
+
+
+// Keeps the music's volume in line with the settings and the intensity. The sinks only
+// show up once the tracks have loaded, so a new sink gets caught up too.
+fn music_volume_sys(
+	settings : Res<Settings>,
+	intensity : Res<MusicIntensity>,
+	mut sinks : Query<(&Music, &mut AudioSink)>,
+) {
+	for (music, mut sink) in sinks.iter_mut() {
+		if settings.is_changed() || intensity.is_changed() || sink.is_added() {
+			let volume = match music {
+				Music::Calm => settings.music(),
+				Music::Intense => settings.music() * intensity.0,
+			};
+			sink.set_volume(Volume::Linear(volume));
+		}
+	}
+}
@@ -0,0 +1,411 @@
+// Mutators, which change the rules of a run to make it more of a challenge. They're switched
+// on and off on their own screen, reached from the menu, and any number of them can be on at
+// once. Each one hooks into the run with its own systems, or a check in the rule it changes,
+// so they stack without knowing about each other.
+//
+// No walls lets the snake go off one edge and come back on the other, like in zen. Double
+// speed makes every tick take half as long. Fog keeps only a few cells around the head in
+// view. Mirrored controls swap left and right, like the mirror on the menu does. Tiny arena
+// starts the run with the outside of the arena already deadly, so there's only a little
+// square left in the middle. Poison rain drops poison on the board every so often, and
+// eating it bites the end off the snake's tail.
+//
+// Scores with mutators on go on their own leaderboard for that set of them, so they're only
+// ever up against runs played by the same rules. A run with any of them on isn't kept as a
+// replay, since the replay simulation doesn't know about them.
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::events::spawn_dark_frame;
+use crate::settings::{self, MoveSpeed, Settings};
+use crate::{
+	countdown_done, get_random_pos, ArenaMap, DangerZone, Food, GameRng, GameState, GridPosition, OccupancyGrid, SnakeHead, SnakeSegment,
+	SnakeState, TickSet, CELL_SIZE, WHITE,
+};
+
+
+// How much faster the ticks come with double speed.
+const SPEED_FACTOR : f32 = 2.0;
+// How far from the head can still be seen in the fog, in cells, and how thick it is.
+const FOG_RADIUS : f32 = 4.0;
+const FOG_COLOR : Color = Color::srgba(0.55, 0.6, 0.65, 0.85);
+// How many cells across the tiny arena leaves, if the snake's start allows it.
+const TINY_ARENA_CELLS : i32 = 10;
+// The chance of some poison dropping on any tick, how many can be out at once, and how
+// many ticks each lasts before it soaks away.
+const POISON_CHANCE : f64 = 1.0 / 15.0;
+const MAX_POISON : usize = 4;
+const POISON_TICKS : u32 = 50;
+// How many segments eating poison bites off the tail.
+const POISON_CHOP : usize = 2;
+const POISON_COLOR : Color = Color::srgb(0.55, 0.85, 0.1);
+// With the food.
+const POISON_Z : f32 = 2.0;
+
+
+
+// Runs the mutators screen, and each mutator's rules during runs.
+pub struct MutatorsPlugin;
+impl Plugin for MutatorsPlugin {
+	fn build(&self, app : &mut App) {
+		app.init_resource::<MutatorsCursor>()
+			.add_systems(OnEnter(GameState::Mutators), spawn_mutators_ui)
+			.add_systems(OnExit(GameState::Mutators), cleanup_mutators_ui)
+			.add_systems(Update, (mutators_menu_input, update_mutators_ui_sys)
+				.chain()
+				.run_if(in_state(GameState::Mutators)))
+			// The danger zone has just been cleared for the run, and the food has to go out
+			// after it's closed in.
+			.add_systems(OnEnter(GameState::Playing), shrink_to_tiny_sys
+				.after(crate::reset_run_sys)
+				.before(crate::respawn_food_sys)
+				.run_if(mutator_on(Mutator::TinyArena)))
+			.add_systems(OnEnter(GameState::Playing), spawn_fog_sys.run_if(mutator_on(Mutator::Fog)))
+			.add_systems(PostUpdate, double_speed_sys
+				.before(settings::apply_move_speed_sys)
+				.run_if(in_state(GameState::Playing).and(mutator_on(Mutator::DoubleSpeed))))
+			.add_systems(FixedUpdate, drop_poison_sys
+				.in_set(TickSet::Spawn)
+				.run_if(in_state(GameState::Playing).and(countdown_done).and(mutator_on(Mutator::PoisonRain))))
+			.add_systems(FixedUpdate, eat_poison_sys
+				.in_set(TickSet::Collide)
+				.after(crate::food_collision_sys)
+				.run_if(in_state(GameState::Playing).and(countdown_done).and(mutator_on(Mutator::PoisonRain))))
+			.add_systems(OnExit(GameState::Playing), cleanup_mutators_sys);
+	}
+}
+
+
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Mutator {
+	NoWalls,
+	DoubleSpeed,
+	Fog,
+	MirroredControls,
+	TinyArena,
+	PoisonRain,
+}
+impl Mutator {
+	const ALL : [Mutator; 6] = [
+		Mutator::NoWalls,
+		Mutator::DoubleSpeed,
+		Mutator::Fog,
+		Mutator::MirroredControls,
+		Mutator::TinyArena,
+		Mutator::PoisonRain,
+	];
+
+	fn name(self) -> &'static str {
+		match self {
+			Mutator::NoWalls => "No Walls",
+			Mutator::DoubleSpeed => "Double Speed",
+			Mutator::Fog => "Fog",
+			Mutator::MirroredControls => "Mirrored Controls",
+			Mutator::TinyArena => "Tiny Arena",
+			Mutator::PoisonRain => "Poison Rain",
+		}
+	}
+
+	fn from_name(name : &str) -> Option<Self> {
+		match name {
+			"no_walls" => Some(Mutator::NoWalls),
+			"double_speed" => Some(Mutator::DoubleSpeed),
+			"fog" => Some(Mutator::Fog),
+			"mirrored_controls" => Some(Mutator::MirroredControls),
+			"tiny_arena" => Some(Mutator::TinyArena),
+			"poison_rain" => Some(Mutator::PoisonRain),
+			_ => None,
+		}
+	}
+
+	fn to_name(self) -> &'static str {
+		match self {
+			Mutator::NoWalls => "no_walls",
+			Mutator::DoubleSpeed => "double_speed",
+			Mutator::Fog => "fog",
+			Mutator::MirroredControls => "mirrored_controls",
+			Mutator::TinyArena => "tiny_arena",
+			Mutator::PoisonRain => "poison_rain",
+		}
+	}
+
+	// What it does, for the mutators screen.
+	fn description(self) -> &'static str {
+		match self {
+			Mutator::NoWalls => "Go off one edge and come back on the other",
+			Mutator::DoubleSpeed => "Everything moves twice as fast",
+			Mutator::Fog => "Only see a few cells around the head",
+			Mutator::MirroredControls => "Left is right and right is left",
+			Mutator::TinyArena => "Only the middle of the arena is safe",
+			Mutator::PoisonRain => "Eating poison bites off the tail",
+		}
+	}
+
+	fn bit(self) -> u8 {
+		1 << self as u8
+	}
+}
+
+// Which mutators are on, one bit for each.
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub struct Mutators(u8);
+impl Mutators {
+	pub fn has(self, mutator : Mutator) -> bool {
+		self.0 & mutator.bit() != 0
+	}
+
+	fn toggle(&mut self, mutator : Mutator) {
+		self.0 ^= mutator.bit();
+	}
+
+	pub fn is_empty(self) -> bool {
+		self.0 == 0
+	}
+
+	fn iter(self) -> impl Iterator<Item = Mutator> {
+		Mutator::ALL.into_iter().filter(move |&mutator| self.has(mutator))
+	}
+
+	// For the menu button, where there isn't room for all of their names.
+	pub fn summary(self) -> String {
+		match self.iter().count() {
+			0 => "None".to_string(),
+			1 => self.iter().map(Mutator::name).collect(),
+			on => format!("{on} on"),
+		}
+	}
+
+	// All of their names, for the top of a leaderboard.
+	pub fn names(self) -> String {
+		self.iter().map(Mutator::name).collect::<Vec<_>>().join(", ")
+	}
+
+	pub fn to_names(self) -> Vec<&'static str> {
+		self.iter().map(Mutator::to_name).collect()
+	}
+
+	// Nothing if any of the names isn't a mutator.
+	pub fn from_names<'a>(names : impl IntoIterator<Item = &'a str>) -> Option<Self> {
+		let mut mutators = Mutators::default();
+		for name in names {
+			mutators.0 |= Mutator::from_name(name)?.bit();
+		}
+		Some(mutators)
+	}
+}
+
+// Which mutator is picked on the mutators screen.
+#[derive(Resource, Default)]
+struct MutatorsCursor(usize);
+
+// The mutators screen, so it can be cleaned up.
+#[derive(Component)]
+struct MutatorsUI;
+
+// The list of mutators, so it can be updated.
+#[derive(Component)]
+struct MutatorsText;
+
+// The fog around the head.
+#[derive(Component, Clone)]
+struct Fog;
+
+// Some poison, and how many ticks it has left before it soaks away.
+#[derive(Component)]
+struct Poison(u32);
+
+
+
+// Whether a mutator is on for the run.
+pub fn mutator_on(mutator : Mutator) -> impl Fn(Res<ArenaMap>) -> bool {
+	move |map : Res<ArenaMap>| map.mutators.has(mutator)
+}
+
+
+
+fn spawn_mutators_ui(mut commands : Commands, map : Res<ArenaMap>, cursor : Res<MutatorsCursor>) {
+	commands.spawn((
+		MutatorsUI,
+		Node {
+			width: Val::Percent(100.0),
+			height: Val::Percent(100.0),
+			flex_direction: FlexDirection::Column,
+			justify_content: JustifyContent::Center,
+			align_items: AlignItems::Center,
+			..default()
+		},
+		BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+	))
+	.with_child((
+		MutatorsText,
+		Text::new(mutators_text(map.mutators, cursor.0)),
+		TextFont {
+			font_size : 28.0,
+			..default()
+		},
+		TextColor(WHITE),
+		TextLayout::new(Justify::Center, LineBreak::WordBoundary),
+	));
+}
+
+
+
+fn cleanup_mutators_ui(mut commands : Commands, ui : Query<Entity, With<MutatorsUI>>) {
+	for e in &ui {
+		commands.entity(e).despawn();
+	}
+}
+
+
+
+// Up and down pick a mutator, enter switches it on or off. O goes back to the menu.
+fn mutators_menu_input(
+	keys : Res<ButtonInput<KeyCode>>,
+	mut cursor : ResMut<MutatorsCursor>,
+	mut map : ResMut<ArenaMap>,
+	mut next_state : ResMut<NextState<GameState>>,
+) {
+	if keys.just_pressed(KeyCode::KeyO) {
+		next_state.set(GameState::Menu);
+		return;
+	}
+	if keys.just_pressed(KeyCode::ArrowUp) {
+		cursor.0 = (cursor.0 + Mutator::ALL.len() - 1) % Mutator::ALL.len();
+	}
+	if keys.just_pressed(KeyCode::ArrowDown) {
+		cursor.0 = (cursor.0 + 1) % Mutator::ALL.len();
+	}
+	if settings::enter_pressed(&keys) {
+		map.mutators.toggle(Mutator::ALL[cursor.0]);
+	}
+}
+
+
+
+fn update_mutators_ui_sys(map : Res<ArenaMap>, cursor : Res<MutatorsCursor>, mut text : Query<&mut Text, With<MutatorsText>>) {
+	if !map.is_changed() && !cursor.is_changed() {
+		return;
+	}
+	for mut text in text.iter_mut() {
+		text.0 = mutators_text(map.mutators, cursor.0);
+	}
+}
+
+
+
+fn mutators_text(mutators : Mutators, cursor : usize) -> String {
+	let mut text = String::from("MUTATORS\n");
+	for (i, mutator) in Mutator::ALL.into_iter().enumerate() {
+		let marker = if i == cursor { ">" } else { " " };
+		let on = if mutators.has(mutator) { "ON " } else { "OFF" };
+		text.push_str(&format!("{marker} {on} {} - {}\n", mutator.name(), mutator.description()));
+	}
+	text.push_str("Scores with mutators go on their own leaderboards\n");
+	text.push_str("UP/DOWN to pick, ENTER to switch on or off\nPress O to go back");
+	text
+}
+
+
+
+// Starts the run with everything but the middle of the arena deadly. It never closes in past
+// where the snake starts though, so on the big arena, or off to one side of a shaped one, it
+// doesn't get quite so tiny.
+fn shrink_to_tiny_sys(map : Res<ArenaMap>, mut danger : ResMut<DangerZone>) {
+	let start = map.start();
+	let room = start.x.min(start.y).min(map.width() - 1 - start.x).min(map.height() - 1 - start.y);
+	danger.rings = ((map.width().min(map.height()) - TINY_ARENA_CELLS) / 2).clamp(0, room);
+}
+
+
+
+fn spawn_fog_sys(mut commands : Commands) {
+	spawn_dark_frame(&mut commands, FOG_RADIUS, FOG_COLOR, Fog);
+}
+
+
+
+fn double_speed_sys(mut speed : ResMut<MoveSpeed>) {
+	speed.double_speed = 1.0 / SPEED_FACTOR;
+}
+
+
+
+// Sometimes drops some poison on an empty cell, and soaks away any that's been out too long.
+// The drops come from the run's own random numbers, like everything else on the board.
+fn drop_poison_sys(
+	mut commands : Commands,
+	mut rng : ResMut<GameRng>,
+	occupancy : Res<OccupancyGrid>,
+	danger : Res<DangerZone>,
+	food : Query<&GridPosition, With<Food>>,
+	mut poison : Query<(Entity, &GridPosition, &mut Poison)>,
+) {
+	for (e, _, mut drop) in poison.iter_mut() {
+		drop.0 = drop.0.saturating_sub(1);
+		if drop.0 == 0 {
+			commands.entity(e).despawn();
+		}
+	}
+	if poison.iter().count() >= MAX_POISON || !rng.rng.gen_bool(POISON_CHANCE) {
+		return;
+	}
+	for _ in 0..20 {
+		let pos = get_random_pos(&mut rng.rng, &danger, occupancy.width, occupancy.height);
+		let taken = food.iter().chain(poison.iter().map(|(_, pos, _)| pos)).any(|&other| other == pos);
+		if !occupancy.contains(pos) || occupancy.is_blocked(pos) || taken {
+			continue;
+		}
+		commands.spawn((
+			Poison(POISON_TICKS),
+			pos,
+			Sprite {
+				color : POISON_COLOR,
+				custom_size : Some(Vec2::splat(CELL_SIZE * 0.5)),
+				..default()
+			},
+			Transform::from_xyz(0.0, 0.0, POISON_Z),
+		));
+		return;
+	}
+}
+
+
+
+// Poison never kills. It just bites the end off the tail, as much of it as there is.
+fn eat_poison_sys(
+	mut commands : Commands,
+	mut snake : ResMut<SnakeState>,
+	mut occupancy : ResMut<OccupancyGrid>,
+	settings : Res<Settings>,
+	head : Query<&GridPosition, With<SnakeHead>>,
+	segments : Query<&GridPosition, With<SnakeSegment>>,
+	poison : Query<(Entity, &GridPosition), With<Poison>>,
+) {
+	let Ok(&head) = head.single() else {
+		return;
+	};
+	for (e, &pos) in &poison {
+		if pos != head {
+			continue;
+		}
+		commands.entity(e).despawn();
+		let keep = snake.segments.len().saturating_sub(POISON_CHOP);
+		for cut in snake.truncate(keep) {
+			if let Ok(&pos) = segments.get(cut) {
+				occupancy.remove_segment(pos);
+			}
+			commands.entity(cut).despawn();
+		}
+		crate::spawn_score_popup(&mut commands, &settings, head, format!("-{POISON_CHOP}"));
+		debug!(length = snake.segments.len() + 1, "Ate poison");
+	}
+}
+
+
+
+fn cleanup_mutators_sys(mut commands : Commands, fog : Query<Entity, With<Fog>>, poison : Query<Entity, With<Poison>>) {
+	for e in fog.iter().chain(poison.iter()) {
+		commands.entity(e).despawn();
+	}
+}
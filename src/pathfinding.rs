@@ -0,0 +1,163 @@
+// The path overlay. F8 shows the shortest way from the head to the nearest food, worked
+// out fresh every tick with A*, as faint cells on the board. It works in the demo too, for
+// watching what the AI is up against, or for seeing how A* goes about it.
+//
+// The path goes around the snake's body and the obstacles. It doesn't know the tail will
+// have moved on by the time the head gets there, so it's a bit more careful than it needs
+// to be.
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use bevy::prelude::*;
+
+use crate::{Food, GameState, GridPosition, OccupancyGrid, SnakeHead, TickSet, CELL_SIZE};
+
+
+const PATH_COLOR : Color = Color::srgba(1.0, 1.0, 1.0, 0.2);
+// Above the grid's fill, and below the snake and the food.
+const PATH_Z : f32 = 1.5;
+
+
+
+// Draws the path overlay.
+pub struct PathOverlayPlugin;
+impl Plugin for PathOverlayPlugin {
+	fn build(&self, app : &mut App) {
+		app.init_resource::<PathOverlay>()
+			.add_systems(Update, toggle_path_overlay_on_input)
+			.add_systems(FixedUpdate, draw_path_sys
+				.after(TickSet::Spawn)
+				.run_if(showing_path.and(in_state(GameState::Playing).or(in_state(GameState::Demo)))))
+			.add_systems(OnExit(GameState::Playing), clear_path_sys)
+			.add_systems(OnExit(GameState::Demo), clear_path_sys);
+	}
+}
+
+
+
+// Whether the overlay is switched on.
+#[derive(Resource, Default)]
+struct PathOverlay(bool);
+
+// One cell of the path.
+#[derive(Component)]
+struct PathCell;
+
+
+
+fn showing_path(overlay : Res<PathOverlay>) -> bool {
+	overlay.0
+}
+
+
+
+fn toggle_path_overlay_on_input(
+	mut commands : Commands,
+	keys : Res<ButtonInput<KeyCode>>,
+	mut overlay : ResMut<PathOverlay>,
+	cells : Query<Entity, With<PathCell>>,
+) {
+	if !keys.just_pressed(KeyCode::F8) {
+		return;
+	}
+	overlay.0 = !overlay.0;
+	if !overlay.0 {
+		for e in &cells {
+			commands.entity(e).despawn();
+		}
+	}
+	crate::spawn_toast(&mut commands, if overlay.0 { "Path overlay on" } else { "Path overlay off" });
+}
+
+
+
+fn draw_path_sys(
+	mut commands : Commands,
+	occupancy : Res<OccupancyGrid>,
+	head : Query<&GridPosition, With<SnakeHead>>,
+	food : Query<&GridPosition, With<Food>>,
+	old_cells : Query<Entity, With<PathCell>>,
+) {
+	for e in &old_cells {
+		commands.entity(e).despawn();
+	}
+	let Ok(&head) = head.single() else {
+		return;
+	};
+	let distance = |pos : &&GridPosition| (pos.x - head.x).abs() + (pos.y - head.y).abs();
+	let Some(&goal) = food.iter().min_by_key(distance) else {
+		return;
+	};
+	let Some(path) = find_path(head, goal, &occupancy) else {
+		return;
+	};
+	// The head and the food are already drawn.
+	for &pos in path.iter().filter(|&&pos| pos != head && pos != goal) {
+		commands.spawn((
+			PathCell,
+			pos,
+			Sprite {
+				color : PATH_COLOR,
+				custom_size : Some(Vec2::splat(CELL_SIZE)),
+				..default()
+			},
+			Transform::from_xyz(0.0, 0.0, PATH_Z),
+		));
+	}
+}
+
+
+
+fn clear_path_sys(mut commands : Commands, cells : Query<Entity, With<PathCell>>) {
+	for e in &cells {
+		commands.entity(e).despawn();
+	}
+}
+
+
+
+// The shortest way from one cell to another, both ends included, going around anything
+// in the way. A* with the number of cells left to go, ignoring anything in the way, as its
+// guess at how far the rest is, which is never more than the real distance. The chaser
+// uses it to come after the snake too.
+pub fn find_path(from : GridPosition, to : GridPosition, occupancy : &OccupancyGrid) -> Option<Vec<GridPosition>> {
+	let index = |pos : GridPosition| (pos.y * occupancy.width + pos.x) as usize;
+	let guess = |pos : GridPosition| (to.x - pos.x).abs() + (to.y - pos.y).abs();
+	let cells = (occupancy.width * occupancy.height) as usize;
+	if !occupancy.contains(from) || !occupancy.contains(to) {
+		return None;
+	}
+
+	let mut best = vec![i32::MAX; cells];
+	let mut came_from : Vec<Option<GridPosition>> = vec![None; cells];
+	let mut open = BinaryHeap::new();
+	best[index(from)] = 0;
+	open.push(Reverse((guess(from), 0, from.x, from.y)));
+	while let Some(Reverse((_, steps, x, y))) = open.pop() {
+		let pos = GridPosition { x, y };
+		if pos == to {
+			let mut path = vec![pos];
+			while let Some(prev) = came_from[index(*path.last().unwrap())] {
+				path.push(prev);
+			}
+			path.reverse();
+			return Some(path);
+		}
+		// Already found a shorter way here.
+		if steps > best[index(pos)] {
+			continue;
+		}
+		for (dx, dy) in [(0, 1), (0, -1), (-1, 0), (1, 0)] {
+			let next = GridPosition { x : x + dx, y : y + dy };
+			if !occupancy.contains(next) || (occupancy.is_blocked(next) && next != to) {
+				continue;
+			}
+			if steps + 1 < best[index(next)] {
+				best[index(next)] = steps + 1;
+				came_from[index(next)] = Some(pos);
+				open.push(Reverse((steps + 1 + guess(next), steps + 1, next.x, next.y)));
+			}
+		}
+	}
+	None
+}
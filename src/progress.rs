@@ -0,0 +1,244 @@
+// The prestige ladder, which sticks around between launches.
+//
+// Every rung of the ladder needs a higher best score than the last. Once the player has
+// reached it they can choose to prestige from the progress screen, which wipes their best
+// score and starts them on the next rung, but gives them a small perk that lasts forever.
+// The file is plain text with one "name value" pair per line, like the settings file.
+use bevy::prelude::*;
+
+use crate::settings::{self, MoveSpeed};
+use crate::shop::Wallet;
+use crate::storage;
+use crate::{GameState, Score, SnakeState, Direction, WHITE};
+
+
+// Where the progress gets saved.
+pub const PROGRESS_FILE : &str = "progress.txt";
+// How much the score needed to prestige goes up by for every rung of the ladder.
+const RUNG_SCORE : u32 = 20;
+// The most extra segments the snake can start with, however far up the ladder it is.
+const MAX_EXTRA_LENGTH : u32 = 5;
+// How long the snake moves slower for at the start of a run once it has prestiged, and
+// how much slower.
+const SLOW_START_SECONDS : f32 = 10.0;
+const SLOW_START_FACTOR : f32 = 1.5;
+
+
+
+// How far up the ladder the player is.
+#[derive(Resource, Default, PartialEq, Debug)]
+pub struct Progress {
+	// How many times the player has prestiged.
+	pub prestige : u32,
+	// The best score since the last prestige.
+	pub best_score : u32,
+}
+impl Progress {
+	// Reads the progress file, or starts at the bottom of the ladder if there isn't one yet.
+	pub fn load() -> Self {
+		match storage::read(PROGRESS_FILE) {
+			Some(text) => Progress::from_text(&text),
+			None => Progress::default(),
+		}
+	}
+
+	// The best score needed to prestige again.
+	fn next_rung(&self) -> u32 {
+		RUNG_SCORE * (self.prestige + 1)
+	}
+
+	fn can_prestige(&self) -> bool {
+		self.best_score >= self.next_rung()
+	}
+
+	// How many segments the snake starts with on top of its head. One more per prestige.
+	pub fn start_growth(&self) -> u32 {
+		self.prestige.min(MAX_EXTRA_LENGTH)
+	}
+
+	// Whether the start of every run is slowed down, which comes with the first prestige.
+	fn slow_start(&self) -> bool {
+		self.prestige > 0
+	}
+
+	fn to_text(&self) -> String {
+		format!("prestige {}\nbest_score {}\n", self.prestige, self.best_score)
+	}
+
+	fn from_text(text : &str) -> Self {
+		let mut progress = Progress::default();
+		for line in text.lines() {
+			let Some((name, value)) = line.split_once(' ') else {
+				continue;
+			};
+			let value = value.trim();
+			match name {
+				"prestige" => if let Ok(v) = value.parse() { progress.prestige = v },
+				"best_score" => if let Ok(v) = value.parse() { progress.best_score = v },
+				_ => {}
+			}
+		}
+		progress
+	}
+}
+
+
+
+// Writes the progress back to the file whenever it changes.
+pub fn save_progress_sys(progress : Res<Progress>) {
+	if !progress.is_changed() || progress.is_added() {
+		return;
+	}
+	if let Err(err) = storage::write(PROGRESS_FILE, &progress.to_text()) {
+		warn!("Couldn't save progress: {err}");
+	}
+}
+
+
+
+// Counts the score from a finished run towards the next rung.
+pub fn record_score_sys(score : Res<Score>, mut progress : ResMut<Progress>) {
+	if score.0 > progress.best_score {
+		progress.best_score = score.0;
+	}
+}
+
+
+
+// How long the current run has been going, for the slow start perk.
+#[derive(Resource, Default)]
+pub struct SlowStart(f32);
+
+pub fn reset_slow_start_sys(mut slow : ResMut<SlowStart>) {
+	slow.0 = 0.0;
+}
+
+// The snake starts a bit longer for every prestige, or as long as the perk from the shop
+// makes it, whichever's longer.
+pub fn apply_start_growth_sys(progress : Res<Progress>, wallet : Res<Wallet>, mut snake : ResMut<SnakeState>) {
+	snake.grow = progress.start_growth().max(wallet.start_growth());
+}
+
+// Slows the snake down for the start of a run, if the player has the perk. The clock only
+// starts once the snake does, like the mode clocks.
+pub fn slow_start_sys(
+	time : Res<Time>,
+	state : Res<State<GameState>>,
+	snake : Res<SnakeState>,
+	progress : Res<Progress>,
+	mut slow : ResMut<SlowStart>,
+	mut speed : ResMut<MoveSpeed>,
+) {
+	let playing = *state.get() == GameState::Playing;
+	if playing && snake.dir != Direction::None {
+		slow.0 += time.delta_secs();
+	}
+	if playing && progress.slow_start() && slow.0 < SLOW_START_SECONDS {
+		speed.slow_start = SLOW_START_FACTOR;
+	}
+}
+
+
+
+// The progress screen, so it can be cleaned up.
+#[derive(Component)]
+pub struct ProgressUI;
+
+// The text on the progress screen, so it can be updated after prestiging.
+#[derive(Component)]
+pub struct ProgressText;
+
+
+
+pub fn spawn_progress_ui(mut commands : Commands, progress : Res<Progress>) {
+	commands.spawn((
+		ProgressUI,
+		Node {
+			width: Val::Percent(100.0),
+			height: Val::Percent(100.0),
+			justify_content: JustifyContent::Center,
+			align_items: AlignItems::Center,
+			..default()
+		},
+		BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+	))
+	.with_children(|parent| {
+		parent.spawn((
+			ProgressText,
+			Text::new(progress_text(&progress)),
+			TextFont {
+				font_size : 40.0,
+				..default()
+			},
+			TextColor(WHITE),
+			TextLayout::new(Justify::Center, LineBreak::WordBoundary),
+		));
+	});
+}
+
+
+
+pub fn cleanup_progress_ui(mut commands : Commands, ui : Query<Entity, With<ProgressUI>>) {
+	for e in &ui {
+		commands.entity(e).despawn();
+	}
+}
+
+
+
+// P prestiges, if the player has made it to the next rung. S opens the skins screen, and B
+// the shop. O or enter goes back to the menu.
+pub fn progress_menu_input(
+	keys : Res<ButtonInput<KeyCode>>,
+	mut progress : ResMut<Progress>,
+	mut next_state : ResMut<NextState<GameState>>,
+) {
+	if keys.just_pressed(KeyCode::KeyO) || settings::enter_pressed(&keys) {
+		next_state.set(GameState::Menu);
+		return;
+	}
+	if keys.just_pressed(KeyCode::KeyS) {
+		next_state.set(GameState::Skins);
+		return;
+	}
+	if keys.just_pressed(KeyCode::KeyB) {
+		next_state.set(GameState::Shop);
+		return;
+	}
+	if keys.just_pressed(KeyCode::KeyP) && progress.can_prestige() {
+		progress.prestige += 1;
+		progress.best_score = 0;
+	}
+}
+
+
+
+pub fn update_progress_text_sys(progress : Res<Progress>, mut text : Query<&mut Text, With<ProgressText>>) {
+	if !progress.is_changed() {
+		return;
+	}
+	for mut text in text.iter_mut() {
+		text.0 = progress_text(&progress);
+	}
+}
+
+
+
+fn progress_text(progress : &Progress) -> String {
+	let mut text = format!(
+		"PROGRESS\nPrestige: {}\nBest score: {} / {}\n",
+		progress.prestige,
+		progress.best_score,
+		progress.next_rung(),
+	);
+	if progress.prestige == 0 {
+		text.push_str("No perks yet\n");
+	} else {
+		text.push_str(&format!("Perks: start {} longer, slower first {}s\n", progress.start_growth(), SLOW_START_SECONDS));
+	}
+	if progress.can_prestige() {
+		text.push_str("Press P to prestige. Your best score goes back to 0!\n");
+	}
+	text.push_str("Press S for skins, B for the shop\nPress O to go back");
+	text
+}
@@ -0,0 +1,232 @@
+// Remote control, for driving the snake from another program, like a bot, a stream's chat,
+// or a homemade controller.
+//
+// Start the game with `--remote <address>`, eg. `--remote 127.0.0.1:9001`, and it listens
+// for WebSocket connections there. Every tick of a run, each connection gets sent the board
+// as a line of JSON:
+//
+//     {"score":3,"lives":1,"width":20,"height":20,"shape":"rectangle","dir":"up",
+//      "head":[10,11],"body":[[10,10]],"food":[[4,7]],"obstacles":[],"ice":[],"mud":[]}
+//
+// and when the run's over it gets `{"game_over":true,"score":3}`. Positions are [x, y] on
+// the board, with y going up. Send `up`, `down`, `left` or `right` to steer, which are the
+// board's directions even when it's turned on the screen, `pause` to pause, and `restart`
+// to start again from the game over screen. The snake can't turn while its head is on one
+// of the ice cells, and only moves every other tick while it's in the mud.
+//
+// The connections are looked after on their own threads, and only pass directions and
+// text back and forth with the game.
+use std::io::ErrorKind;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use tungstenite::{Message as WsMessage, WebSocket};
+
+use crate::input::{self, PlayerAction};
+use crate::tiles::{Tile, Tiles};
+use crate::{ArenaMap, Direction, Food, GameState, GridPosition, Lives, Score, SnakeHead, SnakeSegment, SnakeState, TickSet};
+
+
+// How long a connection waits to hear from its program before checking for board updates
+// to send it.
+const POLL_PERIOD : Duration = Duration::from_millis(5);
+
+
+
+// Runs the remote control server, if it was asked for on the command line.
+pub struct RemotePlugin;
+impl Plugin for RemotePlugin {
+	fn build(&self, app : &mut App) {
+		let Some(address) = std::env::args().skip_while(|arg| arg != "--remote").nth(1) else {
+			return;
+		};
+		match RemoteControl::listen(&address) {
+			Ok(remote) => {
+				info!("Listening for remote control on {address}");
+				app.insert_resource(remote);
+			}
+			Err(err) => {
+				error!("Couldn't listen for remote control on {address}: {err}");
+				return;
+			}
+		}
+		app.add_systems(Update, remote_commands_sys.before(input::steer_snake_sys))
+			.add_systems(FixedUpdate, send_board_sys
+				.after(TickSet::Spawn)
+				.run_if(in_state(GameState::Playing)))
+			.add_systems(OnEnter(GameState::GameOver), send_game_over_sys);
+	}
+}
+
+
+
+// Something a remote program asked for.
+enum RemoteCommand {
+	Steer(Direction),
+	Pause,
+	Restart,
+}
+impl RemoteCommand {
+	fn parse(text : &str) -> Option<Self> {
+		match text.trim().to_lowercase().as_str() {
+			"up" => Some(RemoteCommand::Steer(Direction::Up)),
+			"down" => Some(RemoteCommand::Steer(Direction::Down)),
+			"left" => Some(RemoteCommand::Steer(Direction::Left)),
+			"right" => Some(RemoteCommand::Steer(Direction::Right)),
+			"pause" => Some(RemoteCommand::Pause),
+			"restart" => Some(RemoteCommand::Restart),
+			_ => None,
+		}
+	}
+}
+
+// The game's end of the connections. The receiver is only ever used from one system, but
+// resources have to be shareable between threads.
+#[derive(Resource)]
+struct RemoteControl {
+	commands : Mutex<Receiver<RemoteCommand>>,
+	// Where to send text to each connection. Closed connections get dropped the next time
+	// something's sent.
+	clients : Arc<Mutex<Vec<Sender<String>>>>,
+}
+impl RemoteControl {
+	fn listen(address : &str) -> std::io::Result<Self> {
+		let listener = TcpListener::bind(address)?;
+		let (command_tx, command_rx) = mpsc::channel();
+		let clients = Arc::new(Mutex::new(Vec::new()));
+		let thread_clients = clients.clone();
+		std::thread::spawn(move || {
+			for stream in listener.incoming().flatten() {
+				let commands = command_tx.clone();
+				let clients = thread_clients.clone();
+				std::thread::spawn(move || serve_client(stream, commands, clients));
+			}
+		});
+		Ok(RemoteControl { commands : Mutex::new(command_rx), clients })
+	}
+
+	fn broadcast(&self, text : String) {
+		self.clients.lock().unwrap().retain(|client| client.send(text.clone()).is_ok());
+	}
+}
+
+// Looks after one connection until it closes.
+fn serve_client(stream : TcpStream, commands : Sender<RemoteCommand>, clients : Arc<Mutex<Vec<Sender<String>>>>) {
+	let peer = stream.peer_addr().map(|addr| addr.to_string()).unwrap_or_default();
+	let mut socket = match tungstenite::accept(stream) {
+		Ok(socket) => socket,
+		Err(err) => {
+			warn!("Remote control connection from {peer} failed: {err}");
+			return;
+		}
+	};
+	if socket.get_ref().set_read_timeout(Some(POLL_PERIOD)).is_err() {
+		return;
+	}
+	let (board_tx, board_rx) = mpsc::channel();
+	clients.lock().unwrap().push(board_tx);
+	info!("Remote control connected from {peer}");
+	if let Err(err) = pump(&mut socket, &commands, &board_rx) {
+		debug!("Remote control from {peer} stopped: {err}");
+	}
+	info!("Remote control from {peer} disconnected");
+}
+
+// Passes commands one way and boards the other, until the connection closes.
+fn pump(socket : &mut WebSocket<TcpStream>, commands : &Sender<RemoteCommand>, boards : &Receiver<String>) -> tungstenite::Result<()> {
+	loop {
+		for text in boards.try_iter() {
+			socket.send(WsMessage::text(text))?;
+		}
+		match socket.read() {
+			Ok(WsMessage::Text(text)) => match RemoteCommand::parse(text.as_str()) {
+				// The game's gone, so there's no one to pass it on to.
+				Some(command) => if commands.send(command).is_err() {
+					return Ok(());
+				},
+				None => socket.send(WsMessage::text(format!("{{\"error\":\"unknown command {:?}\"}}", text.as_str())))?,
+			},
+			Ok(WsMessage::Close(_)) => return Ok(()),
+			Ok(_) => {}
+			Err(tungstenite::Error::Io(err)) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+			Err(err) => return Err(err),
+		}
+	}
+}
+
+
+
+// Everything on the board that gets sent out each tick.
+#[derive(SystemParam)]
+struct BoardState<'w, 's> {
+	score : Res<'w, Score>,
+	lives : Res<'w, Lives>,
+	snake : Res<'w, SnakeState>,
+	map : Res<'w, ArenaMap>,
+	tiles : Res<'w, Tiles>,
+	head : Query<'w, 's, &'static GridPosition, With<SnakeHead>>,
+	segments : Query<'w, 's, &'static GridPosition, With<SnakeSegment>>,
+	food : Query<'w, 's, &'static GridPosition, With<Food>>,
+}
+impl BoardState<'_, '_> {
+	fn to_json(&self) -> String {
+		let cell = |pos : &GridPosition| format!("[{},{}]", pos.x, pos.y);
+		let cells = |cells : Vec<String>| format!("[{}]", cells.join(","));
+		let head = self.head.single().map(cell).unwrap_or_else(|_| "null".to_string());
+		let dir = format!("{:?}", self.snake.dir).to_lowercase();
+		let body = self.snake.segments.iter().filter_map(|&e| self.segments.get(e).ok()).map(cell).collect();
+		format!(
+			"{{\"score\":{},\"lives\":{},\"width\":{},\"height\":{},\"shape\":\"{}\",\"dir\":\"{dir}\",\"head\":{head},\"body\":{},\"food\":{},\"obstacles\":{},\"ice\":{},\"mud\":{}}}",
+			self.score.0,
+			self.lives.0,
+			self.map.width(),
+			self.map.height(),
+			self.map.shape.to_name(),
+			cells(body),
+			cells(self.food.iter().map(cell).collect()),
+			cells(self.map.obstacles.iter().map(cell).collect()),
+			cells(self.tiles.cells(Tile::Ice).map(|pos| cell(&pos)).collect()),
+			cells(self.tiles.cells(Tile::Mud).map(|pos| cell(&pos)).collect()),
+		)
+	}
+}
+
+
+
+// Turns what the remote programs asked for into the same actions the keyboard makes.
+fn remote_commands_sys(
+	remote : Res<RemoteControl>,
+	state : Res<State<GameState>>,
+	mut actions : MessageWriter<PlayerAction>,
+	mut next_state : ResMut<NextState<GameState>>,
+) {
+	for command in remote.commands.lock().unwrap().try_iter() {
+		match command {
+			RemoteCommand::Steer(dir) => {
+				actions.write(PlayerAction::SteerBoard(dir));
+			}
+			RemoteCommand::Pause => {
+				actions.write(PlayerAction::Pause);
+			}
+			RemoteCommand::Restart => if *state.get() == GameState::GameOver {
+				next_state.set(GameState::Restarting);
+			},
+		}
+	}
+}
+
+
+
+fn send_board_sys(remote : Res<RemoteControl>, board : BoardState) {
+	remote.broadcast(board.to_json());
+}
+
+
+
+fn send_game_over_sys(remote : Res<RemoteControl>, score : Res<Score>) {
+	remote.broadcast(format!("{{\"game_over\":true,\"score\":{}}}", score.0));
+}
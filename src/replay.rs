@@ -0,0 +1,518 @@
+// Replays of finished classic runs.
+//
+// A replay is just the run's seed, how long the snake started out, plus the direction the
+// snake moved on every tick. Since the food is spawned from a seeded RNG, that's enough to
+// play the whole run back exactly. Every replay is signed with a key that's generated once
+// per install, so when we import someone else's replay we can tell if it's been edited
+// since it was saved, and we can re-simulate it to check that the score it claims is the
+// score it actually gets. Anyone can sign an edited replay with a key of their own though,
+// so a signature only counts when it's from this install or a key that's been trusted.
+use bevy::prelude::*;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand::RngCore;
+use rand::rngs::OsRng;
+use std::io;
+use web_time::{SystemTime, UNIX_EPOCH}; // The standard library's clock doesn't work in the browser.
+
+use crate::settings::Settings;
+use crate::storage;
+use crate::snake_core::resimulate;
+#[cfg(feature = "native")]
+use crate::snake_core::Sim;
+use crate::{Direction, GameRng, GameState, Lives, Score, SnakeState, WHITE};
+
+
+// Where finished runs get saved.
+pub const REPLAY_DIR : &str = "replays";
+// Drop other people's replays in here and they'll be checked on startup.
+const IMPORT_DIR : &str = "replays/import";
+// This install's signing key. Don't share it!
+const KEY_FILE : &str = "replays/signing.key";
+// The keys of people whose replays are trusted, one per line in hex, with an optional
+// name after it. This install's own key is always trusted.
+const TRUSTED_KEYS_FILE : &str = "replays/trusted_keys.txt";
+// How many imported replays fit on the replays screen.
+const REPLAYS_SHOWN : usize = 12;
+
+
+
+pub struct ReplaysPlugin;
+
+impl Plugin for ReplaysPlugin {
+	fn build(&self, app : &mut App) {
+		app.init_resource::<ImportedReplays>()
+			.add_systems(Startup, check_imported_replays_sys)
+			.add_systems(OnEnter(GameState::Replays), spawn_replays_ui)
+			.add_systems(OnExit(GameState::Replays), cleanup_replays_ui)
+			.add_systems(Update, replays_menu_input.run_if(in_state(GameState::Replays)));
+	}
+}
+
+
+
+// Keeps track of the run that's currently being played so it can be saved as a replay.
+#[derive(Resource, Default)]
+pub struct ReplayRecorder {
+	seed : u64,
+	lives : u32,
+	growth : u32,
+	ticks : Vec<Direction>,
+	// Set once the run goes past the replay length cap. The ticks are thrown away, since
+	// a replay that's missing the end can't be played back anyway.
+	overflowed : bool,
+}
+impl ReplayRecorder {
+	pub fn ticks_recorded(&self) -> usize {
+		self.ticks.len()
+	}
+
+	// The inputs so far, for a crash report.
+	#[cfg(feature = "native")]
+	pub fn ticks(&self) -> &[Direction] {
+		&self.ticks
+	}
+
+	// The run so far, ready to be played back from the start. There isn't one once the
+	// recording's been cut off.
+	#[cfg(feature = "native")]
+	pub fn playback(&self) -> Option<(Sim, &[Direction])> {
+		if self.overflowed {
+			return None;
+		}
+		Some((Sim::new(self.seed, self.lives, self.growth), &self.ticks))
+	}
+
+	// The score the run so far gets when it's played back.
+	pub fn resimulated_score(&self) -> u32 {
+		resimulate(self.seed, self.lives, self.growth, &self.ticks)
+	}
+
+	// Roughly how much memory the recording is holding on to.
+	pub fn memory_bytes(&self) -> usize {
+		self.ticks.capacity() * std::mem::size_of::<Direction>()
+	}
+
+	// Stops the run that's being recorded from being saved, for when it didn't start from
+	// the beginning.
+	pub fn discard(&mut self) {
+		self.overflowed = true;
+		self.ticks = Vec::new();
+	}
+
+	// The best saved run on the same seed as this one, out of the ones that started out
+	// the same way.
+	pub fn best_saved_run(&self) -> Option<Replay> {
+		storage::list(REPLAY_DIR)
+			.into_iter()
+			.filter_map(|path| storage::read(&path))
+			.filter_map(|text| Replay::from_text(&text).ok())
+			.filter(|replay| replay.seed == self.seed && replay.lives == self.lives && replay.growth == self.growth)
+			.max_by_key(|replay| replay.score)
+	}
+}
+
+
+
+// A finished run, along with the signature that vouches for it.
+pub struct Replay {
+	pub seed : u64,
+	// How many lives the run started with.
+	pub lives : u32,
+	// How many segments the snake started with on top of its head, from prestiging.
+	pub growth : u32,
+	// The score the replay says it got.
+	pub score : u32,
+	pub ticks : Vec<Direction>,
+	// The public half of the key that signed it.
+	pub public_key : [u8; 32],
+	pub signature : [u8; 64],
+}
+
+impl Replay {
+	// Records and signs a run with this install's key.
+	fn sign(seed : u64, lives : u32, growth : u32, score : u32, ticks : Vec<Direction>, key : &SigningKey) -> Self {
+		let signature = key.sign(signed_message(seed, lives, growth, score, &ticks).as_bytes());
+		Replay {
+			seed,
+			lives,
+			growth,
+			score,
+			ticks,
+			public_key : key.verifying_key().to_bytes(),
+			signature : signature.to_bytes(),
+		}
+	}
+
+	// Checks that the replay hasn't been changed since it was signed by the key it comes
+	// with. That key could be anyone's, so on its own this doesn't say much.
+	pub fn signature_is_valid(&self) -> bool {
+		let Ok(key) = VerifyingKey::from_bytes(&self.public_key) else {
+			return false;
+		};
+		let signature = Signature::from_bytes(&self.signature);
+		key.verify(signed_message(self.seed, self.lives, self.growth, self.score, &self.ticks).as_bytes(), &signature).is_ok()
+	}
+
+	// The replay file format. It's plain text so it's easy to look at.
+	fn to_text(&self) -> String {
+		format!(
+			"seed {}\nlives {}\ngrowth {}\nscore {}\nticks {}\nkey {}\nsignature {}\n",
+			self.seed,
+			self.lives,
+			self.growth,
+			self.score,
+			ticks_to_text(&self.ticks),
+			to_hex(&self.public_key),
+			to_hex(&self.signature),
+		)
+	}
+
+	fn from_text(text : &str) -> Result<Self, String> {
+		let mut seed = None;
+		let mut lives = None;
+		// Replays from before the prestige ladder don't have this, and started from nothing.
+		let mut growth = 0;
+		let mut score = None;
+		let mut ticks = None;
+		let mut public_key = None;
+		let mut signature = None;
+		for line in text.lines() {
+			let (field, value) = line.split_once(' ').unwrap_or((line, ""));
+			match field {
+				"seed" => seed = Some(value.parse().map_err(|_| "bad seed")?),
+				"lives" => lives = Some(value.parse().map_err(|_| "bad lives")?),
+				"growth" => growth = value.parse().map_err(|_| "bad growth")?,
+				"score" => score = Some(value.parse().map_err(|_| "bad score")?),
+				"ticks" => ticks = Some(ticks_from_text(value)?),
+				"key" => public_key = Some(from_hex(value)?.try_into().map_err(|_| "bad key")?),
+				"signature" => signature = Some(from_hex(value)?.try_into().map_err(|_| "bad signature")?),
+				_ => {},
+			}
+		}
+		Ok(Replay {
+			seed : seed.ok_or("missing seed")?,
+			lives : lives.ok_or("missing lives")?,
+			growth,
+			score : score.ok_or("missing score")?,
+			ticks : ticks.ok_or("missing ticks")?,
+			public_key : public_key.ok_or("missing key")?,
+			signature : signature.ok_or("missing signature")?,
+		})
+	}
+}
+
+
+
+// The keys we believe replays from, along with who they belong to.
+pub struct TrustedKeys(Vec<([u8; 32], String)>);
+
+impl TrustedKeys {
+	// This install's own key, plus everyone in the trusted keys file.
+	pub fn load() -> Self {
+		let mut keys = Vec::new();
+		if let Some(key) = load_key() {
+			keys.push((key.verifying_key().to_bytes(), "you".to_string()));
+		}
+		for line in storage::read(TRUSTED_KEYS_FILE).unwrap_or_default().lines() {
+			let line = line.trim();
+			let (hex, name) = line.split_once(' ').unwrap_or((line, ""));
+			match from_hex(hex).ok().and_then(|bytes| <[u8; 32]>::try_from(bytes).ok()) {
+				// Keys without a name go by their fingerprint.
+				Some(key) if name.trim().is_empty() => keys.push((key, fingerprint(&key))),
+				Some(key) => keys.push((key, name.trim().to_string())),
+				None if line.is_empty() => {},
+				None => warn!("{TRUSTED_KEYS_FILE} has a line that isn't a key: {line}"),
+			}
+		}
+		TrustedKeys(keys)
+	}
+
+	fn name_of(&self, key : &[u8; 32]) -> Option<&str> {
+		self.0.iter().find(|(trusted, _)| trusted == key).map(|(_, name)| name.as_str())
+	}
+}
+
+
+
+// What we found out about an imported replay.
+pub struct ReplayCheck {
+	pub signature_valid : bool,
+	// Who signed it, if it's a key we trust.
+	pub signer : Option<String>,
+	// A short form of the key that signed it, to check against whoever sent it.
+	pub fingerprint : String,
+	pub claimed_score : u32,
+	pub simulated_score : u32,
+}
+
+impl ReplayCheck {
+	pub fn is_trustworthy(&self) -> bool {
+		self.signature_valid && self.signer.is_some() && self.claimed_score == self.simulated_score
+	}
+
+	// One line on what's wrong with the replay, or who it's from if nothing is.
+	fn verdict(&self) -> String {
+		if !self.signature_valid {
+			"TAMPERED, it was changed after it was signed".to_string()
+		}
+		else if self.claimed_score != self.simulated_score {
+			format!("TAMPERED, claims {} but plays back to {}", self.claimed_score, self.simulated_score)
+		}
+		else if let Some(signer) = &self.signer {
+			format!("OK, {} points, signed by {signer}", self.claimed_score)
+		}
+		else {
+			format!("UNTRUSTED, signed by unknown key {}", self.fingerprint)
+		}
+	}
+}
+
+pub fn check_replay(replay : &Replay, trusted : &TrustedKeys) -> ReplayCheck {
+	ReplayCheck {
+		signature_valid : replay.signature_is_valid(),
+		signer : trusted.name_of(&replay.public_key).map(str::to_string),
+		fingerprint : fingerprint(&replay.public_key),
+		claimed_score : replay.score,
+		simulated_score : resimulate(replay.seed, replay.lives, replay.growth, &replay.ticks),
+	}
+}
+
+// The first few bytes of a key, spaced out so they're easy to read out to someone.
+pub fn fingerprint(key : &[u8; 32]) -> String {
+	key[..8].chunks(2).map(to_hex).collect::<Vec<_>>().join(" ")
+}
+
+
+
+// Starts recording as soon as a run starts. Has to run after the run has been reseeded.
+pub fn start_recording_sys(
+	mut recorder : ResMut<ReplayRecorder>,
+	rng : Res<GameRng>,
+	lives : Res<Lives>,
+	snake : Res<SnakeState>,
+) {
+	recorder.seed = rng.seed;
+	recorder.lives = lives.0;
+	recorder.growth = snake.grow;
+	recorder.ticks.clear();
+	recorder.overflowed = false;
+}
+
+
+
+// Writes down which way the snake went this tick, until the run gets too long to keep.
+pub fn record_tick_sys(mut recorder : ResMut<ReplayRecorder>, snake : Res<SnakeState>, settings : Res<Settings>) {
+	if recorder.overflowed {
+		return;
+	}
+	if recorder.ticks.len() >= settings.max_replay_ticks {
+		warn!("Run went past {} ticks, it won't be saved as a replay", settings.max_replay_ticks);
+		recorder.overflowed = true;
+		recorder.ticks = Vec::new();
+		return;
+	}
+	recorder.ticks.push(snake.dir);
+}
+
+
+
+// Signs the run that just ended and saves it to the replays folder.
+pub fn save_replay_sys(recorder : Res<ReplayRecorder>, score : Res<Score>) {
+	if recorder.overflowed {
+		return;
+	}
+	let result = load_or_create_key().and_then(|key| {
+		let replay = Replay::sign(recorder.seed, recorder.lives, recorder.growth, score.0, recorder.ticks.clone(), &key);
+		let stamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+		let path = format!("{REPLAY_DIR}/run-{stamp}.replay");
+		storage::write(&path, &replay.to_text()).map(|_| path)
+	});
+	match result {
+		Ok(path) => info!("Saved replay to {path}"),
+		Err(err) => warn!("Couldn't save replay: {err}"),
+	}
+}
+
+
+
+// How each replay in the import folder checked out, for the replays screen. Ones that
+// couldn't be read have the reason instead.
+#[derive(Resource, Default)]
+pub struct ImportedReplays(Vec<(String, Result<ReplayCheck, String>)>);
+
+
+
+// Checks every replay in the import folder and complains about any that don't add up.
+fn check_imported_replays_sys(mut imported : ResMut<ImportedReplays>) {
+	let trusted = TrustedKeys::load();
+	for path in storage::list(IMPORT_DIR) {
+		let name = path.rsplit('/').next().unwrap_or(&path).to_string();
+		let text = storage::read(&path).ok_or_else(|| "it couldn't be read".to_string());
+		let replay = match text.and_then(|text| Replay::from_text(&text)) {
+			Ok(replay) => replay,
+			Err(err) => {
+				warn!("{path} isn't a valid replay: {err}");
+				imported.0.push((name, Err(err)));
+				continue;
+			}
+		};
+
+		let check = check_replay(&replay, &trusted);
+		if !check.signature_valid {
+			warn!("{path} has a bad signature, it was changed after it was saved");
+		}
+		else if check.signer.is_none() {
+			warn!("{path} was signed by a key that isn't trusted ({})", check.fingerprint);
+		}
+		if check.claimed_score != check.simulated_score {
+			warn!(
+				"{path} claims a score of {}, but playing it back only scores {}",
+				check.claimed_score,
+				check.simulated_score,
+			);
+		}
+		if check.is_trustworthy() {
+			info!("{path} checks out with a score of {}", check.claimed_score);
+		}
+		imported.0.push((name, Ok(check)));
+	}
+}
+
+
+
+// The replays screen, so it can be cleaned up.
+#[derive(Component)]
+struct ReplaysUI;
+
+
+
+fn spawn_replays_ui(mut commands : Commands, imported : Res<ImportedReplays>) {
+	commands.spawn((
+		ReplaysUI,
+		Node {
+			width: Val::Percent(100.0),
+			height: Val::Percent(100.0),
+			justify_content: JustifyContent::Center,
+			align_items: AlignItems::Center,
+			..default()
+		},
+		BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+	))
+	.with_child((
+		Text::new(replays_text(&imported)),
+		TextFont {
+			font_size : 20.0,
+			..default()
+		},
+		TextColor(WHITE),
+		TextLayout::new(Justify::Center, LineBreak::WordBoundary),
+	));
+}
+
+
+
+fn cleanup_replays_ui(mut commands : Commands, ui : Query<Entity, With<ReplaysUI>>) {
+	for e in &ui {
+		commands.entity(e).despawn();
+	}
+}
+
+
+
+// O or enter goes back to the menu.
+fn replays_menu_input(keys : Res<ButtonInput<KeyCode>>, mut next_state : ResMut<NextState<GameState>>) {
+	if keys.just_pressed(KeyCode::KeyO) || crate::settings::enter_pressed(&keys) {
+		next_state.set(GameState::Menu);
+	}
+}
+
+
+
+fn replays_text(imported : &ImportedReplays) -> String {
+	let mut text = String::from("IMPORTED REPLAYS\n");
+	if imported.0.is_empty() {
+		text.push_str(&format!("Nothing in {IMPORT_DIR} yet\n"));
+	}
+	for (name, check) in imported.0.iter().take(REPLAYS_SHOWN) {
+		match check {
+			Ok(check) => text.push_str(&format!("{name}: {}\n", check.verdict())),
+			Err(err) => text.push_str(&format!("{name}: UNREADABLE, {err}\n")),
+		}
+	}
+	if imported.0.len() > REPLAYS_SHOWN {
+		text.push_str(&format!("...and {} more\n", imported.0.len() - REPLAYS_SHOWN));
+	}
+	let own = load_key().map(|key| fingerprint(&key.verifying_key().to_bytes())).unwrap_or_else(|| "none yet".to_string());
+	text.push_str(&format!("\nYour key: {own}\nTrust someone's key by adding it to {TRUSTED_KEYS_FILE}\nPress O to go back"));
+	text
+}
+
+
+
+// Loads this install's signing key, if it's made one yet.
+fn load_key() -> Option<SigningKey> {
+	let text = storage::read(KEY_FILE)?;
+	let bytes = from_hex(text.trim()).ok().and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())?;
+	Some(SigningKey::from_bytes(&bytes))
+}
+
+// Loads this install's signing key, or makes one the first time it's needed.
+fn load_or_create_key() -> io::Result<SigningKey> {
+	if let Some(key) = load_key() {
+		return Ok(key);
+	}
+
+	let mut bytes = [0u8; 32];
+	OsRng.fill_bytes(&mut bytes);
+	storage::write(KEY_FILE, &to_hex(&bytes))?;
+	Ok(SigningKey::from_bytes(&bytes))
+}
+
+
+
+// Everything in the replay that the signature covers. The growth is only added when there
+// is some, so replays from before the prestige ladder still check out.
+fn signed_message(seed : u64, lives : u32, growth : u32, score : u32, ticks : &[Direction]) -> String {
+	let growth = if growth > 0 { format!("growth {growth}\n") } else { String::new() };
+	format!("rusty-snake replay\n{seed}\n{lives}\n{growth}{score}\n{}", ticks_to_text(ticks))
+}
+
+
+
+// One letter per tick: N(one), U(p), D(own), L(eft), R(ight).
+pub fn ticks_to_text(ticks : &[Direction]) -> String {
+	ticks.iter().map(|dir| match dir {
+		Direction::None => 'N',
+		Direction::Up => 'U',
+		Direction::Down => 'D',
+		Direction::Left => 'L',
+		Direction::Right => 'R',
+	}).collect()
+}
+
+pub fn ticks_from_text(text : &str) -> Result<Vec<Direction>, String> {
+	text.chars().map(|c| match c {
+		'N' => Ok(Direction::None),
+		'U' => Ok(Direction::Up),
+		'D' => Ok(Direction::Down),
+		'L' => Ok(Direction::Left),
+		'R' => Ok(Direction::Right),
+		_ => Err(format!("bad tick '{c}'")),
+	}).collect()
+}
+
+
+
+fn to_hex(bytes : &[u8]) -> String {
+	bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn from_hex(text : &str) -> Result<Vec<u8>, String> {
+	if !text.len().is_multiple_of(2) {
+		return Err("odd number of hex digits".to_string());
+	}
+	(0..text.len())
+		.step_by(2)
+		.map(|i| u8::from_str_radix(text.get(i..i + 2).unwrap_or(""), 16).map_err(|_| "bad hex".to_string()))
+		.collect()
+}
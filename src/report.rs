@@ -0,0 +1,184 @@
+// Bug reports. Everything that helps work out what went wrong gets bundled up into one
+// text file that can be attached to an issue: the system, the settings, the progress,
+// the stats, the last replay, and the crash message if there was one. It also gets the
+// run that's going, if there is one: its seed, the inputs so far, and a snapshot of the
+// board, so it can be played up to the same point again.
+//
+// A report can be saved from the menu, and one gets saved automatically if the game
+// crashes. F9 during a run saves a whole bundle instead, a folder with the report and a
+// screenshot of the board in it. It's passed through a redaction step before being written, so it doesn't give
+// away the player's name or where their home folder is. The replay signing key never goes
+// in at all.
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use bevy::render::view::screenshot::{save_to_disk, Screenshot, ScreenshotCaptured};
+
+use crate::progress::PROGRESS_FILE;
+use crate::replay::REPLAY_DIR;
+use crate::settings::SETTINGS_FILE;
+use crate::stats::STATS_FILE;
+
+
+// Where the reports get saved.
+const REPORT_DIR : &str = "reports";
+
+// The run that's going right now, written down after every tick. A panic hook can't get
+// at the game's resources, so it reads this instead.
+static RUN_IN_PROGRESS : Mutex<String> = Mutex::new(String::new());
+
+
+
+// Puts a report together and saves it. The panic message gets included when it's a crash.
+pub fn write_report(panic : Option<&str>) -> io::Result<PathBuf> {
+	fs::create_dir_all(REPORT_DIR)?;
+	let path = Path::new(REPORT_DIR).join(format!("report-{}.txt", stamp()));
+	fs::write(&path, redact(&report_text(panic)))?;
+	Ok(path)
+}
+
+// Makes a folder for a bundle and saves the report into it. The screenshot gets added
+// once it's been taken.
+fn write_bundle() -> io::Result<PathBuf> {
+	let dir = Path::new(REPORT_DIR).join(format!("bundle-{}", stamp()));
+	fs::create_dir_all(&dir)?;
+	fs::write(dir.join("report.txt"), redact(&report_text(None)))?;
+	Ok(dir)
+}
+
+fn report_text(panic : Option<&str>) -> String {
+	let mut report = String::from("Rusty Snake bug report\n");
+	section(&mut report, "System", &system_info());
+	if let Some(panic) = panic {
+		section(&mut report, "Crash", panic);
+	}
+	section(&mut report, "Run in progress", &run_in_progress());
+	section(&mut report, "Settings", &read_or_missing(Path::new(SETTINGS_FILE)));
+	section(&mut report, "Progress", &read_or_missing(Path::new(PROGRESS_FILE)));
+	section(&mut report, "Stats", &read_or_missing(Path::new(STATS_FILE)));
+	section(&mut report, "Last replay", &last_replay());
+	report
+}
+
+fn stamp() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+
+
+// F9 saves a bundle for a bug report in the middle of a run. The toast waits until the
+// screenshot's been taken, so it isn't in it.
+pub fn capture_bundle_on_input(mut commands : Commands, keys : Res<ButtonInput<KeyCode>>) {
+	if !keys.just_pressed(KeyCode::F9) {
+		return;
+	}
+	let dir = match write_bundle() {
+		Ok(dir) => dir,
+		Err(err) => {
+			warn!("Couldn't save a bug report bundle: {err}");
+			crate::spawn_toast(&mut commands, "Couldn't save a bug report");
+			return;
+		}
+	};
+	let message = format!("Saved a bug report to {}", dir.display());
+	commands
+		.spawn(Screenshot::primary_window())
+		.observe(save_to_disk(dir.join("screenshot.png")))
+		.observe(move |_ : On<ScreenshotCaptured>, mut commands : Commands| {
+			crate::spawn_toast(&mut commands, message.clone());
+		});
+}
+
+
+
+// Saves a report whenever the game panics, after the usual panic message has been printed.
+pub fn install_crash_hook() {
+	let default_hook = std::panic::take_hook();
+	std::panic::set_hook(Box::new(move |info| {
+		default_hook(info);
+		match write_report(Some(&info.to_string())) {
+			Ok(path) => eprintln!("Saved a bug report to {}", path.display()),
+			Err(err) => eprintln!("Couldn't save a bug report: {err}"),
+		}
+	}));
+}
+
+
+
+// Keeps track of the run that's going, for a crash report to pick up. An empty string
+// means there isn't one.
+pub fn remember_run(run : String) {
+	// A panic while this was held leaves it poisoned, but the text is still fine to use.
+	*RUN_IN_PROGRESS.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = run;
+}
+
+fn run_in_progress() -> String {
+	let run = RUN_IN_PROGRESS.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+	if run.is_empty() { "(not playing)".to_string() } else { run }
+}
+
+
+
+fn section(report : &mut String, title : &str, body : &str) {
+	report.push_str(&format!("\n== {title} ==\n{}\n", body.trim_end()));
+}
+
+fn system_info() -> String {
+	let cpus = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+	format!(
+		"version {}\nos {}\narch {}\ncpus {}\n",
+		env!("CARGO_PKG_VERSION"),
+		std::env::consts::OS,
+		std::env::consts::ARCH,
+		cpus,
+	)
+}
+
+fn read_or_missing(path : &Path) -> String {
+	fs::read_to_string(path).unwrap_or_else(|_| format!("({} not found)", path.display()))
+}
+
+
+
+// The newest replay in the replays folder. The key and signature are left out, since
+// they're only there to tie the replay to this install.
+fn last_replay() -> String {
+	let newest = fs::read_dir(REPLAY_DIR).ok().and_then(|entries| {
+		entries
+			.flatten()
+			.map(|entry| entry.path())
+			.filter(|path| path.extension().is_some_and(|ext| ext == "replay"))
+			.max_by_key(|path| fs::metadata(path).and_then(|meta| meta.modified()).ok())
+	});
+	let Some(path) = newest else {
+		return "(no replays)".to_string();
+	};
+	let text = read_or_missing(&path);
+	text.lines()
+		.filter(|line| !line.starts_with("key ") && !line.starts_with("signature "))
+		.map(|line| format!("{line}\n"))
+		.collect()
+}
+
+
+
+// Blanks out anything that says who the player is or where their files are.
+fn redact(report : &str) -> String {
+	let mut report = report.to_string();
+	for var in ["HOME", "USERPROFILE"] {
+		if let Ok(home) = std::env::var(var) && !home.is_empty() {
+			report = report.replace(&home, "<home>");
+		}
+	}
+	// A really short name would blank out bits of ordinary words too.
+	for var in ["USER", "USERNAME"] {
+		if let Ok(user) = std::env::var(var) && user.len() >= 3 {
+			report = report.replace(&user, "<user>");
+		}
+	}
+	report
+}
@@ -0,0 +1,66 @@
+// F12 takes a screenshot, wherever you are.
+//
+// On desktop they go in the Screenshots folder in the player's pictures, falling back on a
+// screenshots folder next to the game if there isn't one. In the browser they get
+// downloaded instead.
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy::render::view::screenshot::{save_to_disk, Screenshot, ScreenshotCaptured};
+use web_time::{SystemTime, UNIX_EPOCH}; // The standard library's clock doesn't work in the browser.
+
+
+// Where screenshots go when the pictures folder can't be found.
+const FALLBACK_DIR : &str = "screenshots";
+
+
+
+// Takes screenshots.
+pub struct ScreenshotPlugin;
+impl Plugin for ScreenshotPlugin {
+	fn build(&self, app : &mut App) {
+		app.add_systems(Update, screenshot_on_input);
+	}
+}
+
+
+
+// The pictures folder's Screenshots folder, made if it isn't there yet.
+#[cfg(not(target_arch = "wasm32"))]
+fn screenshot_dir() -> PathBuf {
+	let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"));
+	let pictures = std::env::var_os("XDG_PICTURES_DIR")
+		.map(PathBuf::from)
+		.or_else(|| home.map(|home| PathBuf::from(home).join("Pictures")));
+	let dir = pictures.map(|pictures| pictures.join("Screenshots")).unwrap_or_else(|| PathBuf::from(FALLBACK_DIR));
+	if let Err(err) = std::fs::create_dir_all(&dir) {
+		warn!("Couldn't make {}, using {FALLBACK_DIR} instead: {err}", dir.display());
+		let _ = std::fs::create_dir_all(FALLBACK_DIR);
+		return PathBuf::from(FALLBACK_DIR);
+	}
+	dir
+}
+
+// The browser only needs a name for the download.
+#[cfg(target_arch = "wasm32")]
+fn screenshot_dir() -> PathBuf {
+	PathBuf::new()
+}
+
+
+
+// The confirmation waits until the screenshot's been taken, so it isn't in it.
+fn screenshot_on_input(mut commands : Commands, keys : Res<ButtonInput<KeyCode>>) {
+	if !keys.just_pressed(KeyCode::F12) {
+		return;
+	}
+	let stamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+	let path = screenshot_dir().join(format!("rusty-snake-{stamp}.png"));
+	let message = format!("Saved a screenshot to {}", path.display());
+	commands
+		.spawn(Screenshot::primary_window())
+		.observe(save_to_disk(path))
+		.observe(move |_ : On<ScreenshotCaptured>, mut commands : Commands| {
+			crate::spawn_toast(&mut commands, message.clone());
+		});
+}
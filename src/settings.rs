@@ -0,0 +1,577 @@
+// The player's settings, saved to a config file so they stick around between launches.
+//
+// The file is plain text with one "name value" pair per line, the same as the replay
+// files. Anything missing or unreadable just falls back to the default.
+use bevy::prelude::*;
+use bevy::window::{MonitorSelection, PrimaryWindow, WindowMode};
+use std::time::Duration;
+
+use crate::storage;
+use crate::skins::Skin;
+use crate::theme::Palette;
+use crate::{GameState, MOVE_PERIOD, WHITE};
+
+
+// Where the settings get saved.
+pub const SETTINGS_FILE : &str = "settings.txt";
+// How loud the music and sound effects are out of the box, from 0 to 1.
+const DEFAULT_MUSIC_VOLUME : f32 = 0.5;
+const DEFAULT_SFX_VOLUME : f32 = 0.5;
+// How much one press of a volume key changes the volume by.
+const VOLUME_STEP : f32 = 0.1;
+// The longest run that gets kept as a replay, in ticks. This is about four hours of play.
+const DEFAULT_MAX_REPLAY_TICKS : usize = 100_000;
+// How far the time between moves can be changed, and by how much per key press, in milliseconds.
+const MIN_MOVE_PERIOD_MS : u32 = 50;
+const MAX_MOVE_PERIOD_MS : u32 = 300;
+const MOVE_PERIOD_STEP_MS : u32 = 10;
+// The slowest the demo player's reactions can be set to, in ticks.
+const MAX_BOT_REACTION_TICKS : u32 = 5;
+// How many lines there are on the settings screen, and how many fit on it at once. The
+// rest scroll into view as the cursor gets to them.
+const SETTINGS_ROWS : usize = 13;
+const SETTINGS_VISIBLE_ROWS : usize = 8;
+const SETTINGS_FONT_SIZE : f32 = 32.0;
+// The rows from here down go under the accessibility heading.
+const ACCESSIBILITY_ROW : usize = 10;
+
+
+
+// Which keys steer the snake.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Controls {
+	Arrows,
+	Wasd,
+	// The snake turns towards the mouse cursor. The arrow keys still work too.
+	Mouse,
+}
+impl Controls {
+	fn name(self) -> &'static str {
+		match self {
+			Controls::Arrows => "Arrow keys",
+			Controls::Wasd => "WASD",
+			Controls::Mouse => "Mouse",
+		}
+	}
+
+	fn from_name(name : &str) -> Option<Self> {
+		match name {
+			"arrows" => Some(Controls::Arrows),
+			"wasd" => Some(Controls::Wasd),
+			"mouse" => Some(Controls::Mouse),
+			_ => None,
+		}
+	}
+
+	fn to_name(self) -> &'static str {
+		match self {
+			Controls::Arrows => "arrows",
+			Controls::Wasd => "wasd",
+			Controls::Mouse => "mouse",
+		}
+	}
+
+	// The keys for up, down, left, and right.
+	pub fn keys(self) -> [KeyCode; 4] {
+		match self {
+			Controls::Arrows | Controls::Mouse => [KeyCode::ArrowUp, KeyCode::ArrowDown, KeyCode::ArrowLeft, KeyCode::ArrowRight],
+			Controls::Wasd => [KeyCode::KeyW, KeyCode::KeyS, KeyCode::KeyA, KeyCode::KeyD],
+		}
+	}
+}
+
+
+
+// Who plays the demo on the menu.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum DemoBot {
+	// Heads straight for the food, and sooner or later traps itself.
+	Greedy,
+	// Never traps itself, and keeps going until the board's full. Good for seeing how the
+	// game copes with a really long snake.
+	Perfect,
+	// Played by a mod's steer function. The greedy bot stands in if there isn't one.
+	Script,
+}
+impl DemoBot {
+	fn name(self) -> &'static str {
+		match self {
+			DemoBot::Greedy => "Greedy",
+			DemoBot::Perfect => "Perfect",
+			DemoBot::Script => "Mod",
+		}
+	}
+
+	fn from_name(name : &str) -> Option<Self> {
+		match name {
+			"greedy" => Some(DemoBot::Greedy),
+			"perfect" => Some(DemoBot::Perfect),
+			"script" => Some(DemoBot::Script),
+			_ => None,
+		}
+	}
+
+	fn to_name(self) -> &'static str {
+		match self {
+			DemoBot::Greedy => "greedy",
+			DemoBot::Perfect => "perfect",
+			DemoBot::Script => "script",
+		}
+	}
+}
+
+
+
+#[derive(Resource, Clone, PartialEq, Debug)]
+pub struct Settings {
+	// How many milliseconds between the snake moving. Lower is faster.
+	pub move_period_ms : u32,
+	pub controls : Controls,
+	// Turns the board a quarter turn clockwise, for portrait screens. The controls get
+	// turned with it, so up on the keyboard is still up on the screen.
+	pub rotated : bool,
+	// Fills the whole screen instead of sitting in a window. The board keeps its shape,
+	// with bars down the sides.
+	pub fullscreen : bool,
+	// Puts arrows in the corner of the screen that can be touched to steer.
+	pub dpad : bool,
+	pub demo_bot : DemoBot,
+	// How many ticks late the demo player's decisions go through. Zero means it reacts
+	// instantly, raising it makes it react more like a human would.
+	pub bot_reaction_ticks : u32,
+	// Effects like the burst when food gets eaten. They can be turned off to go easier
+	// on slow machines.
+	pub effects : bool,
+	// The colours the board is drawn in.
+	pub palette : Palette,
+	// The skin the snake wears, picked on the skins screen.
+	pub skin : Skin,
+	// Gives the food, the bank, and the obstacles their own shapes, so they can be told
+	// apart without going by colour.
+	pub shapes : bool,
+	// Shakes the camera when the snake dies, or when a big pot gets banked.
+	pub shake : bool,
+	// Volumes go from 0 (silent) to 1 (full volume).
+	pub music_volume : f32,
+	pub sfx_volume : f32,
+	// Muting keeps the volumes as they were, so unmuting puts them back.
+	pub muted : bool,
+	// Runs longer than this stop being recorded, so the replay doesn't grow forever.
+	pub max_replay_ticks : usize,
+}
+impl Default for Settings {
+	fn default() -> Self {
+		Settings {
+			move_period_ms : (MOVE_PERIOD * 1000.0) as u32,
+			controls : Controls::Arrows,
+			rotated : false,
+			fullscreen : false,
+			// Phones and tablets have nothing else to steer with but swiping.
+			dpad : cfg!(any(target_os = "android", target_os = "ios")),
+			effects : true,
+			demo_bot : DemoBot::Greedy,
+			bot_reaction_ticks : 0,
+			palette : Palette::default(),
+			skin : Skin::default(),
+			shapes : false,
+			shake : true,
+			music_volume : DEFAULT_MUSIC_VOLUME,
+			sfx_volume : DEFAULT_SFX_VOLUME,
+			muted : false,
+			max_replay_ticks : DEFAULT_MAX_REPLAY_TICKS,
+		}
+	}
+}
+impl Settings {
+	// Reads the settings file, or uses the defaults if there isn't one yet.
+	pub fn load() -> Self {
+		match storage::read(SETTINGS_FILE) {
+			Some(text) => Settings::from_text(&text),
+			None => Settings::default(),
+		}
+	}
+
+	// How long between the snake moving.
+	pub fn move_period(&self) -> Duration {
+		Duration::from_millis(self.move_period_ms as u64)
+	}
+
+	// One step faster for a positive change, which means less time between moves, or slower
+	// for a negative one.
+	fn step_move_period(&mut self, change : i32) {
+		let period = self.move_period_ms as i32 - change * MOVE_PERIOD_STEP_MS as i32;
+		self.move_period_ms = (period.max(0) as u32).clamp(MIN_MOVE_PERIOD_MS, MAX_MOVE_PERIOD_MS);
+	}
+
+	// The camera's rotation. Turning the camera anticlockwise makes the board look like
+	// it's been turned clockwise.
+	pub fn board_rotation(&self) -> Quat {
+		if self.rotated { Quat::from_rotation_z(std::f32::consts::FRAC_PI_2) } else { Quat::IDENTITY }
+	}
+
+	// How loud the music should actually be, taking muting into account.
+	#[cfg(feature = "audio")]
+	pub fn music(&self) -> f32 {
+		if self.muted { 0.0 } else { self.music_volume }
+	}
+
+	// How loud the sound effects should actually be, taking muting into account.
+	#[cfg(feature = "audio")]
+	pub fn sfx(&self) -> f32 {
+		if self.muted { 0.0 } else { self.sfx_volume }
+	}
+
+	fn to_text(&self) -> String {
+		format!(
+			"move_period_ms {}\ncontrols {}\nrotated {}\nfullscreen {}\ndpad {}\neffects {}\ndemo_bot {}\nbot_reaction_ticks {}\npalette {}\nskin {}\nshapes {}\nshake {}\nmusic_volume {}\nsfx_volume {}\nmuted {}\nmax_replay_ticks {}\n",
+			self.move_period_ms,
+			self.controls.to_name(),
+			self.rotated,
+			self.fullscreen,
+			self.dpad,
+			self.effects,
+			self.demo_bot.to_name(),
+			self.bot_reaction_ticks,
+			self.palette.to_name(),
+			self.skin.to_name(),
+			self.shapes,
+			self.shake,
+			self.music_volume,
+			self.sfx_volume,
+			self.muted,
+			self.max_replay_ticks,
+		)
+	}
+
+	fn from_text(text : &str) -> Self {
+		let mut settings = Settings::default();
+		for line in text.lines() {
+			let Some((name, value)) = line.split_once(' ') else {
+				continue;
+			};
+			let value = value.trim();
+			match name {
+				"move_period_ms" => if let Ok(v) = value.parse::<u32>() { settings.move_period_ms = v.clamp(MIN_MOVE_PERIOD_MS, MAX_MOVE_PERIOD_MS) },
+				"controls" => if let Some(v) = Controls::from_name(value) { settings.controls = v },
+				"rotated" => if let Ok(v) = value.parse() { settings.rotated = v },
+				"fullscreen" => if let Ok(v) = value.parse() { settings.fullscreen = v },
+				"dpad" => if let Ok(v) = value.parse() { settings.dpad = v },
+				"effects" => if let Ok(v) = value.parse() { settings.effects = v },
+				"demo_bot" => if let Some(v) = DemoBot::from_name(value) { settings.demo_bot = v },
+				"bot_reaction_ticks" => if let Ok(v) = value.parse::<u32>() { settings.bot_reaction_ticks = v.min(MAX_BOT_REACTION_TICKS) },
+				"palette" => if let Some(v) = Palette::from_name(value) { settings.palette = v },
+				"skin" => if let Some(v) = Skin::from_name(value) { settings.skin = v },
+				"shapes" => if let Ok(v) = value.parse() { settings.shapes = v },
+				"shake" => if let Ok(v) = value.parse() { settings.shake = v },
+				"music_volume" => if let Ok(v) = value.parse() { settings.music_volume = clamp_volume(v) },
+				"sfx_volume" => if let Ok(v) = value.parse() { settings.sfx_volume = clamp_volume(v) },
+				"muted" => if let Ok(v) = value.parse() { settings.muted = v },
+				"max_replay_ticks" => if let Ok(v) = value.parse() { settings.max_replay_ticks = v },
+				_ => {}
+			}
+		}
+		settings
+	}
+}
+
+// Keeps a volume between 0 and 1, rounded to the nearest step so it doesn't drift.
+fn clamp_volume(volume : f32) -> f32 {
+	((volume / VOLUME_STEP).round() * VOLUME_STEP).clamp(0.0, 1.0)
+}
+
+
+
+// Writes the settings back to the file whenever they change.
+pub fn save_settings_sys(settings : Res<Settings>) {
+	if !settings.is_changed() || settings.is_added() {
+		return;
+	}
+	if let Err(err) = storage::write(SETTINGS_FILE, &settings.to_text()) {
+		warn!("Couldn't save settings: {err}");
+	}
+}
+
+
+
+// M mutes and unmutes everything, wherever you are.
+pub fn mute_on_input(keys : Res<ButtonInput<KeyCode>>, mut settings : ResMut<Settings>) {
+	if keys.just_pressed(KeyCode::KeyM) {
+		settings.muted = !settings.muted;
+	}
+}
+
+
+
+// F11 or alt and enter switches between a window and fullscreen, wherever you are.
+pub fn fullscreen_on_input(keys : Res<ButtonInput<KeyCode>>, mut settings : ResMut<Settings>) {
+	let alt_enter = keys.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]) && keys.just_pressed(KeyCode::Enter);
+	if keys.just_pressed(KeyCode::F11) || alt_enter {
+		settings.fullscreen = !settings.fullscreen;
+	}
+}
+
+// The square brackets change the speed in the middle of a run, paused or not, the same as
+// the first row of the settings does. Plus and minus are already the zoom.
+pub fn speed_on_input(mut commands : Commands, keys : Res<ButtonInput<KeyCode>>, mut settings : ResMut<Settings>) {
+	let change = if keys.just_pressed(KeyCode::BracketRight) {
+		1
+	}
+	else if keys.just_pressed(KeyCode::BracketLeft) {
+		-1
+	}
+	else {
+		return;
+	};
+	settings.step_move_period(change);
+	crate::spawn_toast(&mut commands, format!("Time between moves: {} ms", settings.move_period_ms));
+}
+
+// Whether enter was pressed on its own, and not as part of alt and enter.
+pub fn enter_pressed(keys : &ButtonInput<KeyCode>) -> bool {
+	keys.just_pressed(KeyCode::Enter) && !keys.any_pressed([KeyCode::AltLeft, KeyCode::AltRight])
+}
+
+
+
+// Starts the frame's speed off from the settings, with nothing else changing it yet.
+pub fn reset_move_speed_sys(settings : Res<Settings>, mut speed : ResMut<MoveSpeed>) {
+	*speed = MoveSpeed::from_base(settings.move_period());
+}
+
+// Puts the speed into effect once everything's had its say, straight away, even in the
+// middle of a run.
+pub fn apply_move_speed_sys(speed : Res<MoveSpeed>, mut fixed : ResMut<Time<Fixed>>) {
+	let period = speed.period();
+	if fixed.timestep() != period {
+		fixed.set_timestep(period);
+	}
+}
+
+
+
+// Switches the window in and out of fullscreen to match the setting.
+pub fn apply_window_mode_sys(settings : Res<Settings>, mut windows : Query<&mut Window, With<PrimaryWindow>>) {
+	if !settings.is_changed() {
+		return;
+	}
+	let mode = if settings.fullscreen {
+		WindowMode::BorderlessFullscreen(MonitorSelection::Current)
+	} else {
+		WindowMode::Windowed
+	};
+	for mut window in windows.iter_mut() {
+		if window.mode != mode {
+			window.mode = mode;
+		}
+	}
+}
+
+
+
+// Turns the camera to match the board rotation setting.
+pub fn apply_board_rotation_sys(settings : Res<Settings>, mut camera : Query<&mut Transform, With<Camera2d>>) {
+	if !settings.is_changed() {
+		return;
+	}
+	for mut transform in camera.iter_mut() {
+		transform.rotation = settings.board_rotation();
+	}
+}
+
+
+
+// The text on the settings screen, so it can be updated as things get changed.
+#[derive(Component)]
+pub struct SettingsText;
+
+// The screen itself, so it can be cleaned up.
+#[derive(Component)]
+pub struct SettingsUI;
+
+// Which line of the settings screen is picked.
+#[derive(Resource, Default)]
+pub struct SettingsCursor(usize);
+
+// What the time between moves is made of. The base comes from the settings, or the daily,
+// and everything that speeds the snake up or slows it down has its own factor that the
+// base gets multiplied by. They all go back to 1 at the start of every frame, and
+// apply_move_speed_sys puts them together at the end of it, so nothing has to know what
+// else is going on with the speed.
+#[derive(Resource)]
+pub struct MoveSpeed {
+	pub base : Duration,
+	// The slow start perk, at the start of a run.
+	pub slow_start : f32,
+	// Boosting or braking.
+	pub boost : f32,
+	// A speed storm event.
+	pub storm : f32,
+	// The double speed mutator.
+	pub double_speed : f32,
+	// Whatever a mod has set the speed to.
+	pub mods : f32,
+}
+impl MoveSpeed {
+	fn from_base(base : Duration) -> Self {
+		MoveSpeed { base, slow_start : 1.0, boost : 1.0, storm : 1.0, double_speed : 1.0, mods : 1.0 }
+	}
+
+	// The time between moves with every factor taken into account.
+	pub fn period(&self) -> Duration {
+		self.base.mul_f32(self.slow_start * self.boost * self.storm * self.double_speed * self.mods)
+	}
+}
+impl Default for MoveSpeed {
+	fn default() -> Self {
+		MoveSpeed::from_base(Duration::from_secs_f32(MOVE_PERIOD))
+	}
+}
+
+
+
+pub fn spawn_settings_ui(mut commands : Commands, settings : Res<Settings>, cursor : Res<SettingsCursor>) {
+	commands.spawn((
+		SettingsUI,
+		Node {
+			width: Val::Percent(100.0),
+			height: Val::Percent(100.0),
+			justify_content: JustifyContent::Center,
+			align_items: AlignItems::Center,
+			..default()
+		},
+		BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+	))
+	.with_children(|parent| {
+		parent.spawn((
+			SettingsText,
+			Text::new(settings_text(&settings, cursor.0)),
+			TextFont {
+				font_size : SETTINGS_FONT_SIZE,
+				..default()
+			},
+			TextColor(WHITE),
+			TextLayout::new(Justify::Center, LineBreak::WordBoundary),
+		));
+	});
+}
+
+
+
+pub fn cleanup_settings_ui(mut commands : Commands, ui : Query<Entity, With<SettingsUI>>) {
+	for e in &ui {
+		commands.entity(e).despawn();
+	}
+}
+
+
+
+// Up and down pick a line, left and right change it. O or enter goes back to the menu.
+pub fn settings_menu_input(
+	keys : Res<ButtonInput<KeyCode>>,
+	mut cursor : ResMut<SettingsCursor>,
+	mut settings : ResMut<Settings>,
+	mut next_state : ResMut<NextState<GameState>>,
+) {
+	if keys.just_pressed(KeyCode::KeyO) || enter_pressed(&keys) {
+		next_state.set(GameState::Menu);
+		return;
+	}
+	if keys.just_pressed(KeyCode::ArrowUp) {
+		cursor.0 = (cursor.0 + SETTINGS_ROWS - 1) % SETTINGS_ROWS;
+	}
+	if keys.just_pressed(KeyCode::ArrowDown) {
+		cursor.0 = (cursor.0 + 1) % SETTINGS_ROWS;
+	}
+
+	let change : i32 = if keys.just_pressed(KeyCode::ArrowRight) {
+		1
+	}
+	else if keys.just_pressed(KeyCode::ArrowLeft) {
+		-1
+	}
+	else {
+		return;
+	};
+	match cursor.0 {
+		// Right makes the snake faster, which means less time between moves.
+		0 => settings.step_move_period(change),
+		1 => settings.music_volume = clamp_volume(settings.music_volume + change as f32 * VOLUME_STEP),
+		2 => settings.sfx_volume = clamp_volume(settings.sfx_volume + change as f32 * VOLUME_STEP),
+		3 => {
+			settings.controls = match (settings.controls, change > 0) {
+				(Controls::Arrows, true) | (Controls::Mouse, false) => Controls::Wasd,
+				(Controls::Wasd, true) | (Controls::Arrows, false) => Controls::Mouse,
+				(Controls::Mouse, true) | (Controls::Wasd, false) => Controls::Arrows,
+			};
+		}
+		4 => settings.rotated = !settings.rotated,
+		5 => settings.fullscreen = !settings.fullscreen,
+		6 => settings.dpad = !settings.dpad,
+		7 => settings.effects = !settings.effects,
+		8 => {
+			settings.demo_bot = match (settings.demo_bot, change > 0) {
+				(DemoBot::Greedy, true) | (DemoBot::Script, false) => DemoBot::Perfect,
+				(DemoBot::Perfect, true) | (DemoBot::Greedy, false) => DemoBot::Script,
+				(DemoBot::Script, true) | (DemoBot::Perfect, false) => DemoBot::Greedy,
+			};
+		}
+		9 => settings.bot_reaction_ticks = settings.bot_reaction_ticks.saturating_add_signed(change).min(MAX_BOT_REACTION_TICKS),
+		10 => settings.palette = if change > 0 { settings.palette.next() } else { settings.palette.previous() },
+		11 => settings.shapes = !settings.shapes,
+		_ => settings.shake = !settings.shake,
+	}
+}
+
+
+
+pub fn update_settings_text_sys(
+	settings : Res<Settings>,
+	cursor : Res<SettingsCursor>,
+	mut text : Query<&mut Text, With<SettingsText>>,
+) {
+	if !settings.is_changed() && !cursor.is_changed() {
+		return;
+	}
+	for mut text in text.iter_mut() {
+		text.0 = settings_text(&settings, cursor.0);
+	}
+}
+
+
+
+fn settings_text(settings : &Settings, cursor : usize) -> String {
+	let percent = |volume : f32| (volume * 100.0).round() as u32;
+	let rows = [
+		format!("Time between moves: {} ms", settings.move_period_ms),
+		format!("Music: {}%", percent(settings.music_volume)),
+		format!("Sound: {}%", percent(settings.sfx_volume)),
+		format!("Controls: {}", settings.controls.name()),
+		format!("Board: {}", if settings.rotated { "Portrait" } else { "Normal" }),
+		format!("Screen: {}", if settings.fullscreen { "Fullscreen" } else { "Windowed" }),
+		format!("Touch D-pad: {}", if settings.dpad { "On" } else { "Off" }),
+		format!("Effects: {}", if settings.effects { "On" } else { "Off" }),
+		format!("Demo player: {}", settings.demo_bot.name()),
+		format!("Demo reactions: {} ticks late", settings.bot_reaction_ticks),
+		format!("Colours: {}", settings.palette.name()),
+		format!("Shapes: {}", if settings.shapes { "On" } else { "Off" }),
+		format!("Screen shake: {}", if settings.shake { "On" } else { "Off" }),
+	];
+	// Only the rows around the cursor are shown, with dots where there are more.
+	let first = cursor.saturating_sub(SETTINGS_VISIBLE_ROWS / 2).min(SETTINGS_ROWS - SETTINGS_VISIBLE_ROWS);
+	let shown = first..first + SETTINGS_VISIBLE_ROWS;
+	let mut text = String::from("SETTINGS\n");
+	text.push_str(if first > 0 { "...\n" } else { "\n" });
+	for (i, row) in rows.iter().enumerate().filter(|(i, _)| shown.contains(i)) {
+		if i == ACCESSIBILITY_ROW {
+			text.push_str("ACCESSIBILITY\n");
+		}
+		let marker = if i == cursor { ">" } else { " " };
+		text.push_str(&format!("{marker} {row}\n"));
+	}
+	text.push_str(if shown.end < SETTINGS_ROWS { "...\n" } else { "\n" });
+	text.push_str("UP/DOWN to pick, LEFT/RIGHT to change\nPress O to go back");
+	if settings.muted {
+		text.push_str("\n(M: muted)");
+	}
+	text
+}
@@ -0,0 +1,133 @@
+// Sound effects for eating, turning, and dying.
+//
+// The sounds are loaded from assets/sfx when the game starts. Every time one plays it gets
+// its own entity, which despawns itself once the sound is done, so sounds that happen on
+// the same tick can overlap. A sound that's missing or broken just doesn't play.
+use bevy::prelude::*;
+use bevy::asset::LoadState;
+use bevy::audio::Volume;
+
+use crate::{AssetMissing, DirectionChanged, FoodEaten, SnakeDied};
+use crate::settings::Settings;
+
+
+
+// Loads the sounds and plays them as things happen.
+pub struct SfxPlugin;
+impl Plugin for SfxPlugin {
+	fn build(&self, app : &mut App) {
+		app.init_resource::<SoundEffects>()
+			.add_systems(Update, (check_sfx_sys, play_sfx_sys)
+				.chain()
+				.after(crate::settings::mute_on_input));
+	}
+}
+
+
+
+// How a sound file's loading is going.
+#[derive(Clone, Copy, PartialEq)]
+pub enum AudioCheck {
+	Loading,
+	Ready,
+	// Missing, or not a sound file. Trying to play a broken file would crash the game.
+	Broken,
+}
+
+// Checks on a sound file that's been asked to load.
+pub fn check_audio(assets : &AssetServer, sources : &Assets<AudioSource>, handle : &Handle<AudioSource>) -> AudioCheck {
+	match assets.load_state(handle) {
+		LoadState::Failed(_) => AudioCheck::Broken,
+		LoadState::Loaded => match sources.get(handle) {
+			Some(source) if is_wav(&source.bytes) => AudioCheck::Ready,
+			_ => AudioCheck::Broken,
+		},
+		LoadState::NotLoaded | LoadState::Loading => AudioCheck::Loading,
+	}
+}
+
+// All of the game's sounds are WAV files, which always start with this header.
+fn is_wav(bytes : &[u8]) -> bool {
+	bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WAVE"
+}
+
+
+
+// A sound, and whether it's ready to play.
+struct Sound {
+	handle : Handle<AudioSource>,
+	check : AudioCheck,
+}
+
+// The loaded sounds, so they only get loaded once.
+#[derive(Resource)]
+struct SoundEffects {
+	eat : Sound,
+	turn : Sound,
+	death : Sound,
+}
+impl FromWorld for SoundEffects {
+	fn from_world(world : &mut World) -> Self {
+		let assets = world.resource::<AssetServer>();
+		let load = |path : &'static str| Sound { handle : assets.load(path), check : AudioCheck::Loading };
+		SoundEffects {
+			eat : load("sfx/eat.wav"),
+			turn : load("sfx/turn.wav"),
+			death : load("sfx/death.wav"),
+		}
+	}
+}
+
+
+
+// Keeps an eye on the sounds until they've all loaded, and speaks up about any that didn't.
+fn check_sfx_sys(
+	assets : Res<AssetServer>,
+	sources : Res<Assets<AudioSource>>,
+	mut sounds : ResMut<SoundEffects>,
+	mut missing : MessageWriter<AssetMissing>,
+) {
+	let sounds = sounds.as_mut();
+	for sound in [&mut sounds.eat, &mut sounds.turn, &mut sounds.death] {
+		if sound.check != AudioCheck::Loading {
+			continue;
+		}
+		sound.check = check_audio(&assets, &sources, &sound.handle);
+		if sound.check == AudioCheck::Broken {
+			missing.write(AssetMissing::from_handle(&sound.handle));
+		}
+	}
+}
+
+
+
+// Plays a sound for everything that happened since last frame.
+fn play_sfx_sys(
+	mut commands : Commands,
+	sounds : Res<SoundEffects>,
+	settings : Res<Settings>,
+	mut eaten : MessageReader<FoodEaten>,
+	mut turned : MessageReader<DirectionChanged>,
+	mut died : MessageReader<SnakeDied>,
+) {
+	// A slow frame can cover more than one tick, but one of each sound is plenty.
+	// When it's muted, nothing gets played at all.
+	let volume = settings.sfx();
+	let mut play = |sound : &Sound| {
+		if volume > 0.0 && sound.check == AudioCheck::Ready {
+			commands.spawn((
+				AudioPlayer::new(sound.handle.clone()),
+				PlaybackSettings::DESPAWN.with_volume(Volume::Linear(volume)),
+			));
+		}
+	};
+	if eaten.read().count() > 0 {
+		play(&sounds.eat);
+	}
+	if turned.read().count() > 0 {
+		play(&sounds.turn);
+	}
+	if died.read().count() > 0 {
+		play(&sounds.death);
+	}
+}
@@ -0,0 +1,385 @@
+// Coins, and the shop they get spent in.
+//
+// Every so often a coin turns up somewhere on the board during a run, and it goes away
+// again if it isn't picked up soon enough. Coins don't make the snake grow or count for
+// the score, they go in the wallet, which sticks around between launches. They come from
+// their own random numbers, so a seeded run plays out the same with or without them.
+//
+// The shop is reached from the progress screen. It sells the skins that haven't been
+// earned yet, a palette that can't be had any other way, and a perk that starts every run
+// five long. The file is plain text with one "name value" pair per line, like the others.
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::progress::Progress;
+use crate::settings::{self, Settings};
+use crate::skins::Skin;
+use crate::stats::Stats;
+use crate::storage;
+use crate::theme::Palette;
+use crate::{countdown_done, get_random_pos, DangerZone, Food, GameRng, GameState, GridPosition, OccupancyGrid, SnakeHead, TickSet, CELL_SIZE, WHITE};
+
+
+// Where the wallet gets saved.
+const WALLET_FILE : &str = "wallet.txt";
+// The chance of a coin turning up on any tick there isn't one already, and how many ticks
+// it stays for.
+const COIN_CHANCE : f64 = 0.01;
+const COIN_TICKS : u32 = 60;
+const COIN_COLOR : Color = Color::srgb(1.0, 0.8, 0.1);
+// Above the food.
+const COIN_Z : f32 = 3.0;
+// How many segments the long start perk starts the snake with, on top of its head.
+const LONG_START_GROWTH : u32 = 4;
+// Everything in the shop, from top to bottom.
+const SHOP_ITEMS : [ShopItem; 6] = [
+	ShopItem::Skin(Skin::Gold),
+	ShopItem::Skin(Skin::Ruby),
+	ShopItem::Skin(Skin::Midnight),
+	ShopItem::Skin(Skin::Ghost),
+	ShopItem::Palette(Palette::Sunset),
+	ShopItem::LongStart,
+];
+
+
+
+// Puts coins out during runs, keeps the wallet saved, and runs the shop screen.
+pub struct ShopPlugin;
+impl Plugin for ShopPlugin {
+	fn build(&self, app : &mut App) {
+		app.insert_resource(Wallet::load())
+			.init_resource::<ShopCursor>()
+			.add_systems(FixedUpdate, pick_up_coin_sys
+				.in_set(TickSet::Collide)
+				.after(crate::food_collision_sys)
+				.run_if(in_state(GameState::Playing).and(countdown_done)))
+			.add_systems(FixedUpdate, drop_coin_sys
+				.in_set(TickSet::Spawn)
+				.run_if(in_state(GameState::Playing).and(countdown_done)))
+			.add_systems(OnExit(GameState::Playing), clear_coins_sys)
+			.add_systems(Update, save_wallet_sys)
+			.add_systems(OnEnter(GameState::Shop), spawn_shop_ui)
+			.add_systems(OnExit(GameState::Shop), cleanup_shop_ui)
+			.add_systems(Update, (shop_menu_input, update_shop_text_sys)
+				.chain()
+				.run_if(in_state(GameState::Shop)));
+	}
+}
+
+
+
+// The coins that have been picked up, and what's been bought with them.
+#[derive(Resource, Default, PartialEq, Debug)]
+pub struct Wallet {
+	pub coins : u32,
+	pub skins : Vec<Skin>,
+	pub palettes : Vec<Palette>,
+	pub long_start : bool,
+}
+impl Wallet {
+	// Reads the wallet file, or starts with an empty one if there isn't one yet.
+	fn load() -> Self {
+		match storage::read(WALLET_FILE) {
+			Some(text) => Wallet::from_text(&text),
+			None => Wallet::default(),
+		}
+	}
+
+	// How many segments the snake starts with on top of its head, from the perk.
+	pub fn start_growth(&self) -> u32 {
+		if self.long_start { LONG_START_GROWTH } else { 0 }
+	}
+
+	fn to_text(&self) -> String {
+		let skins = self.skins.iter().map(|skin| skin.to_name()).collect::<Vec<_>>().join(" ");
+		let palettes = self.palettes.iter().map(|palette| palette.to_name()).collect::<Vec<_>>().join(" ");
+		format!("coins {}\nskins {skins}\npalettes {palettes}\nlong_start {}\n", self.coins, self.long_start)
+	}
+
+	fn from_text(text : &str) -> Self {
+		let mut wallet = Wallet::default();
+		for line in text.lines() {
+			let (name, value) = line.split_once(' ').unwrap_or((line, ""));
+			let value = value.trim();
+			match name {
+				"coins" => if let Ok(v) = value.parse() { wallet.coins = v },
+				"skins" => wallet.skins = value.split_whitespace().filter_map(Skin::from_name).collect(),
+				"palettes" => wallet.palettes = value.split_whitespace().filter_map(Palette::from_name).collect(),
+				"long_start" => if let Ok(v) = value.parse() { wallet.long_start = v },
+				_ => {}
+			}
+		}
+		wallet
+	}
+}
+
+// Something that can be bought in the shop.
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum ShopItem {
+	Skin(Skin),
+	Palette(Palette),
+	LongStart,
+}
+impl ShopItem {
+	fn name(self) -> String {
+		match self {
+			ShopItem::Skin(skin) => format!("{} skin", skin.name()),
+			ShopItem::Palette(palette) => format!("{} colours", palette.name()),
+			ShopItem::LongStart => format!("Start {} long", LONG_START_GROWTH + 1),
+		}
+	}
+
+	fn price(self) -> u32 {
+		match self {
+			ShopItem::Skin(Skin::Gold) => 30,
+			ShopItem::Skin(Skin::Ruby) => 40,
+			ShopItem::Skin(Skin::Midnight) => 50,
+			ShopItem::Skin(_) => 75,
+			ShopItem::Palette(_) => 60,
+			ShopItem::LongStart => 100,
+		}
+	}
+
+	// Skins that have been earned by playing count as owned too, so they can't be bought.
+	fn owned(self, wallet : &Wallet, stats : &Stats, progress : &Progress) -> bool {
+		match self {
+			ShopItem::Skin(skin) => skin.unlocked(stats, progress, wallet),
+			ShopItem::Palette(palette) => wallet.palettes.contains(&palette),
+			ShopItem::LongStart => wallet.long_start,
+		}
+	}
+
+	// Whether it's what's being worn or used right now.
+	fn equipped(self, settings : &Settings) -> bool {
+		match self {
+			ShopItem::Skin(skin) => settings.skin == skin,
+			ShopItem::Palette(palette) => settings.palette == palette,
+			ShopItem::LongStart => false,
+		}
+	}
+}
+
+// A coin on the board, and how many ticks it has left before it goes away.
+#[derive(Component)]
+struct Coin(u32);
+
+// Which item is picked in the shop.
+#[derive(Resource, Default)]
+struct ShopCursor(usize);
+
+// The shop screen, so it can be cleaned up.
+#[derive(Component)]
+struct ShopUI;
+
+// The list of items, so it can be updated.
+#[derive(Component)]
+struct ShopText;
+
+
+
+// Sometimes puts a coin out on an empty cell, and takes away one that's been left too long.
+// Coins come from the run's side generator, so they don't change where the food goes.
+fn drop_coin_sys(
+	mut commands : Commands,
+	mut rng : ResMut<GameRng>,
+	occupancy : Res<OccupancyGrid>,
+	food : Query<&GridPosition, With<Food>>,
+	mut coins : Query<(Entity, &mut Coin)>,
+) {
+	for (e, mut coin) in coins.iter_mut() {
+		coin.0 = coin.0.saturating_sub(1);
+		if coin.0 == 0 {
+			commands.entity(e).despawn();
+		}
+	}
+	let rng = &mut rng.side;
+	if !coins.is_empty() || !rng.gen_bool(COIN_CHANCE) {
+		return;
+	}
+	for _ in 0..20 {
+		let pos = get_random_pos(rng, &DangerZone::default(), occupancy.width, occupancy.height);
+		if !occupancy.contains(pos) || occupancy.is_blocked(pos) || food.iter().any(|&other| other == pos) {
+			continue;
+		}
+		commands.spawn((
+			Coin(COIN_TICKS),
+			pos,
+			Sprite {
+				color : COIN_COLOR,
+				custom_size : Some(Vec2::splat(CELL_SIZE * 0.4)),
+				..default()
+			},
+			Transform::from_xyz(0.0, 0.0, COIN_Z),
+		));
+		return;
+	}
+}
+
+
+
+fn pick_up_coin_sys(
+	mut commands : Commands,
+	mut wallet : ResMut<Wallet>,
+	settings : Res<Settings>,
+	head : Query<&GridPosition, With<SnakeHead>>,
+	coins : Query<(Entity, &GridPosition), With<Coin>>,
+) {
+	let Ok(&head) = head.single() else {
+		return;
+	};
+	for (e, &pos) in &coins {
+		if pos == head {
+			commands.entity(e).despawn();
+			wallet.coins += 1;
+			crate::spawn_score_popup(&mut commands, &settings, head, "+1 coin".to_string());
+		}
+	}
+}
+
+
+
+fn clear_coins_sys(mut commands : Commands, coins : Query<Entity, With<Coin>>) {
+	for e in &coins {
+		commands.entity(e).despawn();
+	}
+}
+
+
+
+// Writes the wallet back to the file whenever it changes.
+fn save_wallet_sys(wallet : Res<Wallet>) {
+	if !wallet.is_changed() || wallet.is_added() {
+		return;
+	}
+	if let Err(err) = storage::write(WALLET_FILE, &wallet.to_text()) {
+		warn!("Couldn't save the wallet: {err}");
+	}
+}
+
+
+
+fn spawn_shop_ui(
+	mut commands : Commands,
+	settings : Res<Settings>,
+	wallet : Res<Wallet>,
+	stats : Res<Stats>,
+	progress : Res<Progress>,
+	mut cursor : ResMut<ShopCursor>,
+) {
+	cursor.0 = 0;
+	commands.spawn((
+		ShopUI,
+		Node {
+			width: Val::Percent(100.0),
+			height: Val::Percent(100.0),
+			justify_content: JustifyContent::Center,
+			align_items: AlignItems::Center,
+			..default()
+		},
+		BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+	))
+	.with_children(|parent| {
+		parent.spawn((
+			ShopText,
+			Text::new(shop_text(&settings, &wallet, &stats, &progress, cursor.0)),
+			TextFont {
+				font_size : 32.0,
+				..default()
+			},
+			TextColor(WHITE),
+			TextLayout::new(Justify::Center, LineBreak::WordBoundary),
+		));
+	});
+}
+
+
+
+fn cleanup_shop_ui(mut commands : Commands, ui : Query<Entity, With<ShopUI>>) {
+	for e in &ui {
+		commands.entity(e).despawn();
+	}
+}
+
+
+
+// Up and down pick an item, and enter buys it if there's enough coins. Skins and colours
+// get put on as soon as they're bought, and enter puts them back on again afterwards. O
+// goes back to the progress screen.
+fn shop_menu_input(
+	keys : Res<ButtonInput<KeyCode>>,
+	stats : Res<Stats>,
+	progress : Res<Progress>,
+	mut cursor : ResMut<ShopCursor>,
+	mut wallet : ResMut<Wallet>,
+	mut settings : ResMut<Settings>,
+	mut next_state : ResMut<NextState<GameState>>,
+) {
+	if keys.just_pressed(KeyCode::KeyO) {
+		next_state.set(GameState::Progress);
+		return;
+	}
+	if keys.just_pressed(KeyCode::ArrowUp) {
+		cursor.0 = (cursor.0 + SHOP_ITEMS.len() - 1) % SHOP_ITEMS.len();
+	}
+	if keys.just_pressed(KeyCode::ArrowDown) {
+		cursor.0 = (cursor.0 + 1) % SHOP_ITEMS.len();
+	}
+	if !settings::enter_pressed(&keys) {
+		return;
+	}
+	let picked = SHOP_ITEMS[cursor.0];
+	if !picked.owned(&wallet, &stats, &progress) {
+		if wallet.coins < picked.price() {
+			return;
+		}
+		wallet.coins -= picked.price();
+		match picked {
+			ShopItem::Skin(skin) => wallet.skins.push(skin),
+			ShopItem::Palette(palette) => wallet.palettes.push(palette),
+			ShopItem::LongStart => wallet.long_start = true,
+		}
+		info!(item = picked.name(), "Bought from the shop");
+	}
+	match picked {
+		ShopItem::Skin(skin) => settings.skin = skin,
+		ShopItem::Palette(palette) => settings.palette = palette,
+		ShopItem::LongStart => {}
+	}
+}
+
+
+
+fn update_shop_text_sys(
+	settings : Res<Settings>,
+	wallet : Res<Wallet>,
+	stats : Res<Stats>,
+	progress : Res<Progress>,
+	cursor : Res<ShopCursor>,
+	mut text : Query<&mut Text, With<ShopText>>,
+) {
+	if !settings.is_changed() && !wallet.is_changed() && !cursor.is_changed() {
+		return;
+	}
+	for mut text in text.iter_mut() {
+		text.0 = shop_text(&settings, &wallet, &stats, &progress, cursor.0);
+	}
+}
+
+
+
+fn shop_text(settings : &Settings, wallet : &Wallet, stats : &Stats, progress : &Progress, cursor : usize) -> String {
+	let mut text = format!("SHOP\nCoins: {}\n", wallet.coins);
+	for (i, item) in SHOP_ITEMS.into_iter().enumerate() {
+		let marker = if i == cursor { ">" } else { " " };
+		let status = if item.equipped(settings) {
+			"Equipped".to_string()
+		} else if item.owned(wallet, stats, progress) {
+			"Owned".to_string()
+		} else {
+			format!("{} coins", item.price())
+		};
+		text.push_str(&format!("{marker} {} - {status}\n", item.name()));
+	}
+	text.push_str("UP/DOWN to pick, ENTER to buy\nPress O to go back");
+	text
+}
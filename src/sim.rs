@@ -0,0 +1,212 @@
+// Headless self-play, for seeing what a rule change does to the game over a lot of runs.
+//
+//     snake-sim [--bot greedy|perfect|random] [--reaction 0] [--seeds 0..1000] [--lives 3] [--growth 0]
+//
+// plays a classic run on every seed in the range with the chosen bot, using the same
+// simulation replays are checked with, and prints the totals as JSON. No window gets
+// opened. The greedy and perfect bots are the ones that can play the demo on the menu, and
+// the random one picks any direction that doesn't kill it straight away.
+//
+// --reaction holds every decision the bot makes back by that many ticks, the same way the
+// demo's reaction setting does, to see how a bot does with a human's reaction time.
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use crate::snake_core::Sim;
+use crate::cycle::choose_cycle_direction;
+use crate::stats::DeathCause;
+use crate::{choose_ai_direction, Direction, GridPosition, OccupancyGrid, Reaction, NUM_CELLS, STARTING_LIVES};
+
+
+// A run that goes on this long is stopped, in case a bot has found a loop it'll never
+// leave.
+const MAX_TICKS : u32 = 100_000;
+const DEFAULT_SEEDS : Range<u64> = 0..1000;
+
+
+
+// Plays all the runs and prints the totals. Bad arguments get printed instead.
+pub fn run() {
+	match SimOptions::from_args() {
+		Ok(options) => println!("{}", simulate(&options).to_json(&options)),
+		Err(err) => eprintln!("{err}"),
+	}
+}
+
+
+
+#[derive(Copy, Clone)]
+enum Bot {
+	Greedy,
+	Perfect,
+	Random,
+}
+impl Bot {
+	fn from_name(name : &str) -> Option<Self> {
+		match name {
+			"greedy" => Some(Bot::Greedy),
+			"perfect" => Some(Bot::Perfect),
+			"random" => Some(Bot::Random),
+			_ => None,
+		}
+	}
+
+	fn to_name(self) -> &'static str {
+		match self {
+			Bot::Greedy => "greedy",
+			Bot::Perfect => "perfect",
+			Bot::Random => "random",
+		}
+	}
+}
+
+struct SimOptions {
+	bot : Bot,
+	// How many ticks late the bot's decisions go through.
+	reaction : u32,
+	seeds : Range<u64>,
+	lives : u32,
+	growth : u32,
+}
+impl SimOptions {
+	fn from_args() -> Result<Self, String> {
+		let bot = match arg_value("--bot") {
+			Some(name) => Bot::from_name(&name).ok_or(format!("Unknown bot {name}, try greedy, perfect or random"))?,
+			None => Bot::Greedy,
+		};
+		let seeds = match arg_value("--seeds") {
+			Some(range) => parse_range(&range).ok_or(format!("Seeds should look like 0..1000, not {range}"))?,
+			None => DEFAULT_SEEDS,
+		};
+		let number = |flag : &str, default : u32| match arg_value(flag) {
+			Some(value) => value.parse().map_err(|_| format!("{flag} should be a number, not {value}")),
+			None => Ok(default),
+		};
+		Ok(SimOptions {
+			bot,
+			reaction : number("--reaction", 0)?,
+			seeds,
+			lives : number("--lives", STARTING_LIVES)?.max(1),
+			growth : number("--growth", 0)?,
+		})
+	}
+}
+
+// The value after a flag on the command line.
+fn arg_value(flag : &str) -> Option<String> {
+	let mut args = std::env::args().skip_while(|arg| arg != flag);
+	args.next()?;
+	args.next()
+}
+
+fn parse_range(text : &str) -> Option<Range<u64>> {
+	let (start, end) = text.split_once("..")?;
+	Some(start.trim().parse().ok()?..end.trim().parse().ok()?)
+}
+
+
+
+// The totals over every run.
+#[derive(Default)]
+struct SimTotals {
+	games : u32,
+	// Runs that hit the tick limit before they ran out of lives.
+	unfinished : u32,
+	scores : Vec<u32>,
+	ticks : u64,
+	wall_deaths : u32,
+	body_deaths : u32,
+	// How many times the snake died at each length, head included.
+	death_lengths : BTreeMap<usize, u32>,
+}
+impl SimTotals {
+	fn to_json(&self, options : &SimOptions) -> String {
+		let games = self.games.max(1) as f64;
+		let total : u64 = self.scores.iter().map(|&score| score as u64).sum();
+		let lengths : Vec<String> = self.death_lengths.iter().map(|(length, count)| format!("\"{length}\":{count}")).collect();
+		format!(
+			"{{\"bot\":\"{}\",\"reaction\":{},\"seeds\":[{},{}],\"lives\":{},\"growth\":{},\"games\":{},\"unfinished\":{},\"mean_score\":{:.3},\"min_score\":{},\"max_score\":{},\"mean_ticks\":{:.1},\"deaths\":{{\"wall\":{},\"body\":{}}},\"death_lengths\":{{{}}}}}",
+			options.bot.to_name(),
+			options.reaction,
+			options.seeds.start,
+			options.seeds.end,
+			options.lives,
+			options.growth,
+			self.games,
+			self.unfinished,
+			total as f64 / games,
+			self.scores.iter().min().copied().unwrap_or(0),
+			self.scores.iter().max().copied().unwrap_or(0),
+			self.ticks as f64 / games,
+			self.wall_deaths,
+			self.body_deaths,
+			lengths.join(","),
+		)
+	}
+}
+
+
+
+fn simulate(options : &SimOptions) -> SimTotals {
+	let mut totals = SimTotals::default();
+	for seed in options.seeds.clone() {
+		let mut sim = Sim::new(seed, options.lives, options.growth);
+		// The random bot gets its own generator, so it doesn't take numbers from the food's.
+		let mut rng = StdRng::seed_from_u64(seed);
+		let mut occupancy = OccupancyGrid::new(NUM_CELLS, NUM_CELLS);
+		let mut reaction = Reaction::new(options.reaction);
+		let mut dir = Direction::None;
+		let mut ticks = 0;
+		while !sim.finished && ticks < MAX_TICKS {
+			occupancy.clear_segments();
+			for &pos in &sim.body {
+				occupancy.add_segment(pos);
+			}
+			let decision = match options.bot {
+				Bot::Greedy => choose_ai_direction(dir, sim.head, sim.food, &occupancy),
+				Bot::Perfect => choose_cycle_direction(dir, sim.head, sim.body.last().copied(), sim.food, &occupancy),
+				Bot::Random => random_direction(&mut rng, dir, sim.head, &occupancy),
+			};
+			// Until a decision comes through, the snake carries on the way it's going.
+			dir = reaction.delay(dir, decision).unwrap_or(dir);
+			let length = sim.body.len() + 1;
+			sim.step(dir);
+			ticks += 1;
+			if let Some(cause) = sim.last_death {
+				*totals.death_lengths.entry(length).or_default() += 1;
+				match cause {
+					DeathCause::Wall => totals.wall_deaths += 1,
+					_ => totals.body_deaths += 1,
+				}
+				// The new snake sets off from scratch.
+				dir = Direction::None;
+			}
+		}
+		totals.games += 1;
+		totals.unfinished += u32::from(!sim.finished);
+		totals.scores.push(sim.score);
+		totals.ticks += ticks as u64;
+	}
+	totals
+}
+
+// Any direction that doesn't run straight into something, or straight ahead if they all do.
+fn random_direction(rng : &mut StdRng, dir : Direction, head : GridPosition, occupancy : &OccupancyGrid) -> Direction {
+	let safe : Vec<Direction> = [Direction::Up, Direction::Down, Direction::Left, Direction::Right]
+		.into_iter()
+		.filter(|&candidate| {
+			let (dx, dy) = candidate.delta();
+			let next = GridPosition { x : head.x + dx, y : head.y + dy };
+			!dir.is_opposite(candidate) && occupancy.contains(next) && !occupancy.is_blocked(next)
+		})
+		.collect();
+	match safe.choose(rng) {
+		Some(&candidate) => candidate,
+		None if dir == Direction::None => Direction::Up,
+		None => dir,
+	}
+}
@@ -0,0 +1,268 @@
+// Skins for the snake, earned by playing. Each one is unlocked by a lifetime stat or by
+// prestiging, or by buying it in the shop, and once it's equipped on the skins screen it
+// paints the snake in place of the palette's colour. The equipped skin is saved with the
+// settings.
+//
+// The skins screen is reached from the progress screen.
+use bevy::prelude::*;
+
+use crate::progress::Progress;
+use crate::settings::{self, Settings};
+use crate::shop::Wallet;
+use crate::stats::Stats;
+use crate::{GameState, WHITE};
+
+
+// How many cells long the snake on the preview is, and how big each one is in pixels.
+const PREVIEW_CELLS : usize = 6;
+const PREVIEW_CELL_SIZE : f32 = 32.0;
+
+
+
+// Shows the skins screen, and lets skins be picked on it.
+pub struct SkinsPlugin;
+impl Plugin for SkinsPlugin {
+	fn build(&self, app : &mut App) {
+		app.init_resource::<SkinsCursor>()
+			.add_systems(OnEnter(GameState::Skins), spawn_skins_ui)
+			.add_systems(OnExit(GameState::Skins), cleanup_skins_ui)
+			.add_systems(Update, (skins_menu_input, update_skins_ui_sys)
+				.chain()
+				.run_if(in_state(GameState::Skins)));
+	}
+}
+
+
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum Skin {
+	// Whatever colour the palette gives the snake.
+	#[default]
+	Classic,
+	Gold,
+	Ruby,
+	Midnight,
+	Ghost,
+}
+impl Skin {
+	const ALL : [Skin; 5] = [Skin::Classic, Skin::Gold, Skin::Ruby, Skin::Midnight, Skin::Ghost];
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Skin::Classic => "Classic",
+			Skin::Gold => "Gold",
+			Skin::Ruby => "Ruby",
+			Skin::Midnight => "Midnight",
+			Skin::Ghost => "Ghost",
+		}
+	}
+
+	pub fn from_name(name : &str) -> Option<Self> {
+		match name {
+			"classic" => Some(Skin::Classic),
+			"gold" => Some(Skin::Gold),
+			"ruby" => Some(Skin::Ruby),
+			"midnight" => Some(Skin::Midnight),
+			"ghost" => Some(Skin::Ghost),
+			_ => None,
+		}
+	}
+
+	pub fn to_name(self) -> &'static str {
+		match self {
+			Skin::Classic => "classic",
+			Skin::Gold => "gold",
+			Skin::Ruby => "ruby",
+			Skin::Midnight => "midnight",
+			Skin::Ghost => "ghost",
+		}
+	}
+
+	// What it takes to unlock it.
+	fn requirement(self) -> &'static str {
+		match self {
+			Skin::Classic => "Always yours",
+			Skin::Gold => "Eat 250 food",
+			Skin::Ruby => "Grow to 30 long",
+			Skin::Midnight => "Play 100 games",
+			Skin::Ghost => "Prestige once",
+		}
+	}
+
+	pub fn unlocked(self, stats : &Stats, progress : &Progress, wallet : &Wallet) -> bool {
+		let earned = match self {
+			Skin::Classic => true,
+			Skin::Gold => stats.food_eaten >= 250,
+			Skin::Ruby => stats.best_length >= 30,
+			Skin::Midnight => stats.games_played >= 100,
+			Skin::Ghost => progress.prestige >= 1,
+		};
+		earned || wallet.skins.contains(&self)
+	}
+
+	// The colour it paints the snake, or nothing to keep the palette's colour.
+	pub fn color(self) -> Option<Color> {
+		match self {
+			Skin::Classic => None,
+			Skin::Gold => Some(Color::srgb(0.95, 0.75, 0.15)),
+			Skin::Ruby => Some(Color::srgb(0.75, 0.05, 0.2)),
+			Skin::Midnight => Some(Color::srgb(0.15, 0.15, 0.45)),
+			Skin::Ghost => Some(Color::srgb(0.85, 0.9, 0.95)),
+		}
+	}
+}
+
+
+
+// Which skin is picked on the skins screen.
+#[derive(Resource, Default)]
+struct SkinsCursor(usize);
+
+// The skins screen, so it can be cleaned up.
+#[derive(Component)]
+struct SkinsUI;
+
+// The list of skins, so it can be updated.
+#[derive(Component)]
+struct SkinsText;
+
+// One cell of the preview snake.
+#[derive(Component)]
+struct PreviewCell;
+
+
+
+fn spawn_skins_ui(
+	mut commands : Commands,
+	settings : Res<Settings>,
+	stats : Res<Stats>,
+	progress : Res<Progress>,
+	wallet : Res<Wallet>,
+	mut cursor : ResMut<SkinsCursor>,
+) {
+	// Start on whatever's equipped.
+	cursor.0 = Skin::ALL.iter().position(|&skin| skin == settings.skin).unwrap_or(0);
+	commands.spawn((
+		SkinsUI,
+		Node {
+			width: Val::Percent(100.0),
+			height: Val::Percent(100.0),
+			flex_direction: FlexDirection::Column,
+			justify_content: JustifyContent::Center,
+			align_items: AlignItems::Center,
+			row_gap: Val::Px(16.0),
+			..default()
+		},
+		BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+	))
+	.with_children(|parent| {
+		parent.spawn(Node::default()).with_children(|preview| {
+			for _ in 0..PREVIEW_CELLS {
+				preview.spawn((
+					PreviewCell,
+					Node {
+						width: Val::Px(PREVIEW_CELL_SIZE),
+						height: Val::Px(PREVIEW_CELL_SIZE),
+						border: UiRect::all(Val::Px(1.0)),
+						..default()
+					},
+					BorderColor::all(Color::BLACK),
+					BackgroundColor(Color::NONE),
+				));
+			}
+		});
+		parent.spawn((
+			SkinsText,
+			Text::new(skins_text(&settings, &stats, &progress, &wallet, cursor.0)),
+			TextFont {
+				font_size : 32.0,
+				..default()
+			},
+			TextColor(WHITE),
+			TextLayout::new(Justify::Center, LineBreak::WordBoundary),
+		));
+	});
+}
+
+
+
+fn cleanup_skins_ui(mut commands : Commands, ui : Query<Entity, With<SkinsUI>>) {
+	for e in &ui {
+		commands.entity(e).despawn();
+	}
+}
+
+
+
+// Up and down pick a skin, enter equips it if it's unlocked. O goes back to the progress
+// screen.
+fn skins_menu_input(
+	keys : Res<ButtonInput<KeyCode>>,
+	stats : Res<Stats>,
+	progress : Res<Progress>,
+	wallet : Res<Wallet>,
+	mut cursor : ResMut<SkinsCursor>,
+	mut settings : ResMut<Settings>,
+	mut next_state : ResMut<NextState<GameState>>,
+) {
+	if keys.just_pressed(KeyCode::KeyO) {
+		next_state.set(GameState::Progress);
+		return;
+	}
+	if keys.just_pressed(KeyCode::ArrowUp) {
+		cursor.0 = (cursor.0 + Skin::ALL.len() - 1) % Skin::ALL.len();
+	}
+	if keys.just_pressed(KeyCode::ArrowDown) {
+		cursor.0 = (cursor.0 + 1) % Skin::ALL.len();
+	}
+	let picked = Skin::ALL[cursor.0];
+	if settings::enter_pressed(&keys) && picked.unlocked(&stats, &progress, &wallet) && settings.skin != picked {
+		settings.skin = picked;
+	}
+}
+
+
+
+// Keeps the list and the preview up to date with what's picked.
+fn update_skins_ui_sys(
+	settings : Res<Settings>,
+	stats : Res<Stats>,
+	progress : Res<Progress>,
+	wallet : Res<Wallet>,
+	cursor : Res<SkinsCursor>,
+	mut text : Query<&mut Text, With<SkinsText>>,
+	mut cells : Query<&mut BackgroundColor, With<PreviewCell>>,
+) {
+	if !settings.is_changed() && !cursor.is_changed() {
+		return;
+	}
+	for mut text in text.iter_mut() {
+		text.0 = skins_text(&settings, &stats, &progress, &wallet, cursor.0);
+	}
+	// A locked skin still gets previewed, just greyed out.
+	let picked = Skin::ALL[cursor.0];
+	let color = picked.color().unwrap_or(settings.palette.theme().snake);
+	let color = if picked.unlocked(&stats, &progress, &wallet) { color } else { color.with_alpha(0.3) };
+	for mut cell in cells.iter_mut() {
+		cell.0 = color;
+	}
+}
+
+
+
+fn skins_text(settings : &Settings, stats : &Stats, progress : &Progress, wallet : &Wallet, cursor : usize) -> String {
+	let mut text = String::from("SKINS\n");
+	for (i, skin) in Skin::ALL.into_iter().enumerate() {
+		let marker = if i == cursor { ">" } else { " " };
+		let status = if skin == settings.skin {
+			"Equipped".to_string()
+		} else if skin.unlocked(stats, progress, wallet) {
+			"Unlocked".to_string()
+		} else {
+			format!("Locked: {}", skin.requirement())
+		};
+		text.push_str(&format!("{marker} {} - {status}\n", skin.name()));
+	}
+	text.push_str("UP/DOWN to pick, ENTER to equip\nPress O to go back");
+	text
+}
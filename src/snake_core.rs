@@ -0,0 +1,218 @@
+// The classic rules on their own, played one tick at a time without Bevy's world or
+// schedule. Replays, the ghost, GIF clips and snake-sim all play runs back with this, and
+// the Python bindings in python/ wrap Env so bots can be trained against the real rules.
+//
+// Sim has to follow the same rules, in the same order, as the TickSet steps in the game:
+// move, then the wall, the body, and the food, then growth and scoring. It only knows
+// about classic mode on an open board, since that's all a replay can be. Ticks during
+// the countdown aren't recorded, so they don't need to be skipped here.
+//
+// Env is the same thing the way a player sees it. It takes key presses rather than the
+// way the snake went, so turning straight back does nothing, like in the game, and it
+// hands back the board after every step.
+pub use crate::stats::DeathCause;
+pub use crate::{Direction, GridPosition};
+use crate::{Combo, CountedRng, DangerZone, GameRng, respawn_growth, roll_food_pos, NUM_CELLS};
+
+
+// What's in each cell of an observation.
+pub const EMPTY : u8 = 0;
+pub const BODY : u8 = 1;
+pub const HEAD : u8 = 2;
+pub const FOOD : u8 = 3;
+
+
+
+// A classic run, one tick at a time.
+#[derive(Clone)]
+pub struct Sim {
+	rng : CountedRng,
+	width : i32,
+	height : i32,
+	pub food : GridPosition,
+	pub head : GridPosition,
+	pub body : Vec<GridPosition>,
+	grow : u32,
+	lives : u32,
+	pub score : u32,
+	combo : Combo,
+	// Set once the run has run out of lives.
+	pub finished : bool,
+	// What killed the snake on the last tick, if anything did.
+	pub last_death : Option<DeathCause>,
+}
+impl Sim {
+	// A run on the normal board, the same as the game's.
+	pub fn new(seed : u64, lives : u32, growth : u32) -> Self {
+		Sim::sized(seed, lives, growth, NUM_CELLS, NUM_CELLS)
+	}
+
+	// A run on a board this many cells across and up. The snake starts in the middle.
+	pub fn sized(seed : u64, lives : u32, growth : u32, width : i32, height : i32) -> Self {
+		let mut rng = GameRng::from_seed(seed).rng;
+		let start = Sim::start(width, height);
+		// Classic runs never have a danger zone.
+		let food = roll_food_pos(&mut rng, &DangerZone::default(), width, height, |_| true, |pos| pos == start);
+		Sim {
+			rng,
+			width,
+			height,
+			food,
+			head : start,
+			body : Vec::new(),
+			grow : growth,
+			lives,
+			score : 0,
+			combo : Combo::default(),
+			finished : false,
+			last_death : None,
+		}
+	}
+
+	fn start(width : i32, height : i32) -> GridPosition {
+		GridPosition { x : width / 2, y : height / 2 }
+	}
+
+	pub fn width(&self) -> i32 {
+		self.width
+	}
+
+	pub fn height(&self) -> i32 {
+		self.height
+	}
+
+	// Plays one tick with the snake going this way.
+	pub fn step(&mut self, dir : Direction) {
+		if self.finished {
+			return;
+		}
+		self.last_death = None;
+		// Move the head, and then every segment into the spot in front of it.
+		let (dx, dy) = dir.delta();
+		let mut prev_pos = self.head;
+		self.head.x += dx;
+		self.head.y += dy;
+		for seg_pos in self.body.iter_mut() {
+			std::mem::swap(seg_pos, &mut prev_pos);
+		}
+
+		// A new segment doesn't exist until the end of the tick, so it can't be hit yet.
+		let mut new_segment = None;
+		if self.grow > 0 && dir != Direction::None {
+			new_segment = Some(self.body.last().copied().unwrap_or(self.head));
+			self.grow -= 1;
+		}
+
+		let head = self.head;
+		let hit_wall = head.x < 0 || head.x >= self.width || head.y < 0 || head.y >= self.height;
+		let ate = !hit_wall && head == self.food;
+		if ate {
+			let body = &self.body;
+			let taken = |pos : GridPosition| pos == head || body.contains(&pos);
+			self.food = roll_food_pos(&mut self.rng, &DangerZone::default(), self.width, self.height, |_| true, taken);
+			self.grow += 1;
+		}
+
+		// Dying breaks the combo before the food gets scored.
+		let died = hit_wall || self.body.contains(&head);
+		if died {
+			self.combo = Combo::default();
+		}
+		self.combo.tick();
+		if ate {
+			self.score += self.combo.eat();
+		}
+
+		if died {
+			self.last_death = Some(if hit_wall { DeathCause::Wall } else { DeathCause::Body });
+			if self.lives <= 1 {
+				self.finished = true;
+				return;
+			}
+			// The death starts the countdown before the snake grows, so a segment that was due
+			// this tick never gets added.
+			self.lives -= 1;
+			self.grow = respawn_growth(self.body.len() + 1);
+			self.head = Sim::start(self.width, self.height);
+			self.body.clear();
+			return;
+		}
+
+		self.body.extend(new_segment);
+	}
+
+	// The board, a row at a time from the bottom, with one of EMPTY, BODY, HEAD or FOOD for
+	// each cell. A head that's just gone off the edge isn't on it.
+	pub fn observation(&self) -> Vec<u8> {
+		let mut cells = vec![EMPTY; (self.width * self.height) as usize];
+		let mut put = |pos : GridPosition, what : u8| {
+			if pos.x >= 0 && pos.x < self.width && pos.y >= 0 && pos.y < self.height {
+				cells[(pos.y * self.width + pos.x) as usize] = what;
+			}
+		};
+		put(self.food, FOOD);
+		for &pos in &self.body {
+			put(pos, BODY);
+		}
+		put(self.head, HEAD);
+		cells
+	}
+}
+
+
+
+// Plays a classic run back and returns the score it ends with.
+pub fn resimulate(seed : u64, lives : u32, growth : u32, ticks : &[Direction]) -> u32 {
+	let mut sim = Sim::new(seed, lives, growth);
+	for &dir in ticks {
+		sim.step(dir);
+		if sim.finished {
+			break;
+		}
+	}
+	sim.score
+}
+
+
+
+// A run that's played a key press at a time, shaped like a Gym environment: reset with a
+// seed, then step with an action to get the board, the reward and whether it's over. The
+// reward is the points the step scored.
+pub struct Env {
+	sim : Sim,
+	width : i32,
+	height : i32,
+	lives : u32,
+	facing : Direction,
+}
+impl Env {
+	pub fn new(width : i32, height : i32, lives : u32) -> Self {
+		Env { sim : Sim::sized(0, lives, 0, width, height), width, height, lives, facing : Direction::None }
+	}
+
+	// Starts a fresh run on this seed, and returns the board.
+	pub fn reset(&mut self, seed : u64) -> Vec<u8> {
+		self.sim = Sim::sized(seed, self.lives, 0, self.width, self.height);
+		self.facing = Direction::None;
+		self.sim.observation()
+	}
+
+	// Presses a key for one tick. Going straight back is ignored, so the snake carries on
+	// the way it was going.
+	pub fn step(&mut self, pressed : Direction) -> (Vec<u8>, u32, bool) {
+		if !self.facing.is_opposite(pressed) {
+			self.facing = pressed;
+		}
+		let score = self.sim.score;
+		self.sim.step(self.facing);
+		// A new snake after a death hasn't gone anywhere yet, so it can set off any way.
+		if self.sim.last_death.is_some() {
+			self.facing = Direction::None;
+		}
+		(self.sim.observation(), self.sim.score - score, self.sim.finished)
+	}
+
+	pub fn sim(&self) -> &Sim {
+		&self.sim
+	}
+}
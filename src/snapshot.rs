@@ -0,0 +1,609 @@
+// Saving a run partway through and picking it up again later.
+//
+// F6 writes down everything on the board, and where the run's random numbers were up to,
+// so F7 can put it all back exactly as it was, food and all. The run gets set up the same
+// way a restart does, and then the snapshot is laid over the top, so it comes back with a
+// countdown to get ready. Hotseat turns, banked food and the boss aren't kept, so those
+// modes can't be saved. Bombs aren't kept either, so a run with them comes back with a
+// clear board, and neither is an event that's going on, or the food from a frenzy. A run
+// that's been picked up again can't be played back from the start, so it doesn't get kept
+// as a replay.
+//
+// Quitting in the middle of a run saves it too, off to the side so it doesn't overwrite
+// the F6 save, and the menu offers to resume it the next time the game starts. It can only
+// be resumed once.
+//
+// The last few seconds of every run are kept as snapshots too, so when the snake dies for
+// the last time the player can rewind and have another go at it. That's once a game, and
+// not for the daily challenge or the featured seeds, where everyone's meant to get the
+// same chances.
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+use std::time::Duration;
+
+use bevy::app::AppExit;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+use crate::daily::Daily;
+use crate::events::FrenzyFood;
+use crate::featured::FeaturedSeeds;
+use crate::hazards::Hazards;
+use crate::mutators::Mutators;
+use crate::replay::{self, ReplayRecorder};
+use crate::settings;
+use crate::storage;
+use crate::tiles::{Floor, Tile, Tiles};
+use crate::{
+	ArenaMap, ArenaShape, ArenaSize, CellMeshes, Combo, DangerZone, Direction, Food, GameMode, GameRng, GameState, GridPosition, Lives,
+	MapSymmetry, MenuCursor, MenuEntry, MenuPicks, Mirror, Obstacle, ObstacleHits, OccupancyGrid, RoundTimer, RunInfo, Score,
+	SnakeHead, SnakeSegment, SnakeState, SurvivalTime, TickSet, WHITE,
+};
+
+
+// Where the saved run goes.
+pub const SNAPSHOT_FILE : &str = "snapshot.txt";
+// Where the run that was going when the game was quit goes.
+pub const AUTOSAVE_FILE : &str = "autosave.txt";
+// How far back a rewind goes.
+const REWIND_SECONDS : f32 = 3.0;
+
+
+
+// Saves and loads runs in the middle of playing them.
+pub struct SnapshotPlugin;
+impl Plugin for SnapshotPlugin {
+	fn build(&self, app : &mut App) {
+		app.init_resource::<PendingSnapshot>()
+			.init_resource::<RewindBuffer>()
+			.add_systems(Startup, offer_resume_sys)
+			.add_systems(Update, (save_snapshot_on_input, load_snapshot_on_input).run_if(in_state(GameState::Playing)))
+			// The game quits at the end of the frame it's asked to, so this is the last chance.
+			.add_systems(Last, autosave_on_exit_sys.run_if(in_state(GameState::Playing)))
+			// The run has to be all set up, and the recording started, before the snapshot
+			// goes over the top of it.
+			.add_systems(OnEnter(GameState::Playing), restore_snapshot_sys
+				.after(replay::start_recording_sys)
+				.run_if(restoring))
+			// Rewinding only goes back over ticks where the snake could move.
+			.add_systems(OnEnter(GameState::Playing), start_rewind_buffer_sys)
+			.add_systems(FixedUpdate, record_rewind_sys
+				.after(TickSet::Spawn)
+				.run_if(in_state(GameState::Playing).and(crate::countdown_done)))
+			.add_systems(OnEnter(GameState::Dying), offer_rewind_sys)
+			.add_systems(Update, rewind_on_input.run_if(in_state(GameState::Dying).and(offering_rewind)))
+			.add_systems(OnExit(GameState::Dying), cleanup_rewind_prompt_sys);
+
+		// Crash reports are only saved on desktop.
+		#[cfg(feature = "native")]
+		app.add_systems(FixedUpdate, remember_run_for_crash_sys
+				.after(crate::TickSet::Spawn)
+				.run_if(in_state(GameState::Playing)))
+			.add_systems(OnExit(GameState::Playing), forget_run_for_crash_sys);
+	}
+}
+
+
+
+// Everything about a run that can't be worked out again from the seed.
+struct Snapshot {
+	mode : GameMode,
+	symmetry : MapSymmetry,
+	arena : ArenaSize,
+	shape : ArenaShape,
+	floor : Floor,
+	hazards : Hazards,
+	obstacle_hits : ObstacleHits,
+	events : bool,
+	mirror : Mirror,
+	mutators : Mutators,
+	seed : u64,
+	// How far into the seed the run's random numbers had got.
+	draws : u64,
+	score : u32,
+	lives : u32,
+	combo : Combo,
+	survived : f32,
+	round_elapsed : f32,
+	danger_rings : i32,
+	dir : Direction,
+	grow : u32,
+	head : GridPosition,
+	// From the neck to the tail.
+	segments : Vec<GridPosition>,
+	food : Vec<GridPosition>,
+	obstacles : Vec<GridPosition>,
+	ice : Vec<GridPosition>,
+	mud : Vec<GridPosition>,
+}
+impl Snapshot {
+	// The run as it is right now, unless it's in a mode that can't be saved. The head's only
+	// missing for the tick after a death, and there's nothing worth saving then either.
+	fn take(run : &RunInfo, rng : &GameRng, board : &BoardView) -> Option<Self> {
+		if !can_save(*run.mode) {
+			return None;
+		}
+		let &head = board.head.single().ok()?;
+		Some(Snapshot {
+			mode : *run.mode,
+			symmetry : board.map.symmetry,
+			arena : board.map.arena,
+			shape : board.map.shape,
+			floor : board.map.floor,
+			hazards : board.map.hazards,
+			obstacle_hits : board.map.obstacle_hits,
+			events : board.map.events,
+			mirror : board.map.mirror,
+			mutators : board.map.mutators,
+			seed : rng.seed,
+			draws : rng.rng.draws,
+			score : run.score.0,
+			lives : run.lives.0,
+			combo : Combo { multiplier : run.combo.multiplier, ticks_left : run.combo.ticks_left },
+			survived : run.survived.0,
+			round_elapsed : run.round.0.elapsed_secs(),
+			danger_rings : board.danger.rings,
+			dir : run.snake.dir,
+			grow : run.snake.grow,
+			head,
+			segments : run.snake.segments.iter().filter_map(|&e| board.segments.get(e).ok()).copied().collect(),
+			food : board.food.iter().copied().collect(),
+			obstacles : board.map.obstacles.clone(),
+			ice : board.tiles.cells(Tile::Ice).collect(),
+			mud : board.tiles.cells(Tile::Mud).collect(),
+		})
+	}
+
+	// The file is plain text, one "name value" line for each thing, like the other saves.
+	// Lists of cells are "x,y" pairs with spaces in between.
+	fn to_text(&self) -> String {
+		let cells = |cells : &[GridPosition]| cells.iter().map(|pos| format!("{},{}", pos.x, pos.y)).collect::<Vec<_>>().join(" ");
+		format!(
+			"mode {}\nmap {}\narena {}\nshape {}\nfloor {}\nhazards {}\nobstacle_hits {}\nevents {}\nmirror {}\nmutators {}\nseed {}\ndraws {}\nscore {}\nlives {}\ncombo {}\ncombo_ticks {}\nsurvived {}\nround {}\ndanger {}\ndir {}\ngrow {}\nhead {}\nsegments {}\nfood {}\nobstacles {}\nice {}\nmud {}\n",
+			self.mode.to_name(),
+			self.symmetry.to_name(),
+			self.arena.to_name(),
+			self.shape.to_name(),
+			self.floor.to_name(),
+			self.hazards.to_name(),
+			self.obstacle_hits.to_name(),
+			self.events,
+			self.mirror.to_name(),
+			self.mutators.to_names().join(" "),
+			self.seed,
+			self.draws,
+			self.score,
+			self.lives,
+			self.combo.multiplier,
+			self.combo.ticks_left,
+			self.survived,
+			self.round_elapsed,
+			self.danger_rings,
+			replay::ticks_to_text(&[self.dir]),
+			self.grow,
+			cells(&[self.head]),
+			cells(&self.segments),
+			cells(&self.food),
+			cells(&self.obstacles),
+			cells(&self.ice),
+			cells(&self.mud),
+		)
+	}
+
+	fn from_text(text : &str) -> Result<Self, String> {
+		let mut fields = HashMap::new();
+		for line in text.lines() {
+			let (name, value) = line.split_once(' ').unwrap_or((line, ""));
+			fields.insert(name, value.trim());
+		}
+		let field = |name : &str| fields.get(name).copied().ok_or(format!("missing {name}"));
+		let cells = |name : &str| -> Result<Vec<GridPosition>, String> {
+			field(name)?
+				.split_whitespace()
+				.map(|cell| {
+					let (x, y) = cell.split_once(',').ok_or(format!("bad {name}"))?;
+					Ok(GridPosition {
+						x : x.parse().map_err(|_| format!("bad {name}"))?,
+						y : y.parse().map_err(|_| format!("bad {name}"))?,
+					})
+				})
+				.collect()
+		};
+		let mode = GameMode::from_name(field("mode")?).ok_or("bad mode")?;
+		if !can_save(mode) {
+			return Err(format!("{} runs can't be saved", mode.name()));
+		}
+		Ok(Snapshot {
+			mode,
+			symmetry : MapSymmetry::from_name(field("map")?).ok_or("bad map")?,
+			arena : ArenaSize::from_name(field("arena")?).ok_or("bad arena")?,
+			shape : ArenaShape::from_name(field("shape")?).ok_or("bad shape")?,
+			floor : Floor::from_name(field("floor")?).ok_or("bad floor")?,
+			hazards : Hazards::from_name(field("hazards")?).ok_or("bad hazards")?,
+			obstacle_hits : ObstacleHits::from_name(field("obstacle_hits")?).ok_or("bad obstacle_hits")?,
+			events : parse_field(&fields, "events")?,
+			mirror : Mirror::from_name(field("mirror")?).ok_or("bad mirror")?,
+			mutators : Mutators::from_names(field("mutators")?.split_whitespace()).ok_or("bad mutators")?,
+			seed : parse_field(&fields, "seed")?,
+			draws : parse_field(&fields, "draws")?,
+			score : parse_field(&fields, "score")?,
+			lives : parse_field(&fields, "lives")?,
+			combo : Combo {
+				multiplier : parse_field(&fields, "combo")?,
+				ticks_left : parse_field(&fields, "combo_ticks")?,
+			},
+			survived : parse_field(&fields, "survived")?,
+			round_elapsed : parse_field(&fields, "round")?,
+			danger_rings : parse_field(&fields, "danger")?,
+			dir : replay::ticks_from_text(field("dir")?)?.first().copied().ok_or("bad dir")?,
+			grow : parse_field(&fields, "grow")?,
+			head : cells("head")?.first().copied().ok_or("bad head")?,
+			segments : cells("segments")?,
+			food : cells("food")?,
+			obstacles : cells("obstacles")?,
+			ice : cells("ice")?,
+			mud : cells("mud")?,
+		})
+	}
+}
+
+fn parse_field<T : FromStr>(fields : &HashMap<&str, &str>, name : &str) -> Result<T, String> {
+	fields.get(name).ok_or(format!("missing {name}"))?.parse().map_err(|_| format!("bad {name}"))
+}
+
+// Hotseat, banking and the boss keep track of more than the snapshot does.
+fn can_save(mode : GameMode) -> bool {
+	!matches!(mode, GameMode::Hotseat | GameMode::Banking | GameMode::Boss)
+}
+
+
+
+// A snapshot that's waiting for its run to be set up.
+#[derive(Resource, Default)]
+pub struct PendingSnapshot(Option<Snapshot>);
+
+// Where everything on the board is.
+#[derive(SystemParam)]
+struct BoardView<'w, 's> {
+	map : Res<'w, ArenaMap>,
+	tiles : Res<'w, Tiles>,
+	danger : Res<'w, DangerZone>,
+	head : Query<'w, 's, &'static GridPosition, With<SnakeHead>>,
+	segments : Query<'w, 's, &'static GridPosition, With<SnakeSegment>>,
+	// Food from a frenzy isn't kept.
+	food : Query<'w, 's, &'static GridPosition, (With<Food>, Without<FrenzyFood>)>,
+}
+
+// The parts of a run that get put back when it's restored.
+#[derive(SystemParam)]
+pub struct RestoredRun<'w> {
+	rng : ResMut<'w, GameRng>,
+	score : ResMut<'w, Score>,
+	lives : ResMut<'w, Lives>,
+	combo : ResMut<'w, Combo>,
+	survived : ResMut<'w, SurvivalTime>,
+	round : ResMut<'w, RoundTimer>,
+	danger : ResMut<'w, DangerZone>,
+	recorder : ResMut<'w, ReplayRecorder>,
+}
+
+// Everything on the board that gets replaced when a run is restored.
+#[derive(SystemParam)]
+pub struct RestoredBoard<'w, 's> {
+	snake : ResMut<'w, SnakeState>,
+	occupancy : ResMut<'w, OccupancyGrid>,
+	map : ResMut<'w, ArenaMap>,
+	tiles : ResMut<'w, Tiles>,
+	cells : Res<'w, CellMeshes>,
+	head : Query<'w, 's, Entity, With<SnakeHead>>,
+	segments : Query<'w, 's, Entity, With<SnakeSegment>>,
+	food : Query<'w, 's, Entity, With<Food>>,
+	obstacles : Query<'w, 's, Entity, With<Obstacle>>,
+}
+
+
+
+// The last few seconds of the run, oldest first, and whether this game has had its rewind.
+#[derive(Resource, Default)]
+pub struct RewindBuffer {
+	snapshots : VecDeque<Snapshot>,
+	used : bool,
+	// Set on the way back into a run that's being rewound, so it doesn't get another go.
+	rewinding : bool,
+	// Whether the player's being asked if they want to rewind.
+	offering : bool,
+}
+
+// The question on the way to the game over screen.
+#[derive(Component)]
+struct RewindPromptUI;
+
+
+
+fn restoring(pending : Res<PendingSnapshot>) -> bool {
+	pending.0.is_some()
+}
+
+// The game over screen waits while the player's deciding whether to rewind.
+pub fn offering_rewind(buffer : Res<RewindBuffer>) -> bool {
+	buffer.offering
+}
+
+
+
+// Gets a saved run ready to be picked up, with the mode and map it was on. It starts once
+// the game goes into Playing.
+pub fn load(path : &str, picks : &mut MenuPicks) -> Result<(), String> {
+	let text = storage::read(path).ok_or("nothing saved")?;
+	let snapshot = Snapshot::from_text(&text)?;
+	*picks.mode = snapshot.mode;
+	picks.map.symmetry = snapshot.symmetry;
+	picks.map.arena = snapshot.arena;
+	picks.map.shape = snapshot.shape;
+	picks.map.floor = snapshot.floor;
+	picks.map.hazards = snapshot.hazards;
+	picks.map.obstacle_hits = snapshot.obstacle_hits;
+	picks.map.events = snapshot.events;
+	picks.map.mirror = snapshot.mirror;
+	picks.map.mutators = snapshot.mutators;
+	// The seed comes from the snapshot, whatever was being played before.
+	picks.featured.playing = false;
+	picks.daily.playing = false;
+	picks.snapshot.0 = Some(snapshot);
+	Ok(())
+}
+
+// Gets the run from when the game was last quit ready to be picked up, and makes sure it
+// can't be picked up again.
+pub fn resume_last_game(picks : &mut MenuPicks) -> Result<(), String> {
+	load(AUTOSAVE_FILE, picks)?;
+	if let Err(err) = storage::write(AUTOSAVE_FILE, "") {
+		warn!("Couldn't clear the last game: {err}");
+	}
+	Ok(())
+}
+
+
+
+// Puts the menu cursor on the resume button if there's a game to resume.
+fn offer_resume_sys(mut commands : Commands, mut cursor : ResMut<MenuCursor>) {
+	let resumable = storage::read(AUTOSAVE_FILE).is_some_and(|text| Snapshot::from_text(&text).is_ok());
+	if !resumable {
+		return;
+	}
+	cursor.0 = crate::MENU_ENTRIES.iter().position(|&entry| entry == MenuEntry::Resume).unwrap_or(0);
+	crate::spawn_toast(&mut commands, "Your last game was saved when you quit");
+}
+
+
+
+// F6 saves the run as it is right now.
+fn save_snapshot_on_input(
+	mut commands : Commands,
+	keys : Res<ButtonInput<KeyCode>>,
+	run : RunInfo,
+	rng : Res<GameRng>,
+	board : BoardView,
+) {
+	if !keys.just_pressed(KeyCode::F6) {
+		return;
+	}
+	let Some(snapshot) = Snapshot::take(&run, &rng, &board) else {
+		crate::spawn_toast(&mut commands, format!("{} runs can't be saved right now", run.mode.name()));
+		return;
+	};
+	match storage::write(SNAPSHOT_FILE, &snapshot.to_text()) {
+		Ok(()) => crate::spawn_toast(&mut commands, "Saved the run, press F7 to load it"),
+		Err(err) => {
+			warn!("Couldn't save the run: {err}");
+			crate::spawn_toast(&mut commands, "Couldn't save the run");
+		}
+	}
+}
+
+
+
+// F7 throws away the current run and loads the saved one in its place.
+fn load_snapshot_on_input(
+	mut commands : Commands,
+	keys : Res<ButtonInput<KeyCode>>,
+	mut picks : MenuPicks,
+	mut next_state : ResMut<NextState<GameState>>,
+) {
+	if !keys.just_pressed(KeyCode::F7) {
+		return;
+	}
+	match load(SNAPSHOT_FILE, &mut picks) {
+		Ok(()) => next_state.set(GameState::Restarting),
+		Err(err) => {
+			warn!("Couldn't load the saved run: {err}");
+			crate::spawn_toast(&mut commands, format!("Couldn't load the saved run: {err}"));
+		}
+	}
+}
+
+
+
+// Saves the run if the game is quit in the middle of it, with Esc or by closing the window.
+fn autosave_on_exit_sys(mut exits : MessageReader<AppExit>, run : RunInfo, rng : Res<GameRng>, board : BoardView) {
+	if exits.read().count() == 0 {
+		return;
+	}
+	let Some(snapshot) = Snapshot::take(&run, &rng, &board) else {
+		return;
+	};
+	if let Err(err) = storage::write(AUTOSAVE_FILE, &snapshot.to_text()) {
+		warn!("Couldn't save the run on the way out: {err}");
+	}
+}
+
+
+
+// Lays the saved run over the fresh one that's just been set up.
+pub fn restore_snapshot_sys(
+	mut commands : Commands,
+	mut pending : ResMut<PendingSnapshot>,
+	mut run : RestoredRun,
+	mut board : RestoredBoard,
+) {
+	let Some(snapshot) = pending.0.take() else {
+		return;
+	};
+
+	*run.rng = GameRng::resume(snapshot.seed, snapshot.draws);
+	run.score.0 = snapshot.score;
+	run.lives.0 = snapshot.lives;
+	*run.combo = snapshot.combo;
+	run.survived.0 = snapshot.survived;
+	run.round.0.set_elapsed(Duration::from_secs_f32(snapshot.round_elapsed));
+	run.danger.rings = snapshot.danger_rings;
+	run.recorder.discard();
+
+	// The obstacles and food that were just laid out make way for the saved ones. Tron
+	// keeps its food hidden, and never eats it, so that's left alone.
+	let food = board.food.iter().filter(|_| snapshot.mode != GameMode::Tron);
+	for e in board.obstacles.iter().chain(food).chain(board.segments.iter()) {
+		commands.entity(e).despawn();
+	}
+	board.occupancy.clear_obstacles();
+	board.occupancy.clear_segments();
+	for &pos in &snapshot.obstacles {
+		board.occupancy.add_obstacle(pos);
+		crate::spawn_obstacle_at(&mut commands, &board.cells, pos);
+	}
+	board.map.obstacles = snapshot.obstacles;
+	// So does the floor, which gets drawn again once it's changed.
+	*board.tiles = Tiles::plain(board.map.width(), board.map.height());
+	for &pos in &snapshot.ice {
+		board.tiles.set(pos, Tile::Ice);
+	}
+	for &pos in &snapshot.mud {
+		board.tiles.set(pos, Tile::Mud);
+	}
+	for &pos in snapshot.food.iter().filter(|_| snapshot.mode != GameMode::Tron) {
+		crate::spawn_food_at(&mut commands, &board.cells, pos);
+	}
+
+	// The snake carries on the way it was going once the countdown's done.
+	for e in &board.head {
+		commands.entity(e).insert((snapshot.head, crate::PreviousGridPosition(snapshot.head)));
+	}
+	board.snake.segments.clear();
+	for &pos in &snapshot.segments {
+		board.occupancy.add_segment(pos);
+		let segment = crate::spawn_segment_at(&mut commands, &board.cells, pos);
+		board.snake.segments.push(segment);
+	}
+	board.snake.dir = snapshot.dir;
+	board.snake.next_dir = snapshot.dir;
+	board.snake.grow = snapshot.grow;
+}
+
+
+
+// A fresh run gets a fresh rewind, but one that's just been rewound doesn't.
+fn start_rewind_buffer_sys(mut buffer : ResMut<RewindBuffer>) {
+	buffer.snapshots.clear();
+	if !buffer.rewinding {
+		buffer.used = false;
+	}
+	buffer.rewinding = false;
+	buffer.offering = false;
+}
+
+
+
+// Keeps the last few seconds of snapshots. The tick the last life is lost on has a fresh
+// snake in it that's never going anywhere, so that one's left out.
+fn record_rewind_sys(mut buffer : ResMut<RewindBuffer>, run : RunInfo, rng : Res<GameRng>, board : BoardView) {
+	if run.lives.0 == 0 {
+		return;
+	}
+	let Some(snapshot) = Snapshot::take(&run, &rng, &board) else {
+		return;
+	};
+	let keep = (REWIND_SECONDS / run.fixed.timestep().as_secs_f32()).ceil() as usize;
+	buffer.snapshots.push_back(snapshot);
+	while buffer.snapshots.len() > keep {
+		buffer.snapshots.pop_front();
+	}
+}
+
+
+
+// Asks whether to rewind, if this game still has its rewind.
+fn offer_rewind_sys(
+	mut commands : Commands,
+	mut buffer : ResMut<RewindBuffer>,
+	mode : Res<GameMode>,
+	daily : Res<Daily>,
+	featured : Res<FeaturedSeeds>,
+) {
+	let casual = can_save(*mode) && !daily.playing && !featured.playing;
+	if !casual || buffer.used || buffer.snapshots.is_empty() {
+		return;
+	}
+	buffer.offering = true;
+	commands.spawn((
+		RewindPromptUI,
+		Text::new(format!("Rewind {REWIND_SECONDS} seconds?\nZ to rewind, Enter to carry on")),
+		TextFont {
+			font_size : 48.0,
+			..default()
+		},
+		TextColor(WHITE),
+		TextLayout::new_with_justify(Justify::Center),
+		Node {
+			position_type : PositionType::Absolute,
+			top : Val::Percent(40.0),
+			width : Val::Percent(100.0),
+			..default()
+		},
+		BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+	));
+}
+
+
+
+// Z takes the run back to the oldest snapshot, which gets set up the same way a loaded one
+// does. Enter or space lets the game over screen come up.
+fn rewind_on_input(
+	keys : Res<ButtonInput<KeyCode>>,
+	mut buffer : ResMut<RewindBuffer>,
+	mut pending : ResMut<PendingSnapshot>,
+	mut next_state : ResMut<NextState<GameState>>,
+) {
+	if keys.just_pressed(KeyCode::KeyZ) {
+		pending.0 = buffer.snapshots.pop_front();
+		buffer.used = true;
+		buffer.rewinding = true;
+		buffer.offering = false;
+		next_state.set(GameState::Restarting);
+	} else if keys.just_pressed(KeyCode::Space) || settings::enter_pressed(&keys) {
+		buffer.offering = false;
+	}
+}
+
+
+
+fn cleanup_rewind_prompt_sys(mut commands : Commands, mut buffer : ResMut<RewindBuffer>, prompt : Query<Entity, With<RewindPromptUI>>) {
+	buffer.offering = false;
+	for e in &prompt {
+		commands.entity(e).despawn();
+	}
+}
+
+
+
+// Writes down where the run is up to after every tick, in case the game crashes. Modes that
+// can't be saved still get their seed and inputs written down.
+#[cfg(feature = "native")]
+fn remember_run_for_crash_sys(run : RunInfo, rng : Res<GameRng>, board : BoardView, recorder : Res<ReplayRecorder>) {
+	let state = Snapshot::take(&run, &rng, &board).map_or_else(|| format!("mode {}\n", run.mode.to_name()), |snapshot| snapshot.to_text());
+	crate::report::remember_run(format!("inputs {}\n{state}", replay::ticks_to_text(recorder.ticks())));
+}
+
+#[cfg(feature = "native")]
+fn forget_run_for_crash_sys() {
+	crate::report::remember_run(String::new());
+}
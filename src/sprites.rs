@@ -0,0 +1,152 @@
+// Pictures for the snake and the food, from a sprite sheet in assets/sprites.
+//
+// Everything starts out drawn as plain coloured squares. Once the sheet has loaded, each
+// piece swaps its squares for a picture off the sheet, and anything spawned after that
+// gets swapped as soon as it shows up. If the sheet is missing or broken the squares
+// just stay.
+use bevy::prelude::*;
+use bevy::asset::LoadState;
+
+use crate::effects::Pulse;
+use crate::{AssetMissing, BodyFill, BodyPiece, Direction, Food, SnakeHead, SnakeSegment, SnakeState, CELL_SIZE};
+
+
+const SHEET_FILE : &str = "sprites/snake.png";
+// How big each picture on the sheet is in pixels, and how many there are. They're all in
+// one row.
+const TILE_SIZE : u32 = 32;
+const TILES : u32 = 6;
+// Where each picture is on the sheet. The snake's pieces are all drawn facing up, and the
+// body pieces join up with the top edge, like the meshes do.
+const HEAD : usize = 0;
+const STRAIGHT : usize = 1;
+const CORNER : usize = 2;
+const TAIL : usize = 3;
+const APPLE : usize = 4;
+const LONE : usize = 5;
+
+
+
+// How loading the sheet is going.
+#[derive(PartialEq)]
+enum SheetState {
+	Loading,
+	Ready,
+	Missing,
+}
+
+// The sprite sheet, and how it's split up into pictures.
+#[derive(Resource)]
+pub struct SpriteSheet {
+	image : Handle<Image>,
+	layout : Handle<TextureAtlasLayout>,
+	state : SheetState,
+}
+impl FromWorld for SpriteSheet {
+	fn from_world(world : &mut World) -> Self {
+		let image = world.resource::<AssetServer>().load(SHEET_FILE);
+		let layout = TextureAtlasLayout::from_grid(UVec2::splat(TILE_SIZE), TILES, 1, None, None);
+		let layout = world.resource_mut::<Assets<TextureAtlasLayout>>().add(layout);
+		SpriteSheet { image, layout, state : SheetState::Loading }
+	}
+}
+impl SpriteSheet {
+	// A cell sized sprite of one of the pictures.
+	fn sprite(&self, index : usize) -> Sprite {
+		let mut sprite = Sprite::from_atlas_image(
+			self.image.clone(),
+			TextureAtlas { layout : self.layout.clone(), index },
+		);
+		sprite.custom_size = Some(Vec2::splat(CELL_SIZE));
+		sprite
+	}
+}
+
+// Where the picture for a piece of the body is on the sheet.
+pub fn piece_index(piece : BodyPiece) -> usize {
+	match piece {
+		BodyPiece::Lone => LONE,
+		BodyPiece::Straight => STRAIGHT,
+		BodyPiece::Corner => CORNER,
+		BodyPiece::Tail => TAIL,
+	}
+}
+
+
+
+// Put on anything that's been switched over to the sprite sheet.
+#[derive(Component)]
+pub struct Skinned;
+
+// Query filter for the snake and the food, until they've been switched over.
+type Unskinned = (Or<(With<SnakeHead>, With<SnakeSegment>, With<Food>)>, Without<Skinned>);
+
+// The picture of the head, so it can be turned to face the way the snake is going.
+#[derive(Component)]
+pub struct HeadSprite;
+
+
+
+// Keeps an eye on the sheet until it's loaded, and speaks up if it couldn't be.
+pub fn check_sheet_sys(
+	assets : Res<AssetServer>,
+	mut sheet : ResMut<SpriteSheet>,
+	mut missing : MessageWriter<AssetMissing>,
+) {
+	if sheet.state != SheetState::Loading {
+		return;
+	}
+	match assets.load_state(&sheet.image) {
+		LoadState::Loaded => sheet.state = SheetState::Ready,
+		LoadState::Failed(_) => {
+			missing.write(AssetMissing::from_handle(&sheet.image));
+			sheet.state = SheetState::Missing;
+		}
+		LoadState::NotLoaded | LoadState::Loading => {}
+	}
+}
+
+
+
+// Swaps the squares on the snake and the food for pictures off the sheet.
+pub fn apply_sheet_sys(
+	mut commands : Commands,
+	sheet : Res<SpriteSheet>,
+	things : Query<(Entity, Has<SnakeHead>, Has<SnakeSegment>), Unskinned>,
+) {
+	if sheet.state != SheetState::Ready {
+		return;
+	}
+	for (entity, is_head, is_segment) in &things {
+		let index = if is_head { HEAD } else if is_segment { LONE } else { APPLE };
+		commands.entity(entity)
+			.despawn_related::<Children>()
+			.insert(Skinned)
+			.with_children(|parent| {
+				// Above the grid's fill, like the squares were.
+				let mut picture = parent.spawn((sheet.sprite(index), Transform::from_xyz(0.0, 0.0, 2.0)));
+				if is_head {
+					picture.insert(HeadSprite);
+				}
+				if is_segment {
+					picture.insert(BodyFill);
+				}
+				if !is_head && !is_segment {
+					picture.insert(Pulse);
+				}
+			});
+	}
+}
+
+
+
+// Turns the head to face the way the snake is going. A snake that's standing still keeps
+// facing the way it was.
+pub fn turn_head_sprite_sys(snake : Res<SnakeState>, mut heads : Query<&mut Transform, With<HeadSprite>>) {
+	if snake.dir == Direction::None {
+		return;
+	}
+	for mut transform in heads.iter_mut() {
+		transform.rotation = snake.dir.facing();
+	}
+}
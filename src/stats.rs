@@ -0,0 +1,316 @@
+// Lifetime statistics, which stick around between launches and show on the stats screen.
+//
+// They're counted from what happens in real runs, so the demo doesn't add to them. The
+// play time goes up every frame, so rather than saving on every change like the
+// progress, the stats get saved once each run is over.
+use bevy::prelude::*;
+
+use crate::settings;
+use crate::storage;
+use crate::{Direction, DirectionChanged, FoodEaten, GameState, SnakeDied, SnakeGrew, SnakeState, WHITE};
+
+
+// Where the stats get saved.
+pub const STATS_FILE : &str = "stats.txt";
+
+
+
+// Counts things up during runs, and shows them on the stats screen.
+pub struct StatsPlugin;
+impl Plugin for StatsPlugin {
+	fn build(&self, app : &mut App) {
+		app.insert_resource(Stats::load())
+			.init_resource::<RunStats>()
+			.add_systems(OnEnter(GameState::Playing), count_game_sys)
+			.add_systems(Update, (record_stats_sys, record_growth_sys).run_if(in_state(GameState::Playing)))
+			.add_systems(OnExit(GameState::Playing), save_stats_sys)
+			.add_systems(OnEnter(GameState::Stats), spawn_stats_ui)
+			.add_systems(OnExit(GameState::Stats), cleanup_stats_ui)
+			.add_systems(Update, stats_menu_input.run_if(in_state(GameState::Stats)));
+	}
+}
+
+
+
+// What killed the snake.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum DeathCause {
+	// Went off the edge of the arena.
+	Wall,
+	Obstacle,
+	// Stayed in the survival danger zone.
+	Danger,
+	// Ran into its own body, or its trail in tron.
+	Body,
+	// Was caught in a bomb's blast.
+	Explosion,
+	// Was caught by the chaser.
+	Chaser,
+	// Was on the line when a laser fired.
+	Laser,
+}
+impl DeathCause {
+	pub fn from_name(name : &str) -> Option<Self> {
+		match name {
+			"wall" => Some(DeathCause::Wall),
+			"obstacle" => Some(DeathCause::Obstacle),
+			"danger" => Some(DeathCause::Danger),
+			"body" => Some(DeathCause::Body),
+			"explosion" => Some(DeathCause::Explosion),
+			"chaser" => Some(DeathCause::Chaser),
+			"laser" => Some(DeathCause::Laser),
+			_ => None,
+		}
+	}
+
+	pub fn to_name(self) -> &'static str {
+		match self {
+			DeathCause::Wall => "wall",
+			DeathCause::Obstacle => "obstacle",
+			DeathCause::Danger => "danger",
+			DeathCause::Body => "body",
+			DeathCause::Explosion => "explosion",
+			DeathCause::Chaser => "chaser",
+			DeathCause::Laser => "laser",
+		}
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			DeathCause::Wall => "Hit the wall",
+			DeathCause::Obstacle => "Hit an obstacle",
+			DeathCause::Danger => "Stayed in the danger zone",
+			DeathCause::Body => "Bit itself",
+			DeathCause::Explosion => "Blown up",
+			DeathCause::Chaser => "Caught by the chaser",
+			DeathCause::Laser => "Hit by a laser",
+		}
+	}
+}
+
+
+
+#[derive(Resource, Default, PartialEq, Debug)]
+pub struct Stats {
+	pub games_played : u32,
+	pub food_eaten : u32,
+	pub wall_deaths : u32,
+	pub obstacle_deaths : u32,
+	pub danger_deaths : u32,
+	pub body_deaths : u32,
+	pub explosion_deaths : u32,
+	pub chaser_deaths : u32,
+	pub laser_deaths : u32,
+	// How long has been spent in runs, in seconds. Paused time doesn't count.
+	pub play_seconds : f64,
+	// The longest the snake has ever been, head included.
+	pub best_length : u32,
+}
+impl Stats {
+	// Reads the stats file, or starts from nothing if there isn't one yet.
+	pub fn load() -> Self {
+		match storage::read(STATS_FILE) {
+			Some(text) => Stats::from_text(&text),
+			None => Stats::default(),
+		}
+	}
+
+	fn deaths(&self) -> u32 {
+		self.wall_deaths + self.obstacle_deaths + self.danger_deaths + self.body_deaths + self.explosion_deaths + self.chaser_deaths + self.laser_deaths
+	}
+
+	fn to_text(&self) -> String {
+		format!(
+			"games_played {}\nfood_eaten {}\nwall_deaths {}\nobstacle_deaths {}\ndanger_deaths {}\nbody_deaths {}\nexplosion_deaths {}\nchaser_deaths {}\nlaser_deaths {}\nplay_seconds {}\nbest_length {}\n",
+			self.games_played,
+			self.food_eaten,
+			self.wall_deaths,
+			self.obstacle_deaths,
+			self.danger_deaths,
+			self.body_deaths,
+			self.explosion_deaths,
+			self.chaser_deaths,
+			self.laser_deaths,
+			self.play_seconds,
+			self.best_length,
+		)
+	}
+
+	fn from_text(text : &str) -> Self {
+		let mut stats = Stats::default();
+		for line in text.lines() {
+			let Some((name, value)) = line.split_once(' ') else {
+				continue;
+			};
+			let value = value.trim();
+			match name {
+				"games_played" => if let Ok(v) = value.parse() { stats.games_played = v },
+				"food_eaten" => if let Ok(v) = value.parse() { stats.food_eaten = v },
+				"wall_deaths" => if let Ok(v) = value.parse() { stats.wall_deaths = v },
+				"obstacle_deaths" => if let Ok(v) = value.parse() { stats.obstacle_deaths = v },
+				"danger_deaths" => if let Ok(v) = value.parse() { stats.danger_deaths = v },
+				"body_deaths" => if let Ok(v) = value.parse() { stats.body_deaths = v },
+				"explosion_deaths" => if let Ok(v) = value.parse() { stats.explosion_deaths = v },
+				"chaser_deaths" => if let Ok(v) = value.parse() { stats.chaser_deaths = v },
+				"laser_deaths" => if let Ok(v) = value.parse() { stats.laser_deaths = v },
+				"play_seconds" => if let Ok(v) = value.parse() { stats.play_seconds = v },
+				"best_length" => if let Ok(v) = value.parse() { stats.best_length = v },
+				_ => {}
+			}
+		}
+		stats
+	}
+}
+
+
+
+// The same sort of things counted for just the current run, for the game over screen.
+#[derive(Resource, Default)]
+pub struct RunStats {
+	pub food_eaten : u32,
+	pub turns : u32,
+	// How long the snake has been moving for, in seconds. The countdown doesn't count.
+	pub seconds : f32,
+	// How long the snake is, or was when it last died.
+	pub length : u32,
+	// What killed the snake last, if anything has.
+	pub cause : Option<DeathCause>,
+}
+
+
+
+// Every run counts as a game, restarts included.
+fn count_game_sys(mut stats : ResMut<Stats>, mut run : ResMut<RunStats>) {
+	stats.games_played += 1;
+	*run = RunStats::default();
+}
+
+
+
+// Adds up the food, the turns, the deaths, and the time as a run goes.
+fn record_stats_sys(
+	time : Res<Time>,
+	snake : Res<SnakeState>,
+	mut stats : ResMut<Stats>,
+	mut run : ResMut<RunStats>,
+	mut eaten : MessageReader<FoodEaten>,
+	mut died : MessageReader<SnakeDied>,
+	mut turned : MessageReader<DirectionChanged>,
+) {
+	let eaten = eaten.read().count() as u32;
+	stats.food_eaten += eaten;
+	run.food_eaten += eaten;
+	run.turns += turned.read().count() as u32;
+	if snake.dir != Direction::None {
+		run.seconds += time.delta_secs();
+	}
+	// By the time a death gets here the snake has been replaced, so the dead one's length
+	// comes with the message.
+	run.length = snake.segments.len() as u32 + 1;
+	for death in died.read() {
+		run.cause = Some(death.cause);
+		run.length = death.length as u32;
+		match death.cause {
+			DeathCause::Wall => stats.wall_deaths += 1,
+			DeathCause::Obstacle => stats.obstacle_deaths += 1,
+			DeathCause::Danger => stats.danger_deaths += 1,
+			DeathCause::Body => stats.body_deaths += 1,
+			DeathCause::Explosion => stats.explosion_deaths += 1,
+			DeathCause::Chaser => stats.chaser_deaths += 1,
+			DeathCause::Laser => stats.laser_deaths += 1,
+		}
+	}
+	stats.play_seconds += time.delta_secs_f64();
+}
+
+
+
+// Keeps track of the longest the snake has been.
+fn record_growth_sys(mut stats : ResMut<Stats>, mut grew : MessageReader<SnakeGrew>) {
+	for growth in grew.read() {
+		stats.best_length = stats.best_length.max(growth.length as u32);
+	}
+}
+
+
+
+fn save_stats_sys(stats : Res<Stats>) {
+	if let Err(err) = storage::write(STATS_FILE, &stats.to_text()) {
+		warn!("Couldn't save stats: {err}");
+	}
+}
+
+
+
+// The stats screen, so it can be cleaned up.
+#[derive(Component)]
+struct StatsUI;
+
+
+
+fn spawn_stats_ui(mut commands : Commands, stats : Res<Stats>) {
+	commands.spawn((
+		StatsUI,
+		Node {
+			width: Val::Percent(100.0),
+			height: Val::Percent(100.0),
+			justify_content: JustifyContent::Center,
+			align_items: AlignItems::Center,
+			..default()
+		},
+		BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+	))
+	.with_children(|parent| {
+		parent.spawn((
+			Text::new(stats_text(&stats)),
+			TextFont {
+				font_size : 36.0,
+				..default()
+			},
+			TextColor(WHITE),
+			TextLayout::new(Justify::Center, LineBreak::WordBoundary),
+		));
+	});
+}
+
+
+
+fn cleanup_stats_ui(mut commands : Commands, ui : Query<Entity, With<StatsUI>>) {
+	for e in &ui {
+		commands.entity(e).despawn();
+	}
+}
+
+
+
+// O or enter goes back to the menu.
+fn stats_menu_input(keys : Res<ButtonInput<KeyCode>>, mut next_state : ResMut<NextState<GameState>>) {
+	if keys.just_pressed(KeyCode::KeyO) || settings::enter_pressed(&keys) {
+		next_state.set(GameState::Menu);
+	}
+}
+
+
+
+fn stats_text(stats : &Stats) -> String {
+	let total = stats.play_seconds as u64;
+	format!(
+		"STATS\nGames played: {}\nFood eaten: {}\nLongest snake: {}\nTime played: {}h {:02}m {:02}s\n\
+		Deaths: {}\n  Walls: {}\n  Obstacles: {}\n  Danger zone: {}\n  Own body: {}\n  Explosions: {}\n  Chaser: {}\n  Lasers: {}\n\
+		Press J to export your runs as JSON, or C as CSV\nPress O to go back",
+		stats.games_played,
+		stats.food_eaten,
+		stats.best_length,
+		total / 3600,
+		total / 60 % 60,
+		total % 60,
+		stats.deaths(),
+		stats.wall_deaths,
+		stats.obstacle_deaths,
+		stats.danger_deaths,
+		stats.body_deaths,
+		stats.explosion_deaths,
+		stats.chaser_deaths,
+		stats.laser_deaths,
+	)
+}
@@ -0,0 +1,134 @@
+// Where the settings, the progress, and the replays get kept.
+//
+// Saves go through a SaveBackend, picked by what the game was built for. On the desktop
+// they're ordinary files next to the game, and any folders they go in get made as
+// they're needed. The browser doesn't have files, so there they go in the page's local
+// storage instead, using the path as the key. Either way the rest of the game just asks
+// for a path and gets the text back, through the functions at the bottom.
+use std::io;
+
+
+// Somewhere saves can be kept. Paths use forward slashes, like "replays/signing.key".
+pub trait SaveBackend {
+	// The text saved at a path, if there is any.
+	fn read(&self, path : &str) -> Option<String>;
+
+	// Saves text to a path, replacing whatever was there.
+	fn write(&self, path : &str, text : &str) -> io::Result<()>;
+
+	// Adds text onto the end of whatever's saved at a path.
+	fn append(&self, path : &str, text : &str) -> io::Result<()> {
+		let mut saved = self.read(path).unwrap_or_default();
+		saved.push_str(text);
+		self.write(path, &saved)
+	}
+
+	// The paths of everything saved straight in a folder, not counting anything in the
+	// folders inside it.
+	fn list(&self, folder : &str) -> Vec<String>;
+}
+
+
+
+// Files on disk, for the desktop.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct FileBackend;
+#[cfg(not(target_arch = "wasm32"))]
+impl SaveBackend for FileBackend {
+	fn read(&self, path : &str) -> Option<String> {
+		std::fs::read_to_string(path).ok()
+	}
+
+	fn write(&self, path : &str, text : &str) -> io::Result<()> {
+		if let Some(folder) = std::path::Path::new(path).parent() && !folder.as_os_str().is_empty() {
+			std::fs::create_dir_all(folder)?;
+		}
+		std::fs::write(path, text)
+	}
+
+	// Files can be added onto without reading the whole thing first.
+	fn append(&self, path : &str, text : &str) -> io::Result<()> {
+		use std::io::Write;
+		if let Some(folder) = std::path::Path::new(path).parent() && !folder.as_os_str().is_empty() {
+			std::fs::create_dir_all(folder)?;
+		}
+		std::fs::OpenOptions::new().create(true).append(true).open(path)?.write_all(text.as_bytes())
+	}
+
+	fn list(&self, folder : &str) -> Vec<String> {
+		let Ok(entries) = std::fs::read_dir(folder) else {
+			return Vec::new();
+		};
+		entries
+			.flatten()
+			.filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+			.map(|entry| format!("{folder}/{}", entry.file_name().to_string_lossy()))
+			.collect()
+	}
+}
+
+
+
+// The page's local storage, for the browser.
+#[cfg(target_arch = "wasm32")]
+pub struct LocalStorageBackend;
+#[cfg(target_arch = "wasm32")]
+impl LocalStorageBackend {
+	fn storage(&self) -> io::Result<web_sys::Storage> {
+		web_sys::window()
+			.and_then(|window| window.local_storage().ok().flatten())
+			.ok_or_else(|| io::Error::other("local storage isn't available"))
+	}
+}
+#[cfg(target_arch = "wasm32")]
+impl SaveBackend for LocalStorageBackend {
+	fn read(&self, path : &str) -> Option<String> {
+		self.storage().ok()?.get_item(path).ok().flatten()
+	}
+
+	fn write(&self, path : &str, text : &str) -> io::Result<()> {
+		self.storage()?
+			.set_item(path, text)
+			.map_err(|err| io::Error::other(format!("{err:?}")))
+	}
+
+	fn list(&self, folder : &str) -> Vec<String> {
+		let Ok(storage) = self.storage() else {
+			return Vec::new();
+		};
+		let prefix = format!("{folder}/");
+		(0..storage.length().unwrap_or(0))
+			.filter_map(|i| storage.key(i).ok().flatten())
+			.filter(|key| key.strip_prefix(&prefix).is_some_and(|name| !name.contains('/')))
+			.collect()
+	}
+}
+
+
+
+// The backend for whatever the game was built for.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn backend() -> impl SaveBackend {
+	FileBackend
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn backend() -> impl SaveBackend {
+	LocalStorageBackend
+}
+
+pub fn read(path : &str) -> Option<String> {
+	backend().read(path)
+}
+
+pub fn write(path : &str, text : &str) -> io::Result<()> {
+	backend().write(path, text)
+}
+
+pub fn append(path : &str, text : &str) -> io::Result<()> {
+	backend().append(path, text)
+}
+
+pub fn list(folder : &str) -> Vec<String> {
+	backend().list(folder)
+}
@@ -0,0 +1,319 @@
+// Colour palettes for the board, picked on the settings screen.
+//
+// Anything on the board that should follow the palette gets a Themed component saying
+// which of the palette's colours it is, instead of a colour of its own. It gets coloured
+// in as soon as it's spawned, and again whenever the palette changes. The menus and other
+// screens drawn over the top of the board keep their own colours.
+//
+// Some of the palettes are for colour blind players. For anyone who still has trouble
+// telling things apart, the shapes setting gives the food, the bank, and the obstacles
+// different shapes as well.
+//
+// Sunset has to be bought in the shop, and only gets put on from there. Flipping through
+// the palettes on the settings screen goes past it.
+use bevy::prelude::*;
+
+use crate::settings::Settings;
+use crate::CellMeshes;
+
+
+// The palettes to pick from.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum Palette {
+	#[default]
+	Classic,
+	Dark,
+	Neon,
+	Pastel,
+	// For red-green colour blindness, where green looks like red.
+	Deuteranopia,
+	// For red-green colour blindness, where red looks dark.
+	Protanopia,
+	// For blue-yellow colour blindness.
+	Tritanopia,
+	Sunset,
+}
+impl Palette {
+	pub fn name(self) -> &'static str {
+		match self {
+			Palette::Classic => "Classic",
+			Palette::Dark => "Dark",
+			Palette::Neon => "Neon",
+			Palette::Pastel => "Pastel",
+			Palette::Deuteranopia => "Deuteranopia",
+			Palette::Protanopia => "Protanopia",
+			Palette::Tritanopia => "Tritanopia",
+			Palette::Sunset => "Sunset",
+		}
+	}
+
+	pub fn from_name(name : &str) -> Option<Self> {
+		match name {
+			"classic" => Some(Palette::Classic),
+			"dark" => Some(Palette::Dark),
+			"neon" => Some(Palette::Neon),
+			"pastel" => Some(Palette::Pastel),
+			"deuteranopia" => Some(Palette::Deuteranopia),
+			"protanopia" => Some(Palette::Protanopia),
+			"tritanopia" => Some(Palette::Tritanopia),
+			"sunset" => Some(Palette::Sunset),
+			_ => None,
+		}
+	}
+
+	pub fn to_name(self) -> &'static str {
+		match self {
+			Palette::Classic => "classic",
+			Palette::Dark => "dark",
+			Palette::Neon => "neon",
+			Palette::Pastel => "pastel",
+			Palette::Deuteranopia => "deuteranopia",
+			Palette::Protanopia => "protanopia",
+			Palette::Tritanopia => "tritanopia",
+			Palette::Sunset => "sunset",
+		}
+	}
+
+	// The palettes either side of this one, for flipping through them on the settings screen.
+	pub fn next(self) -> Self {
+		match self {
+			Palette::Classic => Palette::Dark,
+			Palette::Dark => Palette::Neon,
+			Palette::Neon => Palette::Pastel,
+			Palette::Pastel => Palette::Deuteranopia,
+			Palette::Deuteranopia => Palette::Protanopia,
+			Palette::Protanopia => Palette::Tritanopia,
+			Palette::Tritanopia | Palette::Sunset => Palette::Classic,
+		}
+	}
+
+	pub fn previous(self) -> Self {
+		match self {
+			Palette::Classic => Palette::Tritanopia,
+			Palette::Dark => Palette::Classic,
+			Palette::Neon => Palette::Dark,
+			Palette::Pastel => Palette::Neon,
+			Palette::Deuteranopia => Palette::Pastel,
+			Palette::Protanopia => Palette::Deuteranopia,
+			Palette::Tritanopia => Palette::Protanopia,
+			Palette::Sunset => Palette::Tritanopia,
+		}
+	}
+
+	pub fn theme(self) -> Theme {
+		match self {
+			Palette::Classic => Theme {
+				background : Color::srgb(0.17, 0.17, 0.17),
+				line : Color::srgb(0.0, 0.0, 0.0),
+				cell : Color::srgb(1.0, 1.0, 1.0),
+				danger : Color::srgb(0.3, 0.3, 0.3),
+				snake : Color::srgb(0.25, 0.75, 0.25),
+				food : Color::srgb(0.75, 0.25, 0.25),
+				bank : Color::srgb(0.9, 0.75, 0.2),
+				obstacle : Color::srgb(0.3, 0.3, 0.3),
+				text : Color::srgb(0.0, 0.0, 0.0),
+			},
+			Palette::Dark => Theme {
+				background : Color::srgb(0.05, 0.05, 0.07),
+				line : Color::srgb(0.0, 0.0, 0.0),
+				cell : Color::srgb(0.12, 0.12, 0.15),
+				danger : Color::srgb(0.3, 0.1, 0.1),
+				snake : Color::srgb(0.3, 0.8, 0.4),
+				food : Color::srgb(0.9, 0.35, 0.3),
+				bank : Color::srgb(0.95, 0.8, 0.3),
+				obstacle : Color::srgb(0.35, 0.35, 0.4),
+				text : Color::srgb(0.9, 0.9, 0.9),
+			},
+			Palette::Neon => Theme {
+				background : Color::srgb(0.0, 0.0, 0.0),
+				line : Color::srgb(0.05, 0.0, 0.1),
+				cell : Color::srgb(0.08, 0.02, 0.15),
+				danger : Color::srgb(0.35, 0.0, 0.2),
+				snake : Color::srgb(0.2, 1.0, 0.6),
+				food : Color::srgb(1.0, 0.2, 0.8),
+				bank : Color::srgb(1.0, 0.9, 0.1),
+				obstacle : Color::srgb(0.1, 0.6, 1.0),
+				text : Color::srgb(0.4, 1.0, 1.0),
+			},
+			Palette::Pastel => Theme {
+				background : Color::srgb(0.85, 0.82, 0.9),
+				line : Color::srgb(0.55, 0.5, 0.6),
+				cell : Color::srgb(0.98, 0.95, 0.92),
+				danger : Color::srgb(0.8, 0.75, 0.85),
+				snake : Color::srgb(0.55, 0.8, 0.6),
+				food : Color::srgb(0.95, 0.6, 0.6),
+				bank : Color::srgb(0.95, 0.85, 0.55),
+				obstacle : Color::srgb(0.7, 0.65, 0.75),
+				text : Color::srgb(0.35, 0.3, 0.4),
+			},
+			// The colour blind palettes keep the snake and the food on opposite sides of the
+			// colours that get mixed up, and make them different brightnesses too.
+			Palette::Deuteranopia => Theme {
+				background : Color::srgb(0.17, 0.17, 0.17),
+				line : Color::srgb(0.0, 0.0, 0.0),
+				cell : Color::srgb(1.0, 1.0, 1.0),
+				danger : Color::srgb(0.3, 0.3, 0.3),
+				snake : Color::srgb(0.0, 0.45, 0.7),
+				food : Color::srgb(0.9, 0.6, 0.0),
+				bank : Color::srgb(0.95, 0.9, 0.25),
+				obstacle : Color::srgb(0.45, 0.45, 0.45),
+				text : Color::srgb(0.0, 0.0, 0.0),
+			},
+			Palette::Protanopia => Theme {
+				background : Color::srgb(0.17, 0.17, 0.17),
+				line : Color::srgb(0.0, 0.0, 0.0),
+				cell : Color::srgb(1.0, 1.0, 1.0),
+				danger : Color::srgb(0.3, 0.3, 0.3),
+				snake : Color::srgb(0.35, 0.7, 0.9),
+				food : Color::srgb(0.95, 0.75, 0.1),
+				bank : Color::srgb(0.0, 0.3, 0.6),
+				obstacle : Color::srgb(0.45, 0.45, 0.45),
+				text : Color::srgb(0.0, 0.0, 0.0),
+			},
+			Palette::Tritanopia => Theme {
+				background : Color::srgb(0.17, 0.17, 0.17),
+				line : Color::srgb(0.0, 0.0, 0.0),
+				cell : Color::srgb(1.0, 1.0, 1.0),
+				danger : Color::srgb(0.3, 0.3, 0.3),
+				snake : Color::srgb(0.0, 0.6, 0.5),
+				food : Color::srgb(0.85, 0.2, 0.1),
+				bank : Color::srgb(0.8, 0.6, 0.7),
+				obstacle : Color::srgb(0.45, 0.45, 0.45),
+				text : Color::srgb(0.0, 0.0, 0.0),
+			},
+			Palette::Sunset => Theme {
+				background : Color::srgb(0.2, 0.08, 0.15),
+				line : Color::srgb(0.1, 0.02, 0.06),
+				cell : Color::srgb(0.98, 0.72, 0.5),
+				danger : Color::srgb(0.55, 0.25, 0.3),
+				snake : Color::srgb(0.45, 0.15, 0.45),
+				food : Color::srgb(0.9, 0.2, 0.2),
+				bank : Color::srgb(1.0, 0.9, 0.4),
+				obstacle : Color::srgb(0.4, 0.2, 0.3),
+				text : Color::srgb(0.15, 0.03, 0.1),
+			},
+		}
+	}
+}
+
+
+
+// The colours from the picked palette.
+#[derive(Resource, Copy, Clone, PartialEq)]
+pub struct Theme {
+	// Behind the board.
+	pub background : Color,
+	// The outlines around the cells and everything on them.
+	pub line : Color,
+	pub cell : Color,
+	// Cells in the danger zone.
+	pub danger : Color,
+	pub snake : Color,
+	pub food : Color,
+	pub bank : Color,
+	pub obstacle : Color,
+	// Text drawn on top of the board, like the HUD.
+	pub text : Color,
+}
+impl Default for Theme {
+	fn default() -> Self {
+		Palette::default().theme()
+	}
+}
+impl Theme {
+	fn color(&self, which : ThemeColor) -> Color {
+		match which {
+			ThemeColor::Line => self.line,
+			ThemeColor::Snake => self.snake,
+			ThemeColor::Text => self.text,
+		}
+	}
+}
+
+// Which of the theme's colours a sprite or some text should be.
+#[derive(Copy, Clone, PartialEq)]
+pub enum ThemeColor {
+	Line,
+	Snake,
+	Text,
+}
+
+#[derive(Component)]
+pub struct Themed(pub ThemeColor);
+
+
+
+// Switches the theme over when a different palette or skin gets picked. The skin's colour
+// goes in place of the palette's snake.
+pub fn apply_palette_sys(settings : Res<Settings>, mut theme : ResMut<Theme>, mut clear : ResMut<ClearColor>) {
+	if !settings.is_changed() {
+		return;
+	}
+	let mut picked = settings.palette.theme();
+	if let Some(color) = settings.skin.color() {
+		picked.snake = color;
+	}
+	theme.set_if_neq(picked);
+	clear.0 = theme.background;
+}
+
+
+
+// Swaps the shapes of the food, the bank, and the obstacles when the shapes setting is
+// flipped. They all share the same meshes, so changing those changes every one of them.
+pub fn apply_shapes_sys(settings : Res<Settings>, cells : Res<CellMeshes>, mut meshes : ResMut<Assets<Mesh>>) {
+	if !settings.is_changed() {
+		return;
+	}
+	for (handle, mesh) in [
+		(&cells.food_fill, CellMeshes::food_mesh(settings.shapes)),
+		(&cells.bank_fill, CellMeshes::bank_mesh(settings.shapes)),
+		(&cells.obstacle_fill, CellMeshes::obstacle_mesh(settings.shapes)),
+	] {
+		if let Some(old) = meshes.get_mut(handle) {
+			*old = mesh;
+		}
+	}
+}
+
+
+
+// Colours in anything new, and everything when the theme changes.
+pub fn restyle_sys(
+	theme : Res<Theme>,
+	mut sprites : Query<(Ref<Themed>, &mut Sprite)>,
+	mut texts : Query<(Ref<Themed>, &mut TextColor)>,
+) {
+	for (themed, mut sprite) in sprites.iter_mut() {
+		if theme.is_changed() || themed.is_added() {
+			sprite.color = theme.color(themed.0);
+		}
+	}
+	for (themed, mut text) in texts.iter_mut() {
+		if theme.is_changed() || themed.is_added() {
+			// Keep any fading that's going on.
+			text.0 = theme.color(themed.0).with_alpha(text.0.alpha());
+		}
+	}
+}
+
+
+
+// Recolours the shared materials for the snake's body, the food, the bank, and the
+// obstacles. Every one of them uses these, so they all change at once.
+pub fn restyle_materials_sys(theme : Res<Theme>, cells : Res<CellMeshes>, mut materials : ResMut<Assets<ColorMaterial>>) {
+	if !theme.is_changed() {
+		return;
+	}
+	for (handle, color) in [
+		(&cells.line, theme.line),
+		(&cells.snake, theme.snake),
+		(&cells.food, theme.food),
+		(&cells.bank, theme.bank),
+		(&cells.obstacle, theme.obstacle),
+	] {
+		if let Some(material) = materials.get_mut(handle) {
+			material.color = color;
+		}
+	}
+}
@@ -0,0 +1,270 @@
+// What the floor of the arena is made of. Most of it is plain, but the floor picked on the
+// menu can put patches of something else down, laid out fresh from the run's seed at the
+// start of every run, away from where the snake starts.
+//
+// Ice is slippery. Once the head's on it the snake keeps going the way it was headed, and
+// any turns get ignored until it's off the other side, so it's worth lining up before
+// getting on. Mud is sticky, and the snake only gets to move on every other tick while its
+// head's in it, so the whole tick gets skipped in between. The AI in the demo has to put
+// up with both.
+//
+// Mods can put tiles down wherever they like too, with `set_tile(x, y, "mud")`.
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+	grid_to_world, quads_mesh, rect_corners, ArenaMap, CellMeshes, Direction, GameRng, GameState, GridPosition, SnakeHead, SnakeState,
+	TickSet, CELL_SIZE,
+};
+
+
+// How many cells of arena there are for each patch, so bigger arenas get more.
+const CELLS_PER_PATCH : i32 = 100;
+// How far a patch can reach from its middle, in cells across plus cells up.
+const PATCH_RADIUS : i32 = 2;
+const ICE_COLOR : Color = Color::srgba(0.7, 0.9, 1.0, 0.45);
+const MUD_COLOR : Color = Color::srgba(0.45, 0.28, 0.1, 0.6);
+// Above the grid's fill, and below the path overlay.
+const TILE_Z : f32 = 1.2;
+
+
+
+// Lays out the floor, draws it, and makes the snake slide on the ice and wade through the
+// mud.
+pub struct TilesPlugin;
+impl Plugin for TilesPlugin {
+	fn build(&self, app : &mut App) {
+		app.init_resource::<Tiles>()
+			.init_resource::<Stuck>()
+			// The obstacles have to be down first so the floor goes around them, and the
+			// snapshot has to come after so it can put a saved floor back.
+			.add_systems(OnEnter(GameState::Playing), lay_out_tiles_sys
+				.after(crate::build_arena_sys)
+				.before(crate::respawn_food_sys))
+			.add_systems(FixedUpdate, (slide_sys, wade_sys)
+				.in_set(TickSet::Input)
+				.after(crate::ai_steer_sys)
+				.run_if(in_state(GameState::Playing).or(in_state(GameState::Demo))))
+			// Everything after the input waits out a tick in the mud, so nothing happens
+			// on it at all.
+			.configure_sets(FixedUpdate, (TickSet::Move, TickSet::Collide, TickSet::Grow, TickSet::Spawn).run_if(not(stuck_in_mud)))
+			.add_systems(Update, draw_tiles_sys.run_if(resource_changed::<Tiles>));
+	}
+}
+
+
+
+// The floors that can be picked on the menu.
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub enum Floor {
+	#[default]
+	Plain,
+	Ice,
+	Mud,
+}
+impl Floor {
+	// The floor after this one, for flipping through them on the menu.
+	pub fn next(self) -> Self {
+		match self {
+			Floor::Plain => Floor::Ice,
+			Floor::Ice => Floor::Mud,
+			Floor::Mud => Floor::Plain,
+		}
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Floor::Plain => "Plain",
+			Floor::Ice => "Ice",
+			Floor::Mud => "Mud",
+		}
+	}
+
+	pub fn from_name(name : &str) -> Option<Self> {
+		match name {
+			"plain" => Some(Floor::Plain),
+			"ice" => Some(Floor::Ice),
+			"mud" => Some(Floor::Mud),
+			_ => None,
+		}
+	}
+
+	pub fn to_name(self) -> &'static str {
+		match self {
+			Floor::Plain => "plain",
+			Floor::Ice => "ice",
+			Floor::Mud => "mud",
+		}
+	}
+}
+
+// What one cell of the floor is.
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub enum Tile {
+	#[default]
+	Plain,
+	Ice,
+	Mud,
+}
+impl Tile {
+	// Only mods ask for tiles by name.
+	#[cfg(feature = "native")]
+	pub fn from_name(name : &str) -> Option<Self> {
+		match name {
+			"plain" => Some(Tile::Plain),
+			"ice" => Some(Tile::Ice),
+			"mud" => Some(Tile::Mud),
+			_ => None,
+		}
+	}
+
+	fn color(self) -> Color {
+		match self {
+			Tile::Plain => Color::NONE,
+			Tile::Ice => ICE_COLOR,
+			Tile::Mud => MUD_COLOR,
+		}
+	}
+}
+
+// The tile in every cell of the arena, a row at a time from the bottom. Anywhere off it
+// counts as plain.
+#[derive(Resource, Default)]
+pub struct Tiles {
+	width : i32,
+	height : i32,
+	tiles : Vec<Tile>,
+}
+impl Tiles {
+	// A plain floor this many cells across and up.
+	pub fn plain(width : i32, height : i32) -> Self {
+		Tiles { width, height, tiles : vec![Tile::Plain; (width * height) as usize] }
+	}
+
+	fn index(&self, pos : GridPosition) -> Option<usize> {
+		let inside = pos.x >= 0 && pos.x < self.width && pos.y >= 0 && pos.y < self.height;
+		inside.then(|| (pos.y * self.width + pos.x) as usize)
+	}
+
+	pub fn at(&self, pos : GridPosition) -> Tile {
+		self.index(pos).map_or(Tile::Plain, |i| self.tiles[i])
+	}
+
+	pub fn set(&mut self, pos : GridPosition, tile : Tile) {
+		if let Some(i) = self.index(pos) {
+			self.tiles[i] = tile;
+		}
+	}
+
+	// Every cell with this tile on it.
+	pub fn cells(&self, tile : Tile) -> impl Iterator<Item = GridPosition> + '_ {
+		(0..self.height)
+			.flat_map(move |y| (0..self.width).map(move |x| GridPosition { x, y }))
+			.filter(move |&pos| self.at(pos) == tile)
+	}
+}
+
+// Whether the snake's sitting out this tick in the mud.
+#[derive(Resource, Default)]
+struct Stuck(bool);
+
+// The mesh the floor's drawn with.
+#[derive(Component)]
+struct TileLayer;
+
+
+
+fn stuck_in_mud(stuck : Res<Stuck>) -> bool {
+	stuck.0
+}
+
+
+
+// Puts down patches of the picked floor, on the arena and off the obstacles, keeping the
+// cells around the start plain so the snake doesn't set off already sliding or stuck.
+fn lay_out_tiles_sys(map : Res<ArenaMap>, mut rng : ResMut<GameRng>, mut tiles : ResMut<Tiles>) {
+	*tiles = Tiles::plain(map.width(), map.height());
+	let tile = match map.floor {
+		Floor::Plain => return,
+		Floor::Ice => Tile::Ice,
+		Floor::Mud => Tile::Mud,
+	};
+
+	let start = map.start();
+	let near_start = |pos : GridPosition| {
+		(pos.x - start.x).abs() <= 2 && (pos.y - start.y).abs() <= 2
+	};
+	let patches = (map.width() * map.height() / CELLS_PER_PATCH).max(1);
+	for _ in 0..patches {
+		let middle = GridPosition {
+			x : rng.rng.gen_range(0..map.width()),
+			y : rng.rng.gen_range(0..map.height()),
+		};
+		for dx in -PATCH_RADIUS..=PATCH_RADIUS {
+			for dy in -PATCH_RADIUS..=PATCH_RADIUS {
+				let pos = GridPosition { x : middle.x + dx, y : middle.y + dy };
+				if dx.abs() + dy.abs() > PATCH_RADIUS || !map.contains(pos) || near_start(pos) || map.obstacles.contains(&pos) {
+					continue;
+				}
+				tiles.set(pos, tile);
+			}
+		}
+	}
+	debug!(cells = tiles.cells(tile).count(), floor = map.floor.name(), "Laid out the floor");
+}
+
+
+
+// Holds the snake to the way it's going while its head is on the ice. The turn it was
+// given stays ignored, rather than waiting for it to get off.
+fn slide_sys(tiles : Res<Tiles>, mut snake : ResMut<SnakeState>, head : Query<&GridPosition, With<SnakeHead>>) {
+	let Ok(&head) = head.single() else {
+		return;
+	};
+	if snake.dir != Direction::None && tiles.at(head) == Tile::Ice {
+		snake.next_dir = snake.dir;
+	}
+}
+
+
+
+// Every other tick the head's in the mud gets skipped, starting with the one straight after
+// it gets there. A snake that's waiting to set off isn't stuck, it's just not moving yet.
+fn wade_sys(tiles : Res<Tiles>, snake : Res<SnakeState>, mut stuck : ResMut<Stuck>, head : Query<&GridPosition, With<SnakeHead>>) {
+	let in_mud = head.single().is_ok_and(|&head| tiles.at(head) == Tile::Mud);
+	stuck.0 = in_mud && snake.dir != Direction::None && !stuck.0;
+}
+
+
+
+// Draws every cell that isn't plain, as one mesh, whenever the floor changes.
+fn draw_tiles_sys(
+	mut commands : Commands,
+	mut meshes : ResMut<Assets<Mesh>>,
+	tiles : Res<Tiles>,
+	cells : Res<CellMeshes>,
+	layers : Query<Entity, With<TileLayer>>,
+) {
+	for e in &layers {
+		commands.entity(e).despawn();
+	}
+	let half = Vec2::splat(CELL_SIZE / 2.0);
+	let mut quads = Vec::new();
+	let mut colors = Vec::new();
+	for tile in [Tile::Ice, Tile::Mud] {
+		for pos in tiles.cells(tile) {
+			quads.push(rect_corners(grid_to_world(pos) - half, grid_to_world(pos) + half));
+			// Each cell is two triangles.
+			colors.extend([tile.color().to_linear().to_f32_array(); 6]);
+		}
+	}
+	if quads.is_empty() {
+		return;
+	}
+	commands.spawn((
+		TileLayer,
+		Mesh2d(meshes.add(quads_mesh(&quads).with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors))),
+		MeshMaterial2d(cells.board.clone()),
+		Transform::from_xyz(0.0, 0.0, TILE_Z),
+	));
+}
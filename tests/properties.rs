@@ -0,0 +1,149 @@
+// Property tests for the rules. Proptest picks the seed and which keys get pressed on
+// every tick, and the runs are played headless, so these hold for the real game and not
+// a copy of its rules.
+use proptest::collection::vec;
+use proptest::prelude::*;
+use rusty_snake::headless::{Direction, HeadlessGame};
+use rusty_snake::snake_core::Env;
+
+
+// How many keys get pressed in a run, at most. It's enough for a few deaths.
+const MAX_PRESSES : usize = 150;
+
+
+
+fn direction() -> impl Strategy<Value = Direction> {
+	prop_oneof![Just(Direction::Up), Just(Direction::Down), Just(Direction::Left), Just(Direction::Right)]
+}
+
+fn opposite(dir : Direction) -> Direction {
+	match dir {
+		Direction::Up => Direction::Down,
+		Direction::Down => Direction::Up,
+		Direction::Left => Direction::Right,
+		Direction::Right => Direction::Left,
+		Direction::None => Direction::None,
+	}
+}
+
+
+
+// The way to the food, for the presses that go for it, unless that's straight back.
+fn towards_food(game : &mut HeadlessGame) -> Direction {
+	let (hx, hy) = game.head();
+	let (fx, fy) = game.food()[0];
+	let facing = game.facing();
+	[
+		(fx > hx, Direction::Right),
+		(fx < hx, Direction::Left),
+		(fy > hy, Direction::Up),
+		(fy < hy, Direction::Down),
+	]
+	.into_iter()
+	.find(|&(wants, dir)| wants && dir != opposite(facing))
+	.map_or(facing, |(_, dir)| dir)
+}
+
+
+
+proptest! {
+	#![proptest_config(ProptestConfig::with_cases(64))]
+
+	// Whatever gets pressed, the snake never turns straight back on itself. A new snake
+	// after a death can go any way it likes.
+	#[test]
+	fn reversals_are_rejected(seed in any::<u64>(), presses in vec(direction(), 1..MAX_PRESSES)) {
+		let mut game = HeadlessGame::new(seed);
+		for pressed in presses {
+			let was_facing = game.facing();
+			let deaths = game.deaths().len();
+			game.steer(pressed);
+			game.tick();
+			if game.is_over() {
+				break;
+			}
+			if game.deaths().len() == deaths && was_facing != Direction::None {
+				prop_assert_ne!(game.facing(), opposite(was_facing));
+			}
+		}
+	}
+
+	// No two bits of the snake share a cell unless it's just died. A new segment starts
+	// out under the tail though, and only moves off it on the next tick.
+	#[test]
+	fn the_snake_never_overlaps_itself(seed in any::<u64>(), presses in vec(direction(), 1..MAX_PRESSES)) {
+		let mut game = HeadlessGame::new(seed);
+		for pressed in presses {
+			let deaths = game.deaths().len();
+			game.steer(pressed);
+			game.tick();
+			if game.is_over() {
+				break;
+			}
+			if game.deaths().len() > deaths {
+				continue;
+			}
+			let mut cells = game.body();
+			if cells.len() >= 2 && cells[cells.len() - 1] == cells[cells.len() - 2] {
+				cells.pop();
+			}
+			let mut unique = cells.clone();
+			unique.sort();
+			unique.dedup();
+			prop_assert_eq!(unique.len(), cells.len(), "snake overlaps itself: {:?}", cells);
+		}
+	}
+
+	// Going off the edge in zen mode always comes back in on the other side.
+	#[test]
+	fn wrapping_stays_on_the_board(seed in any::<u64>(), presses in vec(direction(), 1..MAX_PRESSES)) {
+		let mut game = HeadlessGame::wrapping(seed);
+		let (width, height) = game.size();
+		for pressed in presses {
+			game.steer(pressed);
+			game.tick();
+			for (x, y) in game.body() {
+				prop_assert!((0..width).contains(&x) && (0..height).contains(&y), "({}, {}) is off the board", x, y);
+			}
+		}
+	}
+
+	// Whatever the seed and the keys, playing the replay back gets the score the run got.
+	// Random keys hardly ever find the food, so about half of them go for it.
+	#[test]
+	fn replays_are_deterministic(seed in any::<u64>(), presses in vec(proptest::option::of(direction()), 1..MAX_PRESSES)) {
+		let mut game = HeadlessGame::new(seed);
+		for pressed in presses {
+			let pressed = pressed.unwrap_or_else(|| towards_food(&mut game));
+			game.steer(pressed);
+			game.tick();
+			if game.is_over() {
+				break;
+			}
+		}
+		prop_assert_eq!(game.replayed_score(), game.score());
+	}
+
+	// The Python environment sees the same run as the game, key press for key press.
+	#[test]
+	fn the_environment_plays_like_the_game(seed in any::<u64>(), presses in vec(proptest::option::of(direction()), 1..MAX_PRESSES)) {
+		let mut game = HeadlessGame::new(seed);
+		let (width, height) = game.size();
+		let mut env = Env::new(width, height, 3);
+		env.reset(seed);
+		for pressed in presses {
+			let pressed = pressed.unwrap_or_else(|| towards_food(&mut game));
+			game.steer(pressed);
+			game.tick();
+			let (_, _, done) = env.step(pressed);
+			prop_assert_eq!(done, game.is_over());
+			if done {
+				break;
+			}
+			let sim = env.sim();
+			let body : Vec<_> = std::iter::once(sim.head).chain(sim.body.iter().copied()).map(|pos| (pos.x, pos.y)).collect();
+			prop_assert_eq!(body, game.body());
+			prop_assert_eq!(sim.score, game.score());
+		}
+	}
+}
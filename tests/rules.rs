@@ -0,0 +1,163 @@
+// Plays short runs headless and checks the basic rules hold: eating grows the snake, the
+// walls and the snake's own body kill it, food never turns up on top of it, and a run's
+// replay plays back to the score the run got.
+use rusty_snake::headless::{Direction, HeadlessGame};
+
+
+// How many ticks a run gets to do what a test is waiting for.
+const MAX_TICKS : usize = 2000;
+
+
+
+fn opposite(dir : Direction) -> Direction {
+	match dir {
+		Direction::Up => Direction::Down,
+		Direction::Down => Direction::Up,
+		Direction::Left => Direction::Right,
+		Direction::Right => Direction::Left,
+		Direction::None => Direction::None,
+	}
+}
+
+fn clockwise(dir : Direction) -> Direction {
+	match dir {
+		Direction::Up => Direction::Right,
+		Direction::Right => Direction::Down,
+		Direction::Down => Direction::Left,
+		Direction::Left => Direction::Up,
+		Direction::None => Direction::None,
+	}
+}
+
+fn anticlockwise(dir : Direction) -> Direction {
+	opposite(clockwise(dir))
+}
+
+fn step((x, y) : (i32, i32), dir : Direction) -> (i32, i32) {
+	match dir {
+		Direction::Up => (x, y + 1),
+		Direction::Down => (x, y - 1),
+		Direction::Left => (x - 1, y),
+		Direction::Right => (x + 1, y),
+		Direction::None => (x, y),
+	}
+}
+
+fn in_bounds(game : &HeadlessGame, (x, y) : (i32, i32)) -> bool {
+	let (width, height) = game.size();
+	x >= 0 && x < width && y >= 0 && y < height
+}
+
+// Heads straight for the food, without ever turning back on itself. When the food is
+// right behind, it goes around whichever side has room.
+fn steer_to_food(game : &mut HeadlessGame, facing : Direction) -> Direction {
+	let (hx, hy) = game.head();
+	let (fx, fy) = game.food()[0];
+	let wanted = [
+		(fx > hx, Direction::Right),
+		(fx < hx, Direction::Left),
+		(fy > hy, Direction::Up),
+		(fy < hy, Direction::Down),
+	];
+	let dir = wanted.iter()
+		.find(|&&(wants, dir)| wants && dir != opposite(facing))
+		.map(|&(_, dir)| dir)
+		.unwrap_or_else(|| {
+			let side = clockwise(facing);
+			if in_bounds(game, step((hx, hy), side)) { side } else { opposite(side) }
+		});
+	game.steer(dir);
+	dir
+}
+
+// Keeps eating until the snake is this long, head included.
+fn grow_to(game : &mut HeadlessGame, length : usize) -> Direction {
+	let mut facing = Direction::Up;
+	for _ in 0..MAX_TICKS {
+		if game.body().len() >= length {
+			return facing;
+		}
+		facing = steer_to_food(game, facing);
+		game.tick();
+	}
+	panic!("the snake never got to {length} long");
+}
+
+
+
+#[test]
+fn eating_grows_the_snake() {
+	let mut game = HeadlessGame::new(1);
+	assert_eq!(game.body().len(), 1);
+	grow_to(&mut game, 2);
+	assert_eq!(game.score(), 1);
+	assert!(game.deaths().is_empty());
+}
+
+#[test]
+fn the_wall_kills_the_snake() {
+	let mut game = HeadlessGame::new(2);
+	let (x, _) = game.head();
+	game.steer(Direction::Left);
+	// It gets right up to the wall, and dies on the step after.
+	for _ in 0..=x {
+		assert!(game.deaths().is_empty());
+		game.tick();
+	}
+	assert_eq!(game.deaths(), ["wall"]);
+}
+
+#[test]
+fn the_snake_dies_on_itself() {
+	let mut game = HeadlessGame::new(3);
+	let mut facing = grow_to(&mut game, 5);
+	// Going round in a tight square runs into the body on the way back. It goes round
+	// whichever way isn't up against a wall.
+	let head = game.head();
+	let turn = if in_bounds(&game, step(head, clockwise(facing))) { clockwise } else { anticlockwise };
+	for _ in 0..3 {
+		facing = turn(facing);
+		game.steer(facing);
+		game.tick();
+		if !game.deaths().is_empty() {
+			break;
+		}
+	}
+	assert_eq!(game.deaths(), ["body"]);
+}
+
+#[test]
+fn replays_get_the_same_score() {
+	for seed in 0..5 {
+		let mut game = HeadlessGame::new(seed);
+		let mut facing = Direction::Up;
+		for _ in 0..MAX_TICKS {
+			if game.is_over() {
+				break;
+			}
+			facing = steer_to_food(&mut game, facing);
+			game.tick();
+		}
+		assert!(game.score() > 0);
+		assert_eq!(game.replayed_score(), game.score(), "replay of seed {seed} went differently");
+	}
+}
+
+#[test]
+fn food_never_lands_on_the_snake() {
+	for seed in 0..5 {
+		let mut game = HeadlessGame::new(seed);
+		let mut facing = Direction::Up;
+		for _ in 0..MAX_TICKS {
+			if game.is_over() {
+				break;
+			}
+			let body = game.body();
+			for food in game.food() {
+				assert!(!body.contains(&food), "food at {food:?} is on the snake on seed {seed}");
+			}
+			facing = steer_to_food(&mut game, facing);
+			game.tick();
+		}
+	}
+}